@@ -0,0 +1,13 @@
+//! The history is rejected with an error, never accepted with a record
+//! finishing before it starts.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use tomata_core::history;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(records) = history::read_records(data, 0) {
+        assert!(records
+            .iter()
+            .all(|record| record.finished_at >= record.started_at));
+    }
+});
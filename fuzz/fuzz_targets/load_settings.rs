@@ -0,0 +1,12 @@
+//! The settings are rejected with an error, never accepted out of range.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use tomata_core::settings;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(json) = std::str::from_utf8(data) {
+        if let Ok(settings) = settings::parse_settings(json) {
+            assert!(settings.validate().is_empty());
+        }
+    }
+});
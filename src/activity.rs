@@ -0,0 +1,189 @@
+//! Sampling of the application the user works in, i.e., the one owning
+//! the focused window, so the dominant application of each work session
+//! can be recorded. Like with the idle time, every platform queries
+//! a different system tool.
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+pub trait ActiveWindowProbe: fmt::Debug {
+    /// Returns the name of the application owning the focused window,
+    /// `None` when it cannot be determined.
+    fn active_application(&self) -> Option<String>;
+
+    /// Tells whether the focused window covers the whole screen, e.g.,
+    /// a presentation, a game or a video, `false` when it cannot be determined.
+    fn is_active_window_fullscreen(&self) -> bool;
+
+    /// Tells why the fullscreen window cannot be detected, e.g., the tools
+    /// are not installed, `None` when it can.
+    fn find_fullscreen_detection_problem(&self) -> Option<String>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemActiveWindowProbe;
+
+impl ActiveWindowProbe for SystemActiveWindowProbe {
+    #[cfg(target_os = "linux")]
+    fn active_application(&self) -> Option<String> {
+        // Only X11 sessions are covered, Wayland does not let
+        // the applications see the windows of the others.
+        run_command("xdotool", &["getactivewindow", "getwindowclassname"])
+            .and_then(|output| parse_application_name(&output))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_active_window_fullscreen(&self) -> bool {
+        let window = match run_command("xdotool", &["getactivewindow"]) {
+            Some(window) => window,
+            None => return false,
+        };
+        run_command("xprop", &["-id", window.trim(), "_NET_WM_STATE"])
+            .is_some_and(|output| is_fullscreen_state(&output))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn find_fullscreen_detection_problem(&self) -> Option<String> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Some("not available on Wayland".to_string());
+        }
+        ["xdotool", "xprop"]
+            .iter()
+            .find(|program| !is_installed(program))
+            .map(|program| format!("{} is not installed", program))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn active_application(&self) -> Option<String> {
+        run_command(
+            "osascript",
+            &[
+                "-e",
+                "tell application \"System Events\" to get name of first \
+                 application process whose frontmost is true",
+            ],
+        )
+        .and_then(|output| parse_application_name(&output))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn active_application(&self) -> Option<String> {
+        None
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn is_active_window_fullscreen(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn find_fullscreen_detection_problem(&self) -> Option<String> {
+        Some("only available on Linux with X11".to_string())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Only the failure to start the program tells that it is missing,
+/// the exit status of the help does not matter.
+#[cfg(target_os = "linux")]
+fn is_installed(program: &str) -> bool {
+    Command::new(program).arg("-help").output().map_or_else(
+        |error| error.kind() != std::io::ErrorKind::NotFound,
+        |_| true,
+    )
+}
+
+/// Both tools print the name followed by a line break, e.g., `firefox\n`.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn parse_application_name(output: &str) -> Option<String> {
+    match output.trim() {
+        "" => None,
+        name => Some(name.to_string()),
+    }
+}
+
+/// `xprop` lists the states of the window, e.g.,
+/// `_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN, _NET_WM_STATE_FOCUSED`.
+#[cfg(any(target_os = "linux", test))]
+fn is_fullscreen_state(output: &str) -> bool {
+    output.split_once('=').is_some_and(|(_, states)| {
+        states
+            .split(',')
+            .any(|state| state.trim() == "_NET_WM_STATE_FULLSCREEN")
+    })
+}
+
+/// Counts the samples of each application during a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApplicationSamples {
+    counts: Vec<(String, usize)>,
+}
+
+impl ApplicationSamples {
+    pub fn add(&mut self, application: &str) {
+        match self
+            .counts
+            .iter_mut()
+            .find(|(sampled, _)| sampled == application)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((application.to_string(), 1)),
+        }
+    }
+
+    /// Returns the most often sampled application, the earlier
+    /// sampled one wins a tie.
+    pub fn find_dominant(&self) -> Option<&str> {
+        self.counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(application, _)| application.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_application_name() {
+        assert_eq!(parse_application_name("Code\n"), Some("Code".to_string()));
+        assert_eq!(parse_application_name("\n"), None);
+    }
+
+    #[test]
+    fn finding_dominant_application() {
+        let mut samples = ApplicationSamples::default();
+        assert_eq!(samples.find_dominant(), None);
+        for application in ["firefox", "Code", "Code", "firefox"].iter() {
+            samples.add(application);
+        }
+        assert_eq!(samples.find_dominant(), Some("firefox"));
+        samples.add("Code");
+        assert_eq!(samples.find_dominant(), Some("Code"));
+    }
+
+    #[test]
+    fn parsing_fullscreen_state() {
+        assert!(is_fullscreen_state(
+            "_NET_WM_STATE(ATOM) = _NET_WM_STATE_FULLSCREEN, _NET_WM_STATE_FOCUSED\n"
+        ));
+        assert!(!is_fullscreen_state(
+            "_NET_WM_STATE(ATOM) = _NET_WM_STATE_MAXIMIZED_VERT\n"
+        ));
+        assert!(!is_fullscreen_state("_NET_WM_STATE:  not found.\n"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Background sounds looped during the work periods, masking the noise
+//! around the user. They are synthesized, so no recordings are shipped,
+//! and mixed into the stream of the sounds (see [`crate::sound`]).
+#[cfg(feature = "druid")]
+use druid::Data;
+use serde::{Deserialize, Serialize};
+
+/// How much of the previous brown noise sample is kept, the rest is
+/// the next step of the random walk.
+const BROWN_NOISE_LEAK: f32 = 1.02;
+/// The brown noise is much quieter than the white one, it is made
+/// about as loud.
+const BROWN_NOISE_GAIN: f32 = 3.5;
+/// How often a drop hits, per sample.
+const RAIN_DROP_CHANCE: f32 = 0.0005;
+/// How fast the sound of a drop fades, per sample.
+const RAIN_DROP_DECAY: f32 = 0.995;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum AmbientSound {
+    WhiteNoise,
+    BrownNoise,
+    Rain,
+}
+
+/// Produces the samples of the ambient sound one after another,
+/// between -1 and 1.
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    sound: AmbientSound,
+    seed: u32,
+    brown: f32,
+    rumble: f32,
+    drop: f32,
+}
+
+impl NoiseGenerator {
+    pub fn new(sound: AmbientSound) -> NoiseGenerator {
+        NoiseGenerator {
+            sound,
+            seed: 0x9E37_79B9,
+            brown: 0.0,
+            rumble: 0.0,
+            drop: 0.0,
+        }
+    }
+
+    /// Uniform between -1 and 1, by the xorshift generator, which is
+    /// enough for the noise.
+    fn next_white(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let white = self.next_white();
+        let sample = match self.sound {
+            AmbientSound::WhiteNoise => white,
+            AmbientSound::BrownNoise => {
+                self.brown = (self.brown + 0.02 * white) / BROWN_NOISE_LEAK;
+                self.brown * BROWN_NOISE_GAIN
+            }
+            AmbientSound::Rain => {
+                // The steady rumble of the rain with the drops hitting
+                // nearby now and then.
+                self.rumble += 0.1 * (white - self.rumble);
+                if (self.next_white() + 1.0) / 2.0 < RAIN_DROP_CHANCE {
+                    self.drop = 1.0;
+                }
+                self.drop *= RAIN_DROP_DECAY;
+                0.6 * self.rumble + 0.4 * self.drop * white
+            }
+        };
+        sample.clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generating_noise() {
+        // The average change between the samples tells how rough the noise is.
+        let roughness = |sound| {
+            let mut generator = NoiseGenerator::new(sound);
+            let samples: Vec<f32> = (0..10_000).map(|_| generator.next_sample()).collect();
+            assert!(samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+            assert!(samples.iter().any(|sample| *sample != 0.0));
+            let changes: f32 = samples
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).abs())
+                .sum();
+            changes / samples.len() as f32
+        };
+        let white = roughness(AmbientSound::WhiteNoise);
+        assert!(roughness(AmbientSound::BrownNoise) < white / 4.0);
+        assert!(roughness(AmbientSound::Rain) < white);
+    }
+}
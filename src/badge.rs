@@ -0,0 +1,88 @@
+//! Badge on the icon of the application in the Linux docks showing today's
+//! count of the completed pomodoros. It is announced with the launcher API
+//! of Unity, understood by KDE Plasma, Dash to Dock and Plank, through
+//! `gdbus`. The badge is not available on macOS and Windows, their dock
+//! tile and taskbar overlay icons can be set only through the native APIs
+//! from within the process, so the setting is not offered there.
+use std::fmt;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::process::Command;
+
+/// The desktop entry the docks match the badge with.
+#[cfg(target_os = "linux")]
+const DESKTOP_ENTRY: &str = "application://tomata.desktop";
+#[cfg(target_os = "linux")]
+const LAUNCHER_ENTRY_PATH: &str = "/io/github/khrynczenko/tomata";
+
+pub trait BadgeBackend: fmt::Debug {
+    /// Shows the count on the icon, `None` hides the badge.
+    fn set_count(&self, count: Option<usize>) -> io::Result<()>;
+}
+
+/// Whether the badge can be shown on this platform at all.
+pub fn is_supported() -> bool {
+    cfg!(target_os = "linux")
+}
+
+#[derive(Debug, Default)]
+pub struct SystemBadge;
+
+impl BadgeBackend for SystemBadge {
+    #[cfg(target_os = "linux")]
+    fn set_count(&self, count: Option<usize>) -> io::Result<()> {
+        let status = Command::new("gdbus")
+            .args([
+                "emit",
+                "--session",
+                "--object-path",
+                LAUNCHER_ENTRY_PATH,
+                "--signal",
+                "com.canonical.Unity.LauncherEntry.Update",
+                DESKTOP_ENTRY,
+                &make_launcher_properties(count),
+            ])
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("`gdbus` exited with {}", status),
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_count(&self, _count: Option<usize>) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the badge of the icon is only available on Linux",
+        ))
+    }
+}
+
+/// Makes the properties of the launcher entry in the GVariant text format.
+pub fn make_launcher_properties(count: Option<usize>) -> String {
+    format!(
+        "{{'count': <int64 {}>, 'count-visible': <{}>}}",
+        count.unwrap_or(0),
+        count.is_some()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_launcher_properties() {
+        assert_eq!(
+            make_launcher_properties(Some(3)),
+            "{'count': <int64 3>, 'count-visible': <true>}"
+        );
+        assert_eq!(
+            make_launcher_properties(None),
+            "{'count': <int64 0>, 'count-visible': <false>}"
+        );
+    }
+}
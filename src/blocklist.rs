@@ -0,0 +1,150 @@
+//! Blocks the distracting sites during work periods by pointing them to
+//! a non-routable address in the hosts file. The entries are kept in a
+//! marked section, so the rest of the file is never touched, and they
+//! are removed on breaks and when the application exits.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::events::{EventHook, TomataEvent};
+use crate::settings::Settings;
+use crate::tomata::Period;
+
+#[cfg(windows)]
+pub const HOSTS_FILE: &str = r"C:\Windows\System32\drivers\etc\hosts";
+#[cfg(not(windows))]
+pub const HOSTS_FILE: &str = "/etc/hosts";
+
+const SECTION_START: &str = "# tomata blocklist start";
+const SECTION_END: &str = "# tomata blocklist end";
+
+#[derive(Debug)]
+pub struct BlocklistHook {
+    hosts_file: PathBuf,
+    is_blocking: bool,
+}
+
+impl Default for BlocklistHook {
+    fn default() -> BlocklistHook {
+        BlocklistHook::new()
+    }
+}
+
+impl BlocklistHook {
+    pub fn new() -> BlocklistHook {
+        BlocklistHook {
+            hosts_file: PathBuf::from(HOSTS_FILE),
+            is_blocking: false,
+        }
+    }
+
+    fn update_hosts_file(&mut self, sites: &[&str]) -> io::Result<()> {
+        let hosts = fs::read_to_string(&self.hosts_file)?;
+        let mut updated_hosts = remove_blocklist(&hosts);
+        if !sites.is_empty() {
+            updated_hosts = add_blocklist(&updated_hosts, sites);
+        }
+        if updated_hosts != hosts {
+            fs::write(&self.hosts_file, updated_hosts)?;
+        }
+        self.is_blocking = !sites.is_empty();
+        Ok(())
+    }
+}
+
+impl EventHook for BlocklistHook {
+    fn get_name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    fn handle(&mut self, event: TomataEvent, settings: &Settings) -> io::Result<()> {
+        let period = match event {
+            TomataEvent::PeriodActivated(period) | TomataEvent::BlocklistToggled(period) => {
+                Some(period)
+            }
+            TomataEvent::AlertEscalated(_) => return Ok(()),
+            // The sites must not stay blocked once the application is gone.
+            TomataEvent::ApplicationQuitting => None,
+        };
+        let sites = if period == Some(Period::Work) && settings.is_blocklist_enabled() {
+            settings.get_blocked_sites()
+        } else {
+            Vec::new()
+        };
+        if sites.is_empty() && !self.is_blocking {
+            return Ok(());
+        }
+        self.update_hosts_file(&sites)
+    }
+}
+
+impl Drop for BlocklistHook {
+    fn drop(&mut self) {
+        if self.is_blocking {
+            if let Err(error) = self.update_hosts_file(&[]) {
+                eprintln!("Could not unblock the sites: {}", error);
+            }
+        }
+    }
+}
+
+fn add_blocklist(hosts: &str, sites: &[&str]) -> String {
+    let mut hosts = hosts.to_string();
+    if !hosts.is_empty() && !hosts.ends_with('\n') {
+        hosts.push('\n');
+    }
+    hosts.push_str(SECTION_START);
+    hosts.push('\n');
+    for site in sites {
+        let mut names = vec![site.to_string()];
+        if !site.starts_with("www.") {
+            names.push(format!("www.{}", site));
+        }
+        for name in names {
+            hosts.push_str(&format!("0.0.0.0 {}\n:: {}\n", name, name));
+        }
+    }
+    hosts.push_str(SECTION_END);
+    hosts.push('\n');
+    hosts
+}
+
+fn remove_blocklist(hosts: &str) -> String {
+    let mut is_in_section = false;
+    let mut result = String::with_capacity(hosts.len());
+    for line in hosts.split_inclusive('\n') {
+        match line.trim_end() {
+            SECTION_START => is_in_section = true,
+            SECTION_END => is_in_section = false,
+            _ if !is_in_section => result.push_str(line),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "127.0.0.1 localhost\n::1 localhost\n";
+
+    #[test]
+    fn adding_blocklist() {
+        assert_eq!(
+            add_blocklist(HOSTS, &["reddit.com"]),
+            format!(
+                "{}{}\n0.0.0.0 reddit.com\n:: reddit.com\n\
+                 0.0.0.0 www.reddit.com\n:: www.reddit.com\n{}\n",
+                HOSTS, SECTION_START, SECTION_END
+            )
+        );
+    }
+
+    #[test]
+    fn removing_blocklist_keeps_the_rest_of_the_file() {
+        let blocked = add_blocklist(HOSTS, &["reddit.com", "www.youtube.com"]);
+        assert_eq!(remove_blocklist(&blocked), HOSTS);
+        assert_eq!(remove_blocklist(HOSTS), HOSTS);
+    }
+}
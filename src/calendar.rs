@@ -0,0 +1,364 @@
+//! Integration with a calendar exported to an iCalendar (`.ics`) file.
+//! It is used to tell whether the user is in a meeting right now, i.e.,
+//! inside one of the busy blocks described by the calendar events.
+//!
+//! A calendar of holidays is read the same way, its all-day events mark
+//! the days off.
+//!
+//! Only the subset of the format needed to find the busy blocks is
+//! understood: recurring events are not expanded, all-day and
+//! transparent (free) events are not considered busy.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use time::{Date, PrimitiveDateTime, Time};
+
+use crate::timezone;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BusyBlock {
+    /// Seconds since the UNIX epoch.
+    pub start: u64,
+    /// Seconds since the UNIX epoch.
+    pub end: u64,
+}
+
+impl BusyBlock {
+    pub fn contains(&self, timestamp: u64) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+/// Keeps the busy blocks of the calendar file and re-reads them
+/// whenever the file is modified.
+#[derive(Debug, Default)]
+pub struct Calendar {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    busy_blocks: Vec<BusyBlock>,
+}
+
+impl Calendar {
+    /// Re-reads the calendar stored at `path` if it was modified, `None`
+    /// meaning there is no calendar. Returns the busy blocks only when
+    /// they changed since the last call.
+    pub fn reload(&mut self, path: Option<&Path>) -> Option<&[BusyBlock]> {
+        let had_busy_blocks = !self.busy_blocks.is_empty();
+        let path = match path {
+            Some(path) => path,
+            None => {
+                *self = Calendar::default();
+                return Some(self.busy_blocks.as_slice()).filter(|_| had_busy_blocks);
+            }
+        };
+        match self.refresh(path) {
+            Ok(false) => return None,
+            Ok(true) => {}
+            Err(error) => {
+                eprintln!("Could not read the calendar: {}", error);
+                self.busy_blocks.clear();
+                if !had_busy_blocks {
+                    return None;
+                }
+            }
+        }
+        Some(&self.busy_blocks)
+    }
+
+    /// Returns whether the file was read again.
+    fn refresh(&mut self, path: &Path) -> io::Result<bool> {
+        let modified = fs::metadata(path)?.modified()?;
+        if self.path == path && self.modified == Some(modified) {
+            return Ok(false);
+        }
+        self.busy_blocks = parse_busy_blocks(&fs::read_to_string(path)?);
+        self.path = path.to_path_buf();
+        self.modified = Some(modified);
+        Ok(true)
+    }
+}
+
+/// Keeps the days off, i.e., the all-day events, of the holiday calendar
+/// file and re-reads them whenever the file is modified.
+#[derive(Debug, Default)]
+pub struct HolidayCalendar {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    days_off: Vec<Date>,
+}
+
+impl HolidayCalendar {
+    /// Re-reads the holiday calendar stored at `path` if it was modified,
+    /// `None` meaning there is no calendar. Returns the days off only when
+    /// they changed since the last call.
+    pub fn reload(&mut self, path: Option<&Path>) -> Option<&[Date]> {
+        let had_days_off = !self.days_off.is_empty();
+        let path = match path {
+            Some(path) => path,
+            None => {
+                *self = HolidayCalendar::default();
+                return Some(self.days_off.as_slice()).filter(|_| had_days_off);
+            }
+        };
+        match self.refresh(path) {
+            Ok(false) => return None,
+            Ok(true) => {}
+            Err(error) => {
+                eprintln!("Could not read the holiday calendar: {}", error);
+                self.days_off.clear();
+                if !had_days_off {
+                    return None;
+                }
+            }
+        }
+        Some(&self.days_off)
+    }
+
+    /// Returns whether the file was read again.
+    fn refresh(&mut self, path: &Path) -> io::Result<bool> {
+        let modified = fs::metadata(path)?.modified()?;
+        if self.path == path && self.modified == Some(modified) {
+            return Ok(false);
+        }
+        self.days_off = parse_all_day_dates(&fs::read_to_string(path)?);
+        self.path = path.to_path_buf();
+        self.modified = Some(modified);
+        Ok(true)
+    }
+}
+
+pub fn find_busy_block_at(blocks: &[BusyBlock], timestamp: u64) -> Option<BusyBlock> {
+    blocks
+        .iter()
+        .filter(|block| block.contains(timestamp))
+        .max_by_key(|block| block.end)
+        .copied()
+}
+
+pub fn parse_busy_blocks(ics: &str) -> Vec<BusyBlock> {
+    let mut blocks = Vec::new();
+    let mut start = None;
+    let mut end = None;
+    let mut is_transparent = false;
+    for line in unfold_lines(ics) {
+        let (name, value) = match line.split_once(':') {
+            Some(property) => property,
+            None => continue,
+        };
+        let (name, parameters) = name.split_once(';').unwrap_or((name, ""));
+        match name {
+            "BEGIN" if value == "VEVENT" => {
+                start = None;
+                end = None;
+                is_transparent = false;
+            }
+            "DTSTART" => start = parse_date_time(value, parameters),
+            "DTEND" => end = parse_date_time(value, parameters),
+            "TRANSP" => is_transparent = value == "TRANSPARENT",
+            "END" if value == "VEVENT" => {
+                if let (Some(start), Some(end), false) = (start, end, is_transparent) {
+                    if start < end {
+                        blocks.push(BusyBlock { start, end });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Returns every day covered by the all-day events, the end date
+/// of an event is exclusive.
+pub fn parse_all_day_dates(ics: &str) -> Vec<Date> {
+    let mut dates = Vec::new();
+    let mut start = None;
+    let mut end = None;
+    for line in unfold_lines(ics) {
+        let (name, value) = match line.split_once(':') {
+            Some(property) => property,
+            None => continue,
+        };
+        let (name, parameters) = name.split_once(';').unwrap_or((name, ""));
+        let is_date = parameters.contains("VALUE=DATE") && !parameters.contains("VALUE=DATE-TIME");
+        match name {
+            "BEGIN" if value == "VEVENT" => {
+                start = None;
+                end = None;
+            }
+            "DTSTART" if is_date => start = parse_date(value),
+            "DTEND" if is_date => end = parse_date(value),
+            "END" if value == "VEVENT" => {
+                if let Some(start) = start {
+                    let end = end.unwrap_or_else(|| start.next_day());
+                    let mut date = start;
+                    while date < end {
+                        dates.push(date);
+                        date = date.next_day();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    dates
+}
+
+/// Long lines are folded by inserting a line break followed by a space or a tab.
+fn unfold_lines(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match (
+            line.strip_prefix(|c| c == ' ' || c == '\t'),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Parses `20240131` date values.
+fn parse_date(value: &str) -> Option<Date> {
+    if value.len() != 8 {
+        return None;
+    }
+    Date::try_from_ymd(
+        value[0..4].parse().ok()?,
+        value[4..6].parse().ok()?,
+        value[6..8].parse().ok()?,
+    )
+    .ok()
+}
+
+/// Parses `20240131T093000Z` (UTC) and `20240131T093000` (local time) values.
+/// Dates without the time, i.e., all-day events, are ignored.
+fn parse_date_time(value: &str, parameters: &str) -> Option<u64> {
+    if parameters.contains("VALUE=DATE") && !parameters.contains("VALUE=DATE-TIME") {
+        return None;
+    }
+    let (value, is_utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let (date, time) = value.split_once('T')?;
+    if time.len() != 6 {
+        return None;
+    }
+    let number = |text: &str| text.parse::<u8>().ok();
+    let date = parse_date(date)?;
+    let time = Time::try_from_hms(
+        number(&time[0..2])?,
+        number(&time[2..4])?,
+        number(&time[4..6])?,
+    )
+    .ok()?;
+    let date_time = PrimitiveDateTime::new(date, time);
+    let timestamp = if is_utc {
+        date_time.assume_utc().unix_timestamp()
+    } else {
+        // Times with a `TZID` parameter are treated as local as well,
+        // which holds for the calendars of the user in the vast majority of cases.
+        let offset = timezone::local_offset_at(date_time.assume_utc().unix_timestamp());
+        date_time.assume_offset(offset).unix_timestamp()
+    };
+    if timestamp < 0 {
+        return None;
+    }
+    Some(timestamp as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+SUMMARY:Standup\r
+DTSTART:20240131T093000Z\r
+DTEND:20240131T094500Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20240201\r
+DTEND;VALUE=DATE:20240202\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Focus time\r
+DTSTART:20240131T100000Z\r
+DTEND:20240131T120000Z\r
+TRANSP:TRANSPARENT\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:A very long meeting name that is folded\r
+  onto the next line\r
+DTSTART:20240131T13\r
+ 0000Z\r
+DTEND:20240131T140000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    // 2024-01-31T09:30:00Z
+    const STANDUP_START: u64 = 1_706_693_400;
+
+    #[test]
+    fn parsing_busy_blocks() {
+        let blocks = parse_busy_blocks(CALENDAR);
+        assert_eq!(
+            blocks,
+            vec![
+                BusyBlock {
+                    start: STANDUP_START,
+                    end: STANDUP_START + 15 * 60,
+                },
+                BusyBlock {
+                    start: STANDUP_START + 3 * 3600 + 30 * 60,
+                    end: STANDUP_START + 4 * 3600 + 30 * 60,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finding_busy_block() {
+        let blocks = parse_busy_blocks(CALENDAR);
+        assert_eq!(find_busy_block_at(&blocks, STANDUP_START - 1), None);
+        assert_eq!(find_busy_block_at(&blocks, STANDUP_START), Some(blocks[0]));
+        assert_eq!(find_busy_block_at(&blocks, STANDUP_START + 15 * 60), None);
+    }
+
+    #[test]
+    fn parsing_all_day_dates() {
+        let holidays = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Christmas\r
+DTSTART;VALUE=DATE:20241225\r
+DTEND;VALUE=DATE:20241227\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:New Year\r
+DTSTART;VALUE=DATE:20250101\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let date = |month, day| Date::try_from_ymd(2024, month, day).unwrap();
+        assert_eq!(
+            parse_all_day_dates(holidays),
+            vec![
+                date(12, 25),
+                date(12, 26),
+                Date::try_from_ymd(2025, 1, 1).unwrap()
+            ]
+        );
+        assert_eq!(
+            parse_all_day_dates(CALENDAR),
+            vec![Date::try_from_ymd(2024, 2, 1).unwrap()]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Drawing of the charts of the statistics, shared by the widgets and
+//! the images exported from them, e.g., for sharing in the retrospectives.
+//! The PNG images are rendered offscreen by the same code that paints
+//! the widget, the SVG ones are written from the same layout.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use druid::piet::{Device, FontFamily, ImageFormat, Text, TextLayoutBuilder};
+use druid::{Color, Rect, RenderContext, Size};
+
+use crate::history::{TimelineBlock, TimelineBlockKind};
+use crate::tomata;
+use crate::tomata::HOUR_S;
+
+pub const TIMELINE_BAR_HEIGHT: f64 = 20.0;
+pub const TIMELINE_HEIGHT: f64 = 40.0;
+const TIMELINE_TEXT_SIZE: f64 = 12.0;
+/// Size of the exported images, in the pixels of the widget.
+const EXPORT_SIZE: Size = Size::new(800.0, TIMELINE_HEIGHT);
+/// The exported bitmaps are rendered at twice the size, so they stay sharp.
+const EXPORT_SCALE: f64 = 2.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFileFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFileFormat {
+    /// Tells the format by the extension of `path`, `.png` or `.svg`.
+    pub fn from_path(path: &Path) -> Option<ImageFileFormat> {
+        match path.extension()?.to_str()? {
+            "png" => Some(ImageFileFormat::Png),
+            "svg" => Some(ImageFileFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the beginning and the end of the drawn part of the day, from
+/// the hour of the first block to the hour after the last one.
+fn find_timeline_range(blocks: &[TimelineBlock]) -> Option<(u64, u64)> {
+    let first = blocks.first()?.started_at;
+    let last = blocks.iter().map(|block| block.finished_at).max()?;
+    let start = first - tomata::time_of_local_day(first).as_secs() % HOUR_S;
+    Some((start, (last - start + HOUR_S - 1) / HOUR_S * HOUR_S + start))
+}
+
+/// Returns the index of the block drawn at `x` of the timeline `width` wide.
+pub fn find_timeline_block_at(blocks: &[TimelineBlock], x: f64, width: f64) -> Option<usize> {
+    let (start, end) = find_timeline_range(blocks)?;
+    let timestamp = start as f64 + x / width * (end - start) as f64;
+    blocks.iter().position(|block| {
+        (block.started_at as f64) <= timestamp && timestamp < block.finished_at as f64
+    })
+}
+
+fn get_timeline_color(kind: TimelineBlockKind) -> Color {
+    match kind {
+        TimelineBlockKind::Work => Color::rgb8(0xD0, 0x45, 0x3A),
+        TimelineBlockKind::Break => Color::rgb8(0x4C, 0xAF, 0x50),
+        TimelineBlockKind::Meeting => Color::rgb8(0x3F, 0x7F, 0xD0),
+        TimelineBlockKind::Custom => Color::rgb8(0x9C, 0x5F, 0xC0),
+        TimelineBlockKind::Pause => Color::grey8(0x80),
+    }
+}
+
+/// Returns the rectangles of the blocks scaled to `width`.
+fn layout_timeline(blocks: &[TimelineBlock], width: f64) -> Vec<(Rect, Color)> {
+    let (start, end) = match find_timeline_range(blocks) {
+        Some(range) => range,
+        None => return Vec::new(),
+    };
+    let scale = width / (end - start) as f64;
+    blocks
+        .iter()
+        .map(|block| {
+            let x0 = (block.started_at - start) as f64 * scale;
+            let x1 = (block.finished_at - start) as f64 * scale;
+            let rect = Rect::new(x0, 0.0, x1, TIMELINE_BAR_HEIGHT);
+            (rect, get_timeline_color(block.kind))
+        })
+        .collect()
+}
+
+/// Describes the block, or the drawn part of the day without one,
+/// e.g., `09:00–09:25 Work API refactor`.
+fn describe_timeline(blocks: &[TimelineBlock], hovered: Option<usize>) -> String {
+    match (
+        hovered.and_then(|index| blocks.get(index)),
+        find_timeline_range(blocks),
+    ) {
+        (Some(block), _) => format!(
+            "{}\u{2013}{} {:?} {}",
+            tomata::timestamp_to_local_time_string(block.started_at),
+            tomata::timestamp_to_local_time_string(block.finished_at),
+            block.kind,
+            block.tag.as_deref().unwrap_or_default(),
+        ),
+        (None, Some((start, end))) => format!(
+            "{}\u{2013}{}",
+            tomata::timestamp_to_local_time_string(start),
+            tomata::timestamp_to_local_time_string(end)
+        ),
+        (None, None) => "No sessions.".to_string(),
+    }
+}
+
+/// The colors and the font of the chart, taken from the environment
+/// of the widget or fixed for the exported images.
+#[derive(Debug, Clone)]
+pub struct ChartStyle {
+    pub background: Color,
+    pub bar_background: Color,
+    pub text_color: Color,
+    pub font: FontFamily,
+}
+
+impl Default for ChartStyle {
+    fn default() -> ChartStyle {
+        ChartStyle {
+            background: Color::WHITE,
+            bar_background: Color::grey8(0xE0),
+            text_color: Color::BLACK,
+            font: FontFamily::SYSTEM_UI,
+        }
+    }
+}
+
+/// Draws the timeline into `size`, the details of the hovered block
+/// are written below the bar.
+pub fn draw_timeline(
+    rc: &mut impl RenderContext,
+    size: Size,
+    blocks: &[TimelineBlock],
+    hovered: Option<usize>,
+    style: &ChartStyle,
+) {
+    rc.fill(size.to_rect(), &style.background);
+    rc.fill(
+        Rect::new(0.0, 0.0, size.width, TIMELINE_BAR_HEIGHT),
+        &style.bar_background,
+    );
+    for (rect, color) in layout_timeline(blocks, size.width) {
+        rc.fill(rect, &color);
+    }
+    let layout = rc
+        .text()
+        .new_text_layout(describe_timeline(blocks, hovered))
+        .font(style.font.clone(), TIMELINE_TEXT_SIZE)
+        .text_color(style.text_color.clone())
+        .build();
+    if let Ok(layout) = layout {
+        rc.draw_text(&layout, (0.0, TIMELINE_BAR_HEIGHT + 2.0));
+    }
+}
+
+/// Writes the timeline to the image at `path`, its extension tells the format.
+pub fn export_timeline(path: &Path, blocks: &[TimelineBlock]) -> Result<(), Box<dyn Error>> {
+    let image = match ImageFileFormat::from_path(path) {
+        Some(ImageFileFormat::Png) => render_timeline_png(blocks)?,
+        Some(ImageFileFormat::Svg) => make_timeline_svg(blocks).into_bytes(),
+        None => return Err("the image should be a .png or a .svg file".into()),
+    };
+    fs::write(path, image)?;
+    Ok(())
+}
+
+fn render_timeline_png(blocks: &[TimelineBlock]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let width = (EXPORT_SIZE.width * EXPORT_SCALE) as usize;
+    let height = (EXPORT_SIZE.height * EXPORT_SCALE) as usize;
+    let mut device = Device::new()?;
+    let mut target = device.bitmap_target(width, height, EXPORT_SCALE)?;
+    {
+        let mut rc = target.render_context();
+        draw_timeline(&mut rc, EXPORT_SIZE, blocks, None, &ChartStyle::default());
+        rc.finish()?;
+    }
+    let mut pixels = vec![0; width * height * 4];
+    // The background is opaque, so the premultiplied colors are the plain ones.
+    target.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)?;
+    Ok(encode_png(width as u32, height as u32, &pixels))
+}
+
+fn to_svg_color(color: &Color) -> String {
+    let (red, green, blue, _) = color.as_rgba8();
+    format!("#{:02X}{:02X}{:02X}", red, green, blue)
+}
+
+fn make_timeline_svg(blocks: &[TimelineBlock]) -> String {
+    let style = ChartStyle::default();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"{}\"/>\n\
+         <rect width=\"{w}\" height=\"{}\" fill=\"{}\"/>\n",
+        to_svg_color(&style.background),
+        TIMELINE_BAR_HEIGHT,
+        to_svg_color(&style.bar_background),
+        w = EXPORT_SIZE.width,
+        h = EXPORT_SIZE.height,
+    );
+    for (rect, color) in layout_timeline(blocks, EXPORT_SIZE.width) {
+        svg.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"0\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>\n",
+            rect.x0,
+            rect.width(),
+            TIMELINE_BAR_HEIGHT,
+            to_svg_color(&color)
+        ));
+    }
+    svg.push_str(&format!(
+        "<text x=\"0\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" fill=\"{}\">{}</text>\n</svg>\n",
+        TIMELINE_BAR_HEIGHT + 2.0 + TIMELINE_TEXT_SIZE,
+        TIMELINE_TEXT_SIZE,
+        to_svg_color(&style.text_color),
+        describe_timeline(blocks, None)
+    ));
+    svg
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn push_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes the RGBA pixels as a PNG image. There is no compression
+/// library among the dependencies, so the image data is stored in
+/// uncompressed deflate blocks, which is fine for a few charts.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_length = width as usize * 4;
+    let mut scanlines = Vec::with_capacity((row_length + 1) * height as usize);
+    for row in rgba.chunks(row_length) {
+        scanlines.push(0); // no filter
+        scanlines.extend_from_slice(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = scanlines.chunks(u16::MAX as usize).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push(u8::from(index + 1 == blocks.len()));
+        let length = block.len() as u16;
+        zlib.extend_from_slice(&length.to_le_bytes());
+        zlib.extend_from_slice(&(!length).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&scanlines).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, no filtering method, no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_png_chunk(&mut png, b"IHDR", &header);
+    push_png_chunk(&mut png, b"IDAT", &zlib);
+    push_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_png() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        let png = encode_png(2, 1, &[255, 0, 0, 255, 0, 0, 255, 255]);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01"));
+        assert!(png.ends_with(b"IEND\xAE\x42\x60\x82"));
+    }
+
+    #[test]
+    fn laying_out_timeline() {
+        let block = |kind, started_at, finished_at| TimelineBlock {
+            kind,
+            started_at,
+            finished_at,
+            tag: None,
+        };
+        let start = tomata::start_of_local_day(100 * tomata::DAY_S) + 9 * HOUR_S;
+        let blocks = vec![
+            block(TimelineBlockKind::Work, start, start + 1500),
+            block(TimelineBlockKind::Break, start + 1500, start + 1800),
+        ];
+        let layout = layout_timeline(&blocks, 720.0);
+        assert_eq!(layout[0].0, Rect::new(0.0, 0.0, 300.0, TIMELINE_BAR_HEIGHT));
+        assert_eq!(
+            layout[1].0,
+            Rect::new(300.0, 0.0, 360.0, TIMELINE_BAR_HEIGHT)
+        );
+        assert_eq!(find_timeline_block_at(&blocks, 310.0, 720.0), Some(1));
+        assert_eq!(find_timeline_block_at(&blocks, 400.0, 720.0), None);
+    }
+}
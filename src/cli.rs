@@ -0,0 +1,546 @@
+//! Command line interface of the application. Without any arguments
+//! the graphical application is launched, otherwise the requested
+//! subcommand is run and the process exits with its status code.
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::control;
+use crate::control::ControlCommand;
+use crate::eventlog;
+use crate::export;
+use crate::export::ReportFormat;
+use crate::history;
+use crate::profile;
+use crate::settings;
+use crate::tomata;
+
+pub const USAGE: &str = "\
+Usage:
+    tomata                        launch the application
+    tomata --headless             run the timer without the window, quit with `tomata quit`
+    tomata --big                  launch the application showing only the countdown,
+                                  filling the maximized window
+    tomata check-config [path]    validate a settings file (default: settings.json)
+    tomata export-profile <path>  write the settings to a profile bundle
+    tomata import-profile <path>  merge a profile bundle into the settings
+    tomata export-history [--anonymized] [--format csv|md|json] <path>
+                                  write a report of the history (.csv or .md),
+                                  or the whole of it (.json), without the tags,
+                                  the issues and the notes when anonymized,
+                                  `export` for short
+    tomata export --events [--since YYYY-MM-DD] [--format jsonl] [path]
+                                  write the logged events as JSON lines,
+                                  to the standard output without the path
+    tomata export --events --schema
+                                  describe the lines of the exported events
+    tomata toggle-pause           pause or resume the running application
+    tomata skip-period            skip to the next period in the running application
+    tomata pause | resume         pause or resume the running application
+    tomata show-window | quit     show or quit the running application
+    tomata <tomata://uri>         pass the URI, e.g., tomata://start?duration=25m&tag=PROJ-1,
+                                  to the running application or launch it with the URI
+    tomata register-uri-handler   open the tomata:// URIs with the data of this directory
+
+Exit codes of `check-config`:
+    0    the settings file is valid
+    1    the settings file contains errors
+    2    the settings file could not be read or the arguments are wrong
+
+The exit codes of `export-profile` and `import-profile` are the same,
+with the bundle taking the place of the settings file when importing.
+`export-history`, `export --events`, the commands of the running application, the URIs
+and `register-uri-handler` exit with 0 on success and 2 otherwise.";
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_INVALID_SETTINGS: i32 = 1;
+pub const EXIT_FAILURE: i32 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliCommand {
+    LaunchApplication,
+    LaunchHeadless,
+    /// Launches the application with the debug window, left out of the usage.
+    LaunchDebug,
+    /// The application with the big display of the countdown.
+    LaunchBig,
+    CheckConfig(PathBuf),
+    ExportProfile(PathBuf),
+    ImportProfile(PathBuf),
+    ExportHistory {
+        path: PathBuf,
+        /// Told by the extension of the path when not given.
+        format: Option<ReportFormat>,
+        is_anonymized: bool,
+    },
+    ExportEvents {
+        /// The standard output when not given.
+        path: Option<PathBuf>,
+        /// The timestamp of the local midnight the events are exported from.
+        since: u64,
+    },
+    DescribeEventSchema,
+    Control(ControlCommand),
+    OpenUri(ControlCommand),
+    RegisterUriHandler,
+}
+
+/// Parses the command line arguments, excluding the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliCommand, String> {
+    let mut args = args.into_iter();
+    let command = match args.next() {
+        None => return Ok(CliCommand::LaunchApplication),
+        Some(command) => command,
+    };
+    if control::is_uri(&command) {
+        let uri =
+            control::parse_uri(&command).ok_or_else(|| format!("Invalid URI `{}`.", command))?;
+        if let Some(argument) = args.next() {
+            return Err(format!("Unexpected argument `{}`.", argument));
+        }
+        return Ok(CliCommand::OpenUri(uri));
+    }
+    match command.as_str() {
+        "--headless" => match args.next() {
+            Some(argument) => Err(format!("Unexpected argument `{}`.", argument)),
+            None => Ok(CliCommand::LaunchHeadless),
+        },
+        "--debug" => match args.next() {
+            Some(argument) => Err(format!("Unexpected argument `{}`.", argument)),
+            None => Ok(CliCommand::LaunchDebug),
+        },
+        "--big" => match args.next() {
+            Some(argument) => Err(format!("Unexpected argument `{}`.", argument)),
+            None => Ok(CliCommand::LaunchBig),
+        },
+        "register-uri-handler" => match args.next() {
+            Some(argument) => Err(format!("Unexpected argument `{}`.", argument)),
+            None => Ok(CliCommand::RegisterUriHandler),
+        },
+        "check-config" => {
+            let path = args
+                .next()
+                .map_or_else(|| PathBuf::from(settings::SETTINGS_FILE), PathBuf::from);
+            if let Some(argument) = args.next() {
+                return Err(format!("Unexpected argument `{}`.", argument));
+            }
+            Ok(CliCommand::CheckConfig(path))
+        }
+        "export-profile" | "import-profile" => {
+            let path = args
+                .next()
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("`{}` needs the path of the bundle.", command))?;
+            if let Some(argument) = args.next() {
+                return Err(format!("Unexpected argument `{}`.", argument));
+            }
+            match command.as_str() {
+                "export-profile" => Ok(CliCommand::ExportProfile(path)),
+                _ => Ok(CliCommand::ImportProfile(path)),
+            }
+        }
+        "export-history" | "export" => {
+            let mut path = None;
+            let mut format_name = None;
+            let mut is_anonymized = false;
+            let mut is_events = false;
+            let mut since = None;
+            let mut is_schema = false;
+            while let Some(argument) = args.next() {
+                match argument.as_str() {
+                    "--anonymized" => is_anonymized = true,
+                    "--events" => is_events = true,
+                    "--schema" => is_schema = true,
+                    "--format" => {
+                        format_name = Some(args.next().ok_or_else(|| {
+                            "`--format` needs the name of the format.".to_string()
+                        })?);
+                    }
+                    "--since" => {
+                        let date = args
+                            .next()
+                            .ok_or_else(|| "`--since` needs a date.".to_string())?;
+                        let date = tomata::parse_date(&date).ok_or_else(|| {
+                            format!("Invalid date `{}`, expected YYYY-MM-DD.", date)
+                        })?;
+                        since = Some(tomata::start_of_local_date(date));
+                    }
+                    _ if path.is_none() => path = Some(PathBuf::from(argument)),
+                    _ => return Err(format!("Unexpected argument `{}`.", argument)),
+                }
+            }
+            if is_events {
+                if is_anonymized {
+                    return Err("The events cannot be anonymized.".to_string());
+                }
+                if let Some(name) = format_name.filter(|name| name != "jsonl") {
+                    return Err(format!(
+                        "The events are exported as `jsonl`, not `{}`.",
+                        name
+                    ));
+                }
+                if is_schema {
+                    return match path.or_else(|| since.map(|_| PathBuf::new())) {
+                        Some(_) => Err("`--schema` takes no other arguments.".to_string()),
+                        None => Ok(CliCommand::DescribeEventSchema),
+                    };
+                }
+                return Ok(CliCommand::ExportEvents {
+                    path,
+                    since: since.unwrap_or(0),
+                });
+            }
+            if since.is_some() || is_schema {
+                return Err("`--since` and `--schema` need `--events`.".to_string());
+            }
+            let format = match format_name {
+                Some(name) => Some(
+                    ReportFormat::from_name(&name)
+                        .ok_or_else(|| format!("Unknown format `{}`.", name))?,
+                ),
+                None => None,
+            };
+            let path =
+                path.ok_or_else(|| format!("`{}` needs the path of the report.", command))?;
+            Ok(CliCommand::ExportHistory {
+                path,
+                format,
+                is_anonymized,
+            })
+        }
+        name => match ControlCommand::from_name(name) {
+            Some(command) => {
+                if let Some(argument) = args.next() {
+                    return Err(format!("Unexpected argument `{}`.", argument));
+                }
+                Ok(CliCommand::Control(command))
+            }
+            None => Err(format!("Unknown command `{}`.", command)),
+        },
+    }
+}
+
+/// Prints every problem found in the settings file and returns
+/// the exit code of the `check-config` subcommand.
+pub fn check_config(path: &Path) -> i32 {
+    match settings::check_settings_file(path) {
+        Ok(_) => {
+            println!("{}: OK", path.display());
+            EXIT_SUCCESS
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            if errors
+                .iter()
+                .any(|error| matches!(error, settings::SettingsError::Io(_)))
+            {
+                EXIT_FAILURE
+            } else {
+                EXIT_INVALID_SETTINGS
+            }
+        }
+    }
+}
+
+/// Writes the current settings to the profile bundle at `path`.
+pub fn export_profile(path: &Path) -> i32 {
+    let settings = settings::load_settings_from_file(settings::SETTINGS_FILE).unwrap_or_default();
+    match profile::export_bundle_to_file(&settings, path) {
+        Ok(()) => {
+            println!("{}: exported", path.display());
+            EXIT_SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Merges the profile bundle at `path` into the current settings,
+/// which are left unchanged when the bundle contains any errors.
+pub fn import_profile(path: &Path) -> i32 {
+    let settings = settings::load_settings_from_file(settings::SETTINGS_FILE).unwrap_or_default();
+    match profile::import_bundle_from_file(&settings, path) {
+        Ok(imported) => {
+            if let Err(error) = settings::save_settings_to_file(&imported, settings::SETTINGS_FILE)
+            {
+                eprintln!("{}: {}", settings::SETTINGS_FILE, error);
+                return EXIT_FAILURE;
+            }
+            println!("{}: imported", path.display());
+            EXIT_SUCCESS
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            if errors
+                .iter()
+                .any(|error| matches!(error, settings::SettingsError::Io(_)))
+            {
+                EXIT_FAILURE
+            } else {
+                EXIT_INVALID_SETTINGS
+            }
+        }
+    }
+}
+
+/// Writes the report of the whole history, the format is told by the extension
+/// of `path` unless it is given.
+pub fn export_history(path: &Path, format: Option<ReportFormat>, is_anonymized: bool) -> i32 {
+    let format = match format.or_else(|| ReportFormat::from_path(path)) {
+        Some(format) => format,
+        None => {
+            eprintln!(
+                "{}: expected a `.csv`, `.md` or `.json` file or `--format`",
+                path.display()
+            );
+            return EXIT_FAILURE;
+        }
+    };
+    let settings = settings::load_settings_from_file(settings::SETTINGS_FILE).unwrap_or_default();
+    let result = history::load_records_since(history::HISTORY_FILE, 0)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+        .map(|records| {
+            if is_anonymized {
+                export::anonymize_records(records)
+            } else {
+                records
+            }
+        })
+        .and_then(|records| export::export_report(path, format, &records, &settings));
+    match result {
+        Ok(()) => {
+            println!("{}: exported", path.display());
+            EXIT_SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Writes the events logged since the timestamp to `path`,
+/// or to the standard output.
+pub fn export_events(path: Option<&Path>, since: u64) -> i32 {
+    let events = match eventlog::load_events_since(eventlog::EVENT_LOG_FILE, since) {
+        Ok(events) => events,
+        Err(error) => {
+            eprintln!("{}: {}", eventlog::EVENT_LOG_FILE, error);
+            return EXIT_FAILURE;
+        }
+    };
+    let result = match path {
+        Some(path) => File::create(path)
+            .and_then(|file| eventlog::write_events(BufWriter::new(file), &events)),
+        None => eventlog::write_events(io::stdout().lock(), &events),
+    };
+    match (result, path) {
+        (Ok(()), Some(path)) => {
+            println!("{}: exported", path.display());
+            EXIT_SUCCESS
+        }
+        (Ok(()), None) => EXIT_SUCCESS,
+        (Err(error), path) => {
+            let path = path.map_or_else(|| "stdout".to_string(), |path| path.display().to_string());
+            eprintln!("{}: {}", path, error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Passes the command to the running application.
+pub fn send_control_command(command: &ControlCommand) -> i32 {
+    match control::send_command(control::CONTROL_DIRECTORY, command) {
+        Ok(()) => EXIT_SUCCESS,
+        Err(error) => {
+            eprintln!("{}: {}", control::CONTROL_DIRECTORY, error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Passes the command of the URI to the running application, returns
+/// whether the application has to be launched to carry it out.
+pub fn open_uri(command: &ControlCommand) -> Result<bool, i32> {
+    match send_control_command(command) {
+        EXIT_SUCCESS => Ok(!control::is_instance_running(
+            control::INSTANCE_FILE,
+            tomata::now_timestamp(),
+        )),
+        code => Err(code),
+    }
+}
+
+pub fn register_uri_handler() -> i32 {
+    match control::register_uri_handler() {
+        Ok(()) => {
+            println!("{}:// URIs: registered", control::URI_SCHEME);
+            EXIT_SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Could not register the URI handler: {}", error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parsing_no_arguments() {
+        assert_eq!(parse(&[]), Ok(CliCommand::LaunchApplication));
+    }
+
+    #[test]
+    fn parsing_check_config() {
+        assert_eq!(
+            parse(&["check-config"]),
+            Ok(CliCommand::CheckConfig(PathBuf::from(
+                settings::SETTINGS_FILE
+            )))
+        );
+        assert_eq!(
+            parse(&["check-config", "dotfiles/tomata.json"]),
+            Ok(CliCommand::CheckConfig(PathBuf::from(
+                "dotfiles/tomata.json"
+            )))
+        );
+        assert!(parse(&["check-config", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn parsing_profile_commands() {
+        assert_eq!(
+            parse(&["export-profile", "dotfiles/tomata.bundle.json"]),
+            Ok(CliCommand::ExportProfile(PathBuf::from(
+                "dotfiles/tomata.bundle.json"
+            )))
+        );
+        assert_eq!(
+            parse(&["import-profile", "tomata.bundle.json"]),
+            Ok(CliCommand::ImportProfile(PathBuf::from(
+                "tomata.bundle.json"
+            )))
+        );
+        assert!(parse(&["import-profile"]).is_err());
+        assert_eq!(
+            parse(&["export-history", "worklog.md"]),
+            Ok(CliCommand::ExportHistory {
+                path: PathBuf::from("worklog.md"),
+                format: None,
+                is_anonymized: false,
+            })
+        );
+        assert_eq!(
+            parse(&["export-history", "--anonymized", "stats.csv"]),
+            Ok(CliCommand::ExportHistory {
+                path: PathBuf::from("stats.csv"),
+                format: None,
+                is_anonymized: true,
+            })
+        );
+        assert_eq!(
+            parse(&["export", "dump.txt", "--format", "json"]),
+            Ok(CliCommand::ExportHistory {
+                path: PathBuf::from("dump.txt"),
+                format: Some(ReportFormat::Json),
+                is_anonymized: false,
+            })
+        );
+        assert!(parse(&["export", "--format", "xlsx", "stats.xlsx"]).is_err());
+        assert!(parse(&["export", "--format"]).is_err());
+        assert!(parse(&["export", "a.csv", "b.csv"]).is_err());
+        assert!(parse(&["export-profile", "--anonymized", "a.json"]).is_err());
+    }
+
+    #[test]
+    fn parsing_event_export() {
+        assert_eq!(
+            parse(&[
+                "export",
+                "--events",
+                "--since",
+                "2024-01-01",
+                "--format",
+                "jsonl"
+            ]),
+            Ok(CliCommand::ExportEvents {
+                path: None,
+                since: tomata::start_of_local_date(tomata::parse_date("2024-01-01").unwrap()),
+            })
+        );
+        assert_eq!(
+            parse(&["export", "--events", "events.jsonl"]),
+            Ok(CliCommand::ExportEvents {
+                path: Some(PathBuf::from("events.jsonl")),
+                since: 0,
+            })
+        );
+        assert_eq!(
+            parse(&["export", "--events", "--schema"]),
+            Ok(CliCommand::DescribeEventSchema)
+        );
+        assert!(parse(&["export", "--events", "--format", "csv"]).is_err());
+        assert!(parse(&["export", "--events", "--since", "yesterday"]).is_err());
+        assert!(parse(&["export", "--events", "--anonymized"]).is_err());
+        assert!(parse(&["export", "--since", "2024-01-01", "a.csv"]).is_err());
+        assert!(parse(&["export", "--events", "--schema", "a.jsonl"]).is_err());
+    }
+
+    #[test]
+    fn parsing_control_commands() {
+        assert_eq!(
+            parse(&["toggle-pause"]),
+            Ok(CliCommand::Control(ControlCommand::TogglePause))
+        );
+        assert_eq!(
+            parse(&["skip-period"]),
+            Ok(CliCommand::Control(ControlCommand::SkipPeriod))
+        );
+        assert_eq!(
+            parse(&["show-window"]),
+            Ok(CliCommand::Control(ControlCommand::ShowWindow))
+        );
+        assert!(parse(&["skip-period", "now"]).is_err());
+    }
+
+    #[test]
+    fn parsing_uris() {
+        assert_eq!(
+            parse(&["tomata://start?tag=PROJ-1"]),
+            Ok(CliCommand::OpenUri(ControlCommand::Start {
+                duration: None,
+                tag: Some("PROJ-1".to_string()),
+            }))
+        );
+        assert!(parse(&["tomata://frobnicate"]).is_err());
+        assert_eq!(
+            parse(&["register-uri-handler"]),
+            Ok(CliCommand::RegisterUriHandler)
+        );
+        assert_eq!(parse(&["--headless"]), Ok(CliCommand::LaunchHeadless));
+        assert!(parse(&["--headless", "now"]).is_err());
+        assert_eq!(parse(&["--debug"]), Ok(CliCommand::LaunchDebug));
+        assert_eq!(parse(&["--big"]), Ok(CliCommand::LaunchBig));
+        assert!(parse(&["--big", "now"]).is_err());
+        assert!(parse(&["--debug", "now"]).is_err());
+    }
+
+    #[test]
+    fn parsing_unknown_command() {
+        assert!(parse(&["frobnicate"]).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+//! Noticing the jumps of the wall clock, e.g., the NTP correcting a laptop
+//! whose real-time clock drifted or was reset, by comparing its progress
+//! with the monotonic clock. Only the backward jumps are reported, the
+//! monotonic clock stops while the computer sleeps, so a forward jump
+//! cannot be told apart from waking up.
+use std::time::Instant;
+
+/// Differences smaller than this are the jitter of the ticks.
+const CLOCK_JUMP_TOLERANCE_S: u64 = 5;
+
+#[derive(Debug, Default)]
+pub struct ClockMonitor {
+    last_check: Option<(Instant, u64)>,
+}
+
+impl ClockMonitor {
+    /// Returns by how many seconds the wall clock, read as `timestamp` at
+    /// `instant`, went back since the previous check.
+    pub fn check(&mut self, instant: Instant, timestamp: u64) -> Option<u64> {
+        let last_check = self.last_check.replace((instant, timestamp));
+        let (last_instant, last_timestamp) = last_check?;
+        let expected = last_timestamp + instant.duration_since(last_instant).as_secs();
+        let jump = expected.saturating_sub(timestamp);
+        if jump > CLOCK_JUMP_TOLERANCE_S {
+            Some(jump)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn detecting_backward_clock_jumps() {
+        let mut monitor = ClockMonitor::default();
+        let start = Instant::now();
+        assert_eq!(monitor.check(start, 10_000), None);
+        let later = start + Duration::from_secs(60);
+        assert_eq!(monitor.check(later, 10_061), None);
+        assert_eq!(
+            monitor.check(later + Duration::from_secs(1), 6_462),
+            Some(3600)
+        );
+        assert_eq!(monitor.check(later + Duration::from_secs(2), 20_000), None);
+    }
+}
@@ -0,0 +1,448 @@
+//! Controlling the running application from scripts and window manager
+//! keybindings, e.g., `tomata toggle-pause`. Every subcommand writes a file
+//! of its own to the control directory next to the settings, which the
+//! application reads and empties every second, so no server has to be set up.
+//!
+//! Starting a session and showing the window also come as URIs, e.g.,
+//! `tomata://start?duration=25m&tag=PROJ-1`, from the browser links and
+//! the task managers once the application is registered as the handler
+//! of the `tomata` scheme.
+use std::env;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::tomata;
+
+pub const CONTROL_DIRECTORY: &str = "control.d";
+/// Holds the time the running application was last seen alive.
+pub const INSTANCE_FILE: &str = "instance";
+pub const URI_SCHEME: &str = "tomata";
+
+/// How often the running application refreshes the instance file.
+pub const INSTANCE_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+/// Longer than the refresh interval, so a late refresh is not taken for quitting.
+const INSTANCE_TIMEOUT_S: u64 = 45;
+
+/// Tells apart the commands sent by the process within the same nanosecond.
+static SENT_COMMANDS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    TogglePause,
+    Pause,
+    Resume,
+    SkipPeriod,
+    /// Postpones the period, it starts in a few minutes.
+    Snooze,
+    /// Brings the main window to the front.
+    ShowWindow,
+    Quit,
+    /// Starts a work session, the configured duration is used without one.
+    Start {
+        duration: Option<Duration>,
+        tag: Option<String>,
+    },
+}
+
+impl ControlCommand {
+    /// The name of the subcommand sending the command.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControlCommand::TogglePause => "toggle-pause",
+            ControlCommand::Pause => "pause",
+            ControlCommand::Resume => "resume",
+            ControlCommand::SkipPeriod => "skip-period",
+            ControlCommand::Snooze => "snooze",
+            ControlCommand::ShowWindow => "show-window",
+            ControlCommand::Quit => "quit",
+            ControlCommand::Start { .. } => "start",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ControlCommand> {
+        [
+            ControlCommand::TogglePause,
+            ControlCommand::Pause,
+            ControlCommand::Resume,
+            ControlCommand::SkipPeriod,
+            ControlCommand::Snooze,
+            ControlCommand::ShowWindow,
+            ControlCommand::Quit,
+        ]
+        .iter()
+        .find(|command| command.name() == name)
+        .cloned()
+    }
+
+    /// Parses the command written as the rest of its URI, e.g.,
+    /// `start?duration=25m&tag=PROJ-1`.
+    fn from_line(line: &str) -> Option<ControlCommand> {
+        let (name, query) = match line.find('?') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => (line, ""),
+        };
+        let name = name.trim_end_matches('/');
+        let parameters: Vec<(String, String)> = query
+            .split('&')
+            .filter(|parameter| !parameter.is_empty())
+            .map(|parameter| match parameter.find('=') {
+                Some(index) => (
+                    decode_component(&parameter[..index]),
+                    decode_component(&parameter[index + 1..]),
+                ),
+                None => (decode_component(parameter), String::new()),
+            })
+            .collect();
+        let find_parameter = |key: &str| {
+            parameters
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        if name != "start" {
+            return ControlCommand::from_name(name);
+        }
+        let duration = match find_parameter("duration") {
+            Some(duration) => Some(tomata::parse_duration(duration)?),
+            None => None,
+        };
+        Some(ControlCommand::Start {
+            duration,
+            tag: find_parameter("tag").map(str::to_string),
+        })
+    }
+
+    /// Any web page can open a URI, it must not be able to, e.g., quit
+    /// the application or skip the period of the user.
+    fn is_allowed_in_uri(&self) -> bool {
+        matches!(
+            self,
+            ControlCommand::Start { .. } | ControlCommand::ShowWindow
+        )
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            ControlCommand::Start { duration, tag } => {
+                let mut parameters = Vec::new();
+                if let Some(duration) = duration {
+                    parameters.push(format!("duration={}s", duration.as_secs()));
+                }
+                if let Some(tag) = tag {
+                    parameters.push(format!("tag={}", encode_component(tag)));
+                }
+                format!("{}?{}", self.name(), parameters.join("&"))
+            }
+            _ => self.name().to_string(),
+        }
+    }
+}
+
+/// Parses `uri`, e.g., `tomata://start?duration=25m&tag=PROJ-1`
+/// or `tomata://show-window`, the other commands are not allowed.
+pub fn parse_uri(uri: &str) -> Option<ControlCommand> {
+    let uri = uri.trim();
+    let separator = uri.find("://")?;
+    if !uri[..separator].eq_ignore_ascii_case(URI_SCHEME) {
+        return None;
+    }
+    ControlCommand::from_line(&uri[separator + 3..]).filter(ControlCommand::is_allowed_in_uri)
+}
+
+pub fn is_uri(argument: &str) -> bool {
+    argument
+        .get(..URI_SCHEME.len() + 3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}://", URI_SCHEME)))
+}
+
+fn decode_component(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = component
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn encode_component(component: &str) -> String {
+    component
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Writes the command to a file of its own in `directory`, it is given
+/// its name only once it is written, so it is never read half written.
+/// The names sort in the order the commands were sent.
+pub fn send_command(directory: impl AsRef<Path>, command: &ControlCommand) -> io::Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    let sent_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!(
+        "{:020}-{:010}-{}",
+        sent_at.as_nanos(),
+        process::id(),
+        SENT_COMMANDS.fetch_add(1, Ordering::Relaxed)
+    );
+    // The names starting with a dot are not taken.
+    let written = directory.join(format!(".{}", name));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&written)?;
+    writeln!(file, "{}", command.to_line())?;
+    drop(file);
+    fs::rename(written, directory.join(name))
+}
+
+/// Makes the shell command running the subcommand of `executable`, e.g.,
+/// for the menus and the keybindings, the path might contain spaces.
+pub fn make_shell_command(executable: &Path, command: &ControlCommand) -> String {
+    format!(
+        "'{}' {}",
+        executable.display().to_string().replace('\'', "'\\''"),
+        command.name()
+    )
+}
+
+/// Returns the commands sent to `directory` since the last call, the oldest
+/// first. Each file is removed only after it is read, the commands sent
+/// meanwhile are new files, so none is removed unread.
+pub fn take_commands(directory: impl AsRef<Path>) -> io::Result<Vec<ControlCommand>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with('.') {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    let mut commands = Vec::new();
+    for path in paths {
+        match fs::read_to_string(&path).and_then(|text| fs::remove_file(&path).map(|()| text)) {
+            Ok(text) => commands.extend(parse_commands(&text)),
+            Err(error) if commands.is_empty() => return Err(error),
+            // The failed file and the ones after it are taken the next time.
+            Err(_) => break,
+        }
+    }
+    Ok(commands)
+}
+
+fn parse_commands(commands: &str) -> Vec<ControlCommand> {
+    commands
+        .lines()
+        .filter_map(|line| ControlCommand::from_line(line.trim()))
+        .collect()
+}
+
+/// Records that the application is running at `timestamp`, to be repeated
+/// every [`INSTANCE_REFRESH_INTERVAL`].
+pub fn mark_instance_alive(path: impl AsRef<Path>, timestamp: u64) -> io::Result<()> {
+    fs::write(path, timestamp.to_string())
+}
+
+/// Tells whether an application marked itself alive shortly before `now`,
+/// so the sent commands are read.
+pub fn is_instance_running(path: impl AsRef<Path>, now: u64) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|timestamp| timestamp.trim().parse::<u64>().ok())
+        .is_some_and(|timestamp| now.saturating_sub(timestamp) <= INSTANCE_TIMEOUT_S)
+}
+
+/// Makes the application the handler of the `tomata://` URIs of the user,
+/// using the data in the current working directory.
+pub fn register_uri_handler() -> io::Result<()> {
+    let executable = env::current_exe()?;
+    let directory = env::current_dir()?;
+    if cfg!(windows) {
+        register_windows_uri_handler(&executable, &directory)
+    } else if cfg!(target_os = "macos") {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the URI schemes are registered by the application bundle on macOS",
+        ))
+    } else {
+        register_desktop_uri_handler(&executable, &directory)
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("`{:?}` exited with {}", command, status),
+        ));
+    }
+    Ok(())
+}
+
+fn register_desktop_uri_handler(executable: &Path, directory: &Path) -> io::Result<()> {
+    let applications = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?
+        .join("applications");
+    fs::create_dir_all(&applications)?;
+    let desktop_file = format!("{}-uri.desktop", tomata::APPLICATION_NAME);
+    fs::write(
+        applications.join(&desktop_file),
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Exec=\"{}\" %u\n\
+             Path={}\n\
+             NoDisplay=true\n\
+             MimeType=x-scheme-handler/{};\n",
+            tomata::APPLICATION_NAME,
+            executable.display(),
+            directory.display(),
+            URI_SCHEME
+        ),
+    )?;
+    run(Command::new("xdg-mime").args([
+        "default",
+        &desktop_file,
+        &format!("x-scheme-handler/{}", URI_SCHEME),
+    ]))
+}
+
+fn register_windows_uri_handler(executable: &Path, directory: &Path) -> io::Result<()> {
+    let key = format!("HKCU\\Software\\Classes\\{}", URI_SCHEME);
+    // The handler is started in an arbitrary directory, the data is elsewhere.
+    let handler = format!(
+        "cmd.exe /c cd /d \"{}\" && \"{}\" \"%1\"",
+        directory.display(),
+        executable.display()
+    );
+    let add = |key: &str, name: Option<&str>, value: &str| {
+        let mut command = Command::new("reg");
+        command.args(["add", key]);
+        match name {
+            Some(name) => command.args(["/v", name]),
+            None => command.arg("/ve"),
+        };
+        run(command.args(["/d", value, "/f"]))
+    };
+    add(&key, None, &format!("URL:{}", tomata::APPLICATION_NAME))?;
+    add(&key, Some("URL Protocol"), "")?;
+    add(&format!("{}\\shell\\open\\command", key), None, &handler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_commands() {
+        assert_eq!(
+            parse_commands("toggle-pause\nfrobnicate\n skip-period \n"),
+            vec![ControlCommand::TogglePause, ControlCommand::SkipPeriod]
+        );
+    }
+
+    #[test]
+    fn sending_and_taking_commands() {
+        let path = std::env::temp_dir().join(format!("tomata-control-{}", std::process::id()));
+        assert!(take_commands(&path).unwrap().is_empty());
+        let start = ControlCommand::Start {
+            duration: Some(Duration::from_secs(90)),
+            tag: Some("API & docs".to_string()),
+        };
+        send_command(&path, &ControlCommand::SkipPeriod).unwrap();
+        send_command(&path, &start).unwrap();
+        send_command(&path, &ControlCommand::TogglePause).unwrap();
+        assert_eq!(
+            take_commands(&path).unwrap(),
+            vec![
+                ControlCommand::SkipPeriod,
+                start,
+                ControlCommand::TogglePause
+            ]
+        );
+        assert!(take_commands(&path).unwrap().is_empty());
+        // A command being written is left for the next time.
+        fs::write(path.join(".written"), "quit\n").unwrap();
+        assert!(take_commands(&path).unwrap().is_empty());
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn parsing_uris() {
+        assert_eq!(
+            parse_uri("tomata://start?duration=25m&tag=PROJ-1"),
+            Some(ControlCommand::Start {
+                duration: Some(Duration::from_secs(25 * tomata::MINUTE_S)),
+                tag: Some("PROJ-1".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_uri("TOMATA://start/?tag=Fix%20login+page&utm_source=mail"),
+            Some(ControlCommand::Start {
+                duration: None,
+                tag: Some("Fix login page".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_uri("tomata://show-window"),
+            Some(ControlCommand::ShowWindow)
+        );
+        // A link must not be able to do what the user did not ask for.
+        assert_eq!(parse_uri("tomata://skip-period"), None);
+        assert_eq!(parse_uri("tomata://quit"), None);
+        assert_eq!(parse_uri("tomata://start?duration=forever"), None);
+        assert_eq!(parse_uri("tomata://frobnicate"), None);
+        assert_eq!(parse_uri("https://start?duration=25m"), None);
+        assert!(is_uri("tomata://start"));
+        assert!(!is_uri("toggle-pause"));
+    }
+
+    #[test]
+    fn telling_whether_instance_is_running() {
+        let path = std::env::temp_dir().join(format!("tomata-instance-{}", std::process::id()));
+        assert!(!is_instance_running(&path, 1_000));
+        mark_instance_alive(&path, 1_000).unwrap();
+        assert!(is_instance_running(&path, 1_030));
+        assert!(!is_instance_running(&path, 1_050));
+        fs::remove_file(&path).unwrap();
+    }
+}
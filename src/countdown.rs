@@ -0,0 +1,82 @@
+//! Ad-hoc secondary countdowns, e.g., a tea timer, that run
+//! alongside the Pomodoro cycle and are not affected by it.
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
+use notify_rust::Notification;
+
+use crate::tomata::{APPLICATION_NAME, ZERO};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+pub struct Countdown {
+    id: u64,
+    name: String,
+    // Data cannot be derived for Duration, unless it is in Arc (or Rc, but then
+    // countdowns could not be listed in `Arc<Vec<_>>`)
+    remaining_time: Arc<Duration>,
+}
+
+impl Countdown {
+    pub fn new(id: u64, name: String, duration: Duration) -> Countdown {
+        Countdown {
+            id,
+            name,
+            remaining_time: Arc::new(duration),
+        }
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_remaining_time(&self) -> Duration {
+        *self.remaining_time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        *self.remaining_time == ZERO
+    }
+
+    /// Decreases the remaining time and tells whether
+    /// the countdown has just finished.
+    pub fn advance(&mut self, elapsed: Duration) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.remaining_time = Arc::new(self.remaining_time.checked_sub(elapsed).unwrap_or(ZERO));
+        self.is_finished()
+    }
+}
+
+impl From<&Countdown> for Notification {
+    fn from(countdown: &Countdown) -> Notification {
+        Notification::new()
+            .appname(APPLICATION_NAME)
+            .summary(&format!("{} finished.", countdown.get_name()))
+            .body("The countdown has reached zero.")
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advancing_countdown_until_it_finishes() {
+        let mut countdown = Countdown::new(0, "Tea".to_string(), Duration::from_secs(2));
+        assert!(!countdown.advance(Duration::from_secs(1)));
+        assert_eq!(countdown.get_remaining_time(), Duration::from_secs(1));
+        assert!(countdown.advance(Duration::from_secs(5)));
+        assert_eq!(countdown.get_remaining_time(), ZERO);
+        // Finishing is reported only once.
+        assert!(!countdown.advance(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,85 @@
+//! Sequence of the periods edited by the user, the cycle goes through its
+//! blocks in order instead of the work periods separated by the short breaks
+//! and followed by a long one. The sequences can be kept as named profiles,
+//! e.g., one for the deep work and one for the meetings-heavy days.
+use std::rc::Rc;
+use std::time::Duration;
+
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use crate::tomata::{Period, MINUTE_S};
+
+/// The shortest block left by shortening it, so it keeps running.
+pub const MIN_BLOCK_DURATION: Duration = Duration::from_secs(MINUTE_S);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+pub struct CycleBlock {
+    pub period: Period,
+    pub duration: Rc<Duration>, // Data cannot be derived for Duration, unless it is in Rc
+}
+
+impl CycleBlock {
+    pub fn new(period: Period, duration: Duration) -> CycleBlock {
+        CycleBlock {
+            period,
+            duration: Rc::new(duration),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+#[serde(default)]
+pub struct CycleProfile {
+    pub name: String,
+    pub blocks: Rc<Vec<CycleBlock>>,
+}
+
+/// How long one pass through the blocks takes.
+pub fn calculate_cycle_length(blocks: &[CycleBlock]) -> Duration {
+    blocks.iter().map(|block| *block.duration).sum()
+}
+
+/// Moves the block one place earlier or later, the first and the last
+/// blocks stay where they are.
+pub fn move_block(blocks: &mut [CycleBlock], index: usize, is_moved_up: bool) {
+    let other = if is_moved_up {
+        index.checked_sub(1)
+    } else {
+        Some(index + 1)
+    };
+    if let Some(other) = other.filter(|other| *other < blocks.len() && index < blocks.len()) {
+        blocks.swap(index, other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editing_blocks() {
+        let mut blocks = vec![
+            CycleBlock::new(Period::Work, Duration::from_secs(50 * MINUTE_S)),
+            CycleBlock::new(Period::ShortBreak, Duration::from_secs(10 * MINUTE_S)),
+            CycleBlock::new(Period::Custom(0), Duration::from_secs(15 * MINUTE_S)),
+        ];
+        assert_eq!(
+            calculate_cycle_length(&blocks),
+            Duration::from_secs(75 * MINUTE_S)
+        );
+        move_block(&mut blocks, 2, true);
+        assert_eq!(blocks[1].period, Period::Custom(0));
+        move_block(&mut blocks, 0, true);
+        move_block(&mut blocks, 2, false);
+        move_block(&mut blocks, 5, false);
+        let periods: Vec<Period> = blocks.iter().map(|block| block.period).collect();
+        assert_eq!(
+            periods,
+            vec![Period::Work, Period::Custom(0), Period::ShortBreak]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Recent internals of the engine, i.e., the ticks, the events and the
+//! errors, kept for the hidden debug window opened with `--debug`, so the
+//! timing bugs reported by the users can be diagnosed without a debugger.
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::events::TomataEvent;
+
+/// How many of the recent events and errors are kept.
+pub const DEBUG_LOG_CAPACITY: usize = 20;
+
+#[derive(Debug, Clone, Default)]
+pub struct DebugLog {
+    tick_count: u64,
+    /// When the last tick came and the time it carried.
+    last_tick: Option<(u64, Duration)>,
+    /// The newest last, with the timestamps they were recorded at.
+    events: VecDeque<(u64, TomataEvent)>,
+    errors: VecDeque<(u64, String)>,
+}
+
+fn push_bounded<T>(entries: &mut VecDeque<T>, entry: T) {
+    if entries.len() == DEBUG_LOG_CAPACITY {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+impl DebugLog {
+    pub fn record_tick(&mut self, timestamp: u64, elapsed: Duration) {
+        self.tick_count += 1;
+        self.last_tick = Some((timestamp, elapsed));
+    }
+
+    pub fn record_event(&mut self, timestamp: u64, event: TomataEvent) {
+        push_bounded(&mut self.events, (timestamp, event));
+    }
+
+    pub fn record_error(&mut self, timestamp: u64, message: &str) {
+        push_bounded(&mut self.errors, (timestamp, message.to_string()));
+    }
+
+    /// Describes the log as of `now`, followed by the dump of the state.
+    pub fn make_report(&self, now: u64, state_dump: &str) -> String {
+        let mut report = String::new();
+        let _ = match self.last_tick {
+            Some((timestamp, elapsed)) => writeln!(
+                report,
+                "Ticks: {}, the last one {} s ago, carrying {:?}",
+                self.tick_count,
+                now.saturating_sub(timestamp),
+                elapsed
+            ),
+            None => writeln!(report, "Ticks: none yet"),
+        };
+        let _ = writeln!(report, "\nRecent events:");
+        for (timestamp, event) in self.events.iter().rev() {
+            let _ = writeln!(
+                report,
+                "    {} s ago: {:?}",
+                now.saturating_sub(*timestamp),
+                event
+            );
+        }
+        let _ = writeln!(report, "\nRecent errors:");
+        for (timestamp, message) in self.errors.iter().rev() {
+            let _ = writeln!(
+                report,
+                "    {} s ago: {}",
+                now.saturating_sub(*timestamp),
+                message
+            );
+        }
+        let _ = write!(report, "\nState:\n{}", state_dump);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomata::Period;
+
+    #[test]
+    fn reporting_recent_entries() {
+        let mut log = DebugLog::default();
+        assert!(log.make_report(100, "{}").starts_with("Ticks: none yet\n"));
+        log.record_tick(100, Duration::from_secs(1));
+        log.record_tick(105, Duration::from_millis(250));
+        for _ in 0..DEBUG_LOG_CAPACITY {
+            log.record_event(101, TomataEvent::PeriodActivated(Period::Work));
+        }
+        log.record_event(106, TomataEvent::PeriodActivated(Period::ShortBreak));
+        log.record_error(107, "Could not save the settings");
+        assert_eq!(log.events.len(), DEBUG_LOG_CAPACITY);
+        let report = log.make_report(110, "TomataState {}");
+        assert!(report.starts_with(
+            "Ticks: 2, the last one 5 s ago, carrying 250ms\n\
+             \nRecent events:\
+             \n    4 s ago: PeriodActivated(ShortBreak)\
+             \n    9 s ago: PeriodActivated(Work)\n"
+        ));
+        assert!(report.ends_with(
+            "\nRecent errors:\
+             \n    3 s ago: Could not save the settings\n\
+             \nState:\nTomataState {}"
+        ));
+    }
+}
@@ -0,0 +1,1087 @@
+//! The engine measures the elapsed time on a background thread and
+//! delivers it to the application through [`ExtEventSink`], so counting
+//! the time no longer depends on the timers of the UI event loop.
+//! Every frontend controls the stopwatch by sending an [`EngineCommand`]
+//! instead of mutating [`TomataState`] by itself, all of them are
+//! handled in one place by [`EngineDelegate`].
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use druid::{
+    commands, AppDelegate, Command, DelegateCtx, Env, ExtEventSink, Handled, Selector, Target,
+    WindowConfig, WindowId, WindowState,
+};
+
+use crate::badge;
+use crate::badge::{BadgeBackend, SystemBadge};
+use crate::blocklist::BlocklistHook;
+use crate::calendar;
+use crate::calendar::BusyBlock;
+use crate::chart;
+use crate::clock::ClockMonitor;
+use crate::control;
+use crate::control::ControlCommand;
+use crate::errors::RetryAction;
+use crate::eventlog;
+use crate::eventlog::LoggedEvent;
+use crate::events::{EventHook, TomataEvent};
+use crate::export;
+use crate::export::ReportFormat;
+use crate::history;
+use crate::history::SessionRecord;
+use crate::hotkeys::GlobalHotkeys;
+use crate::issue;
+use crate::lock::{ScreenLocker, SystemScreenLocker};
+use crate::ntfy::NtfyHook;
+use crate::openrgb::OpenRgbHook;
+use crate::org;
+use crate::overlay;
+use crate::planning;
+use crate::planning::PlannedTask;
+use crate::probes::{ProbeNeeds, ProbeReadings, ProbeThread, Probes};
+use crate::server::StatusServer;
+use crate::settings;
+use crate::settings::CloseAction;
+use crate::signals::ReceivedSignal;
+use crate::sound::SoundEvent;
+use crate::state::{AwayTimeClassification, TomataState};
+use crate::summary;
+use crate::theme;
+use crate::theme::ThemeFile;
+use crate::tomata;
+use crate::tomata::{Period, ZERO};
+use crate::tray;
+use crate::tray::TrayIcon;
+use crate::wallpaper::{SystemWallpaperBackend, WallpaperSwitcher};
+use crate::whatsnew;
+use crate::widget;
+use crate::writes::WriteBuffer;
+
+/// Milliseconds between the ticks, the delegate keeps it in line with the settings.
+static TICK_INTERVAL_MS: AtomicU64 = AtomicU64::new(1000);
+
+/// The work that does not need to happen more often than the displayed
+/// time changes, e.g., reading the control directory, is done once per this
+/// interval regardless of how often the ticks come.
+const SLOW_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Carries the time that elapsed since the previous tick.
+pub const TICK: Selector<Duration> = Selector::new("tomata.engine.tick");
+pub const ENGINE_COMMAND: Selector<EngineCommand> = Selector::new("tomata.engine.command");
+/// Carries what the polling of the system found out (see [`start_probes`]).
+pub const PROBED: Selector<ProbeReadings> = Selector::new("tomata.engine.probed");
+/// Carries the signal sent to the application (see [`crate::signals`]).
+pub const SIGNAL: Selector<ReceivedSignal> = Selector::new("tomata.engine.signal");
+/// Asks the application to quit the way it does from the interface,
+/// e.g., on `SIGTERM`.
+pub const SHUT_DOWN: Selector = Selector::new("tomata.engine.shut-down");
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineCommand {
+    StartStopwatch,
+    PauseStopwatch,
+    ToggleStopwatch,
+    ResetStopwatch,
+    ActivatePeriod(Period),
+    EndBreakEarly,
+    DismissEndBreakEarlyPrompt,
+    ClassifyAwayTime(AwayTimeClassification),
+    ConfirmFocusAnswer,
+    DismissFocusPrompt,
+    ConfirmDayPlan,
+    SilenceAlarm,
+    DismissDayPlanPrompt,
+    PickRecentTag(usize),
+    AddCountdown,
+    RemoveCountdown(u64),
+    RequestBlocklist,
+    ConfirmBlocklist,
+    DismissBlocklistPrompt,
+    DisableBlocklist,
+    ToggleMeetingMode,
+    StartWorkAfterMeeting,
+    DismissMeetingEndPrompt,
+    AddPlannedTask,
+    RemovePlannedTask(u64),
+    SaveCycleProfile,
+    ToggleBigDisplay,
+    OpenObserverWindow,
+    OpenSessionIssue(usize),
+    FocusOnPlannedTask(u64),
+    ConfirmAbortReason,
+    DismissAbortReasonPrompt,
+    DismissBanner,
+    SnoozeBanner,
+    ApplySuggestion,
+    DismissSuggestion,
+    DismissWhatsNew,
+    SaveSettings,
+    FinishOnboarding,
+    RetryError,
+    DismissError,
+    ChooseCloseAction(CloseAction),
+    DismissClosePrompt,
+    ReadRemainingTime,
+    PreviewSound(SoundEvent),
+    StartSoundCalibration,
+    SwitchUser,
+}
+
+impl EngineCommand {
+    pub fn apply(self, state: &mut TomataState) {
+        match self {
+            EngineCommand::StartStopwatch => state.start_stopwatch(),
+            EngineCommand::PauseStopwatch => state.pause_stopwatch(),
+            EngineCommand::ToggleStopwatch => state.toggle_stopwatch(),
+            EngineCommand::ResetStopwatch => state.reset_stopwatch(),
+            EngineCommand::ActivatePeriod(period) => state.switch_to_period(period),
+            EngineCommand::EndBreakEarly => state.end_break_early(),
+            EngineCommand::DismissEndBreakEarlyPrompt => state.dismiss_end_break_early_prompt(),
+            EngineCommand::ClassifyAwayTime(classification) => {
+                state.classify_away_time(classification)
+            }
+            EngineCommand::ConfirmFocusAnswer => state.confirm_focus_answer(),
+            EngineCommand::DismissFocusPrompt => state.dismiss_focus_prompt(),
+            EngineCommand::ConfirmDayPlan => state.confirm_day_plan(),
+            EngineCommand::SilenceAlarm => state.silence_alarm(),
+            EngineCommand::DismissDayPlanPrompt => state.dismiss_day_plan_prompt(),
+            EngineCommand::PickRecentTag(index) => state.pick_recent_tag(index),
+            EngineCommand::AddCountdown => state.add_countdown(),
+            EngineCommand::RemoveCountdown(id) => state.remove_countdown(id),
+            EngineCommand::RequestBlocklist => state.request_blocklist(),
+            EngineCommand::ConfirmBlocklist => state.confirm_blocklist(),
+            EngineCommand::DismissBlocklistPrompt => state.dismiss_blocklist_prompt(),
+            EngineCommand::DisableBlocklist => state.disable_blocklist(),
+            EngineCommand::ToggleMeetingMode => state.toggle_meeting_mode(),
+            EngineCommand::StartWorkAfterMeeting => state.start_work_after_meeting(),
+            EngineCommand::DismissMeetingEndPrompt => state.dismiss_meeting_end_prompt(),
+            EngineCommand::AddPlannedTask => state.add_planned_task(),
+            EngineCommand::RemovePlannedTask(id) => state.remove_planned_task(id),
+            EngineCommand::SaveCycleProfile => state.save_cycle_profile(),
+            EngineCommand::ToggleBigDisplay => state.toggle_big_display(),
+            EngineCommand::OpenObserverWindow => state.request_observer_window(),
+            EngineCommand::OpenSessionIssue(index) => state.open_session_issue(index),
+            EngineCommand::FocusOnPlannedTask(id) => state.focus_on_planned_task(id),
+            EngineCommand::ConfirmAbortReason => state.confirm_abort_reason(),
+            EngineCommand::DismissAbortReasonPrompt => state.dismiss_abort_reason_prompt(),
+            EngineCommand::DismissBanner => state.dismiss_banner(),
+            EngineCommand::SnoozeBanner => state.snooze_banner(),
+            EngineCommand::ApplySuggestion => state.apply_suggestion(),
+            EngineCommand::DismissSuggestion => state.dismiss_suggestion(),
+            EngineCommand::DismissWhatsNew => state.dismiss_whats_new(),
+            EngineCommand::SaveSettings => state.request_settings_save(),
+            EngineCommand::FinishOnboarding => state.finish_onboarding(),
+            EngineCommand::RetryError => state.retry_error(),
+            EngineCommand::DismissError => state.dismiss_error(),
+            EngineCommand::ChooseCloseAction(action) => state.choose_close_action(action),
+            EngineCommand::DismissClosePrompt => state.dismiss_close_prompt(),
+            EngineCommand::ReadRemainingTime => state.read_remaining_time(),
+            EngineCommand::PreviewSound(event) => state.preview_sound(event),
+            EngineCommand::StartSoundCalibration => state.start_sound_calibration(),
+            EngineCommand::SwitchUser => state.switch_user(),
+        }
+    }
+}
+
+/// Spawns the thread that sends [`TICK`] to the application
+/// every [`TICK_INTERVAL_MS`].
+pub fn start_clock(sink: ExtEventSink) {
+    thread::spawn(move || {
+        let mut next_tick = Instant::now();
+        loop {
+            let interval = wait_for_next_tick(&mut next_tick);
+            if sink.submit_command(TICK, interval, Target::Global).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Spawns the thread polling the system, it sends the readings
+/// to the application with [`PROBED`].
+pub fn start_probes(sink: ExtEventSink) -> ProbeThread {
+    ProbeThread::start(Probes::default(), move |readings| {
+        sink.submit_command(PROBED, readings, Target::Global)
+            .is_ok()
+    })
+}
+
+/// Sleeps until the tick after `next_tick` is due, returns the interval.
+fn wait_for_next_tick(next_tick: &mut Instant) -> Duration {
+    // Ticks are scheduled against the start instant rather than
+    // by sleeping the interval, so the delays do not accumulate.
+    let interval = Duration::from_millis(TICK_INTERVAL_MS.load(Ordering::Relaxed));
+    *next_tick += interval;
+    let now = Instant::now();
+    if *next_tick > now {
+        thread::sleep(*next_tick - now);
+    }
+    interval
+}
+
+/// Runs the engine without the interface until it is asked to quit,
+/// e.g., with `tomata quit`, the notifications and the sounds are the only
+/// output. The control subcommands and the URIs operate it as usual,
+/// and so do the `signals`.
+pub fn run_headless(mut state: TomataState, signals: Receiver<ReceivedSignal>) {
+    let (reading_sender, readings) = mpsc::channel();
+    let probes = ProbeThread::start(Probes::default(), move |reading| {
+        reading_sender.send(reading).is_ok()
+    });
+    let mut engine = EngineDelegate::new(probes);
+    let mut next_tick = Instant::now();
+    loop {
+        let interval = wait_for_next_tick(&mut next_tick);
+        // There is no window to show.
+        engine.advance(&mut state, interval);
+        while let Ok(reading) = readings.try_recv() {
+            engine.apply_readings(&mut state, &reading);
+        }
+        while let Ok(signal) = signals.try_recv() {
+            if let Some(command) = signal.find_command(state.get_settings()) {
+                apply_control_command(&mut state, command);
+                engine.tray_tooltip_is_stale = true;
+            }
+        }
+        if state.take_quit_request() {
+            engine.shut_down(&mut state);
+        }
+        engine.process_changes(&mut state);
+        if engine.is_shutting_down {
+            break;
+        }
+    }
+}
+
+/// Carries out the command sent from outside of the application, returns
+/// whether the main window was asked to be shown.
+pub fn apply_control_command(state: &mut TomataState, command: ControlCommand) -> bool {
+    match command {
+        ControlCommand::TogglePause => state.toggle_stopwatch(),
+        ControlCommand::Pause => state.pause_stopwatch(),
+        ControlCommand::Resume if state.is_stopwatch_paused() => state.start_stopwatch(),
+        ControlCommand::Resume => {}
+        ControlCommand::SkipPeriod => state.skip_period(),
+        ControlCommand::Snooze => state.snooze_period(),
+        ControlCommand::ShowWindow => return true,
+        ControlCommand::Quit => state.request_quit(),
+        ControlCommand::Start { duration, tag } => state.start_session(duration, tag),
+    }
+    false
+}
+
+pub fn tick(state: &mut TomataState, elapsed: Duration) {
+    state.record_debug_tick(elapsed);
+    if state.is_stopwatch_paused() {
+        state.increase_paused_time(elapsed);
+    } else {
+        state.increase_elapsed_time(elapsed);
+        state.advance_reminders(elapsed);
+    }
+    state.advance_rest(elapsed);
+    state.advance_countdowns(elapsed);
+    state.advance_banner_snooze(elapsed);
+    state.advance_period_snooze(elapsed);
+    state.advance_overtime(elapsed);
+    state.advance_escalation(elapsed);
+    state.advance_alarm_repeat(elapsed);
+    state.advance_sound_calibration(elapsed);
+    if state.is_period_finished() {
+        state.cycle_to_next_period();
+    }
+    state.update_ambient_sound();
+}
+
+/// Writes the summary of the previous week on Monday, unless it is already
+/// written, and sends it by email when the SMTP server is configured.
+fn write_weekly_summary(data: &mut TomataState) {
+    let (week_start, week_end) = match data.find_summarized_week() {
+        Some(week) => week,
+        None => return,
+    };
+    let path = summary::make_summary_file_name(week_start);
+    if Path::new(&path).exists() {
+        return;
+    }
+    let text = data.make_weekly_summary(week_start, week_end);
+    if let Err(error) = fs::write(&path, &text) {
+        data.report_error(
+            format!("Could not write the weekly summary: {}", error),
+            None,
+        );
+        return;
+    }
+    if let Some((smtp_url, email)) = data.get_settings().get_weekly_summary_email() {
+        summary::send_summary_email(smtp_url, email, &text);
+    }
+}
+
+#[derive(Debug)]
+pub struct EngineDelegate {
+    probes: ProbeThread,
+    /// The busy blocks of the calendar read last.
+    busy_blocks: Vec<BusyBlock>,
+    screen_locker: Box<dyn ScreenLocker>,
+    overlay_windows: Vec<WindowId>,
+    focus_banner_window: Option<WindowId>,
+    debug_window: Option<WindowId>,
+    observer_windows: Vec<WindowId>,
+    /// The window the user tried to close, it is minimized or hidden to the
+    /// tray if they choose so.
+    closed_window: Option<WindowId>,
+    /// The window open instead of the main window hidden to the tray,
+    /// see [`overlay::make_tray_keeper_window`].
+    tray_keeper_window: Option<WindowId>,
+    theme_file: ThemeFile,
+    clock_monitor: ClockMonitor,
+    wallpaper_switcher: WallpaperSwitcher,
+    event_hooks: Vec<Box<dyn EventHook>>,
+    /// The messages of the failures on the other threads.
+    failures: Receiver<String>,
+    status_server: Option<StatusServer>,
+    /// The port and the sharing with the local network the server was last
+    /// started with, `None` when it is disabled.
+    status_server_address: Option<(u16, bool)>,
+    since_slow_update: Duration,
+    is_shutting_down: bool,
+    main_window: Option<WindowId>,
+    tray_icon: Option<TrayIcon>,
+    tray_icon_is_enabled: bool,
+    /// Whether the tooltip may be out of date, i.e., the time went by
+    /// or a command was handled since it was last made.
+    tray_tooltip_is_stale: bool,
+    badge: Box<dyn BadgeBackend>,
+    /// The count shown last, `None` when the badge is hidden.
+    badge_count: Option<usize>,
+    /// The start of the day the count was made for, `None` when the badge
+    /// is hidden.
+    badge_day: Option<u64>,
+    global_hotkeys: Option<GlobalHotkeys>,
+    /// The shortcuts, as typed, grabbed last, empty when they are disabled.
+    global_hotkey_texts: Vec<String>,
+    unwritten_records: WriteBuffer<SessionRecord>,
+    unwritten_plans: WriteBuffer<Vec<PlannedTask>>,
+    unwritten_primary_projects: WriteBuffer<Option<String>>,
+    unwritten_events: WriteBuffer<LoggedEvent>,
+    /// When the instance file was last written.
+    instance_marked_at: Option<Instant>,
+}
+
+impl EngineDelegate {
+    /// The system is polled by `probes`, their readings are to be
+    /// handed to [`EngineDelegate::apply_readings`].
+    pub fn new(probes: ProbeThread) -> EngineDelegate {
+        let (failure_sender, failures) = mpsc::channel();
+        EngineDelegate {
+            probes,
+            busy_blocks: Vec::new(),
+            screen_locker: Box::new(SystemScreenLocker),
+            overlay_windows: Vec::new(),
+            focus_banner_window: None,
+            debug_window: None,
+            observer_windows: Vec::new(),
+            closed_window: None,
+            tray_keeper_window: None,
+            theme_file: ThemeFile::default(),
+            clock_monitor: ClockMonitor::default(),
+            wallpaper_switcher: WallpaperSwitcher::new(Box::new(SystemWallpaperBackend)),
+            event_hooks: vec![
+                Box::new(OpenRgbHook),
+                Box::new(NtfyHook::new(failure_sender)),
+                Box::new(BlocklistHook::new()),
+            ],
+            failures,
+            status_server: None,
+            status_server_address: None,
+            since_slow_update: SLOW_UPDATE_INTERVAL,
+            is_shutting_down: false,
+            main_window: None,
+            tray_icon: None,
+            tray_icon_is_enabled: false,
+            tray_tooltip_is_stale: true,
+            badge: Box::new(SystemBadge),
+            badge_count: None,
+            badge_day: None,
+            global_hotkeys: None,
+            global_hotkey_texts: Vec::new(),
+            unwritten_records: WriteBuffer::default(),
+            unwritten_plans: WriteBuffer::default(),
+            unwritten_primary_projects: WriteBuffer::default(),
+            unwritten_events: WriteBuffer::default(),
+            instance_marked_at: None,
+        }
+    }
+
+    /// Finishes the work of the application before it quits, the rest
+    /// of the command handling saves and cleans up the rest.
+    fn shut_down(&mut self, data: &mut TomataState) {
+        if !self.is_shutting_down {
+            self.is_shutting_down = true;
+            data.prepare_shutdown();
+            // Another launch must not take this one for running.
+            let _ = std::fs::remove_file(control::INSTANCE_FILE);
+        }
+    }
+
+    /// Counts the time that elapsed since the previous tick and checks
+    /// the files, returns whether the main window was asked to be shown.
+    fn advance(&mut self, data: &mut TomataState, elapsed: Duration) -> bool {
+        self.since_slow_update += elapsed;
+        let is_slow_update_due = self.since_slow_update >= SLOW_UPDATE_INTERVAL;
+        let mut is_window_requested = false;
+        if is_slow_update_due {
+            self.since_slow_update = ZERO;
+            let now = tomata::now_timestamp();
+            let clock_jump = self.clock_monitor.check(Instant::now(), now);
+            if let Some(jump) = clock_jump {
+                if data.get_settings().is_clock_jump_correction_enabled() {
+                    data.correct_clock_jump(jump);
+                }
+            }
+            match self.theme_file.reload(Path::new(theme::THEME_FILE)) {
+                Some(Ok(theme)) => data.set_theme(theme),
+                Some(Err(errors)) => data.report_error(
+                    format!("Could not load the theme: {}", errors.join(", ")),
+                    None,
+                ),
+                None => {}
+            }
+            data.set_wall_clock_time(now);
+            self.tray_tooltip_is_stale = true;
+            let meeting = calendar::find_busy_block_at(&self.busy_blocks, now)
+                .filter(|_| data.get_settings().get_calendar_file().is_some());
+            data.set_meeting_end(meeting.map(|block| block.end));
+            // The probes are not polled while disabled.
+            if !data.get_settings().is_call_detection_enabled() {
+                data.set_microphone_in_use(false);
+            }
+            if !data.get_settings().is_fullscreen_detection_enabled() {
+                data.set_fullscreen_active(false);
+            }
+            for command in data.take_notification_commands() {
+                is_window_requested |= apply_control_command(data, command);
+            }
+            match control::take_commands(control::CONTROL_DIRECTORY) {
+                Ok(commands) => commands.into_iter().for_each(|command| {
+                    is_window_requested |= apply_control_command(data, command)
+                }),
+                Err(error) => data.report_error(
+                    format!("Could not read the control directory: {}", error),
+                    None,
+                ),
+            }
+            let instant = Instant::now();
+            let is_instance_mark_due = self.instance_marked_at.map_or(true, |marked_at| {
+                instant.duration_since(marked_at) >= control::INSTANCE_REFRESH_INTERVAL
+            });
+            if is_instance_mark_due {
+                self.instance_marked_at = Some(instant);
+                if let Err(error) = control::mark_instance_alive(control::INSTANCE_FILE, now) {
+                    data.report_error(
+                        format!("Could not write the instance file: {}", error),
+                        None,
+                    );
+                }
+            }
+            if data.get_settings().is_weekly_summary_enabled() {
+                write_weekly_summary(data);
+            }
+        }
+        tick(data, elapsed);
+        let interval = data.get_settings().get_tick_interval();
+        TICK_INTERVAL_MS.store(interval.as_millis() as u64, Ordering::Relaxed);
+        is_window_requested
+    }
+
+    /// Hands what the polling of the system found out to the state, unless
+    /// it is not needed any more, e.g., the setting was just disabled.
+    pub fn apply_readings(&mut self, data: &mut TomataState, readings: &ProbeReadings) {
+        self.tray_tooltip_is_stale = true;
+        if let Some(idle_time) = readings.idle_time {
+            data.register_user_idle_time(idle_time);
+        }
+        if let Some(is_in_use) = readings.is_microphone_in_use {
+            if data.get_settings().is_call_detection_enabled() {
+                data.set_microphone_in_use(is_in_use);
+            }
+        }
+        if let Some(problem) = &readings.fullscreen_detection_problem {
+            data.set_fullscreen_detection_problem(problem.clone());
+        }
+        if let Some(is_fullscreen) = readings.is_fullscreen {
+            let settings = data.get_settings();
+            if settings.is_fullscreen_detection_enabled() {
+                let is_fullscreen = is_fullscreen
+                    && settings.is_fullscreen_application_respected(
+                        readings.fullscreen_application.as_deref(),
+                    );
+                data.set_fullscreen_active(is_fullscreen);
+            }
+        }
+        if let Some(workspace) = &readings.focused_workspace {
+            if data.is_watching_workspaces() {
+                data.set_focused_workspace(workspace);
+            }
+        }
+        if let Some(application) = &readings.active_application {
+            data.register_active_application(application);
+        }
+        if let Some(busy_blocks) = &readings.busy_blocks {
+            self.busy_blocks = busy_blocks.clone();
+        }
+        if let Some(days_off) = &readings.days_off {
+            data.set_imported_days_off(days_off.clone());
+        }
+    }
+
+    /// Tells the polling thread what the state needs to know right now.
+    fn update_probes(&mut self, data: &TomataState) {
+        let settings = data.get_settings();
+        self.probes.set_needs(ProbeNeeds {
+            idle_time: data.is_waiting_for_user_return()
+                || data.is_watching_away_time()
+                || data.is_measuring_rest(),
+            microphone: settings.is_call_detection_enabled(),
+            fullscreen: settings.is_fullscreen_detection_enabled(),
+            workspace: data.is_watching_workspaces(),
+            application: data.is_sampling_applications(),
+            calendar_file: settings.get_calendar_file().map(Path::to_path_buf),
+            holiday_calendar_file: settings.get_holiday_calendar_file().map(Path::to_path_buf),
+        });
+    }
+
+    /// Carries out what the handling of a command or a tick requested,
+    /// except for the windows, and saves the changed data.
+    fn process_changes(&mut self, data: &mut TomataState) {
+        if data.take_screen_lock_request() {
+            if let Err(error) = self.screen_locker.lock_screen() {
+                data.report_error(format!("Could not lock the screen: {}", error), None);
+            }
+        }
+        if let Some(url) = data.take_issue_url_to_open() {
+            if let Err(error) = issue::open_in_browser(&url) {
+                data.report_error(format!("Could not open {}: {}", url, error), None);
+            }
+        }
+        let mut is_period_changed = false;
+        let new_records = data.take_unsaved_records();
+        // Every hook handles the new events, only the failed one
+        // the retried events.
+        let mut deliveries = Vec::new();
+        let timestamp = tomata::now_timestamp();
+        for event in data.take_unhandled_events() {
+            deliveries.extend(self.event_hooks.iter().map(|hook| (event, hook.get_name())));
+            self.unwritten_events
+                .extend(Some(LoggedEvent { timestamp, event }), Instant::now());
+        }
+        deliveries.extend(data.take_retried_events());
+        for (event, hook_name) in deliveries {
+            is_period_changed |= matches!(event, TomataEvent::PeriodActivated(_));
+            let hook = self
+                .event_hooks
+                .iter_mut()
+                .find(|hook| hook.get_name() == hook_name);
+            if let Some(Err(error)) = hook.map(|hook| hook.handle(event, data.get_settings())) {
+                data.report_error(
+                    format!("Could not handle {:?} in {}: {}", event, hook_name, error),
+                    Some(RetryAction::HandleEvent(event, hook_name)),
+                );
+            }
+        }
+        // The failures of the work done on the other threads, e.g.,
+        // publishing to ntfy.
+        while let Ok(message) = self.failures.try_recv() {
+            data.report_error(message, None);
+        }
+        if self.is_shutting_down {
+            self.probes.set_needs(ProbeNeeds::default());
+            self.status_server = None;
+            self.tray_icon = None;
+            self.global_hotkeys = None;
+            self.update_badge(data, false);
+            if let Err(error) = self.wallpaper_switcher.restore() {
+                data.report_error(format!("Could not restore the wallpaper: {}", error), None);
+            }
+        } else {
+            self.update_probes(data);
+            self.update_status_server(data);
+            self.update_tray_icon(data);
+            self.update_global_hotkeys(data);
+            self.update_badge(data, !new_records.is_empty());
+            if let Err(error) = self
+                .wallpaper_switcher
+                .switch(data.get_settings(), data.get_current_period())
+            {
+                data.report_error(format!("Could not change the wallpaper: {}", error), None);
+            }
+        }
+        if data.take_settings_save_request() {
+            data.prepare_sounds();
+            if let Err(error) =
+                settings::save_settings_to_file(data.get_settings(), settings::SETTINGS_FILE)
+            {
+                data.report_error(
+                    format!("Could not save the settings: {}", error),
+                    Some(RetryAction::SaveSettings),
+                );
+            }
+        }
+        // The history, the event log and the plan are written in batches,
+        // the failed writes are held back again until they are retried.
+        let now = Instant::now();
+        let is_flush_forced =
+            is_period_changed || self.is_shutting_down || data.take_writes_flush_request();
+        self.unwritten_plans.extend(data.take_modified_plan(), now);
+        if let Some(plan) = self.unwritten_plans.take_due(now, is_flush_forced).pop() {
+            if let Err(error) = planning::save_plan(planning::PLAN_FILE, &plan) {
+                self.unwritten_plans.put_back(vec![plan], now);
+                data.report_error(
+                    format!("Could not save the plan: {}", error),
+                    Some(RetryAction::FlushWrites),
+                );
+            }
+        }
+        // The plan of the day is written right away, it changes rarely.
+        let project = data.take_modified_primary_project();
+        let is_project_modified = project.is_some();
+        self.unwritten_primary_projects.extend(project, now);
+        if let Some(project) = self
+            .unwritten_primary_projects
+            .take_due(now, is_flush_forced || is_project_modified)
+            .pop()
+        {
+            if let Err(error) =
+                whatsnew::save_primary_project(whatsnew::UI_STATE_FILE, project.clone())
+            {
+                self.unwritten_primary_projects.put_back(vec![project], now);
+                data.report_error(
+                    format!("Could not save the plan of the day: {}", error),
+                    Some(RetryAction::FlushWrites),
+                );
+            }
+        }
+        let events = self.unwritten_events.take_due(now, is_flush_forced);
+        if let Err(error) = eventlog::append_events(eventlog::EVENT_LOG_FILE, &events) {
+            self.unwritten_events.put_back(events, now);
+            data.report_error(
+                format!("Could not save the event log: {}", error),
+                Some(RetryAction::FlushWrites),
+            );
+        }
+        self.unwritten_records.extend(new_records, now);
+        let records = self.unwritten_records.take_due(now, is_flush_forced);
+        if let Err(error) = history::append_records(history::HISTORY_FILE, &records) {
+            self.unwritten_records.put_back(records, now);
+            data.report_error(
+                format!("Could not save the session history: {}", error),
+                Some(RetryAction::FlushWrites),
+            );
+            return;
+        }
+        if let Some(path) = data.get_settings().get_org_clock_file() {
+            let heading = data.get_settings().get_org_clock_heading();
+            if let Err(error) = org::clock_records(path, heading, &records) {
+                let message = format!("Could not clock the work in {}: {}", path.display(), error);
+                data.report_error(message, None);
+            }
+        }
+    }
+
+    /// Exports the whole history, the records waiting to be written too.
+    fn export_history(&self, path: &Path, format: ReportFormat, data: &mut TomataState) {
+        let result = history::load_records_since(history::HISTORY_FILE, 0)
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+            .and_then(|mut records| {
+                records.extend(self.unwritten_records.get_pending().iter().cloned());
+                export::export_report(path, format, &records, data.get_settings())
+            });
+        if let Err(error) = result {
+            let message = format!(
+                "Could not export the history to {}: {}",
+                path.display(),
+                error
+            );
+            data.report_error(message, None);
+        }
+    }
+
+    /// Starts or stops the status server whenever its settings change
+    /// and keeps the status it serves up to date.
+    fn update_status_server(&mut self, data: &mut TomataState) {
+        let settings = data.get_settings();
+        let address = Some((
+            settings.get_http_server_port(),
+            settings.is_http_server_shared(),
+        ))
+        .filter(|_| settings.is_http_server_enabled());
+        if address != self.status_server_address {
+            self.status_server_address = address;
+            self.status_server = address.and_then(|(port, is_shared)| {
+                StatusServer::start(port, is_shared)
+                    .map_err(|error| {
+                        data.report_error(
+                            format!("Could not start the HTTP server: {}", error),
+                            None,
+                        )
+                    })
+                    .ok()
+            });
+            data.set_share_link(
+                self.status_server
+                    .as_ref()
+                    .map(StatusServer::get_share_link),
+            );
+        }
+        if let Some(server) = &self.status_server {
+            server.set_status(data.get_focus_status());
+        }
+    }
+
+    /// Shows or hides the tray icon whenever its setting changes and keeps
+    /// its tooltip up to date.
+    fn update_tray_icon(&mut self, data: &mut TomataState) {
+        let is_enabled = data.get_settings().is_tray_icon_enabled();
+        if is_enabled != self.tray_icon_is_enabled {
+            self.tray_icon_is_enabled = is_enabled;
+            self.tray_tooltip_is_stale = true;
+            self.tray_icon = None;
+            let problem = if !is_enabled {
+                None
+            } else if !tray::is_supported() {
+                Some("not available on this system".to_string())
+            } else {
+                match TrayIcon::start() {
+                    Ok(tray_icon) => {
+                        self.tray_icon = Some(tray_icon);
+                        None
+                    }
+                    Err(error) => {
+                        data.report_error(format!("Could not show the tray icon: {}", error), None);
+                        Some(error.to_string())
+                    }
+                }
+            };
+            data.set_tray_icon_problem(problem);
+        }
+        if !std::mem::take(&mut self.tray_tooltip_is_stale) {
+            return;
+        }
+        if let Some(tray_icon) = &mut self.tray_icon {
+            if let Err(error) = tray_icon.set_tooltip(&data.make_tray_tooltip()) {
+                data.report_error(format!("Could not update the tray icon: {}", error), None);
+                data.set_tray_icon_problem(Some(error.to_string()));
+                self.tray_icon = None;
+            }
+        }
+    }
+
+    /// Shows today's count of the work periods on the icon, it is counted
+    /// again only when `is_recorded` a new session or the day changes.
+    /// The badge is hidden while it is disabled or the application quits.
+    fn update_badge(&mut self, data: &mut TomataState, is_recorded: bool) {
+        let day = Some(tomata::start_of_local_day(tomata::now_timestamp())).filter(|_| {
+            data.get_settings().is_icon_badge_enabled()
+                && badge::is_supported()
+                && !self.is_shutting_down
+        });
+        if !is_recorded && day == self.badge_day {
+            return;
+        }
+        self.badge_day = day;
+        let count = day.map(|_| data.sum_up_today().completed_pomodoros);
+        if count == self.badge_count {
+            return;
+        }
+        self.badge_count = count;
+        if let Err(error) = self.badge.set_count(count) {
+            data.report_error(
+                format!("Could not update the badge of the icon: {}", error),
+                None,
+            );
+        }
+    }
+
+    /// Grabs the shortcuts again whenever they or their setting change,
+    /// they are parsed only then.
+    fn update_global_hotkeys(&mut self, data: &mut TomataState) {
+        let settings = data.get_settings();
+        let is_enabled = settings.are_global_hotkeys_enabled();
+        let texts = settings.get_global_hotkey_texts();
+        let texts = if is_enabled { &texts[..] } else { &[] };
+        if self
+            .global_hotkey_texts
+            .iter()
+            .map(String::as_str)
+            .eq(texts.iter().copied())
+        {
+            return;
+        }
+        self.global_hotkey_texts = texts.iter().map(|text| text.to_string()).collect();
+        let bindings = if is_enabled {
+            settings.get_global_hotkeys()
+        } else {
+            Vec::new()
+        };
+        self.global_hotkeys = None;
+        if !bindings.is_empty() {
+            self.global_hotkeys = GlobalHotkeys::start(&bindings)
+                .map_err(|error| {
+                    data.report_error(
+                        format!("Could not grab the global shortcuts: {}", error),
+                        None,
+                    )
+                })
+                .ok();
+        }
+    }
+
+    /// Brings the main window to the front, it is opened again if it was
+    /// hidden to the tray.
+    fn show_main_window(&mut self, ctx: &mut DelegateCtx<'_>, data: &TomataState) {
+        if let Some(keeper) = self.tray_keeper_window.take() {
+            let window = widget::make_main_window(data.is_big_display_shown());
+            self.main_window = Some(window.id);
+            ctx.new_window(window);
+            ctx.submit_command(commands::CLOSE_WINDOW.to(keeper));
+        } else if let Some(id) = self.main_window {
+            ctx.submit_command(commands::SHOW_WINDOW.to(id));
+        }
+    }
+
+    /// Opens the break overlay windows when a break begins
+    /// and closes them when it ends.
+    fn update_break_overlay(&mut self, ctx: &mut DelegateCtx<'_>, data: &TomataState) {
+        let overlay_is_open = !self.overlay_windows.is_empty();
+        if data.is_break_overlay_shown() && !overlay_is_open {
+            for window in overlay::make_break_overlay_windows() {
+                self.overlay_windows.push(window.id);
+                ctx.new_window(window);
+            }
+        } else if !data.is_break_overlay_shown() && overlay_is_open {
+            for id in self.overlay_windows.drain(..) {
+                ctx.submit_command(commands::CLOSE_WINDOW.to(id));
+            }
+        }
+    }
+
+    fn update_focus_banner(&mut self, ctx: &mut DelegateCtx<'_>, data: &TomataState) {
+        match self.focus_banner_window {
+            None if data.is_focus_banner_shown() => {
+                let window = overlay::make_focus_banner_window();
+                self.focus_banner_window = Some(window.id);
+                ctx.new_window(window);
+            }
+            Some(id) if !data.is_focus_banner_shown() => {
+                self.focus_banner_window = None;
+                ctx.submit_command(commands::CLOSE_WINDOW.to(id));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl AppDelegate<TomataState> for EngineDelegate {
+    fn command(
+        &mut self,
+        ctx: &mut DelegateCtx<'_>,
+        target: Target,
+        cmd: &Command,
+        data: &mut TomataState,
+        _env: &Env,
+    ) -> Handled {
+        // The ticks make it stale once a second, see `advance`.
+        if !cmd.is(TICK) {
+            self.tray_tooltip_is_stale = true;
+        }
+        let handled = if let Some(elapsed) = cmd.get(TICK) {
+            if self.advance(data, *elapsed) {
+                self.show_main_window(ctx, data);
+            }
+            Handled::Yes
+        } else if let Some(readings) = cmd.get(PROBED) {
+            self.apply_readings(data, readings);
+            Handled::Yes
+        } else if let Some(command) = cmd.get(ENGINE_COMMAND) {
+            command.apply(data);
+            Handled::Yes
+        } else if let Some(signal) = cmd.get(SIGNAL) {
+            let command = signal.find_command(data.get_settings());
+            if command.is_some_and(|command| apply_control_command(data, command)) {
+                self.show_main_window(ctx, data);
+            }
+            Handled::Yes
+        } else if let Some(file) = cmd.get(commands::OPEN_FILE) {
+            // The only opened files are the sounds.
+            data.set_chosen_sound_file(file.path());
+            Handled::Yes
+        } else if let Some(file) = cmd.get(commands::SAVE_FILE_AS) {
+            // The saved files are the exported history and the exported charts.
+            if let Some(format) = ReportFormat::from_path(file.path()) {
+                self.export_history(file.path(), format, data);
+            } else if let Err(error) = chart::export_timeline(file.path(), &data.make_timeline()) {
+                let message = format!(
+                    "Could not export the timeline to {}: {}",
+                    file.path().display(),
+                    error
+                );
+                data.report_error(message, None);
+            }
+            Handled::Yes
+        } else if cmd.is(SHUT_DOWN) {
+            ctx.submit_command(commands::QUIT_APP);
+            Handled::Yes
+        } else if cmd.is(commands::QUIT_APP) {
+            self.shut_down(data);
+            Handled::No
+        } else if cmd.is(commands::CLOSE_WINDOW) {
+            // Only the main window is affected, not the overlays.
+            match target {
+                Target::Window(id) if self.main_window == Some(id) => {
+                    self.closed_window = Some(id);
+                    if data.request_window_close() {
+                        Handled::No
+                    } else {
+                        Handled::Yes
+                    }
+                }
+                _ => Handled::No,
+            }
+        } else {
+            Handled::No
+        };
+        match data.take_close_action() {
+            Some(CloseAction::Quit) => ctx.submit_command(commands::QUIT_APP),
+            // The window is hidden to the tray, there is no way back to it
+            // without the tray icon.
+            Some(CloseAction::Minimize) if self.tray_icon.is_some() => {
+                if let Some(id) = self
+                    .closed_window
+                    .filter(|&id| self.main_window == Some(id))
+                {
+                    let window = overlay::make_tray_keeper_window();
+                    self.tray_keeper_window = Some(window.id);
+                    self.main_window = None;
+                    ctx.new_window(window);
+                    ctx.submit_command(commands::CLOSE_WINDOW.to(id));
+                }
+            }
+            Some(CloseAction::Minimize) => {
+                if let Some(id) = self.closed_window {
+                    let config = WindowConfig::default().set_window_state(WindowState::MINIMIZED);
+                    ctx.submit_command(commands::CONFIGURE_WINDOW.with(config).to(id));
+                }
+            }
+            Some(CloseAction::Ask) | None => {}
+        }
+        if data.take_quit_request() {
+            ctx.submit_command(commands::QUIT_APP);
+        }
+        if self.tray_keeper_window.is_some() && self.tray_icon.is_none() {
+            self.show_main_window(ctx, data);
+        }
+        self.update_break_overlay(ctx, data);
+        self.update_focus_banner(ctx, data);
+        if data.take_observer_window_request() {
+            let window = overlay::make_observer_window();
+            self.observer_windows.push(window.id);
+            ctx.new_window(window);
+        }
+        self.process_changes(data);
+        handled
+    }
+
+    fn window_added(
+        &mut self,
+        id: WindowId,
+        data: &mut TomataState,
+        _env: &Env,
+        ctx: &mut DelegateCtx<'_>,
+    ) {
+        // The main window is opened first, before any overlay or banner.
+        if self.main_window.is_none() && self.tray_keeper_window.is_none() {
+            self.main_window = Some(id);
+            if data.is_debug_log_enabled() {
+                let window = overlay::make_debug_window();
+                self.debug_window = Some(window.id);
+                ctx.new_window(window);
+            }
+        }
+    }
+
+    fn window_removed(
+        &mut self,
+        id: WindowId,
+        _data: &mut TomataState,
+        _env: &Env,
+        ctx: &mut DelegateCtx<'_>,
+    ) {
+        if self.focus_banner_window == Some(id) {
+            self.focus_banner_window = None;
+        } else if self.debug_window == Some(id) {
+            self.debug_window = None;
+        } else if self.observer_windows.contains(&id) {
+            self.observer_windows.retain(|&observer| observer != id);
+        } else if self.main_window == Some(id) {
+            // Closing the main window quits the application, the one
+            // hidden to the tray is not the main window anymore.
+            ctx.submit_command(commands::QUIT_APP);
+        }
+        self.overlay_windows.retain(|&overlay| overlay != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn make_test_state() -> TomataState {
+        let settings = Settings::new(
+            Duration::from_secs(2),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            2,
+            true,
+            false,
+            false, // during tests we don't want the system notifications
+            false, // during tests we don't want the beep sound effect
+            0.1,
+        );
+        TomataState::new(settings)
+    }
+
+    #[test]
+    fn ticking_paused_stopwatch() {
+        let mut state = make_test_state();
+        tick(&mut state, Duration::from_secs(1));
+        assert_eq!(state.calculate_remaining_time(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn ticking_until_period_is_finished() {
+        let mut state = make_test_state();
+        EngineCommand::StartStopwatch.apply(&mut state);
+        tick(&mut state, Duration::from_secs(1));
+        assert_eq!(state.get_current_period(), Period::Work);
+        tick(&mut state, Duration::from_secs(1));
+        assert_eq!(state.get_current_period(), Period::ShortBreak);
+        assert!(state.is_stopwatch_paused());
+    }
+
+    #[test]
+    fn applying_commands() {
+        let mut state = make_test_state();
+        EngineCommand::StartStopwatch.apply(&mut state);
+        assert!(!state.is_stopwatch_paused());
+        EngineCommand::PauseStopwatch.apply(&mut state);
+        assert!(state.is_stopwatch_paused());
+        EngineCommand::ActivatePeriod(Period::LongBreak).apply(&mut state);
+        assert_eq!(state.get_current_period(), Period::LongBreak);
+    }
+}
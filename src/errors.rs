@@ -0,0 +1,36 @@
+//! Recoverable failures, e.g., the settings could not be saved, queued
+//! in [`TomataState`](crate::state::TomataState) and shown in the error
+//! banner until the user retries the failed action or dismisses them.
+use crate::events::TomataEvent;
+use crate::sound::SoundEvent;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RetryAction {
+    SaveSettings,
+    /// Handles the event again by the event hook of the name, the one
+    /// that failed.
+    HandleEvent(TomataEvent, &'static str),
+    PlaySound(SoundEvent),
+    /// Writes the held back history, plan and plan of the day right away.
+    FlushWrites,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoverableError {
+    message: String,
+    retry: Option<RetryAction>,
+}
+
+impl RecoverableError {
+    pub fn new(message: String, retry: Option<RetryAction>) -> RecoverableError {
+        RecoverableError { message, retry }
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_retry(&self) -> Option<RetryAction> {
+        self.retry
+    }
+}
@@ -0,0 +1,81 @@
+//! Escalation of the alert about the finished period for as long as
+//! the user does not acknowledge it by starting the next period: first
+//! the notification, after a while the sound, finally the fullscreen overlay.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tomata::ZERO;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EscalationStage {
+    Notification,
+    Sound,
+    Overlay,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Escalation {
+    stage: EscalationStage,
+    since_stage: Duration,
+}
+
+impl Default for Escalation {
+    /// The escalation begins with the notification of the new period.
+    fn default() -> Escalation {
+        Escalation {
+            stage: EscalationStage::Notification,
+            since_stage: ZERO,
+        }
+    }
+}
+
+impl Escalation {
+    pub fn get_stage(&self) -> EscalationStage {
+        self.stage
+    }
+
+    /// Moves to the next stage once its delay since the current one
+    /// passes, returns the stage reached by this call.
+    pub fn advance(
+        &mut self,
+        elapsed: Duration,
+        sound_delay: Duration,
+        overlay_delay: Duration,
+    ) -> Option<EscalationStage> {
+        let (next_stage, delay) = match self.stage {
+            EscalationStage::Notification => (EscalationStage::Sound, sound_delay),
+            EscalationStage::Sound => (EscalationStage::Overlay, overlay_delay),
+            EscalationStage::Overlay => return None,
+        };
+        self.since_stage += elapsed;
+        if self.since_stage < delay {
+            return None;
+        }
+        self.stage = next_stage;
+        self.since_stage = ZERO;
+        Some(next_stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalating_through_stages() {
+        let mut escalation = Escalation::default();
+        let mut advance = |seconds| {
+            escalation.advance(
+                Duration::from_secs(seconds),
+                Duration::from_secs(30),
+                Duration::from_secs(60),
+            )
+        };
+        assert_eq!(advance(29), None);
+        assert_eq!(advance(1), Some(EscalationStage::Sound));
+        assert_eq!(advance(59), None);
+        assert_eq!(advance(1), Some(EscalationStage::Overlay));
+        assert_eq!(advance(600), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Log of the events of the state (see [`TomataEvent`]) with the times
+//! they happened at, so the habits can be analyzed with other tools, e.g.,
+//! in Python or R. It is kept in the JSON lines format next to the history
+//! and dumped with `tomata export --events`. The events are written in the
+//! batches of the history (see [`crate::writes`]).
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::escalation::EscalationStage;
+use crate::events::TomataEvent;
+use crate::history::HistoryError;
+use crate::tomata::Period;
+
+pub const EVENT_LOG_FILE: &str = "events.jsonl";
+
+/// An example of every kind of the events, for the documentation of the schema.
+const EXAMPLE_EVENTS: [TomataEvent; 6] = [
+    TomataEvent::PeriodActivated(Period::Work),
+    TomataEvent::PeriodActivated(Period::ShortBreak),
+    TomataEvent::PeriodActivated(Period::Custom(0)),
+    TomataEvent::BlocklistToggled(Period::Work),
+    TomataEvent::AlertEscalated(EscalationStage::Sound),
+    TomataEvent::ApplicationQuitting,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LoggedEvent {
+    /// Seconds since the UNIX epoch.
+    pub timestamp: u64,
+    pub event: TomataEvent,
+}
+
+pub fn append_events(path: impl AsRef<Path>, events: &[LoggedEvent]) -> io::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    write_events(BufWriter::new(file), events)
+}
+
+/// Writes the events in the format of the log.
+pub fn write_events(mut writer: impl Write, events: &[LoggedEvent]) -> io::Result<()> {
+    for event in events {
+        writeln!(writer, "{}", serde_json::to_string(event)?)?;
+    }
+    writer.flush()
+}
+
+/// Returns the events logged at or after `since`, the oldest first.
+/// A missing log has no events.
+pub fn load_events_since(
+    path: impl AsRef<Path>,
+    since: u64,
+) -> Result<Vec<LoggedEvent>, HistoryError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    read_events(BufReader::new(file), since)
+}
+
+pub fn read_events(reader: impl BufRead, since: u64) -> Result<Vec<LoggedEvent>, HistoryError> {
+    let mut events = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: LoggedEvent =
+            serde_json::from_str(&line).map_err(|error| HistoryError::Malformed {
+                line: index + 1,
+                error,
+            })?;
+        if event.timestamp >= since {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+/// Documents the lines of the log with the examples serialized the way
+/// the events are, so it stays true when the events change.
+pub fn describe_schema() -> String {
+    let mut schema = String::from(
+        "Every line of the event log is a JSON object with the fields:\n\
+         \x20   timestamp    seconds since the UNIX epoch\n\
+         \x20   event.kind   the kind of the event\n\
+         \x20   event.value  the data of the event, missing when it has none\n\
+         \nThe kinds of the events:\n",
+    );
+    for event in EXAMPLE_EVENTS.iter() {
+        let logged = LoggedEvent {
+            timestamp: 1_700_000_000,
+            event: *event,
+        };
+        if let Ok(line) = serde_json::to_string(&logged) {
+            let _ = writeln!(schema, "    {}", line);
+        }
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logging_events() {
+        let path = std::env::temp_dir().join(format!("tomata-events-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(load_events_since(&path, 0).unwrap(), Vec::new());
+        let events: Vec<LoggedEvent> = EXAMPLE_EVENTS
+            .iter()
+            .enumerate()
+            .map(|(index, event)| LoggedEvent {
+                timestamp: 100 + index as u64,
+                event: *event,
+            })
+            .collect();
+        append_events(&path, &events[..2]).unwrap();
+        append_events(&path, &[]).unwrap();
+        append_events(&path, &events[2..]).unwrap();
+        assert_eq!(load_events_since(&path, 0).unwrap(), events);
+        assert_eq!(load_events_since(&path, 104).unwrap(), events[4..].to_vec());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn describing_schema() {
+        let schema = describe_schema();
+        assert!(schema.contains(
+            "\n    {\"timestamp\":1700000000,\"event\":{\"kind\":\"period_activated\",\"value\":\"Work\"}}\n"
+        ));
+        assert!(schema.contains(
+            "\n    {\"timestamp\":1700000000,\"event\":{\"kind\":\"period_activated\",\"value\":{\"Custom\":0}}}\n"
+        ));
+        assert!(schema.ends_with(
+            "\n    {\"timestamp\":1700000000,\"event\":{\"kind\":\"application_quitting\"}}\n"
+        ));
+        let lines = schema.lines().filter(|line| line.starts_with("    {"));
+        for line in lines {
+            assert!(read_events(line.as_bytes(), 0).is_ok());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Events emitted by [`TomataState`](crate::state::TomataState) when
+//! something significant happens, e.g., a new period begins. Integrations
+//! with other programs implement [`EventHook`] to react to them instead of
+//! being called from the state directly.
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::escalation::EscalationStage;
+use crate::settings::Settings;
+use crate::tomata::Period;
+
+/// Serialized for the event log (see [`crate::eventlog`]), the `kind` tells
+/// the event and the `value` carries its data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum TomataEvent {
+    PeriodActivated(Period),
+    /// Carries the current period.
+    BlocklistToggled(Period),
+    /// The alert about the finished period is still not acknowledged.
+    AlertEscalated(EscalationStage),
+    /// The application is about to quit, the integrations should undo their changes.
+    ApplicationQuitting,
+}
+
+pub trait EventHook: fmt::Debug {
+    /// Tells the hook apart when its failed event is retried.
+    fn get_name(&self) -> &'static str;
+    fn handle(&mut self, event: TomataEvent, settings: &Settings) -> io::Result<()>;
+}
@@ -0,0 +1,250 @@
+//! Reports of the history exported to CSV or Markdown files, e.g., for
+//! the work logs or the client billing. The durations are rounded
+//! according to the settings only in the reports, the history keeps
+//! the exact ones. The JSON export is the whole history instead, the
+//! breaks too, for the analysis in the spreadsheets and other tools.
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::history::{RecordedPeriod, SessionRecord};
+use crate::settings::{Settings, TimeRounding};
+use crate::tomata;
+use crate::tomata::ZERO;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    /// Tells the format by its name, which is also its extension.
+    pub fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "csv" => Some(ReportFormat::Csv),
+            "md" => Some(ReportFormat::Markdown),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Tells the format by the extension of `path`, `.csv`, `.md` or `.json`.
+    pub fn from_path(path: &Path) -> Option<ReportFormat> {
+        ReportFormat::from_name(path.extension()?.to_str()?)
+    }
+}
+
+/// A record of the JSON export, with its duration spelled out.
+#[derive(Serialize)]
+struct ExportedRecord<'a> {
+    #[serde(flatten)]
+    record: &'a SessionRecord,
+    /// The time between the start and the end, without the pauses.
+    focused_seconds: u64,
+}
+
+fn make_json_export(records: &[SessionRecord]) -> String {
+    let records: Vec<ExportedRecord<'_>> = records
+        .iter()
+        .map(|record| ExportedRecord {
+            record,
+            focused_seconds: record.calculate_focused_time().as_secs(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&records).unwrap_or_default()
+}
+
+pub fn round_duration(duration: Duration, rounding: TimeRounding, step: Duration) -> Duration {
+    let step = step.as_secs();
+    if step == 0 {
+        return duration;
+    }
+    let seconds = duration.as_secs();
+    let rounded = match rounding {
+        TimeRounding::Exact => return duration,
+        TimeRounding::Nearest => (seconds + step / 2) / step * step,
+        TimeRounding::Up if seconds % step == 0 && duration.subsec_nanos() == 0 => seconds,
+        TimeRounding::Up => (seconds / step + 1) * step,
+        TimeRounding::Down => seconds / step * step,
+    };
+    Duration::from_secs(rounded)
+}
+
+/// Makes the report of the sessions spent on work, i.e., the work
+/// periods, the meetings and the custom periods, the breaks are left out,
+/// except from the JSON export.
+pub fn make_report(records: &[SessionRecord], format: ReportFormat, settings: &Settings) -> String {
+    let rows: Vec<(Vec<String>, Duration)> = records
+        .iter()
+        .filter(|record| {
+            !matches!(
+                record.period,
+                RecordedPeriod::ShortBreak | RecordedPeriod::LongBreak
+            )
+        })
+        .map(|record| {
+            let duration = round_duration(
+                record.calculate_focused_time(),
+                settings.get_report_rounding(),
+                settings.get_report_rounding_step(),
+            );
+            let cells = vec![
+                tomata::timestamp_to_local_date_string(record.started_at),
+                tomata::timestamp_to_local_time_string(record.started_at),
+                tomata::timestamp_to_local_time_string(record.finished_at),
+                format!("{:?}", record.period),
+                record.tag.clone().unwrap_or_default(),
+                record
+                    .issue
+                    .as_ref()
+                    .map_or_else(String::new, |issue| issue.to_string()),
+                if record.is_aborted { "yes" } else { "no" }.to_string(),
+                tomata::duration_to_string(&duration),
+            ];
+            (cells, duration)
+        })
+        .collect();
+    let total = rows
+        .iter()
+        .fold(ZERO, |total, (_, duration)| total + *duration);
+    let header = [
+        "Date", "Start", "End", "Period", "Tag", "Issue", "Aborted", "Duration",
+    ];
+    match format {
+        ReportFormat::Csv => {
+            let mut report = header.join(",");
+            report.push('\n');
+            for (cells, _) in rows {
+                let cells: Vec<String> = cells.iter().map(|cell| escape_csv(cell)).collect();
+                report.push_str(&cells.join(","));
+                report.push('\n');
+            }
+            report
+        }
+        ReportFormat::Markdown => {
+            let mut report = format!("| {} |\n", header.join(" | "));
+            report.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+            for (cells, _) in rows {
+                let cells: Vec<String> =
+                    cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                report.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            report.push_str(&format!(
+                "\n**Total: {}**\n",
+                tomata::duration_to_string(&total)
+            ));
+            report
+        }
+        ReportFormat::Json => make_json_export(records),
+    }
+}
+
+/// Strips everything the user wrote or that names their work, i.e., the tags,
+/// the issues, the abort reasons and the applications, so the report keeps only
+/// the times, the durations and the counts and can be shared publicly.
+pub fn anonymize_records(records: Vec<SessionRecord>) -> Vec<SessionRecord> {
+    records
+        .into_iter()
+        .map(|record| SessionRecord {
+            tag: None,
+            abort_reason: None,
+            issue: None,
+            application: None,
+            ..record
+        })
+        .collect()
+}
+
+fn escape_csv(cell: &str) -> String {
+    if cell.contains([',', '"', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+pub fn export_report(
+    path: &Path,
+    format: ReportFormat,
+    records: &[SessionRecord],
+    settings: &Settings,
+) -> io::Result<()> {
+    fs::write(path, make_report(records, format, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomata::MINUTE_S;
+
+    #[test]
+    fn rounding_durations() {
+        let step = Duration::from_secs(15 * MINUTE_S);
+        let duration = Duration::from_secs(22 * MINUTE_S + 29);
+        let round = |rounding| round_duration(duration, rounding, step).as_secs() / MINUTE_S;
+        assert_eq!(round(TimeRounding::Nearest), 15);
+        assert_eq!(round(TimeRounding::Up), 30);
+        assert_eq!(round(TimeRounding::Down), 15);
+        assert_eq!(
+            round_duration(duration, TimeRounding::Exact, step),
+            duration
+        );
+        assert_eq!(round_duration(step, TimeRounding::Up, step), step);
+    }
+
+    #[test]
+    fn making_reports() {
+        let settings: Settings = serde_json::from_str(
+            r#"{"report_rounding": "Up", "report_rounding_step": {"secs": 300, "nanos": 0}}"#,
+        )
+        .unwrap();
+        let make_record = |period, tag: Option<&str>| SessionRecord {
+            tag: tag.map(str::to_string),
+            ..SessionRecord::new(period, 1000, 1000 + 23 * MINUTE_S)
+        };
+        let records = vec![
+            make_record(RecordedPeriod::Work, Some("API, part 1")),
+            make_record(RecordedPeriod::ShortBreak, None),
+            make_record(RecordedPeriod::Meeting, None),
+        ];
+        let csv = make_report(&records, ReportFormat::Csv, &settings);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with(",Work,\"API, part 1\",,no,00:25:00"));
+        let markdown = make_report(&records, ReportFormat::Markdown, &settings);
+        assert!(markdown.ends_with("**Total: 00:50:00**\n"));
+        let csv = make_report(&anonymize_records(records), ReportFormat::Csv, &settings);
+        assert!(csv.lines().nth(1).unwrap().ends_with(",Work,,,no,00:25:00"));
+    }
+
+    #[test]
+    fn exporting_whole_history_to_json() {
+        let record = SessionRecord {
+            paused_seconds: 60,
+            is_skipped: true,
+            ..SessionRecord::new(RecordedPeriod::ShortBreak, 1000, 1300)
+        };
+        let json = make_report(&[record], ReportFormat::Json, &Settings::default());
+        let exported: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            exported,
+            serde_json::json!([{
+                "period": "ShortBreak",
+                "started_at": 1000,
+                "finished_at": 1300,
+                "paused_seconds": 60,
+                "is_skipped": true,
+                "focused_seconds": 240,
+            }])
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("history.json")),
+            Some(ReportFormat::Json)
+        );
+    }
+}
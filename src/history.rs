@@ -0,0 +1,781 @@
+//! History of the finished sessions. The records are stored in the
+//! JSON lines format, i.e., one [`SessionRecord`] per line, so a new
+//! record can be appended without rewriting the whole file.
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::issue::IssueReference;
+use crate::tomata;
+use crate::tomata::Period;
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+/// How many days of the history the application keeps in memory.
+pub const RECENT_DAYS: u64 = 60;
+
+/// Besides the periods of the cycle the history holds the meetings
+/// (see [`TomataState::toggle_meeting_mode`](crate::state::TomataState::toggle_meeting_mode)).
+/// The names match [`Period`], so the older records stay readable.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RecordedPeriod {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+    Meeting,
+    /// One of the periods defined by the user, its name is the tag of the record.
+    Custom,
+}
+
+impl From<Period> for RecordedPeriod {
+    fn from(period: Period) -> RecordedPeriod {
+        match period {
+            Period::Work => RecordedPeriod::Work,
+            Period::ShortBreak => RecordedPeriod::ShortBreak,
+            Period::LongBreak => RecordedPeriod::LongBreak,
+            Period::Custom(_) => RecordedPeriod::Custom,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionRecord {
+    pub period: RecordedPeriod,
+    /// Seconds since the UNIX epoch.
+    pub started_at: u64,
+    /// Seconds since the UNIX epoch.
+    pub finished_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// The period was abandoned before it finished.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_aborted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_reason: Option<String>,
+    /// How long the stopwatch was paused between the start and the end.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub paused_seconds: u64,
+    /// The work went on for longer than the continuous work limit
+    /// because the breaks were skipped.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_long_run: bool,
+    /// The issue referred to by the tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue: Option<IssueReference>,
+    /// The application owning the focused window most of the session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application: Option<String>,
+    /// How many times the user switched to a distraction workspace.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub interruptions: u64,
+    /// The session went on past the midnight, the next record of the
+    /// period, starting at the midnight, holds the rest of it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_continued: bool,
+    /// The period was cut short by skipping to the next one.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_skipped: bool,
+}
+
+impl SessionRecord {
+    /// Makes the record of the period that ran from `started_at` until
+    /// `finished_at`, finished without pauses, tags or interruptions.
+    pub fn new(period: RecordedPeriod, started_at: u64, finished_at: u64) -> SessionRecord {
+        SessionRecord {
+            period,
+            started_at,
+            finished_at,
+            ..SessionRecord::default()
+        }
+    }
+
+    /// Wall-clock time between the start and the end, the pauses included.
+    pub fn calculate_span(&self) -> Duration {
+        Duration::from_secs(self.finished_at.saturating_sub(self.started_at))
+    }
+
+    /// Time actually spent in the period, the pauses excluded.
+    pub fn calculate_focused_time(&self) -> Duration {
+        Duration::from_secs(
+            self.finished_at
+                .saturating_sub(self.started_at)
+                .saturating_sub(self.paused_seconds),
+        )
+    }
+}
+
+/// Makes the record of a 25 minute work session finishing at `finished_at`
+/// for the tests.
+#[cfg(test)]
+pub(crate) fn make_work_record(finished_at: u64) -> SessionRecord {
+    SessionRecord::new(
+        RecordedPeriod::Work,
+        finished_at.saturating_sub(25 * tomata::MINUTE_S),
+        finished_at,
+    )
+}
+
+/// Splits the record of a session running across the midnights into one
+/// record for each day, so the statistics of every day get their part.
+/// The parts are cut a second before the midnights and the next ones start
+/// at the midnights, the pauses are divided in proportion and the
+/// interruptions stay with the last part.
+pub fn split_at_midnights(record: SessionRecord) -> Vec<SessionRecord> {
+    split_at_days(record, tomata::find_next_local_midnight)
+}
+
+fn split_at_days(
+    record: SessionRecord,
+    find_next_midnight: impl Fn(u64) -> u64,
+) -> Vec<SessionRecord> {
+    let mut parts = Vec::new();
+    let mut rest = record;
+    let mut midnight = find_next_midnight(rest.started_at);
+    while rest.finished_at > midnight {
+        // The records finishing at the midnight count for the next day.
+        let cut_at = midnight - 1;
+        let span = rest.finished_at - rest.started_at;
+        let paused_seconds =
+            (rest.paused_seconds * (cut_at - rest.started_at) / span).min(rest.paused_seconds);
+        parts.push(SessionRecord {
+            finished_at: cut_at,
+            paused_seconds,
+            interruptions: 0,
+            is_continued: true,
+            ..rest.clone()
+        });
+        rest.started_at = midnight;
+        rest.paused_seconds -= paused_seconds;
+        midnight = find_next_midnight(midnight);
+    }
+    parts.push(rest);
+    parts
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+pub fn append_records(path: impl AsRef<Path>, records: &[SessionRecord]) -> io::Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        serde_json::to_writer(&mut writer, record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Problems found while reading the history, the lines are counted from 1.
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(io::Error),
+    Malformed {
+        line: usize,
+        error: serde_json::Error,
+    },
+    /// The record finishes before it starts, e.g., it was edited by hand.
+    InvalidSpan {
+        line: usize,
+    },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Io(error) => write!(f, "could not read the file: {}", error),
+            HistoryError::Malformed { line, error } => {
+                write!(f, "malformed record on line {}: {}", line, error)
+            }
+            HistoryError::InvalidSpan { line } => {
+                write!(f, "the record on line {} finishes before it starts", line)
+            }
+        }
+    }
+}
+
+impl Error for HistoryError {}
+
+impl From<io::Error> for HistoryError {
+    fn from(error: io::Error) -> HistoryError {
+        HistoryError::Io(error)
+    }
+}
+
+/// Returns the records finished since `since` (seconds since the UNIX epoch),
+/// there are none if the history file does not exist yet.
+pub fn load_records_since(
+    path: impl AsRef<Path>,
+    since: u64,
+) -> Result<Vec<SessionRecord>, HistoryError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    read_records(BufReader::new(file), since)
+}
+
+/// Reads the records in the JSON lines format, the whole history is
+/// rejected on the first invalid line, e.g., the one cut short by a crash,
+/// rather than losing the records after it unnoticed.
+pub fn read_records(reader: impl BufRead, since: u64) -> Result<Vec<SessionRecord>, HistoryError> {
+    let mut records = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: SessionRecord =
+            serde_json::from_str(&line).map_err(|error| HistoryError::Malformed {
+                line: index + 1,
+                error,
+            })?;
+        if record.finished_at < record.started_at {
+            return Err(HistoryError::InvalidSpan { line: index + 1 });
+        }
+        if record.finished_at >= since {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// Returns up to `count` distinct tags of the work sessions, the most
+/// recently used first.
+pub fn list_recent_tags(records: &[SessionRecord], count: usize) -> Vec<&str> {
+    let mut tags: Vec<&str> = Vec::new();
+    let recent = records
+        .iter()
+        .rev()
+        .filter(|record| record.period == RecordedPeriod::Work)
+        .filter_map(|record| record.tag.as_deref());
+    for tag in recent {
+        if tags.len() == count {
+            break;
+        }
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Counts the sessions aborted since `since` (seconds since the UNIX epoch)
+/// for each of the reasons, the most frequent reasons come first.
+pub fn count_abort_reasons(records: &[SessionRecord], since: u64) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let aborted = records
+        .iter()
+        .filter(|record| record.is_aborted && !record.is_continued && record.finished_at >= since);
+    for record in aborted {
+        let reason = record.abort_reason.as_deref().unwrap_or("no reason");
+        match counts.iter_mut().find(|(counted, _)| counted == reason) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((reason.to_string(), 1)),
+        }
+    }
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    counts
+}
+
+/// Sums the wall-clock span and the focused time of the work sessions
+/// finished since `since` (seconds since the UNIX epoch).
+pub fn summarize_work_time(records: &[SessionRecord], since: u64) -> (Duration, Duration) {
+    records
+        .iter()
+        .filter(|record| record.period == RecordedPeriod::Work && record.finished_at >= since)
+        .fold(
+            (Duration::default(), Duration::default()),
+            |(span, focused), record| {
+                (
+                    span + record.calculate_span(),
+                    focused + record.calculate_focused_time(),
+                )
+            },
+        )
+}
+
+/// What was done since a moment, e.g., the beginning of the week.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Totals {
+    pub completed_pomodoros: usize,
+    pub focused_time: Duration,
+    /// The time on the breaks, without their pauses.
+    pub break_time: Duration,
+}
+
+/// Sums up the records finished since `since` (seconds since the UNIX epoch).
+pub fn sum_up(records: &[SessionRecord], since: u64) -> Totals {
+    let break_time = records
+        .iter()
+        .filter(|record| {
+            matches!(
+                record.period,
+                RecordedPeriod::ShortBreak | RecordedPeriod::LongBreak
+            ) && record.finished_at >= since
+        })
+        .map(SessionRecord::calculate_focused_time)
+        .sum();
+    Totals {
+        completed_pomodoros: count_completed_pomodoros(records, since),
+        focused_time: summarize_work_time(records, since).1,
+        break_time,
+    }
+}
+
+/// Counts the work sessions completed, i.e., not aborted, since `since`
+/// (seconds since the UNIX epoch).
+pub fn count_completed_pomodoros(records: &[SessionRecord], since: u64) -> usize {
+    records
+        .iter()
+        .filter(|record| {
+            record.period == RecordedPeriod::Work
+                && !record.is_aborted
+                && !record.is_continued
+                && record.finished_at >= since
+        })
+        .count()
+}
+
+/// Returns which part of the focused time of the work sessions finished
+/// since `since` (seconds since the UNIX epoch) was spent in each of
+/// the dominant applications, the biggest parts come first.
+pub fn summarize_applications(records: &[SessionRecord], since: u64) -> Vec<(String, f64)> {
+    let sessions: Vec<&SessionRecord> = records
+        .iter()
+        .filter(|record| record.period == RecordedPeriod::Work && record.finished_at >= since)
+        .collect();
+    let total: Duration = sessions
+        .iter()
+        .map(|record| record.calculate_focused_time())
+        .sum();
+    let mut times: Vec<(String, Duration)> = Vec::new();
+    for record in sessions {
+        let application = match &record.application {
+            Some(application) => application,
+            None => continue,
+        };
+        match times.iter_mut().find(|(sampled, _)| sampled == application) {
+            Some((_, time)) => *time += record.calculate_focused_time(),
+            None => times.push((application.clone(), record.calculate_focused_time())),
+        }
+    }
+    times.sort_by(|(_, a), (_, b)| b.cmp(a));
+    if total == Duration::default() {
+        return Vec::new();
+    }
+    times
+        .into_iter()
+        .map(|(application, time)| (application, time.as_secs_f64() / total.as_secs_f64()))
+        .collect()
+}
+
+/// Counts the work sessions finished since `since` (seconds since the UNIX
+/// epoch) past the continuous work limit.
+pub fn count_long_runs(records: &[SessionRecord], since: u64) -> usize {
+    records
+        .iter()
+        .filter(|record| record.is_long_run && !record.is_continued && record.finished_at >= since)
+        .count()
+}
+
+/// Counts the consecutive days up to `today` (the local midnight, as seconds
+/// since the UNIX epoch) with at least one completed pomodoro. The days off
+/// do not break the streak, neither does today until it is over.
+pub fn calculate_streak(
+    records: &[SessionRecord],
+    today: u64,
+    is_day_off: impl Fn(u64) -> bool,
+) -> usize {
+    let mut streak = 0;
+    let mut day = today;
+    let mut day_end = u64::MAX;
+    loop {
+        let has_pomodoro = records.iter().any(|record| {
+            record.period == RecordedPeriod::Work
+                && !record.is_aborted
+                && !record.is_continued
+                && (day..day_end).contains(&record.finished_at)
+        });
+        if has_pomodoro {
+            streak += 1;
+        } else if day != today && !is_day_off(day) {
+            break;
+        }
+        if records.iter().all(|record| record.finished_at >= day) {
+            break;
+        }
+        day_end = day;
+        day = tomata::start_of_local_day(day.saturating_sub(1));
+    }
+    streak
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimelineBlockKind {
+    Work,
+    Break,
+    Meeting,
+    Custom,
+    Pause,
+}
+
+/// Part of the day spent in one way, seconds since the UNIX epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineBlock {
+    pub kind: TimelineBlockKind,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub tag: Option<String>,
+}
+
+/// Lays out the records of the day between `day_start` and `day_end`
+/// in order, the sessions crossing the midnight are cut. The records keep
+/// only the total time of the pauses, it is shown at the end of each session.
+pub fn make_timeline(
+    records: &[SessionRecord],
+    day_start: u64,
+    day_end: u64,
+) -> Vec<TimelineBlock> {
+    let mut blocks = Vec::new();
+    for record in records {
+        let started_at = record.started_at.max(day_start);
+        let finished_at = record.finished_at.min(day_end);
+        if started_at >= finished_at {
+            continue;
+        }
+        let kind = match record.period {
+            RecordedPeriod::Work => TimelineBlockKind::Work,
+            RecordedPeriod::ShortBreak | RecordedPeriod::LongBreak => TimelineBlockKind::Break,
+            RecordedPeriod::Meeting => TimelineBlockKind::Meeting,
+            RecordedPeriod::Custom => TimelineBlockKind::Custom,
+        };
+        let paused_at = finished_at
+            .saturating_sub(record.paused_seconds)
+            .max(started_at);
+        blocks.push(TimelineBlock {
+            kind,
+            started_at,
+            finished_at: paused_at,
+            tag: record.tag.clone(),
+        });
+        if paused_at < finished_at {
+            blocks.push(TimelineBlock {
+                kind: TimelineBlockKind::Pause,
+                started_at: paused_at,
+                finished_at,
+                tag: record.tag.clone(),
+            });
+        }
+    }
+    blocks.retain(|block| block.started_at < block.finished_at);
+    blocks.sort_by_key(|block| block.started_at);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn appending_and_loading_records() {
+        let path =
+            std::env::temp_dir().join(format!("tomata-history-{}.jsonl", std::process::id()));
+        let record = SessionRecord {
+            tag: Some("API refactor".to_string()),
+            paused_seconds: 300,
+            ..make_work_record(1600)
+        };
+        append_records(&path, std::slice::from_ref(&record)).unwrap();
+        append_records(&path, std::slice::from_ref(&record)).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let loaded_records = load_records_since(&path, 1600).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded_records, vec![record.clone(), record.clone()]);
+        assert!(load_records_since(&path, 0).unwrap().is_empty());
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<SessionRecord>(lines[1]).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn counting_abort_reasons() {
+        let make_aborted_record = |reason: Option<&str>, finished_at| SessionRecord {
+            is_aborted: true,
+            abort_reason: reason.map(str::to_string),
+            ..make_work_record(finished_at)
+        };
+        let records = vec![
+            make_aborted_record(Some("phone call"), 10),
+            make_aborted_record(None, 20),
+            make_aborted_record(Some("phone call"), 30),
+            make_aborted_record(Some("meeting"), 5),
+        ];
+        assert_eq!(
+            count_abort_reasons(&records, 10),
+            vec![("phone call".to_string(), 2), ("no reason".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn counting_long_runs() {
+        let make_record = |finished_at, is_long_run| SessionRecord {
+            is_long_run,
+            ..make_work_record(finished_at)
+        };
+        let records = vec![
+            make_record(10, true),
+            make_record(20, false),
+            make_record(30, true),
+        ];
+        assert_eq!(count_long_runs(&records, 20), 1);
+    }
+
+    #[test]
+    fn summarizing_work_time() {
+        let make_record = |period, started_at, finished_at, paused_seconds| SessionRecord {
+            paused_seconds,
+            ..SessionRecord::new(period, started_at, finished_at)
+        };
+        let records = vec![
+            make_record(RecordedPeriod::Work, 0, 100, 10),
+            make_record(RecordedPeriod::ShortBreak, 100, 150, 0),
+            make_record(RecordedPeriod::Work, 150, 250, 0),
+            make_record(RecordedPeriod::Work, 250, 300, 20),
+        ];
+        assert_eq!(
+            summarize_work_time(&records, 150),
+            (Duration::from_secs(150), Duration::from_secs(130))
+        );
+        assert_eq!(
+            sum_up(&records, 100),
+            Totals {
+                completed_pomodoros: 3,
+                focused_time: Duration::from_secs(220),
+                break_time: Duration::from_secs(50),
+            }
+        );
+    }
+
+    #[test]
+    fn calculating_streak_over_days_off() {
+        let today = tomata::start_of_local_day(100 * tomata::DAY_S);
+        let day = |days_ago: u64| tomata::start_of_local_day(today - days_ago * tomata::DAY_S);
+        let make_record = |finished_at, is_aborted| SessionRecord {
+            is_aborted,
+            ..make_work_record(finished_at)
+        };
+        let records = vec![
+            make_record(day(5) + 2 * tomata::HOUR_S, false),
+            make_record(day(3) + 2 * tomata::HOUR_S, false),
+            make_record(day(1) + 2 * tomata::HOUR_S, true),
+            make_record(day(1) + 3 * tomata::HOUR_S, false),
+        ];
+        // Today is not over yet and the 2nd day ago was off.
+        assert_eq!(calculate_streak(&records, today, |d| d == day(2)), 2);
+        assert_eq!(
+            calculate_streak(&records, today, |d| d == day(2) || d == day(4)),
+            3
+        );
+        assert_eq!(calculate_streak(&records, today, |_| false), 1);
+        assert_eq!(calculate_streak(&[], today, |_| true), 0);
+    }
+
+    #[test]
+    fn summarizing_applications() {
+        let make_record = |minutes: u64, application: Option<&str>| SessionRecord {
+            application: application.map(str::to_string),
+            ..SessionRecord::new(RecordedPeriod::Work, 1000, 1000 + minutes * 60)
+        };
+        let records = vec![
+            make_record(30, Some("firefox")),
+            make_record(60, Some("Code")),
+            make_record(10, None),
+            make_record(30, Some("Code")),
+        ];
+        let summary = summarize_applications(&records, 0);
+        let percents: Vec<(&str, u64)> = summary
+            .iter()
+            .map(|(application, share)| (application.as_str(), (share * 100.0).round() as u64))
+            .collect();
+        assert_eq!(percents, vec![("Code", 69), ("firefox", 23)]);
+        assert!(summarize_applications(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn making_timeline_of_day() {
+        let make_record = |period, started_at, finished_at, paused_seconds| SessionRecord {
+            paused_seconds,
+            ..SessionRecord::new(period, started_at, finished_at)
+        };
+        let records = vec![
+            make_record(RecordedPeriod::ShortBreak, 2500, 2800, 0),
+            make_record(RecordedPeriod::Work, 1000, 2500, 300),
+            make_record(RecordedPeriod::Meeting, 500, 1000, 0),
+            make_record(RecordedPeriod::Work, 4000, 6000, 0),
+        ];
+        let blocks: Vec<(TimelineBlockKind, u64, u64)> = make_timeline(&records, 800, 5000)
+            .into_iter()
+            .map(|block| (block.kind, block.started_at, block.finished_at))
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                (TimelineBlockKind::Meeting, 800, 1000),
+                (TimelineBlockKind::Work, 1000, 2200),
+                (TimelineBlockKind::Pause, 2200, 2500),
+                (TimelineBlockKind::Break, 2500, 2800),
+                (TimelineBlockKind::Work, 4000, 5000),
+            ]
+        );
+    }
+
+    #[test]
+    fn splitting_session_at_midnight() {
+        let midnight = tomata::find_next_local_midnight(tomata::now_timestamp());
+        let record = SessionRecord {
+            tag: Some("API".to_string()),
+            paused_seconds: 300,
+            interruptions: 2,
+            ..SessionRecord::new(RecordedPeriod::Work, midnight - 600, midnight + 900)
+        };
+        let parts = split_at_midnights(record.clone());
+        let spans: Vec<(u64, u64, u64, u64)> = parts
+            .iter()
+            .map(|part| {
+                (
+                    part.started_at,
+                    part.finished_at,
+                    part.paused_seconds,
+                    part.interruptions,
+                )
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (midnight - 600, midnight - 1, 119, 0),
+                (midnight, midnight + 900, 181, 2)
+            ]
+        );
+        assert_eq!(parts[1].started_at, midnight);
+        assert!(parts[0].is_continued && !parts[1].is_continued);
+        assert_eq!(count_completed_pomodoros(&parts, 0), 1);
+        let (_, focused) = summarize_work_time(&parts, midnight);
+        assert_eq!(focused, Duration::from_secs(719));
+        let within_day = make_work_record(midnight + 1600);
+        assert_eq!(split_at_midnights(within_day.clone()), vec![within_day]);
+    }
+
+    #[test]
+    fn splitting_session_across_daylight_saving_time_changes() {
+        // The day starting at 100_000 is an hour shorter, the next one an hour longer.
+        let find_next_midnight = |timestamp: u64| match timestamp {
+            t if t < 100_000 => 100_000,
+            t if t < 100_000 + 23 * tomata::HOUR_S => 100_000 + 23 * tomata::HOUR_S,
+            _ => 100_000 + 48 * tomata::HOUR_S,
+        };
+        let record =
+            SessionRecord::new(RecordedPeriod::Work, 90_000, 100_000 + 30 * tomata::HOUR_S);
+        let days: Vec<(u64, u64)> = split_at_days(record, find_next_midnight)
+            .iter()
+            .map(|part| (part.started_at, part.finished_at))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                (90_000, 99_999),
+                (100_000, 99_999 + 23 * tomata::HOUR_S),
+                (100_000 + 23 * tomata::HOUR_S, 100_000 + 30 * tomata::HOUR_S),
+            ]
+        );
+    }
+
+    #[test]
+    fn loading_fuzzing_seeds() {
+        let seeds = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds/load_history");
+        for entry in fs::read_dir(seeds).unwrap() {
+            let path = entry.unwrap().path();
+            let is_valid = path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("valid");
+            let result = load_records_since(&path, 0);
+            assert_eq!(result.is_ok(), is_valid, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn rejecting_truncated_history() {
+        let history = "{\"period\":\"Work\",\"started_at\":100,\"finished_at\":1600}\n\
+                       {\"period\":\"ShortBreak\",\"started_at\":1600,\"finished_at\":1900}\n";
+        let first_line_end = history.find('\n').unwrap();
+        for end in 0..history.len() {
+            let result = read_records(&history.as_bytes()[..end], 0);
+            match end {
+                0 => assert!(result.unwrap().is_empty()),
+                end if end == first_line_end || end == first_line_end + 1 => {
+                    assert_eq!(result.unwrap().len(), 1)
+                }
+                end if end < first_line_end => {
+                    assert!(matches!(
+                        result,
+                        Err(HistoryError::Malformed { line: 1, .. })
+                    ))
+                }
+                end if end == history.len() - 1 => assert_eq!(result.unwrap().len(), 2),
+                _ => assert!(matches!(
+                    result,
+                    Err(HistoryError::Malformed { line: 2, .. })
+                )),
+            }
+        }
+        assert_eq!(read_records(history.as_bytes(), 0).unwrap().len(), 2);
+        let reversed = "{\"period\":\"Work\",\"started_at\":1600,\"finished_at\":100}";
+        assert!(matches!(
+            read_records(reversed.as_bytes(), 0),
+            Err(HistoryError::InvalidSpan { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn listing_recent_tags() {
+        let make_record = |period, tag: Option<&str>| SessionRecord {
+            tag: tag.map(str::to_string),
+            ..SessionRecord::new(period, 100, 1600)
+        };
+        let records = vec![
+            make_record(RecordedPeriod::Work, Some("PROJ-1")),
+            make_record(RecordedPeriod::Work, Some("Email")),
+            make_record(RecordedPeriod::Work, Some("API")),
+            make_record(RecordedPeriod::Custom, Some("Reading")),
+            make_record(RecordedPeriod::Work, None),
+            make_record(RecordedPeriod::Work, Some("PROJ-1")),
+        ];
+        assert_eq!(
+            list_recent_tags(&records, 5),
+            vec!["PROJ-1", "API", "Email"]
+        );
+        assert_eq!(list_recent_tags(&records, 2), vec!["PROJ-1", "API"]);
+    }
+}
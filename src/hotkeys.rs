@@ -0,0 +1,162 @@
+//! System-wide shortcuts starting, pausing and skipping the periods while
+//! the window is unfocused or minimized. The keys are grabbed by `sxhkd`
+//! with a generated configuration, its commands are the control subcommands
+//! of the application (see [`crate::control`]).
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command};
+
+use crate::control;
+use crate::control::ControlCommand;
+use crate::users;
+
+pub const HOTKEYS_CONFIG_FILE: &str = "hotkeys.sxhkdrc";
+
+/// The names of the modifiers in the settings and their names in `sxhkd`.
+const MODIFIERS: &[(&str, &str)] = &[
+    ("ctrl", "ctrl"),
+    ("control", "ctrl"),
+    ("alt", "alt"),
+    ("shift", "shift"),
+    ("super", "super"),
+    ("win", "super"),
+    ("meta", "super"),
+];
+
+/// The keys with names, as the X keysyms.
+const NAMED_KEYS: &[(&str, &str)] = &[
+    ("space", "space"),
+    ("enter", "Return"),
+    ("up", "Up"),
+    ("down", "Down"),
+    ("left", "Left"),
+    ("right", "Right"),
+    ("home", "Home"),
+    ("end", "End"),
+    ("pageup", "Prior"),
+    ("pagedown", "Next"),
+    ("insert", "Insert"),
+    ("delete", "Delete"),
+    ("pause", "Pause"),
+];
+
+/// Converts a shortcut like `Ctrl+Alt+P` to the chord of `sxhkd`, i.e.,
+/// `ctrl + alt + p`. A modifier is required, so the key alone keeps working
+/// in the other applications.
+pub fn parse_hotkey(text: &str) -> Option<String> {
+    let parts: Vec<String> = text
+        .split('+')
+        .map(|part| part.trim().to_lowercase())
+        .collect();
+    let (key, modifiers) = parts.split_last()?;
+    if modifiers.is_empty() {
+        return None;
+    }
+    let mut chord: Vec<String> = Vec::new();
+    for modifier in modifiers {
+        let (_, name) = MODIFIERS.iter().find(|(alias, _)| alias == modifier)?;
+        if !chord.iter().any(|added| added == name) {
+            chord.push(name.to_string());
+        }
+    }
+    chord.push(parse_key(key)?);
+    Some(chord.join(" + "))
+}
+
+fn parse_key(key: &str) -> Option<String> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => return Some(c.to_string()),
+        (Some('f'), Some(_)) => {
+            if let Ok(number @ 1..=24) = key[1..].parse::<u8>() {
+                return Some(format!("F{}", number));
+            }
+        }
+        _ => {}
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, keysym)| keysym.to_string())
+}
+
+#[derive(Debug)]
+pub struct GlobalHotkeys {
+    process: Child,
+}
+
+impl GlobalHotkeys {
+    /// Grabs the chords, each running its command with the data
+    /// of the current directory.
+    pub fn start(bindings: &[(String, ControlCommand)]) -> io::Result<GlobalHotkeys> {
+        let executable = env::current_exe()?;
+        fs::write(HOTKEYS_CONFIG_FILE, make_config(&executable, bindings))?;
+        let process = Command::new("sxhkd")
+            .args(["-c", HOTKEYS_CONFIG_FILE])
+            // The working directory is already the one of the user.
+            .env_remove(users::USER_VARIABLE)
+            .spawn()?;
+        Ok(GlobalHotkeys { process })
+    }
+}
+
+impl Drop for GlobalHotkeys {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn make_config(executable: &Path, bindings: &[(String, ControlCommand)]) -> String {
+    bindings
+        .iter()
+        .map(|(chord, command)| {
+            format!(
+                "{}\n\t{}\n",
+                chord,
+                control::make_shell_command(executable, command)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_hotkeys() {
+        assert_eq!(
+            parse_hotkey("Ctrl+Alt+P").as_deref(),
+            Some("ctrl + alt + p")
+        );
+        assert_eq!(
+            parse_hotkey(" win + shift + PageDown ").as_deref(),
+            Some("super + shift + Next")
+        );
+        assert_eq!(
+            parse_hotkey("Control+Ctrl+F12").as_deref(),
+            Some("ctrl + F12")
+        );
+        assert_eq!(parse_hotkey("P"), None);
+        assert_eq!(parse_hotkey("Ctrl+"), None);
+        assert_eq!(parse_hotkey("Hyper+P"), None);
+        assert_eq!(parse_hotkey("Ctrl+F25"), None);
+        assert_eq!(parse_hotkey("Ctrl+Ä"), None);
+    }
+
+    #[test]
+    fn making_config() {
+        let bindings = vec![
+            ("ctrl + alt + Up".to_string(), ControlCommand::Resume),
+            ("ctrl + alt + Right".to_string(), ControlCommand::SkipPeriod),
+        ];
+        assert_eq!(
+            make_config(Path::new("/opt/my apps/tomata"), &bindings),
+            "ctrl + alt + Up\n\t'/opt/my apps/tomata' resume\n\
+             ctrl + alt + Right\n\t'/opt/my apps/tomata' skip-period\n"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Detection of the time that passed since the last keyboard or mouse
+//! input of the user. There is no portable way of doing that, so each
+//! platform queries a different system tool.
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+use std::time::Duration;
+
+pub trait IdleDetector: fmt::Debug {
+    /// Returns `None` when the idle time cannot be determined.
+    fn idle_time(&self) -> Option<Duration>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemIdleDetector;
+
+impl IdleDetector for SystemIdleDetector {
+    #[cfg(target_os = "linux")]
+    fn idle_time(&self) -> Option<Duration> {
+        // `xprintidle` covers X11 sessions, GNOME on Wayland
+        // exposes the idle time through its idle monitor.
+        run_command("xprintidle", &[])
+            .and_then(|output| parse_xprintidle_output(&output))
+            .or_else(|| {
+                run_command(
+                    "gdbus",
+                    &[
+                        "call",
+                        "--session",
+                        "--dest",
+                        "org.gnome.Mutter.IdleMonitor",
+                        "--object-path",
+                        "/org/gnome/Mutter/IdleMonitor/Core",
+                        "--method",
+                        "org.gnome.Mutter.IdleMonitor.GetIdletime",
+                    ],
+                )
+                .and_then(|output| parse_mutter_output(&output))
+            })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn idle_time(&self) -> Option<Duration> {
+        run_command("ioreg", &["-c", "IOHIDSystem"]).and_then(|output| parse_ioreg_output(&output))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// `xprintidle` prints the idle time in milliseconds, e.g., `1234`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_xprintidle_output(output: &str) -> Option<Duration> {
+    output.trim().parse().ok().map(Duration::from_millis)
+}
+
+/// The idle monitor replies with a tuple in milliseconds, e.g., `(uint64 1234,)`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_mutter_output(output: &str) -> Option<Duration> {
+    let milliseconds = output
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .trim_start_matches("uint64")
+        .trim();
+    milliseconds.parse().ok().map(Duration::from_millis)
+}
+
+/// `ioreg` lists `"HIDIdleTime" = 1234` among other properties, in nanoseconds.
+#[cfg(any(target_os = "macos", test))]
+fn parse_ioreg_output(output: &str) -> Option<Duration> {
+    output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))
+        .and_then(|line| line.rsplit('=').next())
+        .and_then(|nanoseconds| nanoseconds.trim().parse().ok())
+        .map(Duration::from_nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_xprintidle_output() {
+        assert_eq!(
+            parse_xprintidle_output("1500\n"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_xprintidle_output("couldn't open display"), None);
+    }
+
+    #[test]
+    fn parsing_mutter_output() {
+        assert_eq!(
+            parse_mutter_output("(uint64 2500,)\n"),
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn parsing_ioreg_output() {
+        let output =
+            "    | |   \"HIDIdleTime\" = 3000000000\n    | |   \"HIDMouseAcceleration\" = 45056";
+        assert_eq!(parse_ioreg_output(output), Some(Duration::from_secs(3)));
+    }
+}
@@ -0,0 +1,134 @@
+//! References to the issues in the trackers, e.g., `PROJ-123` in Jira
+//! or `org/repo#45` on GitHub, found in the session tags and stored
+//! with the records, so the work logs map to the tickets.
+use std::fmt;
+use std::io;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "tracker")]
+pub enum IssueReference {
+    Jira { key: String },
+    GitHub { repository: String, number: u64 },
+}
+
+impl IssueReference {
+    /// Finds the first reference among the words of `text`.
+    pub fn find(text: &str) -> Option<IssueReference> {
+        text.split_whitespace()
+            .map(|word| word.trim_matches(|c: char| "()[]{}<>,.;:'\"".contains(c)))
+            .find_map(IssueReference::parse)
+    }
+
+    fn parse(word: &str) -> Option<IssueReference> {
+        if let Some((repository, number)) = word.split_once('#') {
+            let is_repository = matches!(repository.split_once('/'), Some((owner, name))
+                if is_repository_name(owner) && is_repository_name(name));
+            return match (is_repository, is_number(number)) {
+                (true, true) => Some(IssueReference::GitHub {
+                    repository: repository.to_string(),
+                    number: number.parse().ok()?,
+                }),
+                _ => None,
+            };
+        }
+        let (project, number) = word.split_once('-')?;
+        let is_project = project.starts_with(|c: char| c.is_ascii_uppercase())
+            && project.len() > 1
+            && project
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        if is_project && is_number(number) {
+            Some(IssueReference::Jira {
+                key: word.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the address of the issue, the Jira issues need
+    /// the address of the Jira instance, e.g., `https://example.atlassian.net`.
+    pub fn make_url(&self, jira_url: Option<&str>) -> Option<String> {
+        match self {
+            IssueReference::Jira { key } => {
+                jira_url.map(|url| format!("{}/browse/{}", url.trim_end_matches('/'), key))
+            }
+            IssueReference::GitHub { repository, number } => Some(format!(
+                "https://github.com/{}/issues/{}",
+                repository, number
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IssueReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssueReference::Jira { key } => write!(f, "{}", key),
+            IssueReference::GitHub { repository, number } => {
+                write!(f, "{}#{}", repository, number)
+            }
+        }
+    }
+}
+
+fn is_repository_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Opens `url` in the default web browser.
+pub fn open_in_browser(url: &str) -> io::Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(windows) {
+        ("cmd", &["/C", "start", ""])
+    } else if cfg!(target_os = "macos") {
+        ("open", &[])
+    } else {
+        ("xdg-open", &[])
+    };
+    Command::new(program).args(args).arg(url).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_references() {
+        assert_eq!(
+            IssueReference::find("Fix login (PROJ-123)"),
+            Some(IssueReference::Jira {
+                key: "PROJ-123".to_string()
+            })
+        );
+        let github = IssueReference::find("review khrynczenko/tomata#45, then lunch").unwrap();
+        assert_eq!(github.to_string(), "khrynczenko/tomata#45");
+        assert_eq!(
+            github.make_url(None).as_deref(),
+            Some("https://github.com/khrynczenko/tomata/issues/45")
+        );
+        assert_eq!(IssueReference::find("write e-mails, #3, Proj-1"), None);
+    }
+
+    #[test]
+    fn making_jira_url() {
+        let issue = IssueReference::find("PROJ-7").unwrap();
+        assert_eq!(issue.make_url(None), None);
+        assert_eq!(
+            issue
+                .make_url(Some("https://example.atlassian.net/"))
+                .as_deref(),
+            Some("https://example.atlassian.net/browse/PROJ-7")
+        );
+    }
+}
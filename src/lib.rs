@@ -0,0 +1,62 @@
+//! The Pomodoro engine of tomata, i.e., the state with the cycling of the
+//! periods, the settings and the history, usable by the other frontends and
+//! tools. The druid interface is built on top of it, `druid::Data` and
+//! `druid::Lens` are derived with the `druid` feature, enabled by default.
+#![deny(
+    warnings,
+    unused,
+    missing_debug_implementations,
+    rust_2018_idioms,
+    rust_2021_compatibility,
+    nonstandard_style,
+    future_incompatible,
+    clippy::all
+)]
+#![forbid(unsafe_code)]
+
+pub mod activity;
+pub mod ambient;
+pub mod badge;
+pub mod blocklist;
+pub mod calendar;
+pub mod clock;
+pub mod control;
+pub mod countdown;
+pub mod cycle;
+pub mod debug;
+pub mod errors;
+pub mod escalation;
+pub mod eventlog;
+pub mod events;
+pub mod export;
+pub mod history;
+pub mod hotkeys;
+pub mod idle;
+pub mod issue;
+pub mod lock;
+pub mod microphone;
+pub mod notifier;
+pub mod ntfy;
+pub mod openrgb;
+pub mod org;
+pub mod planning;
+pub mod probes;
+pub mod profile;
+pub mod reminder;
+pub mod server;
+pub mod settings;
+pub mod signals;
+pub mod sound;
+pub mod speech;
+pub mod state;
+pub mod suggestions;
+pub mod summary;
+#[cfg(feature = "druid")]
+pub mod theme;
+pub mod timezone;
+pub mod tomata;
+pub mod users;
+pub mod wallpaper;
+pub mod whatsnew;
+pub mod workspace;
+pub mod writes;
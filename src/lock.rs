@@ -0,0 +1,54 @@
+//! Locking the screen, or starting the screensaver where locking is not
+//! available, so the user actually steps away during the long break.
+//! Each platform uses a different system tool.
+use std::fmt;
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+use std::process::Command;
+
+pub trait ScreenLocker: fmt::Debug {
+    fn lock_screen(&self) -> io::Result<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemScreenLocker;
+
+impl ScreenLocker for SystemScreenLocker {
+    #[cfg(target_os = "linux")]
+    fn lock_screen(&self) -> io::Result<()> {
+        // `loginctl` works with every desktop that integrates with systemd-logind,
+        // `xdg-screensaver` covers the remaining X11 sessions.
+        run_command("loginctl", &["lock-session"])
+            .or_else(|_| run_command("xdg-screensaver", &["lock"]))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn lock_screen(&self) -> io::Result<()> {
+        run_command("open", &["-a", "ScreenSaverEngine"])
+    }
+
+    #[cfg(windows)]
+    fn lock_screen(&self) -> io::Result<()> {
+        run_command("rundll32.exe", &["user32.dll,LockWorkStation"])
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    fn lock_screen(&self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "locking the screen is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+fn run_command(program: &str, args: &[&str]) -> io::Result<()> {
+    let status = Command::new(program).args(args).status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("`{}` exited with {}", program, status),
+        ));
+    }
+    Ok(())
+}
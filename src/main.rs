@@ -12,31 +12,202 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod settings;
-mod sound;
-mod state;
-mod tomata;
+mod chart;
+mod cli;
+mod engine;
+mod overlay;
+mod tray;
 mod widget;
 
-use druid::{AppLauncher, PlatformError, WindowDesc};
+// The modules of the engine, so the interface refers to them as to its own.
+use tomata_core::{
+    ambient, badge, blocklist, calendar, clock, control, countdown, cycle, errors, eventlog,
+    events, export, history, hotkeys, issue, lock, ntfy, openrgb, org, planning, probes, profile,
+    reminder, server, settings, signals, sound, state, summary, theme, timezone, tomata, users,
+    wallpaper, whatsnew, writes,
+};
 
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use druid::{AppLauncher, PlatformError, Target};
+
+use cli::CliCommand;
+use engine::EngineDelegate;
 use settings::Settings;
-use sound::{SoundSystem, BEEPER};
+#[cfg(unix)]
+use signals::{ReceivedSignal, SignalWatcher};
+use sound::SilentAudioBackend;
 use state::TomataState;
-use tomata::{APPLICATION_NAME, WINDOW_SIZE_PX};
-use widget::TomataApp;
 
 fn main() -> Result<(), PlatformError> {
-    let window = WindowDesc::new(TomataApp::new)
-        .title(APPLICATION_NAME)
-        .window_size(WINDOW_SIZE_PX)
-        .resizable(false);
-    BEEPER.set(SoundSystem::default()).unwrap();
-
-    let settings_result = settings::load_settings_from_file("settings.json");
-    let settings = settings_result.unwrap_or_else(|| {
+    // The local time of every part is in the time zone read before any
+    // other thread starts.
+    timezone::init_local_time_zone();
+    // Every data file is relative to the working directory, the user's one
+    // when the users are separated, for the subcommands too.
+    let current_user = users::find_current_user();
+    if let Some(user) = &current_user {
+        if let Err(error) = users::enter_user_directory(user) {
+            eprintln!("Could not use the data of {}: {}", user, error);
+            std::process::exit(cli::EXIT_FAILURE);
+        }
+    }
+    match cli::parse_args(std::env::args().skip(1)) {
+        Ok(CliCommand::LaunchApplication) => launch_application(current_user, false, false),
+        Ok(CliCommand::LaunchDebug) => launch_application(current_user, true, false),
+        Ok(CliCommand::LaunchBig) => launch_application(current_user, false, true),
+        Ok(CliCommand::LaunchHeadless) => {
+            launch_headless(current_user);
+            Ok(())
+        }
+        Ok(CliCommand::CheckConfig(path)) => std::process::exit(cli::check_config(&path)),
+        Ok(CliCommand::ExportProfile(path)) => std::process::exit(cli::export_profile(&path)),
+        Ok(CliCommand::ImportProfile(path)) => std::process::exit(cli::import_profile(&path)),
+        Ok(CliCommand::ExportHistory {
+            path,
+            format,
+            is_anonymized,
+        }) => std::process::exit(cli::export_history(&path, format, is_anonymized)),
+        Ok(CliCommand::ExportEvents { path, since }) => {
+            std::process::exit(cli::export_events(path.as_deref(), since))
+        }
+        Ok(CliCommand::DescribeEventSchema) => {
+            print!("{}", eventlog::describe_schema());
+            std::process::exit(cli::EXIT_SUCCESS)
+        }
+        Ok(CliCommand::Control(command)) => std::process::exit(cli::send_control_command(&command)),
+        // The launched application reads the URI from the control directory.
+        Ok(CliCommand::OpenUri(command)) => match cli::open_uri(&command) {
+            Ok(true) => launch_application(current_user, false, false),
+            Ok(false) => std::process::exit(cli::EXIT_SUCCESS),
+            Err(code) => std::process::exit(code),
+        },
+        Ok(CliCommand::RegisterUriHandler) => std::process::exit(cli::register_uri_handler()),
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            std::process::exit(cli::EXIT_FAILURE);
+        }
+    }
+}
+
+fn launch_application(
+    current_user: Option<String>,
+    is_debugging: bool,
+    is_big: bool,
+) -> Result<(), PlatformError> {
+    #[cfg(unix)]
+    let signal_watcher = block_signals();
+    let window = widget::make_main_window(is_big);
+    let (mut state, is_fresh_install) = load_state(current_user, false);
+    if is_fresh_install {
+        state.start_onboarding();
+    }
+    if is_big {
+        state.toggle_big_display();
+    }
+    if is_debugging {
+        state.enable_debug_log();
+    }
+    match whatsnew::load_ui_state(whatsnew::UI_STATE_FILE) {
+        Ok(mut ui_state) => {
+            if state.get_settings().is_whats_new_shown() {
+                state.set_whats_new(whatsnew::find_whats_new(
+                    ui_state.last_run_version.as_deref(),
+                    is_fresh_install,
+                ));
+            }
+            ui_state.last_run_version = Some(whatsnew::VERSION.to_string());
+            let today = tomata::start_of_local_day(tomata::now_timestamp());
+            if ui_state.last_planned_day == Some(today) {
+                state.set_primary_project(ui_state.primary_project.clone());
+            } else if state.get_settings().is_day_plan_asked() {
+                state.show_day_plan_prompt();
+                ui_state.last_planned_day = Some(today);
+                ui_state.primary_project = None;
+            }
+            if let Err(error) = whatsnew::save_ui_state(whatsnew::UI_STATE_FILE, &ui_state) {
+                eprintln!("Could not save the UI state: {}", error);
+            }
+        }
+        Err(error) => eprintln!("Could not read the UI state: {}", error),
+    }
+    let launcher = AppLauncher::with_window(window);
+    let probes = engine::start_probes(launcher.get_external_handle());
+    let launcher = launcher.delegate(EngineDelegate::new(probes));
+    engine::start_clock(launcher.get_external_handle());
+    #[cfg(unix)]
+    if let Some(watcher) = signal_watcher {
+        let sink = launcher.get_external_handle();
+        watcher.watch(move |signal| {
+            let _ = match signal {
+                ReceivedSignal::Terminate => {
+                    sink.submit_command(engine::SHUT_DOWN, (), Target::Auto)
+                }
+                _ => sink.submit_command(engine::SIGNAL, signal, Target::Auto),
+            };
+        });
+    }
+    launcher.launch(state)?;
+    Ok(())
+}
+
+fn launch_headless(current_user: Option<String>) {
+    #[cfg(unix)]
+    let signal_watcher = block_signals();
+    let (mut state, is_fresh_install) = load_state(current_user, true);
+    // Without the wizard the defaults are written right away.
+    if is_fresh_install {
+        state.request_settings_save();
+    }
+    let (signal_sender, signals) = mpsc::channel();
+    #[cfg(unix)]
+    if let Some(watcher) = signal_watcher {
+        watcher.watch(move |signal| {
+            let _ = signal_sender.send(signal);
+        });
+    }
+    #[cfg(not(unix))]
+    drop(signal_sender);
+    engine::run_headless(state, signals);
+}
+
+/// The signals are blocked before the sound system starts its threads,
+/// which would receive them otherwise.
+#[cfg(unix)]
+fn block_signals() -> Option<SignalWatcher> {
+    SignalWatcher::block()
+        .map_err(|error| eprintln!("Could not block the signals: {}", error))
+        .ok()
+}
+
+/// Returns the state with the saved data and whether it is the first launch.
+/// The headless mode plays no sounds, it runs on the machines without them.
+fn load_state(current_user: Option<String>, is_headless: bool) -> (TomataState, bool) {
+    // On the first launch the wizard asks for the initial settings and
+    // writes them, instead of silently creating the defaults.
+    let is_fresh_install = !Path::new(settings::SETTINGS_FILE).exists();
+    let settings_result = settings::load_settings_from_file(settings::SETTINGS_FILE);
+    let mut settings_error = None;
+    let settings = settings_result.unwrap_or_else(|error| {
+        if is_fresh_install {
+            return Settings::default();
+        }
+        // The rejected file is kept aside rather than overwritten.
+        settings_error = Some(
+            match fs::rename(settings::SETTINGS_FILE, settings::REJECTED_SETTINGS_FILE) {
+                Ok(()) => format!(
+                    "The settings were reset, the rejected ones are in `{}`: {}",
+                    settings::REJECTED_SETTINGS_FILE,
+                    error
+                ),
+                Err(_) => format!("The settings were reset: {}", error),
+            },
+        );
         let settings = Settings::default();
-        settings::save_settings_to_file(&settings, "settings.json").unwrap_or_else(|_| {
+        settings::save_settings_to_file(&settings, settings::SETTINGS_FILE).unwrap_or_else(|_| {
             panic!(
                 "{} {}",
                 "Could not create `settings.json`", "to store the application settings."
@@ -45,7 +216,33 @@ fn main() -> Result<(), PlatformError> {
         settings
     });
 
-    let state = TomataState::new(settings);
-    AppLauncher::with_window(window).launch(state)?;
-    Ok(())
+    let mut state = TomataState::new(settings);
+    if let Some(message) = settings_error {
+        state.report_error(message, None);
+    }
+    if is_headless {
+        state.set_audio_backend(Rc::new(SilentAudioBackend));
+    } else if let Err(error) = sound::prepare_default_output() {
+        // It is set up again at the next sound.
+        state.report_error(format!("Could not set up the sound: {}", error), None);
+    }
+    let users = users::list_users(current_user.as_deref());
+    state.set_users(current_user, users);
+    state.refresh_output_devices();
+    state.prepare_sounds();
+    // Besides today's statistics the records are used by the suggestions and the streak.
+    let today = tomata::start_of_local_day(tomata::now_timestamp());
+    let since = today.saturating_sub((history::RECENT_DAYS - 1) * tomata::DAY_S);
+    match history::load_records_since(history::HISTORY_FILE, since) {
+        Ok(records) => state.set_recent_records(records),
+        Err(error) => state.report_error(
+            format!("Could not read the session history: {}", error),
+            None,
+        ),
+    }
+    match planning::load_plan(planning::PLAN_FILE) {
+        Ok(tasks) => state.set_planned_tasks(tasks),
+        Err(error) => eprintln!("Could not read the plan: {}", error),
+    }
+    (state, is_fresh_install)
 }
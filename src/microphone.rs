@@ -0,0 +1,71 @@
+//! Detection of the microphone being in use, e.g., during a call, so
+//! the sounds do not go off in the middle of a meeting. Like with the idle
+//! time, there is no portable way of doing that.
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::fs;
+
+pub trait MicrophoneMonitor: fmt::Debug {
+    /// Returns `false` also when the use cannot be determined.
+    fn is_microphone_in_use(&self) -> bool;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemMicrophoneMonitor;
+
+impl MicrophoneMonitor for SystemMicrophoneMonitor {
+    #[cfg(target_os = "linux")]
+    fn is_microphone_in_use(&self) -> bool {
+        // Every capture stream opened through ALSA, also the ones opened
+        // by PulseAudio or PipeWire on behalf of the applications, reports
+        // its state in `/proc/asound/card*/pcm*c/sub*/status`.
+        let is_capture_running = || -> Option<bool> {
+            for card in fs::read_dir("/proc/asound").ok()?.flatten() {
+                for device in fs::read_dir(card.path()).into_iter().flatten().flatten() {
+                    let name = device.file_name();
+                    let name = name.to_string_lossy();
+                    if !name.starts_with("pcm") || !name.ends_with('c') {
+                        continue;
+                    }
+                    for stream in fs::read_dir(device.path()).into_iter().flatten().flatten() {
+                        let status = fs::read_to_string(stream.path().join("status"));
+                        if status.is_ok_and(|status| is_stream_running(&status)) {
+                            return Some(true);
+                        }
+                    }
+                }
+            }
+            Some(false)
+        };
+        is_capture_running().unwrap_or(false)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn is_microphone_in_use(&self) -> bool {
+        false
+    }
+}
+
+/// The status of a closed stream is just `closed`, the open one
+/// lists its state among other values, e.g., `state: RUNNING`.
+#[cfg(any(target_os = "linux", test))]
+fn is_stream_running(status: &str) -> bool {
+    status
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .any(|(key, value)| key.trim() == "state" && value.trim() == "RUNNING")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_stream_status() {
+        assert!(is_stream_running(
+            "state: RUNNING\nowner_pid   : 1234\ntrigger_time: 1.5\n"
+        ));
+        assert!(!is_stream_running("state: PREPARED\n"));
+        assert!(!is_stream_running("closed\n"));
+    }
+}
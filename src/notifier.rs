@@ -0,0 +1,175 @@
+//! Delivering the notifications about the periods, reminders and countdowns.
+//! The way they reach the user is chosen in the settings
+//! (see [`NotificationBackend`]), every way implements [`Notifier`].
+//! The buttons of the desktop notifications send the control commands
+//! named by their actions (see [`crate::control`]) through [`ActionChannel`].
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::sync::{Arc, Mutex};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::thread;
+
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
+use notify_rust::Notification;
+
+use crate::control::ControlCommand;
+use crate::settings::NotificationBackend;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// The notification reached the user without the help of the window.
+    Sent,
+    /// The notification has to be shown as a banner in the window.
+    InWindow,
+}
+
+pub trait Notifier: fmt::Debug {
+    fn notify(&self, notification: &Notification) -> Result<Delivery, Box<dyn Error>>;
+}
+
+/// Carries the commands chosen with the buttons of the notifications
+/// from the threads waiting for them to the state.
+#[derive(Debug, Clone)]
+pub struct ActionChannel {
+    sender: Sender<ControlCommand>,
+    receiver: Rc<Receiver<ControlCommand>>,
+}
+
+impl Default for ActionChannel {
+    fn default() -> ActionChannel {
+        let (sender, receiver) = mpsc::channel();
+        ActionChannel {
+            sender,
+            receiver: Rc::new(receiver),
+        }
+    }
+}
+
+impl ActionChannel {
+    /// Returns the commands chosen since the last call, the oldest first.
+    pub fn take_commands(&self) -> Vec<ControlCommand> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Desktop notifications of the operating system.
+#[derive(Debug)]
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+pub struct SystemNotifier {
+    actions: Sender<ControlCommand>,
+    /// The notification with the buttons whose actions are waited for,
+    /// the next one with the buttons replaces it, so a single thread waits.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    waited_notification: Arc<Mutex<Option<u32>>>,
+}
+
+impl SystemNotifier {
+    pub fn new(actions: Sender<ControlCommand>) -> SystemNotifier {
+        SystemNotifier {
+            actions,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            waited_notification: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl Notifier for SystemNotifier {
+    fn notify(&self, notification: &Notification) -> Result<Delivery, Box<dyn Error>> {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            if notification.actions.is_empty() {
+                notification.show()?;
+                return Ok(Delivery::Sent);
+            }
+            let mut waited = self
+                .waited_notification
+                .lock()
+                .map_err(|_| "the thread waiting for the actions panicked")?;
+            let mut notification = notification.clone();
+            if let Some(id) = *waited {
+                notification.id(id);
+            }
+            let handle = notification.show()?;
+            if waited.is_none() {
+                *waited = Some(handle.id());
+                let waited = self.waited_notification.clone();
+                let actions = self.actions.clone();
+                // Waiting blocks until the notification is acted on or closed,
+                // closing it sends nothing.
+                thread::spawn(move || {
+                    handle.wait_for_action(|action| {
+                        if let Some(command) = ControlCommand::from_name(action) {
+                            let _ = actions.send(command);
+                        }
+                    });
+                    if let Ok(mut waited) = waited.lock() {
+                        *waited = None;
+                    }
+                });
+            }
+        }
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        notification.show()?;
+        Ok(Delivery::Sent)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BannerNotifier;
+
+impl Notifier for BannerNotifier {
+    fn notify(&self, _notification: &Notification) -> Result<Delivery, Box<dyn Error>> {
+        Ok(Delivery::InWindow)
+    }
+}
+
+/// Only writes the notifications to the standard output.
+#[derive(Debug, Default)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, notification: &Notification) -> Result<Delivery, Box<dyn Error>> {
+        println!("{} {}", notification.summary, notification.body);
+        Ok(Delivery::Sent)
+    }
+}
+
+pub fn make_notifier(backend: NotificationBackend, actions: &ActionChannel) -> Rc<dyn Notifier> {
+    match backend {
+        NotificationBackend::System => Rc::new(SystemNotifier::new(actions.sender.clone())),
+        NotificationBackend::Banner => Rc::new(BannerNotifier),
+        NotificationBackend::Log => Rc::new(LogNotifier),
+    }
+}
+
+/// Notification shown inside the main window.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+pub struct Banner {
+    summary: String,
+    body: String,
+}
+
+impl Banner {
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl From<&Notification> for Banner {
+    fn from(notification: &Notification) -> Banner {
+        Banner {
+            summary: notification.summary.clone(),
+            body: notification.body.clone(),
+        }
+    }
+}
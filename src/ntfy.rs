@@ -0,0 +1,133 @@
+//! Publishes the changes of the period to an [ntfy](https://ntfy.sh) topic,
+//! so the others can subscribe to the status of the user on their phones,
+//! or the user receives the changes of the period as push notifications.
+//! The topic is either a name on ntfy.sh or the full URL of a topic on
+//! a self-hosted server. The messages are sent with `curl` on a separate thread.
+use std::io;
+use std::process::Command;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use notify_rust::Notification;
+
+use crate::events::{EventHook, TomataEvent};
+use crate::settings::Settings;
+use crate::tomata;
+use crate::tomata::Period;
+
+const NTFY_SERVER: &str = "https://ntfy.sh";
+
+/// The failures of the publishing are sent back through `failures`,
+/// as they happen on the other threads.
+#[derive(Debug)]
+pub struct NtfyHook {
+    failures: Sender<String>,
+}
+
+impl NtfyHook {
+    pub fn new(failures: Sender<String>) -> NtfyHook {
+        NtfyHook { failures }
+    }
+}
+
+impl EventHook for NtfyHook {
+    fn get_name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn handle(&mut self, event: TomataEvent, settings: &Settings) -> io::Result<()> {
+        let url = match settings.get_ntfy_topic() {
+            Some(topic) => make_topic_url(topic),
+            None => return Ok(()),
+        };
+        let (title, message) = match event {
+            TomataEvent::PeriodActivated(period) => {
+                let ends_at =
+                    tomata::now_timestamp() + settings.convert_period_to_duration(period).as_secs();
+                let notification = match period {
+                    Period::Custom(index) => settings
+                        .get_custom_period(index)
+                        .map_or_else(|| Notification::from(period), Notification::from),
+                    _ => Notification::from(period),
+                };
+                (
+                    notification.summary,
+                    make_message(period, &tomata::timestamp_to_local_time_string(ends_at)),
+                )
+            }
+            TomataEvent::BlocklistToggled(_)
+            | TomataEvent::AlertEscalated(_)
+            | TomataEvent::ApplicationQuitting => return Ok(()),
+        };
+        let failures = self.failures.clone();
+        thread::spawn(move || {
+            let status = Command::new("curl")
+                .args([
+                    "--silent",
+                    "--output",
+                    "/dev/null",
+                    "--header",
+                    &format!("Title: {}", title),
+                    "--data",
+                    &message,
+                    &url,
+                ])
+                .status();
+            let failure = match status {
+                Ok(status) if status.success() => return,
+                Ok(status) => format!("Could not publish to ntfy: `curl` exited with {}", status),
+                Err(error) => format!("Could not publish to ntfy: {}", error),
+            };
+            // The engine is gone when the application quits.
+            let _ = failures.send(failure);
+        });
+        Ok(())
+    }
+}
+
+/// The full URLs are kept as they are, e.g., `https://ntfy.example.com/tomata`,
+/// the bare names are the topics on ntfy.sh.
+fn make_topic_url(topic: &str) -> String {
+    if topic.starts_with("https://") || topic.starts_with("http://") {
+        topic.trim_end_matches('/').to_string()
+    } else {
+        format!("{}/{}", NTFY_SERVER, topic)
+    }
+}
+
+fn make_message(period: Period, ends_at: &str) -> String {
+    match period {
+        Period::Work => format!("Focusing, free at about {}.", ends_at),
+        Period::ShortBreak | Period::LongBreak => format!("On a break until about {}.", ends_at),
+        Period::Custom(_) => format!("Busy, free at about {}.", ends_at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_topic_url() {
+        assert_eq!(
+            make_topic_url("my-pomodoros"),
+            "https://ntfy.sh/my-pomodoros"
+        );
+        assert_eq!(
+            make_topic_url("https://ntfy.example.com/tomata/"),
+            "https://ntfy.example.com/tomata"
+        );
+    }
+
+    #[test]
+    fn making_message() {
+        assert_eq!(
+            make_message(Period::Work, "14:37"),
+            "Focusing, free at about 14:37."
+        );
+        assert_eq!(
+            make_message(Period::LongBreak, "15:00"),
+            "On a break until about 15:00."
+        );
+    }
+}
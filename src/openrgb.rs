@@ -0,0 +1,78 @@
+//! Sets the color of the RGB lighting (e.g., of the keyboard) to the one
+//! configured for the current period. The colors are sent with the
+//! `openrgb` command line client to the running OpenRGB SDK server.
+use std::io;
+use std::process::Command;
+
+use crate::events::{EventHook, TomataEvent};
+use crate::settings::Settings;
+
+const OPENRGB_SERVER: &str = "127.0.0.1:6742";
+
+#[derive(Debug, Default)]
+pub struct OpenRgbHook;
+
+impl EventHook for OpenRgbHook {
+    fn get_name(&self) -> &'static str {
+        "OpenRGB"
+    }
+
+    fn handle(&mut self, event: TomataEvent, settings: &Settings) -> io::Result<()> {
+        if !settings.is_keyboard_lighting_enabled() {
+            return Ok(());
+        }
+        match event {
+            TomataEvent::PeriodActivated(period) => {
+                set_lighting_color(settings.get_lighting_color(period))
+            }
+            TomataEvent::BlocklistToggled(_)
+            | TomataEvent::AlertEscalated(_)
+            | TomataEvent::ApplicationQuitting => Ok(()),
+        }
+    }
+}
+
+fn set_lighting_color(color: &str) -> io::Result<()> {
+    let status = Command::new("openrgb")
+        .args(make_openrgb_args(color))
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("`openrgb` exited with {}", status),
+        ));
+    }
+    Ok(())
+}
+
+/// Without `--device` the color is applied to all the devices.
+fn make_openrgb_args(color: &str) -> Vec<&str> {
+    vec![
+        "--client",
+        OPENRGB_SERVER,
+        "--mode",
+        "static",
+        "--color",
+        color.trim_start_matches('#'),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_openrgb_args() {
+        assert_eq!(
+            make_openrgb_args("#FF0000"),
+            vec![
+                "--client",
+                OPENRGB_SERVER,
+                "--mode",
+                "static",
+                "--color",
+                "FF0000"
+            ]
+        );
+    }
+}
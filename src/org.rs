@@ -0,0 +1,195 @@
+//! Clocking the work periods in an org-mode file, so the clocked time
+//! in the org agenda matches the pomodoros. Each finished work period
+//! becomes a `CLOCK` entry in the `LOGBOOK` drawer of the configured
+//! heading, which is added at the end of the file if it is missing.
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use time::{Date, Weekday};
+
+use crate::history::{RecordedPeriod, SessionRecord};
+use crate::tomata;
+use crate::tomata::{HOUR_S, MINUTE_S};
+
+/// Formats the org inactive timestamp, e.g., `[2024-05-02 Thu 14:00]`.
+fn format_org_timestamp(date: Date, time_of_day: Duration) -> String {
+    let weekday = match date.weekday() {
+        Weekday::Monday => "Mon",
+        Weekday::Tuesday => "Tue",
+        Weekday::Wednesday => "Wed",
+        Weekday::Thursday => "Thu",
+        Weekday::Friday => "Fri",
+        Weekday::Saturday => "Sat",
+        Weekday::Sunday => "Sun",
+    };
+    let seconds = time_of_day.as_secs();
+    format!(
+        "[{}-{:0>2}-{:0>2} {} {:0>2}:{:0>2}]",
+        date.year(),
+        date.month(),
+        date.day(),
+        weekday,
+        seconds / HOUR_S,
+        (seconds % HOUR_S) / MINUTE_S
+    )
+}
+
+fn format_local_org_timestamp(timestamp: u64) -> String {
+    format_org_timestamp(
+        tomata::local_date(timestamp),
+        tomata::time_of_local_day(timestamp),
+    )
+}
+
+/// Makes the entry the way org writes it itself, e.g.,
+/// `CLOCK: [2024-05-02 Thu 14:00]--[2024-05-02 Thu 14:25] =>  0:25`.
+fn make_clock_entry(started: String, finished: String, span: Duration) -> String {
+    let minutes = span.as_secs() / MINUTE_S;
+    format!(
+        "CLOCK: {}--{} => {:>2}:{:0>2}",
+        started,
+        finished,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Returns the heading text without the stars, `None` for the other lines.
+fn parse_heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('*');
+    if text.len() == line.len() || !text.starts_with(' ') {
+        return None;
+    }
+    Some(text.trim())
+}
+
+/// Adds the entries on top of the logbook of `heading`, the latest first
+/// like org does, creating the logbook or the heading when necessary.
+fn insert_clock_entries(org: &str, heading: &str, entries: &[String]) -> String {
+    let mut lines: Vec<&str> = org.lines().collect();
+    let entries = entries.iter().rev().map(String::as_str);
+    let heading_index = match lines
+        .iter()
+        .position(|line| parse_heading(line) == Some(heading))
+    {
+        Some(index) => index,
+        None => {
+            let new_heading = format!("* {}", heading);
+            let mut org = lines.join("\n");
+            if !org.is_empty() {
+                org.push('\n');
+            }
+            org.push_str(&new_heading);
+            org.push_str("\n:LOGBOOK:\n");
+            for entry in entries {
+                org.push_str(entry);
+                org.push('\n');
+            }
+            org.push_str(":END:\n");
+            return org;
+        }
+    };
+    let section_end = lines[heading_index + 1..]
+        .iter()
+        .position(|line| parse_heading(line).is_some())
+        .map_or(lines.len(), |index| heading_index + 1 + index);
+    let logbook = lines[heading_index + 1..section_end]
+        .iter()
+        .position(|line| line.trim() == ":LOGBOOK:")
+        .map(|index| heading_index + 1 + index);
+    let mut insert_at = match logbook {
+        Some(index) => index + 1,
+        None => {
+            // The planning line and the properties drawer must stay
+            // right below the heading.
+            let mut index = heading_index + 1;
+            let is_planning = |line: &str| {
+                ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+                    .iter()
+                    .any(|keyword| line.trim_start().starts_with(keyword))
+            };
+            if index < section_end && is_planning(lines[index]) {
+                index += 1;
+            }
+            if index < section_end && lines[index].trim() == ":PROPERTIES:" {
+                while index < section_end && lines[index].trim() != ":END:" {
+                    index += 1;
+                }
+                index += 1;
+            }
+            lines.insert(index, ":LOGBOOK:");
+            lines.insert(index + 1, ":END:");
+            index + 1
+        }
+    };
+    for entry in entries {
+        lines.insert(insert_at, entry);
+        insert_at += 1;
+    }
+    let mut org = lines.join("\n");
+    org.push('\n');
+    org
+}
+
+/// Clocks the work periods among `records` under `heading` of the file.
+pub fn clock_records(path: &Path, heading: &str, records: &[SessionRecord]) -> io::Result<()> {
+    let entries: Vec<String> = records
+        .iter()
+        .filter(|record| record.period == RecordedPeriod::Work)
+        .map(|record| {
+            make_clock_entry(
+                format_local_org_timestamp(record.started_at),
+                format_local_org_timestamp(record.finished_at),
+                record.calculate_span(),
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let org = match fs::read_to_string(path) {
+        Ok(org) => org,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+    fs::write(path, insert_clock_entries(&org, heading, &entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_clock_entry() {
+        let date = Date::try_from_ymd(2024, 5, 2).unwrap();
+        let started = format_org_timestamp(date, Duration::from_secs(14 * HOUR_S));
+        let finished = format_org_timestamp(date, Duration::from_secs(14 * HOUR_S + 1500));
+        assert_eq!(started, "[2024-05-02 Thu 14:00]");
+        assert_eq!(
+            make_clock_entry(started, finished, Duration::from_secs(1500)),
+            "CLOCK: [2024-05-02 Thu 14:00]--[2024-05-02 Thu 14:25] =>  0:25"
+        );
+    }
+
+    #[test]
+    fn inserting_clock_entries() {
+        let entries = vec!["CLOCK: a".to_string(), "CLOCK: b".to_string()];
+        assert_eq!(
+            insert_clock_entries("", "Pomodoros", &entries),
+            "* Pomodoros\n:LOGBOOK:\nCLOCK: b\nCLOCK: a\n:END:\n"
+        );
+        let org = "* Work\n** Pomodoros\n:PROPERTIES:\n:ID: 1\n:END:\nNotes\n* Home\n";
+        assert_eq!(
+            insert_clock_entries(org, "Pomodoros", &entries[..1]),
+            "* Work\n** Pomodoros\n:PROPERTIES:\n:ID: 1\n:END:\n:LOGBOOK:\nCLOCK: a\n:END:\n\
+             Notes\n* Home\n"
+        );
+        let org = "* Pomodoros\n:LOGBOOK:\nCLOCK: a\n:END:\n";
+        assert_eq!(
+            insert_clock_entries(org, "Pomodoros", &entries[1..]),
+            "* Pomodoros\n:LOGBOOK:\nCLOCK: b\nCLOCK: a\n:END:\n"
+        );
+    }
+}
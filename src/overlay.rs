@@ -0,0 +1,100 @@
+//! Fullscreen overlay shown during breaks, so the work cannot simply
+//! continue in another window. Each connected monitor gets its own
+//! overlay window, otherwise the secondary monitors would stay usable.
+//!
+//! Also the thin focus banner shown during work, e.g., while sharing
+//! the screen or on a hallway display, the debug window and the read-only
+//! observer windows mirroring the timer, e.g., on the second monitor
+//! while pair programming, and the invisible window standing in for the
+//! main window hidden to the tray.
+use druid::widget::SizedBox;
+use druid::{Monitor, Point, Screen, Size, WindowDesc, WindowState};
+
+use crate::state::TomataState;
+use crate::tomata::APPLICATION_NAME;
+use crate::widget;
+
+const FOCUS_BANNER_SIZE: Size = Size::new(360.0, 32.0);
+const DEBUG_WINDOW_SIZE: Size = Size::new(640.0, 720.0);
+const OBSERVER_WINDOW_SIZE: Size = Size::new(400.0, 200.0);
+const TRAY_KEEPER_WINDOW_SIZE: Size = Size::new(1.0, 1.0);
+
+/// Returns one overlay window for each of the connected monitors.
+pub fn make_break_overlay_windows() -> Vec<WindowDesc<TomataState>> {
+    let monitors = Screen::get_monitors();
+    if monitors.is_empty() {
+        // The platform could not list the monitors, the window manager
+        // places the overlay on the primary one then.
+        return vec![make_break_overlay_window(None)];
+    }
+    monitors
+        .iter()
+        .map(|monitor| make_break_overlay_window(Some(monitor)))
+        .collect()
+}
+
+fn make_break_overlay_window(monitor: Option<&Monitor>) -> WindowDesc<TomataState> {
+    let window = WindowDesc::new(widget::make_break_overlay_widget_tree)
+        .title(APPLICATION_NAME)
+        .show_titlebar(false)
+        .resizable(false)
+        .set_window_state(WindowState::MAXIMIZED);
+    match monitor {
+        // Maximizing the window fills the monitor it was placed on.
+        Some(monitor) => {
+            let rect = monitor.virtual_rect();
+            window.set_position(rect.origin()).window_size(rect.size())
+        }
+        None => window,
+    }
+}
+
+/// Returns the banner window placed at the top center of the primary monitor.
+pub fn make_focus_banner_window() -> WindowDesc<TomataState> {
+    let window = WindowDesc::new(widget::make_focus_banner_widget_tree)
+        .title(APPLICATION_NAME)
+        .show_titlebar(false)
+        .resizable(false)
+        .window_size(FOCUS_BANNER_SIZE);
+    match Screen::get_monitors()
+        .iter()
+        .find(|monitor| monitor.is_primary())
+    {
+        Some(monitor) => {
+            let rect = monitor.virtual_work_rect();
+            let x = rect.x0 + (rect.width() - FOCUS_BANNER_SIZE.width) / 2.0;
+            window.set_position(Point::new(x, rect.y0))
+        }
+        None => window,
+    }
+}
+
+/// Returns the window showing the internals, opened with `--debug`.
+pub fn make_debug_window() -> WindowDesc<TomataState> {
+    WindowDesc::new(widget::make_debug_widget_tree)
+        .title(format!("{} (debug)", APPLICATION_NAME))
+        .window_size(DEBUG_WINDOW_SIZE)
+}
+
+/// Returns the window keeping the application running while the main window
+/// is hidden to the tray. druid cannot hide a window, only close it, and
+/// handles the commands from the other threads, e.g., the ticks, only while
+/// some window is open, so this one is as small as it gets and off the screen.
+pub fn make_tray_keeper_window() -> WindowDesc<TomataState> {
+    WindowDesc::new(SizedBox::empty)
+        .title(APPLICATION_NAME)
+        .show_titlebar(false)
+        .resizable(false)
+        .window_size(TRAY_KEEPER_WINDOW_SIZE)
+        .set_position(Point::new(
+            -TRAY_KEEPER_WINDOW_SIZE.width,
+            -TRAY_KEEPER_WINDOW_SIZE.height,
+        ))
+}
+
+/// Returns a window showing the timer without any of the controls.
+pub fn make_observer_window() -> WindowDesc<TomataState> {
+    WindowDesc::new(widget::make_observer_widget_tree)
+        .title(format!("{} (read-only)", APPLICATION_NAME))
+        .window_size(OBSERVER_WINDOW_SIZE)
+}
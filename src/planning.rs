@@ -0,0 +1,165 @@
+//! Planning queue of the tasks with the estimated number of pomodoros,
+//! and the comparison of the plan with the work periods actually
+//! completed, i.e., the daily retrospective of the Pomodoro technique.
+//! A work period counts towards a task when it is tagged with its name.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{RecordedPeriod, SessionRecord};
+
+pub const PLAN_FILE: &str = "plan.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+pub struct PlannedTask {
+    id: u64,
+    name: String,
+    /// Number of the planned pomodoros.
+    estimate: u32,
+}
+
+impl PlannedTask {
+    pub fn new(id: u64, name: String, estimate: u32) -> PlannedTask {
+        PlannedTask { id, name, estimate }
+    }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_estimate(&self) -> u32 {
+        self.estimate
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanComparison {
+    pub name: String,
+    pub planned: u32,
+    pub completed: u32,
+}
+
+impl PlanComparison {
+    pub fn is_overrun(&self) -> bool {
+        self.completed > self.planned
+    }
+}
+
+/// Compares the planned tasks with the work periods finished since `since`
+/// (seconds since the UNIX epoch). The work on unplanned tasks is listed
+/// after the planned ones.
+pub fn compare_plan(
+    tasks: &[PlannedTask],
+    records: &[SessionRecord],
+    since: u64,
+) -> Vec<PlanComparison> {
+    let mut comparisons: Vec<PlanComparison> = tasks
+        .iter()
+        .map(|task| PlanComparison {
+            name: task.name.clone(),
+            planned: task.estimate,
+            completed: 0,
+        })
+        .collect();
+    let finished_work = records.iter().filter(|record| {
+        record.period == RecordedPeriod::Work
+            && !record.is_aborted
+            && !record.is_continued
+            && record.finished_at >= since
+    });
+    for record in finished_work {
+        let name = match &record.tag {
+            Some(tag) => tag,
+            None => continue,
+        };
+        match comparisons
+            .iter_mut()
+            .find(|comparison| &comparison.name == name)
+        {
+            Some(comparison) => comparison.completed += 1,
+            None => comparisons.push(PlanComparison {
+                name: name.clone(),
+                planned: 0,
+                completed: 1,
+            }),
+        }
+    }
+    comparisons
+}
+
+/// Returns an empty plan if the file does not exist yet.
+pub fn load_plan(path: impl AsRef<Path>) -> io::Result<Vec<PlannedTask>> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(io::Error::from),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn save_plan(path: impl AsRef<Path>, tasks: &[PlannedTask]) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(tasks)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::make_work_record;
+
+    #[test]
+    fn comparing_plan_with_completed_work() {
+        let tasks = vec![
+            PlannedTask::new(0, "API refactor".to_string(), 2),
+            PlannedTask::new(1, "Code review".to_string(), 1),
+        ];
+        let make_record = |tag: &str, finished_at| SessionRecord {
+            tag: Some(tag.to_string()),
+            ..make_work_record(finished_at)
+        };
+        let records = vec![
+            make_record("API refactor", 1000),
+            make_record("API refactor", 5000),
+            make_record("API refactor", 7000),
+            make_record("Emails", 8000),
+            make_record("Code review", 3000),
+        ];
+        assert_eq!(
+            compare_plan(&tasks, &records, 4000),
+            vec![
+                PlanComparison {
+                    name: "API refactor".to_string(),
+                    planned: 2,
+                    completed: 2,
+                },
+                PlanComparison {
+                    name: "Code review".to_string(),
+                    planned: 1,
+                    completed: 0,
+                },
+                PlanComparison {
+                    name: "Emails".to_string(),
+                    planned: 0,
+                    completed: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn detecting_overrun() {
+        let comparison = PlanComparison {
+            name: "API refactor".to_string(),
+            planned: 2,
+            completed: 3,
+        };
+        assert!(comparison.is_overrun());
+    }
+}
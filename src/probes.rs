@@ -0,0 +1,286 @@
+//! Polling of the system on a background thread, so spawning the tools
+//! like `xdotool` or `i3-msg` and reading the calendars does not hold up
+//! the ticks and the interface. The engine tells the thread what it needs
+//! to know whenever that changes, the thread reports what it found out
+//! after every round of polling.
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use time::Date;
+
+use crate::activity::{ActiveWindowProbe, SystemActiveWindowProbe};
+use crate::calendar::{BusyBlock, Calendar, HolidayCalendar};
+use crate::idle::{IdleDetector, SystemIdleDetector};
+use crate::microphone::{MicrophoneMonitor, SystemMicrophoneMonitor};
+use crate::workspace::{SystemWorkspaceProbe, WorkspaceProbe};
+
+/// How often the probes are polled, the displayed time does not change
+/// more often either.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the fullscreen window is looked for, it takes two programs
+/// and the fullscreen applications are seldom opened for a short while.
+const FULLSCREEN_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the focused application is sampled during the work periods.
+const APPLICATION_SAMPLING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What the engine needs to know about the system right now.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProbeNeeds {
+    pub idle_time: bool,
+    pub microphone: bool,
+    pub fullscreen: bool,
+    pub workspace: bool,
+    pub application: bool,
+    pub calendar_file: Option<PathBuf>,
+    pub holiday_calendar_file: Option<PathBuf>,
+}
+
+/// What was found out in one round of polling, `None` stands for what
+/// was not polled or could not be determined.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProbeReadings {
+    pub idle_time: Option<Duration>,
+    pub is_microphone_in_use: Option<bool>,
+    pub is_fullscreen: Option<bool>,
+    /// The application owning the fullscreen window.
+    pub fullscreen_application: Option<String>,
+    /// Why the fullscreen window cannot be detected, checked when its
+    /// detection begins, `Some(None)` when it can.
+    pub fullscreen_detection_problem: Option<Option<String>>,
+    pub focused_workspace: Option<String>,
+    pub active_application: Option<String>,
+    /// The busy blocks of the calendar, only when they changed.
+    pub busy_blocks: Option<Vec<BusyBlock>>,
+    /// The days off of the holiday calendar, only when they changed.
+    pub days_off: Option<Vec<Date>>,
+}
+
+/// The probes polled by the thread.
+#[derive(Debug)]
+pub struct Probes {
+    pub idle_detector: Box<dyn IdleDetector + Send>,
+    pub active_window_probe: Box<dyn ActiveWindowProbe + Send>,
+    pub microphone_monitor: Box<dyn MicrophoneMonitor + Send>,
+    pub workspace_probe: Box<dyn WorkspaceProbe + Send>,
+}
+
+impl Default for Probes {
+    fn default() -> Probes {
+        Probes {
+            idle_detector: Box::new(SystemIdleDetector),
+            active_window_probe: Box::new(SystemActiveWindowProbe),
+            microphone_monitor: Box::new(SystemMicrophoneMonitor),
+            workspace_probe: Box::new(SystemWorkspaceProbe),
+        }
+    }
+}
+
+/// The handle of the polling thread, the thread quits once it is dropped.
+#[derive(Debug)]
+pub struct ProbeThread {
+    needs: ProbeNeeds,
+    sender: Sender<ProbeNeeds>,
+}
+
+impl ProbeThread {
+    /// Spawns the thread polling `probes`, it hands the readings to `report`
+    /// until it returns `false`, e.g., when the application is closed.
+    pub fn start(
+        probes: Probes,
+        report: impl FnMut(ProbeReadings) -> bool + Send + 'static,
+    ) -> ProbeThread {
+        let (sender, needs) = mpsc::channel();
+        thread::spawn(move || Poller::new(probes).run(needs, report));
+        ProbeThread {
+            needs: ProbeNeeds::default(),
+            sender,
+        }
+    }
+
+    /// Tells the thread what to poll from now on, nothing is sent
+    /// unless the needs changed.
+    pub fn set_needs(&mut self, needs: ProbeNeeds) {
+        if needs != self.needs {
+            self.needs = needs.clone();
+            // The thread is gone only when there is nobody to report to.
+            let _ = self.sender.send(needs);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Poller {
+    probes: Probes,
+    needs: ProbeNeeds,
+    calendar: Calendar,
+    holiday_calendar: HolidayCalendar,
+    /// `None` until the fullscreen detection is checked.
+    next_fullscreen_poll: Option<Instant>,
+    is_fullscreen_detectable: bool,
+    next_application_sample: Instant,
+}
+
+impl Poller {
+    fn new(probes: Probes) -> Poller {
+        Poller {
+            probes,
+            needs: ProbeNeeds::default(),
+            calendar: Calendar::default(),
+            holiday_calendar: HolidayCalendar::default(),
+            next_fullscreen_poll: None,
+            is_fullscreen_detectable: false,
+            next_application_sample: Instant::now() + APPLICATION_SAMPLING_INTERVAL,
+        }
+    }
+
+    fn run(mut self, needs: Receiver<ProbeNeeds>, mut report: impl FnMut(ProbeReadings) -> bool) {
+        let mut next_poll = Instant::now();
+        loop {
+            let timeout = next_poll.saturating_duration_since(Instant::now());
+            match needs.recv_timeout(timeout) {
+                Ok(new_needs) => {
+                    self.needs = new_needs;
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            let now = Instant::now();
+            next_poll = now + POLL_INTERVAL;
+            let readings = self.poll(now);
+            if readings != ProbeReadings::default() && !report(readings) {
+                return;
+            }
+        }
+    }
+
+    fn poll(&mut self, now: Instant) -> ProbeReadings {
+        let probes = &self.probes;
+        let mut readings = ProbeReadings::default();
+        if self.needs.idle_time {
+            readings.idle_time = probes.idle_detector.idle_time();
+        }
+        if self.needs.microphone {
+            readings.is_microphone_in_use = Some(probes.microphone_monitor.is_microphone_in_use());
+        }
+        if !self.needs.fullscreen {
+            self.next_fullscreen_poll = None;
+        } else if self.next_fullscreen_poll.is_none() {
+            let problem = probes
+                .active_window_probe
+                .find_fullscreen_detection_problem();
+            self.is_fullscreen_detectable = problem.is_none();
+            readings.fullscreen_detection_problem = Some(problem);
+            self.next_fullscreen_poll = Some(now);
+        }
+        if self.is_fullscreen_detectable && self.next_fullscreen_poll.is_some_and(|at| now >= at) {
+            self.next_fullscreen_poll = Some(now + FULLSCREEN_POLL_INTERVAL);
+            let is_fullscreen = probes.active_window_probe.is_active_window_fullscreen();
+            readings.is_fullscreen = Some(is_fullscreen);
+            if is_fullscreen {
+                readings.fullscreen_application = probes.active_window_probe.active_application();
+            }
+        }
+        if self.needs.workspace {
+            readings.focused_workspace = probes.workspace_probe.focused_workspace();
+        }
+        if !self.needs.application {
+            self.next_application_sample = now + APPLICATION_SAMPLING_INTERVAL;
+        } else if now >= self.next_application_sample {
+            self.next_application_sample = now + APPLICATION_SAMPLING_INTERVAL;
+            readings.active_application = probes.active_window_probe.active_application();
+        }
+        readings.busy_blocks = self
+            .calendar
+            .reload(self.needs.calendar_file.as_deref())
+            .map(<[BusyBlock]>::to_vec);
+        readings.days_off = self
+            .holiday_calendar
+            .reload(self.needs.holiday_calendar_file.as_deref())
+            .map(<[Date]>::to_vec);
+        readings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeIdleDetector;
+
+    impl IdleDetector for FakeIdleDetector {
+        fn idle_time(&self) -> Option<Duration> {
+            Some(Duration::from_secs(300))
+        }
+    }
+
+    #[derive(Debug)]
+    struct MissingActiveWindowProbe;
+
+    impl ActiveWindowProbe for MissingActiveWindowProbe {
+        fn active_application(&self) -> Option<String> {
+            None
+        }
+
+        fn is_active_window_fullscreen(&self) -> bool {
+            unreachable!("the missing probe is not polled")
+        }
+
+        fn find_fullscreen_detection_problem(&self) -> Option<String> {
+            Some("xprop is not installed".to_string())
+        }
+    }
+
+    #[test]
+    fn reporting_only_needed_readings() {
+        let probes = Probes {
+            idle_detector: Box::new(FakeIdleDetector),
+            ..Probes::default()
+        };
+        let (sender, readings) = mpsc::channel();
+        let mut thread = ProbeThread::start(probes, move |reading| sender.send(reading).is_ok());
+        // Nothing is needed, so nothing is polled, not even the system tools.
+        assert!(readings.recv_timeout(Duration::from_millis(1500)).is_err());
+
+        thread.set_needs(ProbeNeeds {
+            idle_time: true,
+            ..ProbeNeeds::default()
+        });
+        let reading = readings.recv_timeout(Duration::from_secs(3)).unwrap();
+        assert_eq!(
+            reading,
+            ProbeReadings {
+                idle_time: Some(Duration::from_secs(300)),
+                ..ProbeReadings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn reporting_fullscreen_detection_problem() {
+        let probes = Probes {
+            active_window_probe: Box::new(MissingActiveWindowProbe),
+            ..Probes::default()
+        };
+        let (sender, readings) = mpsc::channel();
+        let mut thread = ProbeThread::start(probes, move |reading| sender.send(reading).is_ok());
+        thread.set_needs(ProbeNeeds {
+            fullscreen: true,
+            ..ProbeNeeds::default()
+        });
+        let reading = readings.recv_timeout(Duration::from_secs(3)).unwrap();
+        assert_eq!(
+            reading.fullscreen_detection_problem,
+            Some(Some("xprop is not installed".to_string()))
+        );
+        assert_eq!(reading.is_fullscreen, None);
+        // There is nothing more to report until the detection is enabled again.
+        assert!(readings.recv_timeout(Duration::from_millis(1500)).is_err());
+    }
+}
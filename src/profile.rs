@@ -0,0 +1,137 @@
+//! The profile bundle is a single file with the settings meant to be kept
+//! in the dotfiles, the history is not a part of it. The export is
+//! deterministic, the keys are sorted, so the bundle changes only when
+//! the settings do. The values specific to the machine, e.g., the paths
+//! of the files, are left out of the export and left untouched by the import.
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::settings::{Settings, SettingsError};
+
+/// Version of the bundle layout, it has to be bumped whenever a change
+/// makes older bundles incompatible with the current application.
+pub const BUNDLE_VERSION: u32 = 1;
+
+const MACHINE_LOCAL_FIELDS: [&str; 4] = [
+    "calendar_file",
+    "holiday_calendar_file",
+    "work_wallpaper",
+    "break_wallpaper",
+];
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Bundle {
+    bundle_version: u32,
+    settings: Map<String, Value>,
+}
+
+pub fn export_bundle(settings: &Settings) -> String {
+    let mut fields = match serde_json::to_value(settings) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    for field in MACHINE_LOCAL_FIELDS.iter() {
+        fields.remove(*field);
+    }
+    let bundle = Bundle {
+        bundle_version: BUNDLE_VERSION,
+        settings: fields,
+    };
+    let mut json = serde_json::to_string_pretty(&bundle).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Returns `settings` with the values of the bundle, except for
+/// the machine-local ones. The settings missing from the bundle,
+/// e.g., added by a newer version, keep their current values.
+pub fn import_bundle(settings: &Settings, bundle: &str) -> Result<Settings, Vec<SettingsError>> {
+    let bundle: Bundle =
+        serde_json::from_str(bundle).map_err(|error| vec![SettingsError::Malformed(error)])?;
+    if bundle.bundle_version != BUNDLE_VERSION {
+        return Err(vec![SettingsError::SchemaVersionMismatch {
+            found: bundle.bundle_version,
+            expected: BUNDLE_VERSION,
+        }]);
+    }
+    let mut fields = match serde_json::to_value(settings) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    let mut errors = Vec::new();
+    for (name, value) in bundle.settings {
+        if MACHINE_LOCAL_FIELDS.contains(&name.as_str()) {
+            continue;
+        }
+        match fields.get_mut(&name) {
+            Some(field) => *field = value,
+            None => errors.push(SettingsError::UnknownField(name)),
+        }
+    }
+    match serde_json::from_value::<Settings>(Value::Object(fields)) {
+        Ok(imported) => {
+            errors.extend(imported.validate());
+            if errors.is_empty() {
+                return Ok(imported);
+            }
+        }
+        Err(error) => errors.push(SettingsError::Malformed(error)),
+    }
+    Err(errors)
+}
+
+pub fn export_bundle_to_file(
+    settings: &Settings,
+    path: impl AsRef<Path>,
+) -> Result<(), SettingsError> {
+    fs::write(path, export_bundle(settings)).map_err(SettingsError::Io)
+}
+
+pub fn import_bundle_from_file(
+    settings: &Settings,
+    path: impl AsRef<Path>,
+) -> Result<Settings, Vec<SettingsError>> {
+    let bundle = fs::read_to_string(path).map_err(|error| vec![SettingsError::Io(error)])?;
+    import_bundle(settings, &bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tomata::Period;
+
+    #[test]
+    fn exporting_and_importing_bundle() {
+        let exported: Settings =
+            serde_json::from_str(r#"{"beep_volume": 0.5, "work_wallpaper": "/home/a/work.png"}"#)
+                .unwrap();
+        let bundle = export_bundle(&exported);
+        assert_eq!(bundle, export_bundle(&exported.clone()));
+        assert!(!bundle.contains("work_wallpaper"));
+
+        let local: Settings =
+            serde_json::from_str(r#"{"work_wallpaper": "/home/b/work.png"}"#).unwrap();
+        let imported = import_bundle(&local, &bundle).unwrap();
+        assert_eq!(imported.get_beep_volume(), 0.5);
+        assert_eq!(
+            imported.get_wallpaper(Period::Work),
+            Some(Path::new("/home/b/work.png"))
+        );
+    }
+
+    #[test]
+    fn importing_invalid_bundle() {
+        let settings = Settings::default();
+        let errors = import_bundle(
+            &settings,
+            r#"{"bundle_version": 1, "settings": {"beep_volume": 2.0, "colour": "red"}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(import_bundle(&settings, r#"{"bundle_version": 2, "settings": {}}"#).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Auxiliary reminders, e.g., to drink some water or to correct the posture,
+//! that fire every configured interval independently of the periods.
+use std::rc::Rc;
+use std::time::Duration;
+
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+use crate::tomata::{APPLICATION_NAME, ZERO};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum ReminderKind {
+    Hydration,
+    Posture,
+}
+
+impl ReminderKind {
+    pub const ALL: [ReminderKind; 2] = [ReminderKind::Hydration, ReminderKind::Posture];
+}
+
+impl From<ReminderKind> for Notification {
+    fn from(kind: ReminderKind) -> Notification {
+        match kind {
+            ReminderKind::Hydration => Notification::new()
+                .appname(APPLICATION_NAME)
+                .summary("Drink some water.")
+                .body("Staying hydrated keeps you focused.")
+                .clone(),
+            ReminderKind::Posture => Notification::new()
+                .appname(APPLICATION_NAME)
+                .summary("Check your posture.")
+                .body("Straighten your back and relax your shoulders.")
+                .clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+pub struct ReminderSettings {
+    reminder_is_enabled: bool,
+    interval: Rc<Duration>, // Data cannot be derived for Duration, unless it is in Rc
+}
+
+impl ReminderSettings {
+    pub fn new(is_enabled: bool, interval: Duration) -> ReminderSettings {
+        ReminderSettings {
+            reminder_is_enabled: is_enabled,
+            interval: Rc::new(interval),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.reminder_is_enabled
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        *self.interval
+    }
+
+    pub fn increase_interval(&mut self, value: Duration) {
+        self.interval = Rc::new(*self.interval + value);
+    }
+
+    pub fn decrease_interval(&mut self, value: Duration) {
+        self.interval = Rc::new(self.interval.checked_sub(value).unwrap_or(ZERO));
+    }
+}
+
+/// Keeps track of the time that passed since each of the reminders fired.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub struct ReminderScheduler {
+    since_hydration: Rc<Duration>,
+    since_posture: Rc<Duration>,
+}
+
+impl Default for ReminderScheduler {
+    fn default() -> ReminderScheduler {
+        ReminderScheduler {
+            since_hydration: Rc::new(ZERO),
+            since_posture: Rc::new(ZERO),
+        }
+    }
+}
+
+impl ReminderScheduler {
+    /// Advances the time of all the enabled reminders and returns
+    /// the ones that are due.
+    pub fn advance(&mut self, settings: &Settings, elapsed: Duration) -> Vec<ReminderKind> {
+        let mut due = Vec::new();
+        for &kind in ReminderKind::ALL.iter() {
+            let reminder = settings.get_reminder(kind);
+            let since = match kind {
+                ReminderKind::Hydration => &mut self.since_hydration,
+                ReminderKind::Posture => &mut self.since_posture,
+            };
+            // A zero interval would fire on every tick.
+            if !reminder.is_enabled() || reminder.get_interval() == ZERO {
+                *since = Rc::new(ZERO);
+                continue;
+            }
+            *since = Rc::new(**since + elapsed);
+            if **since >= reminder.get_interval() {
+                *since = Rc::new(ZERO);
+                due.push(kind);
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_settings(hydration: ReminderSettings, posture: ReminderSettings) -> Settings {
+        Settings {
+            hydration_reminder: hydration,
+            posture_reminder: posture,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn reminder_is_due_after_its_interval() {
+        let settings = make_settings(
+            ReminderSettings::new(true, Duration::from_secs(2)),
+            ReminderSettings::new(false, Duration::from_secs(1)),
+        );
+        let mut scheduler = ReminderScheduler::default();
+        assert!(scheduler
+            .advance(&settings, Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(
+            scheduler.advance(&settings, Duration::from_secs(1)),
+            vec![ReminderKind::Hydration]
+        );
+        assert!(scheduler
+            .advance(&settings, Duration::from_secs(1))
+            .is_empty());
+    }
+
+    #[test]
+    fn disabled_reminder_is_never_due() {
+        let settings = make_settings(
+            ReminderSettings::new(false, Duration::from_secs(1)),
+            ReminderSettings::new(true, ZERO),
+        );
+        let mut scheduler = ReminderScheduler::default();
+        for _ in 0..3 {
+            assert!(scheduler
+                .advance(&settings, Duration::from_secs(1))
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn decreasing_interval_below_zero() {
+        let mut reminder = ReminderSettings::new(true, Duration::from_secs(1));
+        reminder.decrease_interval(Duration::from_secs(10));
+        assert_eq!(reminder.get_interval(), ZERO);
+    }
+}
@@ -0,0 +1,319 @@
+//! Local HTTP server with a read-only status page, so the others can
+//! check whether the user is focusing before interrupting. The page is
+//! available only under a random token, the link is meant to be shared.
+//! Under the same token another page mirrors the timer, e.g., for the
+//! partner in pair programming on another machine. The server listens
+//! only on the loopback interface unless the user shares it with the local
+//! network.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::tomata;
+use crate::tomata::Period;
+
+const STATUS_PATH: &str = "/status/";
+const TIMER_PATH: &str = "/timer/";
+
+/// How often the server checks whether it should stop.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long a client may take to send the request line.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Every connection is served on a thread of its own, the ones above
+/// this count are closed right away, so a flood cannot pile up threads.
+const MAX_CONNECTIONS: usize = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FocusStatus {
+    pub period: Period,
+    pub is_running: bool,
+    /// Seconds since the UNIX epoch, `None` when the user is free already.
+    pub free_at: Option<u64>,
+    pub remaining_seconds: u64,
+}
+
+impl Default for FocusStatus {
+    fn default() -> FocusStatus {
+        FocusStatus {
+            period: Period::Work,
+            is_running: false,
+            free_at: None,
+            remaining_seconds: 0,
+        }
+    }
+}
+
+/// Runs until it is dropped.
+#[derive(Debug)]
+pub struct StatusServer {
+    port: u16,
+    is_shared: bool,
+    token: String,
+    status: Arc<Mutex<FocusStatus>>,
+    is_stopped: Arc<AtomicBool>,
+}
+
+impl StatusServer {
+    /// Listens on `port` of the loopback interface, or of all
+    /// the interfaces when `is_shared` with the local network.
+    pub fn start(port: u16, is_shared: bool) -> io::Result<StatusServer> {
+        let address = if is_shared {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let listener = TcpListener::bind(SocketAddr::from((address, port)))?;
+        listener.set_nonblocking(true)?;
+        let server = StatusServer {
+            port,
+            is_shared,
+            token: generate_token(),
+            status: Arc::new(Mutex::new(FocusStatus::default())),
+            is_stopped: Arc::new(AtomicBool::new(false)),
+        };
+        let token = server.token.clone();
+        let status = Arc::clone(&server.status);
+        let is_stopped = Arc::clone(&server.is_stopped);
+        let connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            while !is_stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            connections.fetch_sub(1, Ordering::SeqCst);
+                            continue;
+                        }
+                        let status = *status.lock().unwrap();
+                        let token = token.clone();
+                        let connections = Arc::clone(&connections);
+                        // A slow client must not hold up the others.
+                        thread::spawn(move || {
+                            if let Err(error) = handle_connection(stream, &token, status) {
+                                eprintln!("Could not serve the status: {}", error);
+                            }
+                            connections.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_INTERVAL)
+                    }
+                    Err(error) => eprintln!("Could not accept a connection: {}", error),
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    pub fn set_status(&self, status: FocusStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    /// Returns the link to the status page, the one that can be opened
+    /// from the other devices in the local network when it is shared.
+    pub fn get_share_link(&self) -> String {
+        let local_address = if self.is_shared {
+            find_local_address()
+        } else {
+            None
+        };
+        let host = local_address.unwrap_or_else(|| Ipv4Addr::LOCALHOST.to_string());
+        format!("http://{}:{}{}{}", host, self.port, STATUS_PATH, self.token)
+    }
+}
+
+/// Turns the link to the status page into the link to the page
+/// mirroring the timer.
+pub fn make_timer_link(share_link: &str) -> String {
+    share_link.replacen(STATUS_PATH, TIMER_PATH, 1)
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn generate_token() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(tomata::now_timestamp());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Connecting a UDP socket sends no packets, but it makes the system
+/// pick the address of the interface used for the outgoing traffic.
+fn find_local_address() -> Option<String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+fn handle_connection(mut stream: TcpStream, token: &str, status: FocusStatus) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let (status_line, body) = respond(&request_line, token, status);
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status_line,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn respond(request_line: &str, token: &str, status: FocusStatus) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method, path),
+        _ => return ("400 Bad Request", String::new()),
+    };
+    if method != "GET" {
+        return ("405 Method Not Allowed", String::new());
+    }
+    if path.strip_prefix(STATUS_PATH) == Some(token) {
+        ("200 OK", make_status_page(&describe_status(status)))
+    } else if path.strip_prefix(TIMER_PATH) == Some(token) {
+        ("200 OK", make_timer_page(status))
+    } else {
+        ("404 Not Found", String::new())
+    }
+}
+
+fn describe_status(status: FocusStatus) -> String {
+    match (status.period, status.is_running, status.free_at) {
+        (Period::Work, true, Some(free_at)) => format!(
+            "Focusing, free at {}.",
+            tomata::timestamp_to_local_time_string(free_at)
+        ),
+        (Period::Work, true, None) => "Focusing.".to_string(),
+        (Period::Work, false, _) => "Not focusing right now.".to_string(),
+        (Period::ShortBreak, _, _) | (Period::LongBreak, _, _) => "On a break.".to_string(),
+        (Period::Custom(_), _, _) => "Busy.".to_string(),
+    }
+}
+
+fn make_status_page(description: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta http-equiv=\"refresh\" content=\"30\">\
+         <title>{}</title></head><body><h1>{}</h1></body></html>",
+        tomata::APPLICATION_NAME,
+        description
+    )
+}
+
+/// The countdown goes on in the browser between the refreshes,
+/// which correct it.
+fn make_timer_page(status: FocusStatus) -> String {
+    let period = match status.period {
+        Period::Work => "Work",
+        Period::ShortBreak => "Short break",
+        Period::LongBreak => "Long break",
+        Period::Custom(_) => "Custom period",
+    };
+    let remaining = tomata::duration_to_string(&Duration::from_secs(status.remaining_seconds));
+    let script = if status.is_running {
+        format!(
+            "<script>var end = Date.now() + {} * 1000;\
+             var pad = function (n) {{ return (n < 10 ? '0' : '') + n; }};\
+             setInterval(function () {{\
+             var s = Math.max(0, Math.round((end - Date.now()) / 1000));\
+             document.getElementById('timer').textContent =\
+             pad(Math.floor(s / 3600)) + ':' + pad(Math.floor(s / 60) % 60) + ':' + pad(s % 60);\
+             }}, 1000);</script>",
+            status.remaining_seconds
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "<!DOCTYPE html><html><head><meta http-equiv=\"refresh\" content=\"30\">\
+         <title>{}</title></head><body style=\"text-align: center\"><h1>{}{}</h1>\
+         <p id=\"timer\" style=\"font-size: 20vw\">{}</p>{}</body></html>",
+        tomata::APPLICATION_NAME,
+        period,
+        if status.is_running { "" } else { " (paused)" },
+        remaining,
+        script
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    #[test]
+    fn responding_with_status_page() {
+        let status = FocusStatus {
+            period: Period::ShortBreak,
+            is_running: true,
+            free_at: None,
+            remaining_seconds: 60,
+        };
+        let (status_line, body) = respond("GET /status/0123456789abcdef HTTP/1.1", TOKEN, status);
+        assert_eq!(status_line, "200 OK");
+        assert!(body.contains("On a break."));
+    }
+
+    #[test]
+    fn rejecting_wrong_token() {
+        let (status_line, _) = respond(
+            "GET /status/fedcba9876543210 HTTP/1.1",
+            TOKEN,
+            FocusStatus::default(),
+        );
+        assert_eq!(status_line, "404 Not Found");
+        let (status_line, _) = respond("GET / HTTP/1.1", TOKEN, FocusStatus::default());
+        assert_eq!(status_line, "404 Not Found");
+    }
+
+    #[test]
+    fn linking_to_unshared_server_locally() {
+        // Any free port does, the link is all that is checked.
+        let server = StatusServer::start(0, false).unwrap();
+        assert!(server.get_share_link().starts_with("http://127.0.0.1:"));
+    }
+
+    #[test]
+    fn describing_paused_work_period() {
+        let status = FocusStatus {
+            period: Period::Work,
+            is_running: false,
+            free_at: Some(0),
+            remaining_seconds: 60,
+        };
+        assert_eq!(describe_status(status), "Not focusing right now.");
+    }
+
+    #[test]
+    fn responding_with_timer_page() {
+        let status = FocusStatus {
+            period: Period::Work,
+            is_running: false,
+            free_at: Some(0),
+            remaining_seconds: 754,
+        };
+        let (status_line, body) = respond("GET /timer/0123456789abcdef HTTP/1.1", TOKEN, status);
+        assert_eq!(status_line, "200 OK");
+        assert!(body.contains("Work (paused)"));
+        assert!(body.contains("00:12:34"));
+        assert!(!body.contains("<script>"));
+        let (status_line, _) = respond("GET /timer/fedcba9876543210 HTTP/1.1", TOKEN, status);
+        assert_eq!(status_line, "404 Not Found");
+        assert_eq!(
+            make_timer_link("http://192.168.1.2:8080/status/0123456789abcdef"),
+            "http://192.168.1.2:8080/timer/0123456789abcdef"
+        );
+    }
+}
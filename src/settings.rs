@@ -1,41 +1,271 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, BufWriter};
+use std::io::BufWriter;
 use std::path::Path;
 use std::rc::Rc;
 use std::time::Duration;
-use std::u64;
 
+#[cfg(feature = "druid")]
 use druid::{Data, Lens};
 use serde::{Deserialize, Serialize};
+use time::Date;
 
-use crate::tomata::{Period, MINUTE_S};
+use crate::ambient::AmbientSound;
+use crate::control::ControlCommand;
+use crate::cycle;
+use crate::cycle::{CycleBlock, CycleProfile};
+use crate::hotkeys;
+use crate::reminder::{ReminderKind, ReminderSettings};
+use crate::sound::SoundEvent;
+use crate::tomata;
+use crate::tomata::{CustomPeriodKind, Period, DAY_S, HOUR_S, MINUTE_S, ZERO};
 
 const TWENTY_FIVE_MINUTES: u64 = MINUTE_S * 25;
 const FIVE_MINUTES: u64 = MINUTE_S * 5;
+const DEFAULT_DAILY_GOAL: u32 = 8;
 const EIGHT_MINUTES: u64 = MINUTE_S * 8;
+const TWENTY_MINUTES: u64 = MINUTE_S * 20;
+const THIRTY_MINUTES: u64 = MINUTE_S * 30;
+const TWO_MINUTES: u64 = MINUTE_S * 2;
+const TEN_MINUTES: u64 = MINUTE_S * 10;
+const FIFTEEN_MINUTES: u64 = MINUTE_S * 15;
+const TWO_HOURS: u64 = MINUTE_S * 120;
+const FIVE_PM: u64 = HOUR_S * 17;
+const TEN_PM: u64 = HOUR_S * 22;
+const SEVEN_AM: u64 = HOUR_S * 7;
+const MIN_ALARM_REPEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// The work after the bedtime is late until this time of the next morning.
+const NIGHT_END: u64 = HOUR_S * 5;
 const DEFAULT_SHORT_BREAKS_BEFORE_LONG_BREAK: usize = 3;
+const DEFAULT_WORK_PERIODS_BEFORE_LONG_BREAK: usize = 4;
+const TOMATO: &str = "\u{1F345}";
+const COFFEE: &str = "\u{2615}";
+const COUCH: &str = "\u{1F6CB}";
+const FINISHED_WORK_NOTIFICATION_TEMPLATE: &str =
+    "{summary} \u{2014} finished {task}, {count} pomodoro today";
+const ORG_CLOCK_HEADING: &str = "Pomodoros";
+const RED: &str = "FF0000";
+const GREEN: &str = "00FF00";
+const DARK_GRAY: &str = "202020";
+const WHITE: &str = "FFFFFF";
+const DEFAULT_HTTP_SERVER_PORT: u16 = 8925;
+const MIN_TICK_INTERVAL_MS: u64 = 50;
+const MAX_TICK_INTERVAL_MS: u64 = 1000;
+/// The input of the break overlay is never locked for longer, whatever the settings say.
+const MAX_BREAK_INPUT_LOCK_S: u64 = MINUTE_S * 5;
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Version of the settings file layout. It has to be bumped whenever
+/// a change makes older files incompatible with the current application.
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
 
 /// Represents all the settings for the application, these are ought be written/read
 /// from a file by means of serialization/deserialization. Most of the settings
 /// can be changed by the user.
+///
+/// The fields are visible in the crate, so the tests of the other modules
+/// set them also without the `druid` lenses.
 #[non_exhaustive]
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Data, Lens)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+#[serde(default)]
 pub struct Settings {
-    work_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
-    short_break_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
-    long_break_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
-    short_breaks_number: usize,
-    long_breaks_are_included: bool,
-    next_period_starts_automatically: bool,
-    system_notifications_are_enabled: bool,
-    period_ending_sound_is_enabled: bool,
-    beep_volume: f64,
+    pub(crate) schema_version: u32,
+    pub(crate) work_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
+    pub(crate) short_break_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
+    pub(crate) long_break_period: Rc<Duration>, // Data cannot be derive fo Duration, unless it is in Rc
+    pub(crate) short_breaks_number: usize,
+    pub(crate) long_breaks_are_included: bool,
+    pub(crate) next_period_starts_automatically: bool,
+    pub(crate) system_notifications_are_enabled: bool,
+    pub(crate) period_ending_sound_is_enabled: bool,
+    pub(crate) beep_volume: f64,
+    pub(crate) alarm_sound_file: String,
+    pub(crate) work_ending_sound_file: String,
+    pub(crate) short_break_ending_sound_file: String,
+    pub(crate) long_break_ending_sound_file: String,
+    pub(crate) sounds_are_headphones_only: bool,
+    pub(crate) output_device: String,
+    pub(crate) ambient_sound: Option<AmbientSound>,
+    pub(crate) ambient_volume: f64,
+    pub(crate) ending_break_early_is_offered: bool,
+    pub(crate) away_time_is_reviewed: bool,
+    pub(crate) hydration_reminder: ReminderSettings,
+    pub(crate) posture_reminder: ReminderSettings,
+    pub(crate) focus_question_is_asked: bool,
+    pub(crate) calendar_file: String,
+    pub(crate) breaks_are_deferred_during_meetings: bool,
+    pub(crate) cycle_is_paused_during_meetings: bool,
+    pub(crate) screen_is_locked_on_long_break: bool,
+    pub(crate) window_is_dimmed_before_break: bool,
+    pub(crate) dimming_duration: Rc<Duration>,
+    pub(crate) break_overlay_is_shown: bool,
+    pub(crate) break_input_lock_is_enabled: bool,
+    pub(crate) break_input_lock_duration: Rc<Duration>,
+    pub(crate) focus_banner_is_shown: bool,
+    pub(crate) focus_banner_background: String,
+    pub(crate) focus_banner_text_color: String,
+    pub(crate) work_wallpaper: String,
+    pub(crate) break_wallpaper: String,
+    pub(crate) keyboard_lighting_is_enabled: bool,
+    pub(crate) work_lighting_color: String,
+    pub(crate) short_break_lighting_color: String,
+    pub(crate) long_break_lighting_color: String,
+    pub(crate) tray_icon_is_enabled: bool,
+    pub(crate) icon_badge_is_enabled: bool,
+    pub(crate) global_hotkeys_are_enabled: bool,
+    pub(crate) start_hotkey: String,
+    pub(crate) pause_hotkey: String,
+    pub(crate) skip_hotkey: String,
+    /// The names of the control commands sent by `SIGUSR1` and `SIGUSR2`,
+    /// empty to ignore the signal.
+    pub(crate) sigusr1_command: String,
+    pub(crate) sigusr2_command: String,
+    pub(crate) http_server_is_enabled: bool,
+    /// Whether the status server is reachable from the local network,
+    /// not only from this computer.
+    pub(crate) http_server_is_shared: bool,
+    pub(crate) http_server_port: u16,
+    pub(crate) ntfy_topic: String,
+    pub(crate) blocklist_is_enabled: bool,
+    pub(crate) blocked_sites: String,
+    pub(crate) distraction_workspaces: String,
+    pub(crate) distraction_workspace_pauses: bool,
+    pub(crate) aborted_sessions_are_recorded: bool,
+    pub(crate) long_break_trigger: LongBreakTrigger,
+    pub(crate) work_periods_before_long_break: usize,
+    pub(crate) tick_interval: Rc<Duration>,
+    pub(crate) notification_backend: NotificationBackend,
+    pub(crate) notification_app_name: String,
+    pub(crate) notification_icon: String,
+    pub(crate) work_icon: String,
+    pub(crate) short_break_icon: String,
+    pub(crate) long_break_icon: String,
+    pub(crate) overtime_reminder_is_enabled: bool,
+    pub(crate) overtime_threshold: Rc<Duration>,
+    pub(crate) long_run_warning_is_enabled: bool,
+    pub(crate) long_run_limit: Rc<Duration>,
+    pub(crate) remaining_time_announcements_are_enabled: bool,
+    pub(crate) remaining_time_announcement_interval: Rc<Duration>,
+    pub(crate) period_changes_are_spoken: bool,
+    pub(crate) rest_is_enforced: bool,
+    pub(crate) day_plan_is_asked: bool,
+    pub(crate) minimum_rest: Rc<Duration>,
+    pub(crate) finished_work_notification_template: String,
+    pub(crate) duration_suggestions_are_shown: bool,
+    pub(crate) workday_end_is_enabled: bool,
+    pub(crate) workday_end: Rc<Duration>, // time since the local midnight
+    pub(crate) bedtime_warning_is_enabled: bool,
+    pub(crate) bedtime: Rc<Duration>, // time since the local midnight
+    pub(crate) late_work_is_held: bool,
+    pub(crate) days_off: String,
+    pub(crate) holiday_calendar_file: String,
+    pub(crate) org_clock_file: String,
+    pub(crate) org_clock_heading: String,
+    pub(crate) weekly_summary_is_enabled: bool,
+    pub(crate) weekly_summary_smtp_url: String,
+    pub(crate) weekly_summary_email: String,
+    pub(crate) custom_periods: Rc<Vec<CustomPeriodKind>>,
+    /// The cycle goes through the blocks when there are any.
+    pub(crate) cycle_sequence: Rc<Vec<CycleBlock>>,
+    pub(crate) cycle_profiles: Rc<Vec<CycleProfile>>,
+    pub(crate) report_rounding: TimeRounding,
+    pub(crate) report_rounding_step: Rc<Duration>,
+    pub(crate) jira_url: String,
+    pub(crate) application_sampling_is_enabled: bool,
+    pub(crate) call_detection_is_enabled: bool,
+    pub(crate) clock_jump_correction_is_enabled: bool,
+    pub(crate) fullscreen_detection_is_enabled: bool,
+    pub(crate) fullscreen_pauses_breaks: bool,
+    pub(crate) fullscreen_allowed_applications: String,
+    pub(crate) fullscreen_ignored_applications: String,
+    pub(crate) escalation_is_enabled: bool,
+    pub(crate) alarm_is_repeated: bool,
+    pub(crate) alarm_repeat_interval: Rc<Duration>,
+    pub(crate) escalation_sound_delay: Rc<Duration>,
+    pub(crate) escalation_overlay_delay: Rc<Duration>,
+    pub(crate) whats_new_is_shown: bool,
+    /// Number of the pomodoros to complete every day.
+    pub(crate) daily_goal: u32,
+    pub(crate) close_action: CloseAction,
+    // the volumes of the events relative to `beep_volume`
+    pub(crate) period_ending_volume: f64,
+    pub(crate) overtime_warning_volume: f64,
+    pub(crate) escalation_volume: f64,
+    pub(crate) quiet_hours_are_enabled: bool,
+    pub(crate) quiet_hours_start: Rc<Duration>, // time since the local midnight
+    pub(crate) quiet_hours_end: Rc<Duration>,   // time since the local midnight
+    /// Scales all the volumes during the quiet hours.
+    pub(crate) quiet_hours_volume: f64,
+}
+
+/// Decides when the long break comes instead of a short one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum LongBreakTrigger {
+    /// After the configured number of the short breaks. Skipped short
+    /// breaks are not counted, so skipping them postpones the long break.
+    AfterShortBreaks,
+    /// After the configured number of the completed work periods,
+    /// regardless of how many short breaks were taken.
+    AfterWorkPeriods,
+}
+
+/// What happens when the user closes the main window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum CloseAction {
+    Quit,
+    /// Hides the window to the tray while the tray icon is shown,
+    /// minimizes it otherwise.
+    Minimize,
+    /// Asks the user to choose one of the above.
+    Ask,
+}
+
+/// Decides how the notifications reach the user when they are enabled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum NotificationBackend {
+    /// Desktop notifications of the operating system.
+    System,
+    /// Banners shown inside the main window.
+    Banner,
+    /// The notifications are only written to the standard output.
+    Log,
+}
+
+/// The sound files chosen in the settings, the ones of the periods
+/// replace the alarm sound when the period ends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum SoundFileSlot {
+    Alarm,
+    WorkEnding,
+    ShortBreakEnding,
+    LongBreakEnding,
+}
+
+/// Decides how the durations are rounded in the exported reports,
+/// the history always keeps the exact ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum TimeRounding {
+    Exact,
+    /// To the nearest multiple of the step, the halves are rounded up.
+    Nearest,
+    Up,
+    Down,
 }
 
 impl Default for Settings {
     fn default() -> Settings {
         Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
             work_period: Rc::new(Duration::from_secs(TWENTY_FIVE_MINUTES)),
             short_break_period: Rc::new(Duration::from_secs(FIVE_MINUTES)),
             long_break_period: Rc::new(Duration::from_secs(EIGHT_MINUTES)),
@@ -45,6 +275,115 @@ impl Default for Settings {
             system_notifications_are_enabled: true,
             period_ending_sound_is_enabled: true,
             beep_volume: 0.1,
+            alarm_sound_file: String::new(),
+            work_ending_sound_file: String::new(),
+            short_break_ending_sound_file: String::new(),
+            long_break_ending_sound_file: String::new(),
+            sounds_are_headphones_only: false,
+            output_device: String::new(),
+            ambient_sound: None,
+            ambient_volume: 0.3,
+            ending_break_early_is_offered: false,
+            away_time_is_reviewed: false,
+            hydration_reminder: ReminderSettings::new(false, Duration::from_secs(THIRTY_MINUTES)),
+            posture_reminder: ReminderSettings::new(false, Duration::from_secs(TWENTY_MINUTES)),
+            focus_question_is_asked: false,
+            calendar_file: String::new(),
+            breaks_are_deferred_during_meetings: false,
+            cycle_is_paused_during_meetings: false,
+            screen_is_locked_on_long_break: false,
+            window_is_dimmed_before_break: false,
+            dimming_duration: Rc::new(Duration::from_secs(TWO_MINUTES)),
+            break_overlay_is_shown: false,
+            break_input_lock_is_enabled: false,
+            break_input_lock_duration: Rc::new(Duration::from_secs(30)),
+            focus_banner_is_shown: false,
+            focus_banner_background: DARK_GRAY.to_string(),
+            focus_banner_text_color: WHITE.to_string(),
+            work_wallpaper: String::new(),
+            break_wallpaper: String::new(),
+            keyboard_lighting_is_enabled: false,
+            work_lighting_color: RED.to_string(),
+            short_break_lighting_color: GREEN.to_string(),
+            long_break_lighting_color: GREEN.to_string(),
+            tray_icon_is_enabled: false,
+            icon_badge_is_enabled: false,
+            global_hotkeys_are_enabled: false,
+            start_hotkey: "Ctrl+Alt+Up".to_string(),
+            pause_hotkey: "Ctrl+Alt+Down".to_string(),
+            skip_hotkey: "Ctrl+Alt+Right".to_string(),
+            sigusr1_command: ControlCommand::TogglePause.name().to_string(),
+            sigusr2_command: ControlCommand::SkipPeriod.name().to_string(),
+            http_server_is_enabled: false,
+            http_server_is_shared: false,
+            http_server_port: DEFAULT_HTTP_SERVER_PORT,
+            ntfy_topic: String::new(),
+            blocklist_is_enabled: false,
+            blocked_sites: String::new(),
+            distraction_workspaces: String::new(),
+            distraction_workspace_pauses: true,
+            aborted_sessions_are_recorded: false,
+            long_break_trigger: LongBreakTrigger::AfterShortBreaks,
+            work_periods_before_long_break: DEFAULT_WORK_PERIODS_BEFORE_LONG_BREAK,
+            tick_interval: Rc::new(Duration::from_millis(MAX_TICK_INTERVAL_MS)),
+            notification_backend: NotificationBackend::System,
+            notification_app_name: String::new(),
+            notification_icon: String::new(),
+            work_icon: TOMATO.to_string(),
+            short_break_icon: COFFEE.to_string(),
+            long_break_icon: COUCH.to_string(),
+            overtime_reminder_is_enabled: false,
+            overtime_threshold: Rc::new(Duration::from_secs(TEN_MINUTES)),
+            long_run_warning_is_enabled: false,
+            long_run_limit: Rc::new(Duration::from_secs(TWO_HOURS)),
+            remaining_time_announcements_are_enabled: false,
+            remaining_time_announcement_interval: Rc::new(Duration::from_secs(FIFTEEN_MINUTES)),
+            period_changes_are_spoken: false,
+            rest_is_enforced: false,
+            day_plan_is_asked: false,
+            minimum_rest: Rc::new(Duration::from_secs(FIVE_MINUTES)),
+            finished_work_notification_template: FINISHED_WORK_NOTIFICATION_TEMPLATE.to_string(),
+            duration_suggestions_are_shown: false,
+            workday_end_is_enabled: false,
+            workday_end: Rc::new(Duration::from_secs(FIVE_PM)),
+            bedtime_warning_is_enabled: false,
+            bedtime: Rc::new(Duration::from_secs(TEN_PM)),
+            late_work_is_held: false,
+            days_off: String::new(),
+            holiday_calendar_file: String::new(),
+            org_clock_file: String::new(),
+            org_clock_heading: ORG_CLOCK_HEADING.to_string(),
+            weekly_summary_is_enabled: false,
+            weekly_summary_smtp_url: String::new(),
+            weekly_summary_email: String::new(),
+            custom_periods: Rc::new(Vec::new()),
+            cycle_sequence: Rc::new(Vec::new()),
+            cycle_profiles: Rc::new(Vec::new()),
+            report_rounding: TimeRounding::Exact,
+            report_rounding_step: Rc::new(Duration::from_secs(FIFTEEN_MINUTES)),
+            jira_url: String::new(),
+            application_sampling_is_enabled: false,
+            call_detection_is_enabled: false,
+            clock_jump_correction_is_enabled: false,
+            fullscreen_detection_is_enabled: false,
+            fullscreen_pauses_breaks: false,
+            fullscreen_allowed_applications: String::new(),
+            fullscreen_ignored_applications: String::new(),
+            escalation_is_enabled: false,
+            alarm_is_repeated: false,
+            alarm_repeat_interval: Rc::new(Duration::from_secs(30)),
+            escalation_sound_delay: Rc::new(Duration::from_secs(30)),
+            escalation_overlay_delay: Rc::new(Duration::from_secs(MINUTE_S)),
+            whats_new_is_shown: true,
+            daily_goal: DEFAULT_DAILY_GOAL,
+            close_action: CloseAction::Quit,
+            period_ending_volume: 1.0,
+            overtime_warning_volume: 1.0,
+            escalation_volume: 1.0,
+            quiet_hours_are_enabled: false,
+            quiet_hours_start: Rc::new(Duration::from_secs(TEN_PM)),
+            quiet_hours_end: Rc::new(Duration::from_secs(SEVEN_AM)),
+            quiet_hours_volume: 0.3,
         }
     }
 }
@@ -64,6 +403,7 @@ impl Settings {
         beep_volume: f64,
     ) -> Settings {
         Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
             work_period: Rc::new(work_period),
             short_break_period: Rc::new(short_break_period),
             long_break_period: Rc::new(long_break_period),
@@ -73,6 +413,7 @@ impl Settings {
             system_notifications_are_enabled,
             period_ending_sound_is_enabled,
             beep_volume,
+            ..Default::default()
         }
     }
 
@@ -83,6 +424,11 @@ impl Settings {
                 self.short_break_period = Rc::new(*self.short_break_period + value)
             }
             Period::LongBreak => self.long_break_period = Rc::new(*self.long_break_period + value),
+            Period::Custom(index) => {
+                if let Some(kind) = Rc::make_mut(&mut self.custom_periods).get_mut(index) {
+                    kind.duration = Rc::new(*kind.duration + value);
+                }
+            }
         }
     }
 
@@ -112,9 +458,43 @@ impl Settings {
                     self.long_break_period = Rc::new(*self.long_break_period - value)
                 }
             }
+            Period::Custom(index) => {
+                if let Some(kind) = Rc::make_mut(&mut self.custom_periods).get_mut(index) {
+                    kind.duration = Rc::new(kind.duration.checked_sub(value).unwrap_or(ZERO));
+                }
+            }
+        }
+    }
+
+    pub fn set_period_duration(&mut self, period: Period, value: Duration) {
+        let value = Rc::new(value);
+        match period {
+            Period::Work => self.work_period = value,
+            Period::ShortBreak => self.short_break_period = value,
+            Period::LongBreak => self.long_break_period = value,
+            Period::Custom(index) => {
+                if let Some(kind) = Rc::make_mut(&mut self.custom_periods).get_mut(index) {
+                    kind.duration = value;
+                }
+            }
         }
     }
 
+    /// Moves the duration of `period` to the next multiple of `step`,
+    /// the one above when `is_increased`, otherwise the one below.
+    pub fn snap_period_duration(&mut self, period: Period, step: Duration, is_increased: bool) {
+        let step = step.as_secs().max(1);
+        let seconds = self.convert_period_to_duration(period).as_secs();
+        let snapped = if is_increased {
+            (seconds / step + 1) * step
+        } else if seconds % step == 0 {
+            seconds.saturating_sub(step)
+        } else {
+            seconds - seconds % step
+        };
+        self.set_period_duration(period, Duration::from_secs(snapped));
+    }
+
     pub fn get_short_breaks_number(&self) -> usize {
         self.short_breaks_number
     }
@@ -131,10 +511,128 @@ impl Settings {
         self.short_breaks_number -= value;
     }
 
+    #[allow(dead_code)] // used in tests
     pub fn are_long_breaks_included(&self) -> bool {
         self.long_breaks_are_included
     }
 
+    /// Periods of zero length are skipped by the cycle,
+    /// as well as the short breaks when their number is zero.
+    pub fn is_period_enabled(&self, period: Period) -> bool {
+        let has_length = self.convert_period_to_duration(period) > ZERO;
+        match period {
+            Period::Work => has_length,
+            Period::ShortBreak => has_length && self.short_breaks_number > 0,
+            Period::LongBreak => has_length && self.long_breaks_are_included,
+            Period::Custom(_) => has_length,
+        }
+    }
+
+    /// Returns the registry of the periods defined by the user,
+    /// [`Period::Custom`] refers to them by the index.
+    pub fn get_custom_periods(&self) -> &Rc<Vec<CustomPeriodKind>> {
+        &self.custom_periods
+    }
+
+    pub fn get_custom_period(&self, index: usize) -> Option<&CustomPeriodKind> {
+        self.custom_periods.get(index)
+    }
+
+    /// Returns the blocks the cycle goes through, an empty sequence
+    /// leaves the classic cycle of the work periods and the breaks.
+    pub fn get_cycle_sequence(&self) -> &Rc<Vec<CycleBlock>> {
+        &self.cycle_sequence
+    }
+
+    /// Appends the period with its current duration.
+    pub fn add_cycle_block(&mut self, period: Period) {
+        let duration = self
+            .convert_period_to_duration(period)
+            .max(cycle::MIN_BLOCK_DURATION);
+        Rc::make_mut(&mut self.cycle_sequence).push(CycleBlock::new(period, duration));
+    }
+
+    pub fn remove_cycle_block(&mut self, index: usize) {
+        if index < self.cycle_sequence.len() {
+            Rc::make_mut(&mut self.cycle_sequence).remove(index);
+        }
+    }
+
+    pub fn move_cycle_block(&mut self, index: usize, is_moved_up: bool) {
+        let blocks: &mut Vec<CycleBlock> = Rc::make_mut(&mut self.cycle_sequence);
+        cycle::move_block(blocks, index, is_moved_up);
+    }
+
+    /// Lengthens or shortens the block by `step`, down to
+    /// [`cycle::MIN_BLOCK_DURATION`].
+    pub fn shift_cycle_block_duration(&mut self, index: usize, step: Duration, is_increased: bool) {
+        if let Some(block) = Rc::make_mut(&mut self.cycle_sequence).get_mut(index) {
+            let duration = if is_increased {
+                *block.duration + step
+            } else {
+                block.duration.saturating_sub(step)
+            };
+            block.duration = Rc::new(duration.max(cycle::MIN_BLOCK_DURATION));
+        }
+    }
+
+    pub fn get_cycle_profiles(&self) -> &Rc<Vec<CycleProfile>> {
+        &self.cycle_profiles
+    }
+
+    /// Keeps the current sequence under the name, replacing the profile
+    /// of the same name.
+    pub fn save_cycle_profile(&mut self, name: &str) {
+        let profile = CycleProfile {
+            name: name.to_string(),
+            blocks: self.cycle_sequence.clone(),
+        };
+        let profiles = Rc::make_mut(&mut self.cycle_profiles);
+        match profiles.iter_mut().find(|saved| saved.name == name) {
+            Some(saved) => *saved = profile,
+            None => profiles.push(profile),
+        }
+    }
+
+    /// Makes the sequence of the profile the current one.
+    pub fn load_cycle_profile(&mut self, index: usize) {
+        if let Some(profile) = self.cycle_profiles.get(index) {
+            self.cycle_sequence = profile.blocks.clone();
+        }
+    }
+
+    pub fn remove_cycle_profile(&mut self, index: usize) {
+        if index < self.cycle_profiles.len() {
+            Rc::make_mut(&mut self.cycle_profiles).remove(index);
+        }
+    }
+
+    pub fn get_long_break_trigger(&self) -> LongBreakTrigger {
+        self.long_break_trigger
+    }
+
+    pub fn get_work_periods_before_long_break(&self) -> usize {
+        self.work_periods_before_long_break
+    }
+
+    pub fn increase_work_periods_before_long_break(&mut self, value: usize) {
+        self.work_periods_before_long_break += value;
+    }
+
+    /// At least one work period has to come before the long break.
+    pub fn decrease_work_periods_before_long_break(&mut self, value: usize) {
+        self.work_periods_before_long_break = self
+            .work_periods_before_long_break
+            .saturating_sub(value)
+            .max(1);
+    }
+
+    /// How often the elapsed time is measured, shorter intervals make
+    /// the progress bar move smoothly.
+    pub fn get_tick_interval(&self) -> Duration {
+        *self.tick_interval
+    }
+
     pub fn does_next_period_start_automatically(&self) -> bool {
         self.next_period_starts_automatically
     }
@@ -143,15 +641,645 @@ impl Settings {
         self.system_notifications_are_enabled
     }
 
+    pub fn get_notification_backend(&self) -> NotificationBackend {
+        self.notification_backend
+    }
+
+    /// The application the notifications are sent as, the server may
+    /// group or theme them by it.
+    pub fn get_notification_app_name(&self) -> &str {
+        match self.notification_app_name.trim() {
+            "" => tomata::APPLICATION_NAME,
+            name => name,
+        }
+    }
+
+    /// Returns the icon of the notifications, a path or a themed icon name, if set.
+    pub fn get_notification_icon(&self) -> Option<&str> {
+        match self.notification_icon.trim() {
+            "" => None,
+            icon => Some(icon),
+        }
+    }
+
     pub fn is_period_ending_sound_enabled(&self) -> bool {
         self.period_ending_sound_is_enabled
     }
 
+    /// The period ending sound can be turned off for each of the custom periods.
+    pub fn is_period_ending_sound_enabled_for(&self, period: Period) -> bool {
+        let is_enabled_for_period = match period {
+            Period::Custom(index) => self
+                .get_custom_period(index)
+                .is_some_and(|kind| kind.ending_sound_is_enabled),
+            _ => true,
+        };
+        self.is_period_ending_sound_enabled() && is_enabled_for_period
+    }
+
+    pub fn is_ending_break_early_offered(&self) -> bool {
+        self.ending_break_early_is_offered
+    }
+
+    /// Whether the user is asked what the time away from the computer
+    /// during a work period was.
+    pub fn is_away_time_reviewed(&self) -> bool {
+        self.away_time_is_reviewed
+    }
+
+    pub fn is_focus_question_asked(&self) -> bool {
+        self.focus_question_is_asked
+    }
+
+    /// Returns the path of the iCalendar file with the meetings of the user, if set.
+    /// Returns the path of the WAV file of the slot, if set.
+    pub fn get_sound_file(&self, slot: SoundFileSlot) -> Option<&Path> {
+        let path = match slot {
+            SoundFileSlot::Alarm => &self.alarm_sound_file,
+            SoundFileSlot::WorkEnding => &self.work_ending_sound_file,
+            SoundFileSlot::ShortBreakEnding => &self.short_break_ending_sound_file,
+            SoundFileSlot::LongBreakEnding => &self.long_break_ending_sound_file,
+        };
+        match path.trim() {
+            "" => None,
+            path => Some(Path::new(path)),
+        }
+    }
+
+    pub fn set_sound_file(&mut self, slot: SoundFileSlot, path: &Path) {
+        let path = path.display().to_string();
+        match slot {
+            SoundFileSlot::Alarm => self.alarm_sound_file = path,
+            SoundFileSlot::WorkEnding => self.work_ending_sound_file = path,
+            SoundFileSlot::ShortBreakEnding => self.short_break_ending_sound_file = path,
+            SoundFileSlot::LongBreakEnding => self.long_break_ending_sound_file = path,
+        }
+    }
+
+    /// Returns the sound file played as the period ends, the one chosen
+    /// for its kind or the alarm sound. `None` means the beep.
+    pub fn get_period_ending_sound_file(&self, period: Period) -> Option<&Path> {
+        let slot = match period {
+            Period::Work => SoundFileSlot::WorkEnding,
+            Period::ShortBreak => SoundFileSlot::ShortBreakEnding,
+            Period::LongBreak => SoundFileSlot::LongBreakEnding,
+            Period::Custom(_) => SoundFileSlot::Alarm,
+        };
+        self.get_sound_file(slot)
+            .or_else(|| self.get_sound_file(SoundFileSlot::Alarm))
+    }
+
+    /// Returns the path of the iCalendar file with the meetings of the user, if set.
+    pub fn get_calendar_file(&self) -> Option<&Path> {
+        match self.calendar_file.trim() {
+            "" => None,
+            path => Some(Path::new(path)),
+        }
+    }
+
+    pub fn are_breaks_deferred_during_meetings(&self) -> bool {
+        self.breaks_are_deferred_during_meetings
+    }
+
+    pub fn is_cycle_paused_during_meetings(&self) -> bool {
+        self.cycle_is_paused_during_meetings
+    }
+
+    pub fn is_screen_locked_on_long_break(&self) -> bool {
+        self.screen_is_locked_on_long_break
+    }
+
+    pub fn is_window_dimmed_before_break(&self) -> bool {
+        self.window_is_dimmed_before_break
+    }
+
+    /// Returns for how long before the end of a work period the window is dimmed.
+    pub fn get_dimming_duration(&self) -> Duration {
+        *self.dimming_duration
+    }
+
+    pub fn increase_dimming_duration(&mut self, value: Duration) {
+        self.dimming_duration = Rc::new(*self.dimming_duration + value);
+    }
+
+    pub fn decrease_dimming_duration(&mut self, value: Duration) {
+        self.dimming_duration = Rc::new(self.dimming_duration.checked_sub(value).unwrap_or(ZERO));
+    }
+
+    pub fn is_overtime_reminder_enabled(&self) -> bool {
+        self.overtime_reminder_is_enabled
+    }
+
+    /// Overtime after which the user is reminded again to take the break.
+    pub fn get_overtime_threshold(&self) -> Duration {
+        *self.overtime_threshold
+    }
+
+    pub fn increase_overtime_threshold(&mut self, value: Duration) {
+        self.overtime_threshold = Rc::new(*self.overtime_threshold + value);
+    }
+
+    /// The threshold does not go below one minute, the reminders would
+    /// come on every tick otherwise.
+    pub fn decrease_overtime_threshold(&mut self, value: Duration) {
+        let threshold = self.overtime_threshold.checked_sub(value).unwrap_or(ZERO);
+        self.overtime_threshold = Rc::new(threshold.max(Duration::from_secs(MINUTE_S)));
+    }
+
+    pub fn is_long_run_warning_enabled(&self) -> bool {
+        self.long_run_warning_is_enabled
+    }
+
+    /// Continuous work time, i.e., without finishing any break, after
+    /// which the user is warned.
+    pub fn get_long_run_limit(&self) -> Duration {
+        *self.long_run_limit
+    }
+
+    pub fn increase_long_run_limit(&mut self, value: Duration) {
+        self.long_run_limit = Rc::new(*self.long_run_limit + value);
+    }
+
+    pub fn decrease_long_run_limit(&mut self, value: Duration) {
+        self.long_run_limit = Rc::new(self.long_run_limit.checked_sub(value).unwrap_or(ZERO));
+    }
+
+    /// The end of a period is also read aloud, for the users who miss
+    /// the notifications.
+    pub fn are_period_changes_spoken(&self) -> bool {
+        self.period_changes_are_spoken
+    }
+
+    /// The next work period does not start by itself until the user rests
+    /// for the minimum time after the finished one.
+    pub fn is_rest_enforced(&self) -> bool {
+        self.rest_is_enforced
+    }
+
+    pub fn get_minimum_rest(&self) -> Duration {
+        *self.minimum_rest
+    }
+
+    pub fn increase_minimum_rest(&mut self, value: Duration) {
+        self.minimum_rest = Rc::new(*self.minimum_rest + value);
+    }
+
+    pub fn decrease_minimum_rest(&mut self, value: Duration) {
+        self.minimum_rest = Rc::new(self.minimum_rest.checked_sub(value).unwrap_or(ZERO));
+    }
+
+    pub fn are_remaining_time_announcements_enabled(&self) -> bool {
+        self.remaining_time_announcements_are_enabled
+    }
+
+    /// The remaining time of the work period is announced every interval.
+    pub fn get_remaining_time_announcement_interval(&self) -> Duration {
+        *self.remaining_time_announcement_interval
+    }
+
+    pub fn increase_remaining_time_announcement_interval(&mut self, value: Duration) {
+        self.remaining_time_announcement_interval =
+            Rc::new(*self.remaining_time_announcement_interval + value);
+    }
+
+    /// The interval does not go below one minute, the announcements would
+    /// come on every tick otherwise.
+    pub fn decrease_remaining_time_announcement_interval(&mut self, value: Duration) {
+        let interval = self
+            .remaining_time_announcement_interval
+            .checked_sub(value)
+            .unwrap_or(ZERO);
+        self.remaining_time_announcement_interval =
+            Rc::new(interval.max(Duration::from_secs(MINUTE_S)));
+    }
+
+    /// Summary of the notification about the break that follows a finished
+    /// work period, `{summary}`, `{task}` and `{count}` are replaced with
+    /// the kind of the break, the finished task and today's pomodoro count.
+    pub fn get_finished_work_notification_template(&self) -> &str {
+        &self.finished_work_notification_template
+    }
+
+    pub fn are_duration_suggestions_shown(&self) -> bool {
+        self.duration_suggestions_are_shown
+    }
+
+    pub fn is_workday_end_enabled(&self) -> bool {
+        self.workday_end_is_enabled
+    }
+
+    /// Time of the day, since the local midnight, after which the next
+    /// periods no longer start automatically.
+    pub fn get_workday_end(&self) -> Duration {
+        *self.workday_end
+    }
+
+    /// The workday ends at the latest a minute before the midnight.
+    pub fn increase_workday_end(&mut self, value: Duration) {
+        let latest = Duration::from_secs(DAY_S - MINUTE_S);
+        self.workday_end = Rc::new((*self.workday_end + value).min(latest));
+    }
+
+    pub fn decrease_workday_end(&mut self, value: Duration) {
+        self.workday_end = Rc::new(self.workday_end.checked_sub(value).unwrap_or(ZERO));
+    }
+
+    /// Time of the day, since the local midnight, after which starting
+    /// the work periods is warned about.
+    pub fn get_bedtime(&self) -> Duration {
+        *self.bedtime
+    }
+
+    pub fn increase_bedtime(&mut self, value: Duration) {
+        let latest = Duration::from_secs(DAY_S - MINUTE_S);
+        self.bedtime = Rc::new((*self.bedtime + value).min(latest));
+    }
+
+    pub fn decrease_bedtime(&mut self, value: Duration) {
+        self.bedtime = Rc::new(self.bedtime.checked_sub(value).unwrap_or(ZERO));
+    }
+
+    /// Tells whether `time_of_day` is between the bedtime and the end
+    /// of the night, when the warning is enabled.
+    pub fn is_after_bedtime(&self, time_of_day: Duration) -> bool {
+        self.bedtime_warning_is_enabled
+            && (time_of_day >= *self.bedtime || time_of_day < Duration::from_secs(NIGHT_END))
+    }
+
+    /// The work periods after the bedtime do not start automatically.
+    pub fn is_late_work_held(&self) -> bool {
+        self.late_work_is_held
+    }
+
+    /// Returns the days off, e.g., vacations, they are written in the
+    /// `YYYY-MM-DD` format and separated with whitespace or commas in the settings.
+    pub fn get_days_off(&self) -> Vec<Date> {
+        split_list(&self.days_off)
+            .filter_map(tomata::parse_date)
+            .collect()
+    }
+
+    /// Returns the path of the iCalendar file whose all-day events are the days off, if set.
+    pub fn get_holiday_calendar_file(&self) -> Option<&Path> {
+        match self.holiday_calendar_file.trim() {
+            "" => None,
+            path => Some(Path::new(path)),
+        }
+    }
+
+    /// Returns the path of the org-mode file the work periods are clocked in, if set.
+    pub fn get_org_clock_file(&self) -> Option<&Path> {
+        match self.org_clock_file.trim() {
+            "" => None,
+            path => Some(Path::new(path)),
+        }
+    }
+
+    /// The heading of the org file whose logbook gets the clock entries.
+    pub fn get_org_clock_heading(&self) -> &str {
+        match self.org_clock_heading.trim() {
+            "" => ORG_CLOCK_HEADING,
+            heading => heading,
+        }
+    }
+
+    pub fn is_weekly_summary_enabled(&self) -> bool {
+        self.weekly_summary_is_enabled
+    }
+
+    /// Returns the SMTP server and the recipient of the weekly summary,
+    /// if both are set.
+    pub fn get_weekly_summary_email(&self) -> Option<(&str, &str)> {
+        match (
+            self.weekly_summary_smtp_url.trim(),
+            self.weekly_summary_email.trim(),
+        ) {
+            ("", _) | (_, "") => None,
+            (url, email) => Some((url, email)),
+        }
+    }
+
+    pub fn get_report_rounding(&self) -> TimeRounding {
+        self.report_rounding
+    }
+
+    /// The durations in the reports are rounded to the multiples of this step.
+    pub fn get_report_rounding_step(&self) -> Duration {
+        *self.report_rounding_step
+    }
+
+    pub fn increase_report_rounding_step(&mut self, value: Duration) {
+        self.report_rounding_step = Rc::new(*self.report_rounding_step + value);
+    }
+
+    /// The step does not go below one minute.
+    pub fn decrease_report_rounding_step(&mut self, value: Duration) {
+        let step = self.report_rounding_step.checked_sub(value).unwrap_or(ZERO);
+        self.report_rounding_step = Rc::new(step.max(Duration::from_secs(MINUTE_S)));
+    }
+
+    /// Returns the address of the Jira instance the issue keys in the tags refer to, if set.
+    pub fn get_jira_url(&self) -> Option<&str> {
+        match self.jira_url.trim() {
+            "" => None,
+            url => Some(url),
+        }
+    }
+
+    pub fn get_close_action(&self) -> CloseAction {
+        self.close_action
+    }
+
+    pub fn set_close_action(&mut self, action: CloseAction) {
+        self.close_action = action;
+    }
+
+    /// The goal and the primary project of the day are asked for
+    /// at the first launch of the day.
+    pub fn is_day_plan_asked(&self) -> bool {
+        self.day_plan_is_asked
+    }
+
+    pub fn get_daily_goal(&self) -> u32 {
+        self.daily_goal
+    }
+
+    pub fn increase_daily_goal(&mut self, value: u32) {
+        self.daily_goal += value;
+    }
+
+    /// The goal does not go below one pomodoro.
+    pub fn decrease_daily_goal(&mut self, value: u32) {
+        self.daily_goal = self.daily_goal.saturating_sub(value).max(1);
+    }
+
+    pub fn is_whats_new_shown(&self) -> bool {
+        self.whats_new_is_shown
+    }
+
+    /// The alarm about the finished period is beeped again and again
+    /// until the user starts the next one.
+    pub fn is_alarm_repeated(&self) -> bool {
+        self.alarm_is_repeated
+    }
+
+    pub fn get_alarm_repeat_interval(&self) -> Duration {
+        *self.alarm_repeat_interval
+    }
+
+    pub fn increase_alarm_repeat_interval(&mut self, value: Duration) {
+        self.alarm_repeat_interval = Rc::new(*self.alarm_repeat_interval + value);
+    }
+
+    /// The interval does not go below five seconds, the beeps would
+    /// overlap otherwise.
+    pub fn decrease_alarm_repeat_interval(&mut self, value: Duration) {
+        let interval = self
+            .alarm_repeat_interval
+            .checked_sub(value)
+            .unwrap_or(ZERO);
+        self.alarm_repeat_interval = Rc::new(interval.max(MIN_ALARM_REPEAT_INTERVAL));
+    }
+
+    pub fn is_escalation_enabled(&self) -> bool {
+        self.escalation_is_enabled
+    }
+
+    /// Time after the notification until the sound is played.
+    pub fn get_escalation_sound_delay(&self) -> Duration {
+        *self.escalation_sound_delay
+    }
+
+    /// Time after the sound until the fullscreen overlay is shown.
+    pub fn get_escalation_overlay_delay(&self) -> Duration {
+        *self.escalation_overlay_delay
+    }
+
+    pub fn is_call_detection_enabled(&self) -> bool {
+        self.call_detection_is_enabled
+    }
+
+    pub fn is_clock_jump_correction_enabled(&self) -> bool {
+        self.clock_jump_correction_is_enabled
+    }
+
+    pub fn is_fullscreen_detection_enabled(&self) -> bool {
+        self.fullscreen_detection_is_enabled
+    }
+
+    /// Whether the running break is paused while a fullscreen application is active.
+    pub fn are_breaks_paused_by_fullscreen(&self) -> bool {
+        self.fullscreen_pauses_breaks
+    }
+
+    /// Tells whether the fullscreen window of `application` suppresses
+    /// the overlays and the sounds. Only the allowed applications do when
+    /// there are some, the ignored ones never do, e.g., a fullscreen editor.
+    pub fn is_fullscreen_application_respected(&self, application: Option<&str>) -> bool {
+        let mut allowed = split_list(&self.fullscreen_allowed_applications).peekable();
+        let is_allowed = match application {
+            _ if allowed.peek().is_none() => true,
+            Some(application) => allowed.any(|allowed| allowed.eq_ignore_ascii_case(application)),
+            None => false,
+        };
+        let is_ignored = application.is_some_and(|application| {
+            split_list(&self.fullscreen_ignored_applications)
+                .any(|ignored| ignored.eq_ignore_ascii_case(application))
+        });
+        is_allowed && !is_ignored
+    }
+
+    pub fn is_application_sampling_enabled(&self) -> bool {
+        self.application_sampling_is_enabled
+    }
+
+    pub fn is_break_overlay_shown(&self) -> bool {
+        self.break_overlay_is_shown
+    }
+
+    pub fn is_break_input_lock_enabled(&self) -> bool {
+        self.break_input_lock_is_enabled
+    }
+
+    /// For how long at the beginning of a break the overlay swallows the input.
+    pub fn get_break_input_lock_duration(&self) -> Duration {
+        *self.break_input_lock_duration
+    }
+
+    pub fn increase_break_input_lock_duration(&mut self, value: Duration) {
+        let duration = *self.break_input_lock_duration + value;
+        self.break_input_lock_duration =
+            Rc::new(duration.min(Duration::from_secs(MAX_BREAK_INPUT_LOCK_S)));
+    }
+
+    pub fn decrease_break_input_lock_duration(&mut self, value: Duration) {
+        self.break_input_lock_duration = Rc::new(
+            self.break_input_lock_duration
+                .checked_sub(value)
+                .unwrap_or(ZERO),
+        );
+    }
+
+    pub fn is_focus_banner_shown(&self) -> bool {
+        self.focus_banner_is_shown
+    }
+
+    /// Returns the background and the text color of the focus banner,
+    /// they do not follow the theme of the main window.
+    pub fn get_focus_banner_colors(&self) -> (&str, &str) {
+        (&self.focus_banner_background, &self.focus_banner_text_color)
+    }
+
+    /// Returns the symbol marking `period` in the window title.
+    pub fn get_period_icon(&self, period: Period) -> &str {
+        match period {
+            Period::Work => &self.work_icon,
+            Period::ShortBreak => &self.short_break_icon,
+            Period::LongBreak => &self.long_break_icon,
+            Period::Custom(index) => self
+                .get_custom_period(index)
+                .map_or("", |kind| kind.icon.as_str()),
+        }
+    }
+
+    /// Returns the path of the wallpaper shown during `period`, if set.
+    pub fn get_wallpaper(&self, period: Period) -> Option<&Path> {
+        let wallpaper = match period {
+            Period::Work | Period::Custom(_) => &self.work_wallpaper,
+            Period::ShortBreak | Period::LongBreak => &self.break_wallpaper,
+        };
+        match wallpaper.trim() {
+            "" => None,
+            path => Some(Path::new(path)),
+        }
+    }
+
+    pub fn is_keyboard_lighting_enabled(&self) -> bool {
+        self.keyboard_lighting_is_enabled
+    }
+
+    /// Returns the `RRGGBB` color of the lighting during `period`.
+    pub fn get_lighting_color(&self, period: Period) -> &str {
+        match period {
+            Period::Work => &self.work_lighting_color,
+            Period::ShortBreak => &self.short_break_lighting_color,
+            Period::LongBreak => &self.long_break_lighting_color,
+            Period::Custom(index) => self
+                .get_custom_period(index)
+                .map_or(&self.work_lighting_color, |kind| &kind.color),
+        }
+    }
+
+    pub fn is_tray_icon_enabled(&self) -> bool {
+        self.tray_icon_is_enabled
+    }
+
+    pub fn is_icon_badge_enabled(&self) -> bool {
+        self.icon_badge_is_enabled
+    }
+
+    pub fn are_global_hotkeys_enabled(&self) -> bool {
+        self.global_hotkeys_are_enabled
+    }
+
+    /// The chords of `sxhkd` with their commands, the empty and the invalid
+    /// shortcuts are left out.
+    pub fn get_global_hotkeys(&self) -> Vec<(String, ControlCommand)> {
+        self.list_hotkeys()
+            .iter()
+            .filter_map(|(_, hotkey, command)| {
+                hotkeys::parse_hotkey(hotkey).map(|chord| (chord, command.clone()))
+            })
+            .collect()
+    }
+
+    /// The shortcuts as they were typed, telling whether they changed
+    /// does not need them parsed.
+    pub fn get_global_hotkey_texts(&self) -> [&str; 3] {
+        [&self.start_hotkey, &self.pause_hotkey, &self.skip_hotkey]
+    }
+
+    fn list_hotkeys(&self) -> [(&'static str, &str, ControlCommand); 3] {
+        [
+            ("start_hotkey", &self.start_hotkey, ControlCommand::Resume),
+            ("pause_hotkey", &self.pause_hotkey, ControlCommand::Pause),
+            ("skip_hotkey", &self.skip_hotkey, ControlCommand::SkipPeriod),
+        ]
+    }
+
+    /// The command sent by `SIGUSR1`, on Unix.
+    pub fn get_sigusr1_command(&self) -> Option<ControlCommand> {
+        ControlCommand::from_name(self.sigusr1_command.trim())
+    }
+
+    /// The command sent by `SIGUSR2`, on Unix.
+    pub fn get_sigusr2_command(&self) -> Option<ControlCommand> {
+        ControlCommand::from_name(self.sigusr2_command.trim())
+    }
+
+    pub fn is_http_server_enabled(&self) -> bool {
+        self.http_server_is_enabled
+    }
+
+    pub fn is_http_server_shared(&self) -> bool {
+        self.http_server_is_shared
+    }
+
+    pub fn get_http_server_port(&self) -> u16 {
+        self.http_server_port
+    }
+
+    pub fn get_ntfy_topic(&self) -> Option<&str> {
+        match self.ntfy_topic.trim() {
+            "" => None,
+            topic => Some(topic),
+        }
+    }
+
+    pub fn is_blocklist_enabled(&self) -> bool {
+        self.blocklist_is_enabled
+    }
+
+    pub fn set_blocklist_enabled(&mut self, is_enabled: bool) {
+        self.blocklist_is_enabled = is_enabled;
+    }
+
+    /// Returns the sites blocked during work periods, they are separated
+    /// with whitespace or commas in the settings.
+    pub fn get_blocked_sites(&self) -> Vec<&str> {
+        split_list(&self.blocked_sites).collect()
+    }
+
+    /// Returns the i3 or sway workspaces counted as interruptions of
+    /// the work, the switches are not watched when there are none.
+    pub fn get_distraction_workspaces(&self) -> Vec<&str> {
+        split_list(&self.distraction_workspaces).collect()
+    }
+
+    /// Whether switching to a distraction workspace also pauses the work period.
+    pub fn is_paused_on_distraction_workspace(&self) -> bool {
+        self.distraction_workspace_pauses
+    }
+
+    pub fn are_aborted_sessions_recorded(&self) -> bool {
+        self.aborted_sessions_are_recorded
+    }
+
+    pub fn get_reminder(&self, kind: ReminderKind) -> &ReminderSettings {
+        match kind {
+            ReminderKind::Hydration => &self.hydration_reminder,
+            ReminderKind::Posture => &self.posture_reminder,
+        }
+    }
+
     pub fn convert_period_to_duration(&self, period: Period) -> Duration {
         match period {
             Period::Work => *self.work_period,
             Period::ShortBreak => *self.short_break_period,
             Period::LongBreak => *self.long_break_period,
+            Period::Custom(index) => self
+                .get_custom_period(index)
+                .map_or(ZERO, |kind| *kind.duration),
         }
     }
 
@@ -161,20 +1289,413 @@ impl Settings {
         // worth the effort.
         self.beep_volume as f32
     }
+
+    /// Whether the sounds are played only through the headphones, so they
+    /// do not disturb the others, and replaced by a banner without them.
+    pub fn are_sounds_headphones_only(&self) -> bool {
+        self.sounds_are_headphones_only
+    }
+
+    /// The name of the device the sounds are played through,
+    /// `None` for the default one.
+    pub fn get_output_device(&self) -> Option<&str> {
+        Some(self.output_device.as_str()).filter(|name| !name.is_empty())
+    }
+
+    /// The sound looped during the work periods, if any.
+    pub fn get_ambient_sound(&self) -> Option<AmbientSound> {
+        self.ambient_sound
+    }
+
+    /// The volume of the ambient sound, independent of the beep volume.
+    pub fn get_ambient_volume(&self) -> f32 {
+        self.ambient_volume as f32
+    }
+
+    pub fn get_quiet_hours_start(&self) -> Duration {
+        *self.quiet_hours_start
+    }
+
+    pub fn get_quiet_hours_end(&self) -> Duration {
+        *self.quiet_hours_end
+    }
+
+    /// The quiet hours wrap around the midnight.
+    pub fn shift_quiet_hours_start(&mut self, value: Duration, is_forward: bool) {
+        self.quiet_hours_start = Rc::new(shift_time_of_day(
+            *self.quiet_hours_start,
+            value,
+            is_forward,
+        ));
+    }
+
+    pub fn shift_quiet_hours_end(&mut self, value: Duration, is_forward: bool) {
+        self.quiet_hours_end = Rc::new(shift_time_of_day(*self.quiet_hours_end, value, is_forward));
+    }
+
+    /// Returns the factor scaling the volumes at `time_of_day`, i.e.,
+    /// the time since the local midnight, one outside the quiet hours.
+    pub fn get_quiet_hours_volume(&self, time_of_day: Duration) -> f32 {
+        let (start, end) = (*self.quiet_hours_start, *self.quiet_hours_end);
+        let is_quiet = if start <= end {
+            start <= time_of_day && time_of_day < end
+        } else {
+            start <= time_of_day || time_of_day < end
+        };
+        if self.quiet_hours_are_enabled && is_quiet {
+            self.quiet_hours_volume as f32
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the volume of the event relative to the beep volume.
+    pub fn get_event_volume(&self, event: SoundEvent) -> f32 {
+        let volume = match event {
+            SoundEvent::PeriodEnding => self.period_ending_volume,
+            SoundEvent::OvertimeWarning(_) => self.overtime_warning_volume,
+            SoundEvent::Escalation => self.escalation_volume,
+            SoundEvent::VolumeCheck => 1.0,
+        };
+        volume as f32
+    }
+
+    /// Lists all the values that the application cannot work with.
+    pub fn validate(&self) -> Vec<SettingsError> {
+        let mut errors = Vec::new();
+        if self.schema_version != SETTINGS_SCHEMA_VERSION {
+            errors.push(SettingsError::SchemaVersionMismatch {
+                found: self.schema_version,
+                expected: SETTINGS_SCHEMA_VERSION,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.beep_volume) {
+            errors.push(SettingsError::OutOfRange {
+                field: "beep_volume",
+                value: self.beep_volume.to_string(),
+                expected: "a number between 0 and 1",
+            });
+        }
+        let event_volumes = [
+            ("period_ending_volume", self.period_ending_volume),
+            ("overtime_warning_volume", self.overtime_warning_volume),
+            ("escalation_volume", self.escalation_volume),
+            ("quiet_hours_volume", self.quiet_hours_volume),
+            ("ambient_volume", self.ambient_volume),
+        ];
+        for (field, volume) in event_volumes.iter() {
+            if !(0.0..=1.0).contains(volume) {
+                errors.push(SettingsError::OutOfRange {
+                    field,
+                    value: volume.to_string(),
+                    expected: "a number between 0 and 1",
+                });
+            }
+        }
+        if self.work_periods_before_long_break == 0 {
+            errors.push(SettingsError::OutOfRange {
+                field: "work_periods_before_long_break",
+                value: self.work_periods_before_long_break.to_string(),
+                expected: "at least 1",
+            });
+        }
+        if *self.break_input_lock_duration > Duration::from_secs(MAX_BREAK_INPUT_LOCK_S) {
+            errors.push(SettingsError::OutOfRange {
+                field: "break_input_lock_duration",
+                value: format!("{:?}", self.break_input_lock_duration),
+                expected: "at most 5 minutes",
+            });
+        }
+        let tick_interval = Duration::from_millis(MIN_TICK_INTERVAL_MS)
+            ..=Duration::from_millis(MAX_TICK_INTERVAL_MS);
+        if !tick_interval.contains(&*self.tick_interval) {
+            errors.push(SettingsError::OutOfRange {
+                field: "tick_interval",
+                value: format!("{:?}", self.tick_interval),
+                expected: "an interval between 50 ms and 1 s",
+            });
+        }
+        let quiet_hours = [
+            ("quiet_hours_start", *self.quiet_hours_start),
+            ("quiet_hours_end", *self.quiet_hours_end),
+        ];
+        for (field, time) in quiet_hours.iter() {
+            if *time >= Duration::from_secs(DAY_S) {
+                errors.push(SettingsError::OutOfRange {
+                    field,
+                    value: format!("{:?}", time),
+                    expected: "a time shorter than 24 hours",
+                });
+            }
+        }
+        if *self.workday_end >= Duration::from_secs(DAY_S) {
+            errors.push(SettingsError::OutOfRange {
+                field: "workday_end",
+                value: format!("{:?}", self.workday_end),
+                expected: "a time of the day shorter than 24 h",
+            });
+        }
+        if *self.alarm_repeat_interval < MIN_ALARM_REPEAT_INTERVAL {
+            errors.push(SettingsError::OutOfRange {
+                field: "alarm_repeat_interval",
+                value: format!("{:?}", self.alarm_repeat_interval),
+                expected: "at least 5 seconds",
+            });
+        }
+        if *self.bedtime >= Duration::from_secs(DAY_S) {
+            errors.push(SettingsError::OutOfRange {
+                field: "bedtime",
+                value: format!("{:?}", self.bedtime),
+                expected: "a time of the day shorter than 24 h",
+            });
+        }
+        for day in split_list(&self.days_off).filter(|day| tomata::parse_date(day).is_none()) {
+            errors.push(SettingsError::OutOfRange {
+                field: "days_off",
+                value: day.to_string(),
+                expected: "dates in the YYYY-MM-DD format",
+            });
+        }
+        if *self.report_rounding_step == ZERO {
+            errors.push(SettingsError::OutOfRange {
+                field: "report_rounding_step",
+                value: format!("{:?}", self.report_rounding_step),
+                expected: "a step longer than zero",
+            });
+        }
+        if self.http_server_port == 0 {
+            errors.push(SettingsError::OutOfRange {
+                field: "http_server_port",
+                value: self.http_server_port.to_string(),
+                expected: "a port between 1 and 65535",
+            });
+        }
+        for (field, hotkey, _) in self.list_hotkeys().iter() {
+            if !hotkey.trim().is_empty() && hotkeys::parse_hotkey(hotkey).is_none() {
+                errors.push(SettingsError::OutOfRange {
+                    field,
+                    value: hotkey.to_string(),
+                    expected: "a shortcut with a modifier, like Ctrl+Alt+P",
+                });
+            }
+        }
+        let signal_commands = [
+            ("sigusr1_command", &self.sigusr1_command),
+            ("sigusr2_command", &self.sigusr2_command),
+        ];
+        for (field, command) in signal_commands.iter() {
+            let command = command.trim();
+            if !command.is_empty() && ControlCommand::from_name(command).is_none() {
+                errors.push(SettingsError::OutOfRange {
+                    field,
+                    value: command.to_string(),
+                    expected: "a control command, like toggle-pause",
+                });
+            }
+        }
+        let lighting_colors = [
+            ("work_lighting_color", &self.work_lighting_color),
+            (
+                "short_break_lighting_color",
+                &self.short_break_lighting_color,
+            ),
+            ("long_break_lighting_color", &self.long_break_lighting_color),
+            ("focus_banner_background", &self.focus_banner_background),
+            ("focus_banner_text_color", &self.focus_banner_text_color),
+        ];
+        let blocks = self.cycle_sequence.iter().chain(
+            self.cycle_profiles
+                .iter()
+                .flat_map(|profile| profile.blocks.iter()),
+        );
+        for block in blocks {
+            if *block.duration == ZERO {
+                errors.push(SettingsError::OutOfRange {
+                    field: "cycle_sequence",
+                    value: "0s".to_string(),
+                    expected: "a block longer than zero",
+                });
+            }
+            if let Period::Custom(index) = block.period {
+                if index >= self.custom_periods.len() {
+                    errors.push(SettingsError::OutOfRange {
+                        field: "cycle_sequence",
+                        value: format!("Custom({})", index),
+                        expected: "a block of one of the custom periods",
+                    });
+                }
+            }
+        }
+        for profile in self.cycle_profiles.iter() {
+            if profile.name.trim().is_empty() {
+                errors.push(SettingsError::OutOfRange {
+                    field: "cycle_profiles",
+                    value: format!("{:?}", profile.name),
+                    expected: "a profile with a name",
+                });
+            }
+        }
+        for kind in self.custom_periods.iter() {
+            if kind.name.trim().is_empty() {
+                errors.push(SettingsError::OutOfRange {
+                    field: "custom_periods",
+                    value: format!("{:?}", kind.name),
+                    expected: "a period with a name",
+                });
+            }
+            if !is_rgb_color(&kind.color) {
+                errors.push(SettingsError::OutOfRange {
+                    field: "custom_periods",
+                    value: kind.color.clone(),
+                    expected: "a color in the RRGGBB format",
+                });
+            }
+        }
+        for (field, color) in lighting_colors.iter() {
+            if !is_rgb_color(color) {
+                errors.push(SettingsError::OutOfRange {
+                    field,
+                    value: color.to_string(),
+                    expected: "a color in the RRGGBB format",
+                });
+            }
+        }
+        let smtp_url = self.weekly_summary_smtp_url.trim();
+        if !smtp_url.is_empty()
+            && !["smtp://", "smtps://"]
+                .iter()
+                .any(|s| smtp_url.starts_with(s))
+        {
+            errors.push(SettingsError::OutOfRange {
+                field: "weekly_summary_smtp_url",
+                value: smtp_url.to_string(),
+                expected: "an smtp:// or smtps:// address",
+            });
+        }
+        errors
+    }
+}
+
+/// Moves the time of the day by `value`, wrapping around the midnight.
+fn shift_time_of_day(time: Duration, value: Duration, is_forward: bool) -> Duration {
+    let (time, value) = (time.as_secs() % DAY_S, value.as_secs() % DAY_S);
+    if is_forward {
+        Duration::from_secs((time + value) % DAY_S)
+    } else {
+        Duration::from_secs((time + DAY_S - value) % DAY_S)
+    }
+}
+
+/// Splits a list written by the user, the items are separated with whitespace or commas.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|item| !item.is_empty())
+}
+
+fn is_rgb_color(color: &str) -> bool {
+    let color = color.trim_start_matches('#');
+    color.len() == 6 && color.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Problems that can be found in a settings file by [`check_settings_file`].
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Malformed(serde_json::Error),
+    UnknownField(String),
+    OutOfRange {
+        field: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    SchemaVersionMismatch {
+        found: u32,
+        expected: u32,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "could not read the file: {}", error),
+            SettingsError::Malformed(error) => write!(f, "malformed settings: {}", error),
+            SettingsError::UnknownField(name) => write!(f, "unknown field `{}`", name),
+            SettingsError::OutOfRange {
+                field,
+                value,
+                expected,
+            } => write!(f, "`{}` is {}, expected {}", field, value, expected),
+            SettingsError::SchemaVersionMismatch { found, expected } => write!(
+                f,
+                "schema version {} is not supported, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+/// Where a settings file that could not be loaded is moved to, so the user
+/// can fix it while the application runs with the defaults.
+pub const REJECTED_SETTINGS_FILE: &str = "settings.json.rejected";
+
+pub fn load_settings_from_file(path: impl AsRef<Path>) -> Result<Settings, SettingsError> {
+    let json = fs::read_to_string(path).map_err(SettingsError::Io)?;
+    parse_settings(&json)
+}
+
+/// Rejects the malformed settings and the values the application cannot
+/// work with, e.g., no work periods before the long break. The unknown
+/// fields are ignored, they might be written by a newer version.
+pub fn parse_settings(json: &str) -> Result<Settings, SettingsError> {
+    let settings: Settings = serde_json::from_str(json).map_err(SettingsError::Malformed)?;
+    match settings.validate().into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(settings),
+    }
+}
+
+/// Unlike [`load_settings_from_file`], which stops at the first problem,
+/// this function reports everything that is wrong with the settings file.
+pub fn check_settings_file(path: impl AsRef<Path>) -> Result<Settings, Vec<SettingsError>> {
+    let json = fs::read_to_string(path).map_err(|error| vec![SettingsError::Io(error)])?;
+    check_settings_json(&json)
 }
 
-pub fn load_settings_from_file(path: impl AsRef<Path>) -> Option<Settings> {
-    let open_result = File::open(path);
-    if open_result.is_err() {
-        return None;
+fn check_settings_json(json: &str) -> Result<Settings, Vec<SettingsError>> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|error| vec![SettingsError::Malformed(error)])?;
+    let mut errors = Vec::new();
+    if let Some(fields) = value.as_object() {
+        let known_fields = known_field_names();
+        errors.extend(
+            fields
+                .keys()
+                .filter(|name| !known_fields.contains(name))
+                .map(|name| SettingsError::UnknownField(name.clone())),
+        );
     }
 
-    let reader = BufReader::new(open_result.unwrap());
-    let deserialize_result = serde_json::from_reader(reader);
-    if deserialize_result.is_err() {
-        return None;
+    // Deserializing from the text rather than from `value` keeps the
+    // line and column numbers in the error messages.
+    match serde_json::from_str::<Settings>(json) {
+        Ok(settings) => {
+            errors.extend(settings.validate());
+            if errors.is_empty() {
+                return Ok(settings);
+            }
+        }
+        Err(error) => errors.push(SettingsError::Malformed(error)),
+    }
+    Err(errors)
+}
+
+fn known_field_names() -> Vec<String> {
+    match serde_json::to_value(Settings::default()) {
+        Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+        _ => Vec::new(),
     }
-    Some(deserialize_result.unwrap())
 }
 
 pub fn save_settings_to_file(settings: &Settings, path: impl AsRef<Path>) -> io::Result<()> {
@@ -244,17 +1765,124 @@ mod tests {
 
     #[test]
     fn decreasing_period_duration_below_zero() {
-        let mut settings = Settings::default();
-        settings.long_break_period = Rc::new(Duration::from_secs(1));
+        let mut settings = Settings {
+            long_break_period: Rc::new(Duration::from_secs(1)),
+            ..Default::default()
+        };
         settings.decrease_period_duration(Period::LongBreak, Duration::from_secs(10));
         let post_change = *settings.long_break_period;
         assert_eq!(Duration::from_secs(0), post_change);
     }
 
     #[test]
-    fn getting_short_breaks_number() {
+    fn snapping_period_duration() {
+        let five_minutes = Duration::from_secs(FIVE_MINUTES);
+        let mut settings = Settings {
+            work_period: Rc::new(Duration::from_secs(MINUTE_S * 23 + 10)),
+            ..Default::default()
+        };
+        settings.snap_period_duration(Period::Work, five_minutes, true);
+        assert_eq!(*settings.work_period, Duration::from_secs(MINUTE_S * 25));
+        settings.snap_period_duration(Period::Work, five_minutes, true);
+        assert_eq!(*settings.work_period, Duration::from_secs(MINUTE_S * 30));
+        settings.work_period = Rc::new(Duration::from_secs(MINUTE_S * 7));
+        settings.snap_period_duration(Period::Work, five_minutes, false);
+        assert_eq!(*settings.work_period, five_minutes);
+        settings.snap_period_duration(Period::Work, five_minutes, false);
+        settings.snap_period_duration(Period::Work, five_minutes, false);
+        assert_eq!(*settings.work_period, ZERO);
+    }
+
+    #[test]
+    fn decreasing_overtime_threshold_below_one_minute() {
+        let mut settings = Settings::default();
+        settings.decrease_overtime_threshold(Duration::from_secs(HOUR_S));
+        assert_eq!(
+            settings.get_overtime_threshold(),
+            Duration::from_secs(MINUTE_S)
+        );
+    }
+
+    #[test]
+    fn increasing_workday_end_past_midnight() {
+        let mut settings = Settings::default();
+        settings.increase_workday_end(Duration::from_secs(10 * HOUR_S));
+        assert_eq!(
+            settings.get_workday_end(),
+            Duration::from_secs(DAY_S - MINUTE_S)
+        );
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn getting_days_off() {
+        let settings = Settings {
+            days_off: "2024-12-24, 2024-12-25\n2024-13-01".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.get_days_off(),
+            vec![
+                Date::try_from_ymd(2024, 12, 24).unwrap(),
+                Date::try_from_ymd(2024, 12, 25).unwrap(),
+            ]
+        );
+        assert_eq!(settings.validate().len(), 1);
+    }
+
+    #[test]
+    fn adjusting_custom_period() {
+        let mut settings: Settings =
+            serde_json::from_str(r#"{"custom_periods": [{"name": "Email"}]}"#).unwrap();
+        let email = Period::Custom(0);
+        settings.increase_period_duration(email, Duration::from_secs(MINUTE_S));
+        assert_eq!(
+            settings.convert_period_to_duration(email),
+            Duration::from_secs(16 * MINUTE_S)
+        );
+        settings.decrease_period_duration(email, Duration::from_secs(HOUR_S));
+        assert!(!settings.is_period_enabled(email));
+        assert_eq!(settings.get_lighting_color(email), "0000FF");
+        // A period missing from the registry, e.g., after editing the settings file.
+        assert_eq!(settings.convert_period_to_duration(Period::Custom(1)), ZERO);
+        assert!(!settings.is_period_ending_sound_enabled_for(Period::Custom(1)));
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn editing_cycle_sequence() {
         let mut settings = Settings::default();
-        settings.short_breaks_number = 2;
+        settings.add_cycle_block(Period::Work);
+        settings.add_cycle_block(Period::ShortBreak);
+        settings.shift_cycle_block_duration(1, Duration::from_secs(HOUR_S), false);
+        assert_eq!(
+            *settings.get_cycle_sequence()[1].duration,
+            cycle::MIN_BLOCK_DURATION
+        );
+        settings.save_cycle_profile("Deep work");
+        settings.move_cycle_block(1, true);
+        settings.save_cycle_profile("Deep work");
+        settings.remove_cycle_block(0);
+        assert_eq!(settings.get_cycle_sequence().len(), 1);
+        assert_eq!(settings.get_cycle_profiles().len(), 1);
+        settings.load_cycle_profile(0);
+        assert_eq!(settings.get_cycle_sequence()[0].period, Period::ShortBreak);
+        assert!(settings.validate().is_empty());
+
+        settings.add_cycle_block(Period::Custom(0));
+        settings.save_cycle_profile(" ");
+        assert_eq!(settings.validate().len(), 3);
+        settings.remove_cycle_profile(1);
+        settings.remove_cycle_block(2);
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn getting_short_breaks_number() {
+        let settings = Settings {
+            short_breaks_number: 2,
+            ..Default::default()
+        };
         assert_eq!(2, settings.get_short_breaks_number());
     }
 
@@ -270,14 +1898,34 @@ mod tests {
 
     #[test]
     fn descreasing_short_breaks_number() {
-        let mut settings = Settings::default();
-        settings.short_breaks_number = 1;
+        let mut settings = Settings {
+            short_breaks_number: 1,
+            ..Default::default()
+        };
         settings.decrease_short_breaks_number(1);
         assert_eq!(0, settings.short_breaks_number);
         settings.decrease_short_breaks_number(1);
         assert_eq!(0, settings.short_breaks_number);
     }
 
+    #[test]
+    fn checking_if_period_is_enabled() {
+        let mut settings = Settings::default();
+        assert!(settings.is_period_enabled(Period::ShortBreak));
+        settings.decrease_period_duration(Period::ShortBreak, Duration::from_secs(HOUR_S));
+        assert!(!settings.is_period_enabled(Period::ShortBreak));
+        settings.long_breaks_are_included = false;
+        assert!(!settings.is_period_enabled(Period::LongBreak));
+        assert!(settings.is_period_enabled(Period::Work));
+    }
+
+    #[test]
+    fn decreasing_work_periods_before_long_break_below_one() {
+        let mut settings = Settings::default();
+        settings.decrease_work_periods_before_long_break(10);
+        assert_eq!(settings.get_work_periods_before_long_break(), 1);
+    }
+
     #[test]
     fn checking_if_long_breaks_are_active() {
         let settings = Settings::default();
@@ -291,4 +1939,252 @@ mod tests {
         let actual = settings.period_ending_sound_is_enabled;
         assert_eq!(actual, settings.is_period_ending_sound_enabled());
     }
+
+    #[test]
+    fn getting_blocked_sites() {
+        let settings = Settings {
+            blocked_sites: " reddit.com,youtube.com\nnews.ycombinator.com ".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.get_blocked_sites(),
+            vec!["reddit.com", "youtube.com", "news.ycombinator.com"]
+        );
+    }
+
+    #[test]
+    fn checking_valid_settings() {
+        let json = serde_json::to_string(&Settings::default()).unwrap();
+        assert_eq!(check_settings_json(&json).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn checking_settings_from_before_schema_version() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("schema_version");
+        assert!(check_settings_json(&json.to_string()).is_ok());
+    }
+
+    #[test]
+    fn checking_settings_with_unknown_field() {
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json["beep_colour"] = serde_json::Value::from("red");
+        let errors = check_settings_json(&json.to_string()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], SettingsError::UnknownField(name) if name == "beep_colour"));
+    }
+
+    #[test]
+    fn checking_settings_with_out_of_range_value() {
+        let settings = Settings {
+            beep_volume: 1.5,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let errors = check_settings_json(&json).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            SettingsError::OutOfRange {
+                field: "beep_volume",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn checking_settings_with_invalid_lighting_color() {
+        let settings = Settings {
+            work_lighting_color: "red".to_string(),
+            short_break_lighting_color: "#00ff00".to_string(),
+            ..Default::default()
+        };
+        let errors = settings.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            SettingsError::OutOfRange {
+                field: "work_lighting_color",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn getting_global_hotkeys() {
+        let settings = Settings {
+            pause_hotkey: String::new(),
+            skip_hotkey: "Right".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.get_global_hotkeys(),
+            vec![("ctrl + alt + Up".to_string(), ControlCommand::Resume)]
+        );
+        let errors = settings.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            SettingsError::OutOfRange {
+                field: "skip_hotkey",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn choosing_signal_commands() {
+        let settings = Settings {
+            sigusr2_command: String::new(),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.get_sigusr1_command(),
+            Some(ControlCommand::TogglePause)
+        );
+        assert_eq!(settings.get_sigusr2_command(), None);
+        assert!(settings.validate().is_empty());
+        let settings = Settings {
+            sigusr1_command: "start".to_string(),
+            ..Default::default()
+        };
+        let errors = settings.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            SettingsError::OutOfRange {
+                field: "sigusr1_command",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn choosing_period_ending_sound_files() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.get_period_ending_sound_file(Period::Work), None);
+        settings.set_sound_file(SoundFileSlot::Alarm, Path::new("alarm.wav"));
+        settings.set_sound_file(SoundFileSlot::LongBreakEnding, Path::new("gong.wav"));
+        assert_eq!(
+            settings.get_period_ending_sound_file(Period::Work),
+            Some(Path::new("alarm.wav"))
+        );
+        assert_eq!(
+            settings.get_period_ending_sound_file(Period::LongBreak),
+            Some(Path::new("gong.wav"))
+        );
+    }
+
+    #[test]
+    fn checking_settings_with_too_short_tick_interval() {
+        let settings = Settings {
+            tick_interval: Rc::new(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let errors = settings.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            SettingsError::OutOfRange {
+                field: "tick_interval",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn overriding_period_icons() {
+        let json = "{ \"short_break_icon\": \"\u{1F9CB}\" }";
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.get_period_icon(Period::Work), TOMATO);
+        assert_eq!(settings.get_period_icon(Period::ShortBreak), "\u{1F9CB}");
+    }
+
+    #[test]
+    fn checking_settings_with_schema_version_mismatch() {
+        let settings = Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION + 1,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let errors = check_settings_json(&json).unwrap_err();
+        assert!(matches!(
+            errors[0],
+            SettingsError::SchemaVersionMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn checking_malformed_settings() {
+        let errors = check_settings_json("{ \"beep_volume\": \"loud\" }").unwrap_err();
+        assert!(matches!(errors[0], SettingsError::Malformed(_)));
+    }
+
+    #[test]
+    fn decreasing_daily_goal_below_one() {
+        let mut settings = Settings::default();
+        settings.increase_daily_goal(2);
+        assert_eq!(settings.get_daily_goal(), DEFAULT_DAILY_GOAL + 2);
+        settings.decrease_daily_goal(100);
+        assert_eq!(settings.get_daily_goal(), 1);
+    }
+
+    #[test]
+    fn scaling_volume_during_quiet_hours() {
+        let mut settings = Settings::default();
+        let at = |hour| Duration::from_secs(hour * HOUR_S);
+        assert_eq!(settings.get_quiet_hours_volume(at(23)), 1.0);
+        settings.quiet_hours_are_enabled = true;
+        assert_eq!(settings.get_quiet_hours_volume(at(23)), 0.3);
+        assert_eq!(settings.get_quiet_hours_volume(at(6)), 0.3);
+        assert_eq!(settings.get_quiet_hours_volume(at(7)), 1.0);
+
+        settings.shift_quiet_hours_start(at(3), true);
+        assert_eq!(settings.get_quiet_hours_start(), at(1));
+        settings.shift_quiet_hours_end(at(8), false);
+        assert_eq!(settings.get_quiet_hours_end(), at(23));
+        assert_eq!(settings.get_quiet_hours_volume(at(12)), 0.3);
+        assert_eq!(settings.get_quiet_hours_volume(at(0)), 1.0);
+    }
+
+    #[test]
+    fn respecting_fullscreen_applications() {
+        let mut settings = Settings::default();
+        assert!(settings.is_fullscreen_application_respected(None));
+        settings.fullscreen_ignored_applications = "Code".to_string();
+        assert!(!settings.is_fullscreen_application_respected(Some("code")));
+        assert!(settings.is_fullscreen_application_respected(Some("mpv")));
+        settings.fullscreen_allowed_applications = "mpv, libreoffice".to_string();
+        assert!(settings.is_fullscreen_application_respected(Some("mpv")));
+        assert!(!settings.is_fullscreen_application_respected(Some("firefox")));
+        assert!(!settings.is_fullscreen_application_respected(None));
+    }
+
+    #[test]
+    fn loading_fuzzing_seeds() {
+        let seeds = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds/load_settings");
+        for entry in fs::read_dir(seeds).unwrap() {
+            let path = entry.unwrap().path();
+            let is_valid = path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("valid-");
+            let result = load_settings_from_file(&path);
+            assert_eq!(result.is_ok(), is_valid, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn rejecting_truncated_settings() {
+        let json = serde_json::to_string_pretty(&Settings::default()).unwrap();
+        assert!(parse_settings(&json).is_ok());
+        for (end, _) in json.char_indices() {
+            assert!(parse_settings(&json[..end]).is_err());
+        }
+        assert!(matches!(
+            parse_settings(r#"{"work_periods_before_long_break": 0}"#),
+            Err(SettingsError::OutOfRange { .. })
+        ));
+    }
 }
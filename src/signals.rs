@@ -0,0 +1,98 @@
+//! Unix signals controlling the running application without any IPC setup,
+//! e.g., `pkill -USR1 tomata` from a window manager keybinding. `SIGUSR1`
+//! and `SIGUSR2` send the control commands chosen in the settings (see
+//! [`crate::control`]), by default toggling the pause and skipping the period.
+//! `SIGTERM`, `SIGINT` and `SIGHUP`, e.g., on logging off, quit the application
+//! the regular way, so the session is recorded and the integrations clean up.
+//!
+//! The signals are waited for on a separate thread, they are blocked on all
+//! the others, so they are not handled asynchronously at all.
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::thread;
+
+#[cfg(unix)]
+use nix::sys::signal::{SigSet, Signal};
+
+use crate::control::ControlCommand;
+use crate::settings::Settings;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReceivedSignal {
+    User1,
+    User2,
+    /// Any of the signals asking to quit.
+    Terminate,
+}
+
+impl ReceivedSignal {
+    /// The command the signal sends, `None` when it is ignored.
+    pub fn find_command(self, settings: &Settings) -> Option<ControlCommand> {
+        match self {
+            ReceivedSignal::User1 => settings.get_sigusr1_command(),
+            ReceivedSignal::User2 => settings.get_sigusr2_command(),
+            ReceivedSignal::Terminate => Some(ControlCommand::Quit),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct SignalWatcher {
+    signals: SigSet,
+}
+
+#[cfg(unix)]
+impl SignalWatcher {
+    /// Blocks the signals on the calling thread and on the threads it starts
+    /// later, so it has to be called before any other thread is started.
+    pub fn block() -> io::Result<SignalWatcher> {
+        let mut signals = SigSet::empty();
+        signals.add(Signal::SIGUSR1);
+        signals.add(Signal::SIGUSR2);
+        signals.add(Signal::SIGTERM);
+        signals.add(Signal::SIGINT);
+        signals.add(Signal::SIGHUP);
+        signals.thread_block().map_err(io::Error::from)?;
+        Ok(SignalWatcher { signals })
+    }
+
+    /// Waits for the signals on a new thread, calling `on_signal` with each.
+    pub fn watch(self, mut on_signal: impl FnMut(ReceivedSignal) + Send + 'static) {
+        thread::spawn(move || loop {
+            let signal = match self.signals.wait() {
+                Ok(Signal::SIGUSR1) => ReceivedSignal::User1,
+                Ok(Signal::SIGUSR2) => ReceivedSignal::User2,
+                Ok(Signal::SIGTERM | Signal::SIGINT | Signal::SIGHUP) => ReceivedSignal::Terminate,
+                Ok(_) => continue,
+                Err(error) => {
+                    eprintln!("Could not wait for the signals: {}", error);
+                    return;
+                }
+            };
+            on_signal(signal);
+        });
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::sys::signal::pthread_sigmask;
+    use nix::sys::signal::SigmaskHow;
+
+    #[test]
+    fn blocking_signals() {
+        let mut blocked = SigSet::empty();
+        thread::spawn(move || {
+            SignalWatcher::block().unwrap();
+            pthread_sigmask(SigmaskHow::SIG_BLOCK, None, Some(&mut blocked)).unwrap();
+            assert!(blocked.contains(Signal::SIGUSR1));
+            assert!(blocked.contains(Signal::SIGUSR2));
+            assert!(blocked.contains(Signal::SIGTERM));
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -1,20 +1,488 @@
-/// Be aware that most of this module is either copied or based on
-/// the `beep` example from the `cpal` crate. For more details
-/// go there.
+//! Be aware that most of this module is either copied or based on
+//! the `beep` example from the `cpal` crate. For more details
+//! go there.
+//!
+//! Instead of the beep an alarm sound read from a WAV file can be played,
+//! the OGG and MP3 files are decoded with `ffmpeg` when it is installed.
+//! An ambient sound can be looped under the others.
+use std::cell::{Cell, RefCell};
+use std::convert::TryInto;
 use std::error::Error;
 use std::f32::consts::PI;
+use std::ffi::OsStr;
 use std::fmt;
-use std::time::Duration;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, SupportedStreamConfig};
-use once_cell::sync::OnceCell;
+use cpal::{Device, Stream, SupportedStreamConfig};
+#[cfg(feature = "druid")]
+use druid::Data;
+use once_cell::sync::Lazy;
 
-pub static BEEPER: OnceCell<SoundSystem> = OnceCell::new();
+use crate::ambient::{AmbientSound, NoiseGenerator};
 
+/// The stream of the default output device, built at the start and again
+/// at the next sound once it is lost, e.g., the device was unplugged.
+static DEFAULT_OUTPUT: Lazy<Mutex<Option<SoundSystem>>> = Lazy::new(|| Mutex::new(None));
+
+const BEEP_DURATION: Duration = Duration::from_millis(500);
+/// The longer alarm sounds are cut, so they do not play over the next ones.
+pub const MAX_ALARM_SOUND_DURATION: Duration = Duration::from_secs(30);
+
+/// Why the sound is played, each of the events can be routed differently.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "druid", derive(Data))]
+pub enum SoundEvent {
+    /// One of the last seconds of the period has passed.
+    PeriodEnding,
+    /// The user tries out the volume set in the settings.
+    VolumeCheck,
+    /// The break is still not taken, carries how many times the user
+    /// has been reminded so far.
+    OvertimeWarning(u32),
+    /// The alert about the finished period is not acknowledged.
+    Escalation,
+}
+
+/// The extensions of the files decoded by `ffmpeg`.
+const FFMPEG_DECODED_EXTENSIONS: [&str; 3] = ["ogg", "oga", "mp3"];
+/// The channels and the sample rate `ffmpeg` decodes the files to.
+const FFMPEG_DECODED_CHANNELS: usize = 2;
+const FFMPEG_DECODED_SAMPLE_RATE: u32 = 44100;
+
+/// How long an output device found missing is not looked for again.
+const MISSING_DEVICE_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Words in the names of the output devices telling they are worn.
+const HEADPHONE_NAME_WORDS: [&str; 6] = [
+    "headphone",
+    "headset",
+    "earphone",
+    "earbud",
+    "airpods",
+    "buds",
+];
+
+/// What is heard, the synthesized beep or the alarm sound of the user.
+#[derive(Debug, Clone)]
+pub enum Sound {
+    Beep,
+    Recording(Arc<AlarmSound>),
+}
+
+/// The decoded samples of an alarm sound file.
+#[derive(Clone, PartialEq)]
+pub struct AlarmSound {
+    channels: usize,
+    sample_rate: u32,
+    /// Between -1 and 1, the channels of every frame one after another.
+    samples: Vec<f32>,
+}
+
+impl fmt::Debug for AlarmSound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlarmSound")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .field("duration", &self.get_duration())
+            .finish()
+    }
+}
+
+impl AlarmSound {
+    pub fn get_duration(&self) -> Duration {
+        let frames = self.samples.len() / self.channels;
+        Duration::from_secs_f64(frames as f64 / f64::from(self.sample_rate))
+    }
+
+    /// Interpolates the sample of the `channel` at the fractional `frame`,
+    /// the channels missing in the file repeat the existing ones.
+    fn sample_at(&self, frame: f64, channel: usize) -> f32 {
+        let channel = channel % self.channels;
+        let index = frame.floor() as usize;
+        let sample = |index: usize| {
+            self.samples
+                .get(index * self.channels + channel)
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let fraction = (frame - frame.floor()) as f32;
+        sample(index) * (1.0 - fraction) + sample(index + 1) * fraction
+    }
+}
+
+/// Reads the WAV, OGG or MP3 file, the other files are rejected.
+pub fn load_alarm_sound(path: &Path) -> Result<AlarmSound, Box<dyn Error>> {
+    let extension = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    if FFMPEG_DECODED_EXTENSIONS.contains(&extension.as_str()) {
+        return decode_with_ffmpeg(path);
+    }
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(b"RIFF") {
+        return Err("only the WAV, OGG and MP3 files are supported".into());
+    }
+    parse_wav(&bytes)
+}
+
+/// Decodes the file with `ffmpeg` into 32 bits floating point samples,
+/// the part longer than [`MAX_ALARM_SOUND_DURATION`] is not decoded.
+fn decode_with_ffmpeg(path: &Path) -> Result<AlarmSound, Box<dyn Error>> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-t", &MAX_ALARM_SOUND_DURATION.as_secs().to_string()])
+        .args(["-f", "f32le", "-ac", &FFMPEG_DECODED_CHANNELS.to_string()])
+        .args(["-ar", &FFMPEG_DECODED_SAMPLE_RATE.to_string(), "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| -> Box<dyn Error> {
+            match error.kind() {
+                io::ErrorKind::NotFound => "playing the OGG and MP3 files needs ffmpeg".into(),
+                _ => error.into(),
+            }
+        })?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg could not decode it: {}", message.trim()).into());
+    }
+    parse_raw_samples(
+        &output.stdout,
+        FFMPEG_DECODED_CHANNELS,
+        FFMPEG_DECODED_SAMPLE_RATE,
+    )
+}
+
+/// Reads the little endian 32 bits floating point samples of the frames,
+/// the channels one after another.
+fn parse_raw_samples(
+    bytes: &[u8],
+    channels: usize,
+    sample_rate: u32,
+) -> Result<AlarmSound, Box<dyn Error>> {
+    let frame_size = channels * 4;
+    let samples: Vec<f32> = bytes[..bytes.len() - bytes.len() % frame_size]
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+    if samples.is_empty() {
+        return Err("the sound file has no samples".into());
+    }
+    Ok(AlarmSound {
+        channels,
+        sample_rate,
+        samples,
+    })
+}
+
+/// Decodes the PCM WAV files with 8, 16, 24 or 32 bits integer samples
+/// and the ones with 32 bits floating point samples.
+pub fn parse_wav(bytes: &[u8]) -> Result<AlarmSound, Box<dyn Error>> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".into());
+    }
+    let mut format = None;
+    let mut data = None;
+    let mut chunks = &bytes[12..];
+    while chunks.len() >= 8 {
+        let size = u32::from_le_bytes(chunks[4..8].try_into()?) as usize;
+        // The length of the data is often wrong in the truncated files.
+        let body = chunks.get(8..8 + size).unwrap_or(&chunks[8..]);
+        match &chunks[0..4] {
+            b"fmt " => format = Some(body),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // The chunks are aligned to two bytes.
+        chunks = chunks.get(8 + size + size % 2..).unwrap_or(&[]);
+    }
+    let format = format.ok_or("the WAV file has no format")?;
+    let data = data.ok_or("the WAV file has no samples")?;
+    if format.len() < 16 {
+        return Err("the format of the WAV file is truncated".into());
+    }
+    let read_u16 = |offset: usize| u16::from_le_bytes([format[offset], format[offset + 1]]);
+    let mut encoding = read_u16(0);
+    let channels = usize::from(read_u16(2));
+    let sample_rate = u32::from_le_bytes(format[4..8].try_into()?);
+    let bits = read_u16(14);
+    // WAVE_FORMAT_EXTENSIBLE tells the encoding in its subformat.
+    if encoding == 0xFFFE && format.len() >= 26 {
+        encoding = read_u16(24);
+    }
+    if channels == 0 || sample_rate == 0 {
+        return Err("the WAV file has no channels".into());
+    }
+    let decode: fn(&[u8]) -> f32 = match (encoding, bits) {
+        (1, 8) => |bytes| (f32::from(bytes[0]) - 128.0) / 128.0,
+        (1, 16) => |bytes| f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32768.0,
+        (1, 24) => |bytes| {
+            (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8_388_608.0
+        },
+        (1, 32) => |bytes| {
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2_147_483_648.0
+        },
+        (3, 32) => |bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => {
+            return Err(format!(
+                "the WAV encoding {} with {} bits samples is not supported",
+                encoding, bits
+            )
+            .into())
+        }
+    };
+    let frame_size = channels * usize::from(bits / 8);
+    let samples: Vec<f32> = data[..data.len() - data.len() % frame_size]
+        .chunks_exact(usize::from(bits / 8))
+        .map(decode)
+        .collect();
+    if samples.is_empty() {
+        return Err("the WAV file has no samples".into());
+    }
+    Ok(AlarmSound {
+        channels,
+        sample_rate,
+        samples,
+    })
+}
+
+/// Plays the sounds without blocking the caller.
+pub trait AudioBackend: fmt::Debug {
+    fn play(&self, sound: &Sound, volume: f32) -> Result<(), Box<dyn Error>>;
+    /// Plays the sound only through the headphones, returns `false`
+    /// without playing anything when none are connected.
+    fn play_through_headphones(&self, sound: &Sound, volume: f32) -> Result<bool, Box<dyn Error>>;
+    /// Plays the sound through the output device of the name, returns
+    /// `false` without playing anything when it is not connected.
+    fn play_through_device(
+        &self,
+        name: &str,
+        sound: &Sound,
+        volume: f32,
+    ) -> Result<bool, Box<dyn Error>>;
+    /// The names of the connected output devices.
+    fn list_output_devices(&self) -> Result<Vec<String>, Box<dyn Error>>;
+    /// Sets up the output device of the name ahead of its first sound,
+    /// returns `false` when it is not connected.
+    fn prepare_device(&self, name: &str) -> Result<bool, Box<dyn Error>>;
+    /// Loops the ambient sound at the volume through the default device,
+    /// replacing the previous one, `None` stops it.
+    fn set_ambient_sound(&self, ambient: Option<(AmbientSound, f32)>)
+        -> Result<(), Box<dyn Error>>;
+}
+
+/// Plays nothing, e.g., in the headless mode on the servers without any
+/// sound device.
+#[derive(Debug, Default)]
+pub struct SilentAudioBackend;
+
+impl AudioBackend for SilentAudioBackend {
+    fn play(&self, _sound: &Sound, _volume: f32) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn play_through_headphones(
+        &self,
+        _sound: &Sound,
+        _volume: f32,
+    ) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+
+    fn play_through_device(
+        &self,
+        _name: &str,
+        _sound: &Sound,
+        _volume: f32,
+    ) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+
+    fn list_output_devices(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+
+    fn prepare_device(&self, _name: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+
+    fn set_ambient_sound(
+        &self,
+        _ambient: Option<(AmbientSound, f32)>,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Tells the headphones apart from the speakers by the name of the device,
+/// e.g., `sysdefault:CARD=Headset`.
+pub fn is_headphone_device_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    HEADPHONE_NAME_WORDS.iter().any(|word| name.contains(word))
+}
+
+/// Sets up the stream of the default output device ahead of the first sound.
+pub fn prepare_default_output() -> Result<(), Box<dyn Error>> {
+    with_default_output(|_, _| Ok(()))
+}
+
+/// Calls `use_output` with the stream of the default output device, and
+/// whether it is built anew because there was none or it was lost.
+fn with_default_output(
+    use_output: impl FnOnce(&SoundSystem, bool) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut output = DEFAULT_OUTPUT
+        .lock()
+        .map_err(|_| "the sound system has panicked")?;
+    let is_built = output.as_ref().map_or(true, SoundSystem::is_lost);
+    if is_built {
+        *output = Some(SoundSystem::for_default_device()?);
+    }
+    let output = output.as_ref().ok_or("there is no sound output device")?;
+    use_output(output, is_built)
+}
+
+/// Plays the sounds through the default output device, or the one the sound
+/// is routed to, whose stream is kept for the following sounds.
+#[derive(Debug, Default)]
+pub struct SystemAudioBackend {
+    outputs: RefCell<Vec<(String, Rc<SoundSystem>)>>,
+    /// The ambient sound asked for, it goes on in the rebuilt default stream.
+    ambient: Cell<Option<(AmbientSound, f32)>>,
+    /// The devices looked for but not connected and when, by the name,
+    /// `None` for the headphones, so the sounds do not list the devices.
+    missing_devices: RefCell<Vec<(Option<String>, Instant)>>,
+}
+
+impl AudioBackend for SystemAudioBackend {
+    fn play(&self, sound: &Sound, volume: f32) -> Result<(), Box<dyn Error>> {
+        self.send_to_default_output(Request::Play(sound.clone(), volume))
+    }
+
+    fn play_through_headphones(&self, sound: &Sound, volume: f32) -> Result<bool, Box<dyn Error>> {
+        self.play_through_first_device(None, sound, volume)
+    }
+
+    fn play_through_device(
+        &self,
+        name: &str,
+        sound: &Sound,
+        volume: f32,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.play_through_first_device(Some(name), sound, volume)
+    }
+
+    fn list_output_devices(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        // The devices connected since are looked for again.
+        self.missing_devices.borrow_mut().clear();
+        let mut names = Vec::new();
+        for device in cpal::default_host().output_devices()? {
+            let name = device.name()?;
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    fn prepare_device(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.find_output(Some(name))?.is_some())
+    }
+
+    fn set_ambient_sound(
+        &self,
+        ambient: Option<(AmbientSound, f32)>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.ambient.set(ambient);
+        self.send_to_default_output(Request::Ambient(ambient))
+    }
+}
+
+impl SystemAudioBackend {
+    fn send_to_default_output(&self, request: Request) -> Result<(), Box<dyn Error>> {
+        with_default_output(|output, is_built| {
+            if let (true, Some(ambient)) = (is_built, self.ambient.get()) {
+                output.send(Request::Ambient(Some(ambient)))?;
+            }
+            output.send(request)
+        })
+    }
+
+    /// Plays the sound through the output device of the name, or the first
+    /// headphones for `None`, returns `false` when there is none.
+    fn play_through_first_device(
+        &self,
+        name: Option<&str>,
+        sound: &Sound,
+        volume: f32,
+    ) -> Result<bool, Box<dyn Error>> {
+        match self.find_output(name)? {
+            Some(output) => {
+                output.play(sound, volume)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the kept stream of the output device of the name, or the
+    /// first headphones for `None`, it is built when there is none yet.
+    /// The devices are listed only then, and not again for a while when it
+    /// is missing, listing them takes long on some of the hosts.
+    fn find_output(&self, wanted: Option<&str>) -> Result<Option<Rc<SoundSystem>>, Box<dyn Error>> {
+        let matches = |name: &str| {
+            wanted.map_or_else(|| is_headphone_device_name(name), |wanted| name == wanted)
+        };
+        let mut outputs = self.outputs.borrow_mut();
+        // The stream of the device is lost when it was disconnected.
+        outputs.retain(|(_, output)| !output.is_lost());
+        if let Some((_, output)) = outputs.iter().find(|(name, _)| matches(name)) {
+            return Ok(Some(output.clone()));
+        }
+        let mut missing_devices = self.missing_devices.borrow_mut();
+        missing_devices.retain(|(_, since)| since.elapsed() < MISSING_DEVICE_RECHECK_INTERVAL);
+        if missing_devices
+            .iter()
+            .any(|(name, _)| name.as_deref() == wanted)
+        {
+            return Ok(None);
+        }
+        let device = cpal::default_host().output_devices()?.find_map(|device| {
+            let name = device.name().ok().filter(|name| matches(name))?;
+            Some((name, device))
+        });
+        let (name, device) = match device {
+            Some(device) => device,
+            None => {
+                missing_devices.push((wanted.map(str::to_string), Instant::now()));
+                return Ok(None);
+            }
+        };
+        let output = Rc::new(SoundSystem::for_device(device)?);
+        outputs.push((name, output.clone()));
+        Ok(Some(output))
+    }
+}
+
+/// The output stream of a device, built once and kept by the audio thread.
+/// The sounds are sent to the thread and mixed into the stream, so playing
+/// one neither blocks nor sets up the device again.
 pub struct SoundSystem {
-    device: Device,
-    config: SupportedStreamConfig,
+    requests: Sender<Request>,
+    is_lost: Arc<AtomicBool>,
 }
 
 impl fmt::Debug for SoundSystem {
@@ -23,72 +491,357 @@ impl fmt::Debug for SoundSystem {
     }
 }
 
-impl Default for SoundSystem {
-    fn default() -> SoundSystem {
-        let host = cpal::default_host();
-
-        let device = host
+impl SoundSystem {
+    pub fn for_default_device() -> Result<SoundSystem, Box<dyn Error>> {
+        let device = cpal::default_host()
             .default_output_device()
-            .expect("Failed to find a default sound output device.");
-        let config = device
-            .default_output_config()
-            .expect("Could not initialize default sound configuration.");
+            .ok_or("there is no sound output device")?;
+        SoundSystem::for_device(device)
+    }
 
-        SoundSystem { device, config }
+    /// Starts the audio thread of the device, which runs until the sound
+    /// system is dropped.
+    pub fn for_device(device: Device) -> Result<SoundSystem, Box<dyn Error>> {
+        let config = device.default_output_config()?;
+        let (requests, received_requests) = mpsc::channel();
+        let (started, is_started) = mpsc::sync_channel(1);
+        let is_lost = Arc::new(AtomicBool::new(false));
+        let is_stream_lost = is_lost.clone();
+        // The streams cannot be passed between the threads on every platform,
+        // this one is made and kept by the audio thread.
+        thread::spawn(move || {
+            let stream = match build_stream(&device, &config, is_stream_lost) {
+                Ok(stream) => stream,
+                Err(error) => {
+                    let _ = started.send(Err(error.to_string()));
+                    return;
+                }
+            };
+            let _ = started.send(Ok(()));
+            play_requests(received_requests, stream, config.sample_rate().0);
+        });
+        is_started.recv()??;
+        Ok(SoundSystem { requests, is_lost })
+    }
+
+    pub fn play(&self, sound: &Sound, volume: f32) -> Result<(), Box<dyn Error>> {
+        self.send(Request::Play(sound.clone(), volume))
+    }
+
+    fn send(&self, request: Request) -> Result<(), Box<dyn Error>> {
+        if self.is_lost() {
+            return Err("the sound device is lost".into());
+        }
+        self.requests
+            .send(request)
+            .map_err(|_| "the audio thread has stopped".into())
+    }
+
+    /// Tells whether the stream failed, e.g., the device was disconnected.
+    pub fn is_lost(&self) -> bool {
+        self.is_lost.load(Ordering::Relaxed)
     }
 }
 
-impl SoundSystem {
-    pub fn beep(&self, volume: f32) -> Result<(), Box<dyn Error>> {
-        match self.config.sample_format() {
-            cpal::SampleFormat::F32 => {
-                make_beep_sound::<f32>(&self.device, &self.config.clone().into(), volume)?
-            }
-            cpal::SampleFormat::I16 => {
-                make_beep_sound::<i16>(&self.device, &self.config.clone().into(), volume)?
-            }
-            cpal::SampleFormat::U16 => {
-                make_beep_sound::<u16>(&self.device, &self.config.clone().into(), volume)?
+/// What the audio thread is asked to do.
+enum Request {
+    Play(Sound, f32),
+    Ambient(Option<(AmbientSound, f32)>),
+}
+
+/// How the voices of the stream change.
+enum VoiceChange {
+    Add(Voice),
+    /// Replaces the looped ambient voice, if any.
+    SetAmbient(Option<Voice>),
+}
+
+/// Fills the channels of the next frame of the output.
+type FrameSource = dyn FnMut(&mut [f32]) + Send;
+
+/// A sound being played, mixed with the others played at the same time.
+struct Voice {
+    frames_left: u64,
+    is_ambient: bool,
+    next_frame: Box<FrameSource>,
+}
+
+fn make_voice(sound: &Sound, volume: f32, sample_rate: u32) -> Voice {
+    let sample_rate = sample_rate as f32;
+    let (duration, next_frame): (Duration, Box<FrameSource>) = match sound {
+        Sound::Beep => {
+            // Produce a sinusoid of maximum amplitude.
+            let mut sample_clock = 0f32;
+            let next_frame = move |frame: &mut [f32]| {
+                sample_clock = (sample_clock + 1.0) % sample_rate;
+                let value = (sample_clock * 440.0 * 2.0 * PI / sample_rate).sin() * volume;
+                frame.iter_mut().for_each(|sample| *sample = value);
+            };
+            (BEEP_DURATION, Box::new(next_frame))
+        }
+        Sound::Recording(recording) => {
+            let duration = recording.get_duration().min(MAX_ALARM_SOUND_DURATION);
+            let recording = recording.clone();
+            // Resampled to the rate of the device.
+            let step = f64::from(recording.sample_rate) / f64::from(sample_rate);
+            let mut position = 0f64;
+            let next_frame = move |frame: &mut [f32]| {
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample = recording.sample_at(position, channel) * volume;
+                }
+                position += step;
+            };
+            (duration, Box::new(next_frame))
+        }
+    };
+    Voice {
+        frames_left: (duration.as_secs_f64() * f64::from(sample_rate)) as u64,
+        is_ambient: false,
+        next_frame,
+    }
+}
+
+/// The ambient voice never finishes, it is replaced or removed.
+fn make_ambient_voice(sound: AmbientSound, volume: f32) -> Voice {
+    let mut generator = NoiseGenerator::new(sound);
+    let next_frame = move |frame: &mut [f32]| {
+        let value = generator.next_sample() * volume;
+        frame.iter_mut().for_each(|sample| *sample = value);
+    };
+    Voice {
+        frames_left: u64::MAX,
+        is_ambient: true,
+        next_frame: Box::new(next_frame),
+    }
+}
+
+fn apply_voice_change(voices: &mut Vec<Voice>, change: VoiceChange) {
+    match change {
+        VoiceChange::Add(voice) => voices.push(voice),
+        VoiceChange::SetAmbient(ambient) => {
+            voices.retain(|voice| !voice.is_ambient);
+            voices.extend(ambient);
+        }
+    }
+}
+
+/// Sums the next frames of the voices into `frame`, the finished
+/// voices are dropped.
+fn mix_frame(voices: &mut Vec<Voice>, frame: &mut [f32], voice_frame: &mut [f32]) {
+    frame.iter_mut().for_each(|sample| *sample = 0.0);
+    for voice in voices.iter_mut() {
+        (voice.next_frame)(voice_frame);
+        for (sample, value) in frame.iter_mut().zip(voice_frame.iter()) {
+            *sample = (*sample + value).clamp(-1.0, 1.0);
+        }
+        voice.frames_left = voice.frames_left.saturating_sub(1);
+    }
+    voices.retain(|voice| voice.frames_left > 0);
+}
+
+/// Turns the requested sounds into the voices of the stream of the thread,
+/// until the sound system is dropped.
+fn play_requests(
+    requests: Receiver<Request>,
+    stream: (Stream, Sender<VoiceChange>),
+    sample_rate: u32,
+) {
+    let (_stream, voices) = stream;
+    for request in requests {
+        let change = match request {
+            Request::Play(sound, volume) => {
+                VoiceChange::Add(make_voice(&sound, volume, sample_rate))
             }
+            Request::Ambient(ambient) => VoiceChange::SetAmbient(
+                ambient.map(|(sound, volume)| make_ambient_voice(sound, volume)),
+            ),
+        };
+        if voices.send(change).is_err() {
+            return;
         }
-        Ok(())
     }
 }
 
-fn make_beep_sound<T>(
-    device: &cpal::Device,
+/// Builds the stream playing the voices sent through the returned sender,
+/// silent while there are none.
+fn build_stream(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    is_lost: Arc<AtomicBool>,
+) -> Result<(Stream, Sender<VoiceChange>), Box<dyn Error>> {
+    let stream_config = config.clone().into();
+    let (voices, received_voices) = mpsc::channel();
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => {
+            build_typed_stream::<f32>(device, &stream_config, received_voices, is_lost)?
+        }
+        cpal::SampleFormat::I16 => {
+            build_typed_stream::<i16>(device, &stream_config, received_voices, is_lost)?
+        }
+        cpal::SampleFormat::U16 => {
+            build_typed_stream::<u16>(device, &stream_config, received_voices, is_lost)?
+        }
+    };
+    stream.play()?;
+    Ok((stream, voices))
+}
+
+fn build_typed_stream<T>(
+    device: &Device,
     config: &cpal::StreamConfig,
-    volume: f32,
-) -> Result<(), Box<dyn Error>>
+    received_voices: Receiver<VoiceChange>,
+    is_lost: Arc<AtomicBool>,
+) -> Result<Stream, Box<dyn Error>>
 where
     T: cpal::Sample,
 {
-    let sample_rate = config.sample_rate.0 as f32;
     let channels = config.channels as usize;
-
-    // Produce a sinusoid of maximum amplitude.
-    let mut sample_clock = 0f32;
-    let mut next_value = move || {
-        sample_clock = (sample_clock + 1.0) % sample_rate;
-        (sample_clock * 440.0 * 2.0 * PI / sample_rate).sin() * volume
+    let err_fn = move |err| {
+        eprintln!("an error occurred on stream: {}", err);
+        is_lost.store(true, Ordering::Relaxed);
     };
 
-    let err_fn = |err| eprintln!("an error occurred on stream: {}", err);
-
+    let mut voices = Vec::new();
+    let mut voice_frame = vec![0f32; channels];
+    let mut frame = vec![0f32; channels];
     let stream = device.build_output_stream(
         config,
         move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-            for frame in data.chunks_mut(channels) {
-                let value: T = cpal::Sample::from::<f32>(&next_value());
-                for sample in frame.iter_mut() {
-                    *sample = value;
+            for change in received_voices.try_iter() {
+                apply_voice_change(&mut voices, change);
+            }
+            for samples in data.chunks_mut(channels) {
+                mix_frame(&mut voices, &mut frame, &mut voice_frame);
+                for (sample, value) in samples.iter_mut().zip(frame.iter()) {
+                    *sample = cpal::Sample::from::<f32>(value);
                 }
             }
         },
         err_fn,
     )?;
-    stream.play()?;
-    std::thread::sleep(Duration::from_millis(500));
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizing_headphones() {
+        assert!(is_headphone_device_name("sysdefault:CARD=Headset"));
+        assert!(is_headphone_device_name("Headphones (Realtek Audio)"));
+        assert!(is_headphone_device_name("Galaxy Buds2"));
+        assert!(!is_headphone_device_name("default"));
+        assert!(!is_headphone_device_name("Speakers (Realtek Audio)"));
+    }
 
-    Ok(())
+    fn make_wav(encoding: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0".to_vec();
+        bytes.extend_from_slice(&encoding.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&(8000 * u32::from(channels * bits / 8)).to_le_bytes());
+        bytes.extend_from_slice(&(channels * bits / 8).to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        bytes.extend_from_slice(b"LIST\x03\0\0\0abc\0data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn parsing_wav() {
+        let data = [0x00, 0x40, 0x00, 0xC0, 0xFF, 0x7F, 0x00, 0x80, 0x00];
+        let sound = parse_wav(&make_wav(1, 2, 16, &data)).unwrap();
+        assert_eq!(sound.channels, 2);
+        assert_eq!(sound.sample_rate, 8000);
+        assert_eq!(sound.samples, vec![0.5, -0.5, 32767.0 / 32768.0, -1.0]);
+        assert_eq!(sound.get_duration(), Duration::from_secs_f64(2.0 / 8000.0));
+
+        let sound = parse_wav(&make_wav(1, 1, 8, &[128, 192])).unwrap();
+        assert_eq!(sound.samples, vec![0.0, 0.5]);
+        let sound = parse_wav(&make_wav(3, 1, 32, &0.25f32.to_le_bytes())).unwrap();
+        assert_eq!(sound.samples, vec![0.25]);
+
+        assert!(parse_wav(b"ID3\x03").is_err());
+        assert!(parse_wav(&make_wav(2, 1, 4, &[0, 0])).is_err());
+        assert!(parse_wav(&make_wav(1, 0, 16, &[0, 0])).is_err());
+        assert!(parse_wav(&make_wav(1, 1, 16, &[])).is_err());
+    }
+
+    #[test]
+    fn loading_alarm_sound() {
+        let sound = parse_raw_samples(&[0, 0, 0x80, 0x3E, 0, 0, 0x80, 0xBE, 0], 2, 44100).unwrap();
+        assert_eq!(sound.samples, vec![0.25, -0.25]);
+        assert!(parse_raw_samples(&[0, 0, 0x80], 1, 44100).is_err());
+
+        let path = std::env::temp_dir().join(format!("tomata-sound-{}.flac", std::process::id()));
+        fs::write(&path, b"fLaC").unwrap();
+        let error = load_alarm_sound(&path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "only the WAV, OGG and MP3 files are supported"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resampling_alarm_sound() {
+        let sound = AlarmSound {
+            channels: 1,
+            sample_rate: 8000,
+            samples: vec![0.0, 1.0],
+        };
+        assert_eq!(sound.sample_at(0.5, 0), 0.5);
+        assert_eq!(sound.sample_at(1.0, 1), 1.0);
+        assert_eq!(sound.sample_at(1.5, 0), 0.5);
+        assert_eq!(sound.sample_at(2.0, 0), 0.0);
+    }
+
+    #[test]
+    fn mixing_voices() {
+        let recording = Arc::new(AlarmSound {
+            channels: 1,
+            sample_rate: 4,
+            samples: vec![0.5, 0.5, 0.5, 0.5],
+        });
+        let sound = Sound::Recording(recording);
+        let mut voices = vec![make_voice(&sound, 1.0, 4), make_voice(&sound, 0.5, 4)];
+        voices[1].frames_left = 2;
+        let mut frame = [0.0; 2];
+        let mut voice_frame = [0.0; 2];
+        mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        assert_eq!(frame, [0.75, 0.75]);
+        mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        assert_eq!(voices.len(), 1);
+        voices.push(make_voice(&sound, 2.0, 4));
+        mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        // The sum is clipped.
+        assert_eq!(frame, [1.0, 1.0]);
+        mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        assert_eq!(voices.len(), 1);
+        // A voice without any frames ends instead of overflowing.
+        voices[0].frames_left = 0;
+        mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        assert!(voices.is_empty());
+        assert_eq!(make_voice(&Sound::Beep, 1.0, 48000).frames_left, 24000);
+    }
+
+    #[test]
+    fn replacing_ambient_voice() {
+        let mut voices = vec![make_voice(&Sound::Beep, 1.0, 4)];
+        let ambient = |sound| VoiceChange::SetAmbient(Some(make_ambient_voice(sound, 0.5)));
+        apply_voice_change(&mut voices, ambient(AmbientSound::WhiteNoise));
+        apply_voice_change(&mut voices, ambient(AmbientSound::Rain));
+        assert_eq!(voices.len(), 2);
+        let mut frame = [0.0; 2];
+        let mut voice_frame = [0.0; 2];
+        for _ in 0..2 {
+            mix_frame(&mut voices, &mut frame, &mut voice_frame);
+        }
+        // The beep is over, the ambient sound goes on.
+        assert_eq!(voices.len(), 1);
+        apply_voice_change(&mut voices, VoiceChange::SetAmbient(None));
+        assert!(voices.is_empty());
+    }
 }
@@ -0,0 +1,169 @@
+//! Reading the remaining time and the changes of the periods aloud through
+//! the text-to-speech tool of the platform, e.g., for the visually impaired
+//! users.
+use std::error::Error;
+use std::fmt;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::tomata::{Period, HOUR_S, MINUTE_S};
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// Speaks the text without blocking the caller.
+pub trait SpeechBackend: fmt::Debug {
+    fn speak(&self, text: &str) -> Result<(), Box<dyn Error>>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemSpeechBackend;
+
+impl SpeechBackend for SystemSpeechBackend {
+    fn speak(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        let mut command = if cfg!(target_os = "macos") {
+            Command::new("say")
+        } else if cfg!(windows) {
+            let mut command = Command::new("powershell");
+            command.args([
+                "-NoProfile",
+                "-Command",
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($args[0])",
+            ]);
+            command
+        } else {
+            // Speech Dispatcher, available on most of the desktops.
+            Command::new("spd-say")
+        };
+        command.arg(text).spawn()?;
+        Ok(())
+    }
+}
+
+/// Spells `number` in English words, the numbers above 99 are written
+/// with digits.
+fn spell_number(number: u64) -> String {
+    match number {
+        0..=19 => ONES[number as usize].to_string(),
+        20..=99 if number % 10 == 0 => TENS[number as usize / 10].to_string(),
+        20..=99 => format!(
+            "{}-{}",
+            TENS[number as usize / 10],
+            ONES[number as usize % 10]
+        ),
+        _ => number.to_string(),
+    }
+}
+
+fn spell_count(count: u64, unit: &str) -> String {
+    match count {
+        1 => format!("one {}", unit),
+        _ => format!("{} {}s", spell_number(count), unit),
+    }
+}
+
+/// Makes the phrase read aloud, e.g., "nineteen minutes remaining",
+/// the started minutes are counted as whole ones.
+pub fn make_remaining_time_phrase(remaining: Duration) -> String {
+    if remaining < Duration::from_secs(MINUTE_S) {
+        return "less than a minute remaining".to_string();
+    }
+    let minutes = (remaining.as_secs() + MINUTE_S - 1) / MINUTE_S;
+    let minutes_per_hour = HOUR_S / MINUTE_S;
+    let (hours, minutes) = (minutes / minutes_per_hour, minutes % minutes_per_hour);
+    let time = match (hours, minutes) {
+        (0, minutes) => spell_count(minutes, "minute"),
+        (hours, 0) => spell_count(hours, "hour"),
+        (hours, minutes) => format!(
+            "{} and {}",
+            spell_count(hours, "hour"),
+            spell_count(minutes, "minute")
+        ),
+    };
+    format!("{} remaining", time)
+}
+
+fn name_custom_period(index: usize, settings: &Settings) -> String {
+    settings
+        .get_custom_period(index)
+        .map_or_else(|| "the custom period".to_string(), |kind| kind.name.clone())
+}
+
+/// Makes the phrase read aloud when a period ends by itself, e.g.,
+/// "Work period finished, take a short break".
+pub fn make_period_change_phrase(finished: Period, next: Period, settings: &Settings) -> String {
+    let finished = match finished {
+        Period::Work => "Work period".to_string(),
+        Period::ShortBreak => "Short break".to_string(),
+        Period::LongBreak => "Long break".to_string(),
+        Period::Custom(index) => name_custom_period(index, settings),
+    };
+    let next = match next {
+        Period::Work => "back to work".to_string(),
+        Period::ShortBreak => "take a short break".to_string(),
+        Period::LongBreak => "take a long break".to_string(),
+        Period::Custom(index) => format!("{} is next", name_custom_period(index, settings)),
+    };
+    format!("{} finished, {}", finished, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_remaining_time_phrase() {
+        let phrase = |seconds| make_remaining_time_phrase(Duration::from_secs(seconds));
+        assert_eq!(phrase(18 * MINUTE_S + 30), "nineteen minutes remaining");
+        assert_eq!(phrase(MINUTE_S), "one minute remaining");
+        assert_eq!(phrase(59), "less than a minute remaining");
+        assert_eq!(phrase(42 * MINUTE_S), "forty-two minutes remaining");
+        assert_eq!(phrase(HOUR_S), "one hour remaining");
+        assert_eq!(
+            phrase(2 * HOUR_S + 5 * MINUTE_S),
+            "two hours and five minutes remaining"
+        );
+    }
+
+    #[test]
+    fn making_period_change_phrase() {
+        let settings = Settings::default();
+        assert_eq!(
+            make_period_change_phrase(Period::Work, Period::ShortBreak, &settings),
+            "Work period finished, take a short break"
+        );
+        assert_eq!(
+            make_period_change_phrase(Period::LongBreak, Period::Work, &settings),
+            "Long break finished, back to work"
+        );
+        assert_eq!(
+            make_period_change_phrase(Period::Work, Period::Custom(7), &settings),
+            "Work period finished, the custom period is next"
+        );
+    }
+}
@@ -1,17 +1,101 @@
 //! The state of the application reperesented by [`TomataState`]
 //! acts as a model for the application. It is used by the widgets
 //! to present significant data such as remaining time etc.
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(feature = "druid")]
 use druid::{Data, Lens};
 use notify_rust::Notification;
+use time::Date;
 
-use crate::settings::Settings;
-use crate::sound::BEEPER;
-use crate::tomata::{Period, ZERO};
+use crate::activity::ApplicationSamples;
+use crate::ambient::AmbientSound;
+use crate::control::ControlCommand;
+use crate::countdown::Countdown;
+use crate::cycle::CycleBlock;
+use crate::debug::DebugLog;
+use crate::errors::{RecoverableError, RetryAction};
+use crate::escalation::{Escalation, EscalationStage};
+use crate::events::TomataEvent;
+use crate::history;
+use crate::history::{RecordedPeriod, SessionRecord, TimelineBlock, Totals};
+use crate::issue::IssueReference;
+use crate::notifier;
+use crate::notifier::{ActionChannel, Banner, Delivery, Notifier};
+use crate::planning;
+use crate::planning::{PlanComparison, PlannedTask};
+use crate::reminder::ReminderScheduler;
+use crate::server;
+use crate::server::FocusStatus;
+use crate::settings::{
+    CloseAction, LongBreakTrigger, NotificationBackend, Settings, SoundFileSlot,
+};
+use crate::sound;
+use crate::sound::{AlarmSound, AudioBackend, Sound, SoundEvent, SystemAudioBackend};
+use crate::speech;
+use crate::speech::{SpeechBackend, SystemSpeechBackend};
+use crate::suggestions;
+use crate::suggestions::WorkPeriodSuggestion;
+use crate::summary;
+#[cfg(feature = "druid")]
+use crate::theme::Theme;
+use crate::tomata;
+use crate::tomata::{Period, MINUTE_S, ZERO};
+use crate::users;
 
-#[derive(Debug, Clone, Data, Lens)]
+/// Idle time after which the user is considered to have left the computer,
+/// the rest is counted only then.
+const AWAY_IDLE_TIME: Duration = Duration::from_secs(30);
+/// Idle time below which the user is considered to be back at the computer.
+const RETURN_IDLE_TIME: Duration = Duration::from_secs(2);
+/// Idle time during a work period after which the user is asked what the time
+/// away was, reading or thinking without touching the computer is shorter.
+const AWAY_FROM_WORK_IDLE_TIME: Duration = Duration::from_secs(5 * MINUTE_S);
+/// What the time away from the computer during a work period was.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AwayTimeClassification {
+    Work,
+    Break,
+    /// Neither, e.g., an interruption, it is left out of the history.
+    Discarded,
+}
+
+/// The final part of a period during which the beeps warn about its end.
+const PERIOD_ENDING_WARNING_TIME: Duration = Duration::from_secs(5);
+/// Each of the subsequent overtime warnings is louder by this part of the volume.
+const OVERTIME_VOLUME_STEP: f32 = 0.5;
+/// The sounds played by the calibration, in order.
+pub const CALIBRATED_SOUNDS: [SoundEvent; 4] = [
+    SoundEvent::PeriodEnding,
+    SoundEvent::OvertimeWarning(1),
+    SoundEvent::OvertimeWarning(3),
+    SoundEvent::Escalation,
+];
+const CALIBRATION_SOUND_INTERVAL: Duration = Duration::from_millis(1500);
+const SOUND_FILE_SLOTS: [SoundFileSlot; 4] = [
+    SoundFileSlot::Alarm,
+    SoundFileSlot::WorkEnding,
+    SoundFileSlot::ShortBreakEnding,
+    SoundFileSlot::LongBreakEnding,
+];
+/// How long a snoozed period waits before it starts.
+const PERIOD_SNOOZE_TIME: Duration = Duration::from_secs(5 * MINUTE_S);
+/// How long a snoozed banner stays hidden.
+const BANNER_SNOOZE_TIME: Duration = Duration::from_secs(5 * MINUTE_S);
+/// Opacity of the dimming overlay right before the work period ends.
+const MAX_DIMMING_LEVEL: f64 = 0.6;
+/// How many of the recently used tags the focus prompt offers.
+pub const RECENT_TAGS_COUNT: usize = 5;
+
+/// Presses of the escape key in a row unlocking the input of the break overlay.
+pub const BREAK_INPUT_LOCK_ESCAPES: usize = 5;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
 pub struct TomataState {
     settings: Settings,
     elapsed_time: Rc<Duration>, // Data cannot be derived for Duration, unless it is in Rc
@@ -19,226 +103,4463 @@ pub struct TomataState {
     stopwatch_is_paused: bool,
     period_is_finished: bool,
     short_breaks_finished: usize,
+    /// Completed since the last long break.
+    work_periods_finished: usize,
+    /// The block of the sequence of the user the cycle is at.
+    cycle_position: usize,
+    cycle_profile_name: String,
+    user_is_away: bool,
+    end_break_early_prompt_is_shown: bool,
+    /// The longest idle time of the running work period not asked about yet.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    work_idle_time: Duration,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    away_from_work_time: Duration,
+    away_time_prompt_is_shown: bool,
+    reminders: ReminderScheduler,
+    period_started_at: Option<u64>,
+    /// Accumulated while the started period is paused.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    paused_time: Duration,
+    /// The rest since the last finished work period, if the work
+    /// has not started again since.
+    rest_time: Option<Rc<Duration>>,
+    user_is_resting: bool,
+    work_is_held_for_rest: bool,
+    focus_prompt_is_shown: bool,
+    focus_answer: String,
+    day_plan_prompt_is_shown: bool,
+    day_plan_project: String,
+    /// Today's work periods are tagged with it, unless the user answers
+    /// the focus question.
+    primary_project: Option<String>,
+    primary_project_is_modified: bool,
+    session_tag: Option<String>,
+    /// Replaces the configured duration of the current period, in seconds.
+    period_duration_override: Option<u64>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    unsaved_records: Vec<SessionRecord>,
+    countdowns: Arc<Vec<Countdown>>,
+    next_countdown_id: u64,
+    new_countdown_name: String,
+    new_countdown_duration: String,
+    meeting_ends_at: Option<u64>,
+    deferred_break: Option<Period>,
+    /// Seconds since the UNIX epoch, updated on every tick.
+    wall_clock_time: u64,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    screen_lock_is_requested: bool,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    issue_url_to_open: Option<String>,
+    /// Applications sampled during the current work period.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    application_samples: ApplicationSamples,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    unhandled_events: Vec<TomataEvent>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    retried_events: Vec<(TomataEvent, &'static str)>,
+    share_link: Option<String>,
+    blocklist_prompt_is_shown: bool,
+    meeting_mode_started_at: Option<u64>,
+    stopwatch_was_paused_before_meeting: bool,
+    /// The end of the calendar meeting seen last.
+    calendar_meeting_end: Option<u64>,
+    /// Whether the meeting mode was entered because of the calendar.
+    meeting_mode_follows_calendar: bool,
+    meeting_end_prompt_is_shown: bool,
+    planned_tasks: Arc<Vec<PlannedTask>>,
+    next_task_id: u64,
+    new_task_name: String,
+    new_task_estimate: String,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    plan_is_modified: bool,
+    /// Records of the recent days, including the ones already stored in the history.
+    recent_records: Arc<Vec<SessionRecord>>,
+    /// Local midnight of the day shown in the timeline, `None` for today.
+    timeline_day: Option<u64>,
+    /// Waits for the reason of abandoning it before it is recorded.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    aborted_record: Option<SessionRecord>,
+    abort_reason_prompt_is_shown: bool,
+    abort_reason: String,
+    close_prompt_is_shown: bool,
+    close_choice_is_remembered: bool,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    close_action: Option<CloseAction>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    notifier: Rc<dyn Notifier>,
+    /// Backend the notifier was made for, it is replaced when the settings change.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    notifier_backend: NotificationBackend,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    notification_actions: ActionChannel,
+    banner: Option<Banner>,
+    /// Notes of the upgraded version, shown until dismissed.
+    whats_new: Option<String>,
+    /// The wizard asking for the initial settings on the first launch.
+    onboarding_is_shown: bool,
+    /// Only the countdown fills the window, e.g., on a wall-mounted monitor.
+    big_display_is_shown: bool,
+    observer_window_is_requested: bool,
+    /// Failures waiting for the user to retry or dismiss them, the oldest first.
+    errors: Arc<Vec<RecoverableError>>,
+    /// The internals shown in the debug window, kept only with `--debug`.
+    debug_log: Option<Arc<DebugLog>>,
+    /// Colors and sizes read from the theme file.
+    #[cfg(feature = "druid")]
+    theme: Rc<Theme>,
+    /// The sounds left to play by the calibration, the next one last.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    calibration_queue: Vec<SoundEvent>,
+    calibrated_sound: Option<SoundEvent>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    since_calibration_sound: Duration,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    settings_save_is_requested: bool,
+    writes_flush_is_requested: bool,
+    suggestion_is_dismissed: bool,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    audio: Rc<dyn AudioBackend>,
+    /// The ambient sound looped now, with its volume.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    playing_ambient_sound: Option<(AmbientSound, f32)>,
+    /// The sound files read so far, or why they could not be read.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    alarm_sounds: Vec<(PathBuf, Result<Arc<AlarmSound>, String>)>,
+    /// Where the file chosen in the file dialog goes.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    chosen_sound_file_slot: SoundFileSlot,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    speech: Rc<dyn SpeechBackend>,
+    /// Time since the alarm was last repeated, while the next period
+    /// waits to be started.
+    alarm_repeat: Option<Rc<Duration>>,
+    /// Time since the work period finished while the break was not started.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    overtime: Option<Duration>,
+    /// The task and today's count of the just finished pomodoro,
+    /// they are mentioned by the notification about the following break.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    finished_pomodoro: Option<(Option<String>, usize)>,
+    /// Work time since the last finished break.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    continuous_work_time: Duration,
+    /// Days off read from the holiday calendar file.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    imported_days_off: Vec<Date>,
+    /// Whether some application records from the microphone, e.g., a call.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    microphone_is_in_use: bool,
+    /// Whether a fullscreen application is active, e.g., a presentation.
+    fullscreen_is_active: bool,
+    /// Why the fullscreen window cannot be detected, shown in the settings.
+    fullscreen_detection_problem: Option<String>,
+    /// Why the enabled tray icon is not shown, e.g., `yad` is missing.
+    tray_icon_problem: Option<String>,
+    /// The break was paused because of the fullscreen application and
+    /// resumes once it is gone.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    fullscreen_paused_break: bool,
+    /// The user is on one of the distraction workspaces.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    workspace_is_distracting: bool,
+    /// The work period was paused by switching to a distraction workspace
+    /// and resumes on switching back.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    workspace_paused_work: bool,
+    /// Switches to the distraction workspaces during the started period.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    interruptions: u64,
+    /// Escalation of the alert about the finished period, until the next one is started.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    escalation: Option<Escalation>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    snoozed_banner: Option<Banner>,
+    /// Time left until the snoozed period starts.
+    #[cfg_attr(feature = "druid", data(ignore))]
+    period_snooze_left: Option<Duration>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    banner_snooze_left: Duration,
+    /// Presses of the escape key unlocking the input of the break overlay.
+    break_input_lock_escapes: usize,
+    /// The user whose data is used, `None` when it is shared.
+    current_user: Option<String>,
+    /// The users with their own data, the switcher offers them.
+    users: Arc<Vec<String>>,
+    user_to_switch_to: String,
+    /// The connected output devices, the settings offer them.
+    output_devices: Arc<Vec<String>>,
+    #[cfg_attr(feature = "druid", data(ignore))]
+    quit_is_requested: bool,
 }
 
 impl Default for TomataState {
     fn default() -> TomataState {
         let elapsed_time = Rc::new(ZERO);
         let settings = Settings::default();
+        let notification_actions = ActionChannel::default();
         TomataState {
-            settings,
             elapsed_time,
             current_period: Period::Work,
             stopwatch_is_paused: true,
             period_is_finished: false,
             short_breaks_finished: 0,
+            work_periods_finished: 0,
+            cycle_position: 0,
+            cycle_profile_name: String::new(),
+            user_is_away: false,
+            work_idle_time: ZERO,
+            away_from_work_time: ZERO,
+            away_time_prompt_is_shown: false,
+            end_break_early_prompt_is_shown: false,
+            reminders: ReminderScheduler::default(),
+            period_started_at: None,
+            paused_time: ZERO,
+            rest_time: None,
+            user_is_resting: true,
+            work_is_held_for_rest: false,
+            focus_prompt_is_shown: false,
+            focus_answer: String::new(),
+            day_plan_prompt_is_shown: false,
+            day_plan_project: String::new(),
+            primary_project: None,
+            primary_project_is_modified: false,
+            session_tag: None,
+            period_duration_override: None,
+            unsaved_records: Vec::new(),
+            countdowns: Arc::new(Vec::new()),
+            next_countdown_id: 0,
+            new_countdown_name: String::new(),
+            new_countdown_duration: String::new(),
+            meeting_ends_at: None,
+            deferred_break: None,
+            wall_clock_time: tomata::now_timestamp(),
+            screen_lock_is_requested: false,
+            issue_url_to_open: None,
+            application_samples: ApplicationSamples::default(),
+            unhandled_events: Vec::new(),
+            retried_events: Vec::new(),
+            share_link: None,
+            blocklist_prompt_is_shown: false,
+            meeting_mode_started_at: None,
+            stopwatch_was_paused_before_meeting: true,
+            calendar_meeting_end: None,
+            meeting_mode_follows_calendar: false,
+            meeting_end_prompt_is_shown: false,
+            planned_tasks: Arc::new(Vec::new()),
+            next_task_id: 0,
+            new_task_name: String::new(),
+            new_task_estimate: String::new(),
+            plan_is_modified: false,
+            recent_records: Arc::new(Vec::new()),
+            timeline_day: None,
+            aborted_record: None,
+            abort_reason_prompt_is_shown: false,
+            abort_reason: String::new(),
+            close_prompt_is_shown: false,
+            close_choice_is_remembered: false,
+            close_action: None,
+            notifier: notifier::make_notifier(
+                settings.get_notification_backend(),
+                &notification_actions,
+            ),
+            notifier_backend: settings.get_notification_backend(),
+            notification_actions,
+            banner: None,
+            whats_new: None,
+            onboarding_is_shown: false,
+            big_display_is_shown: false,
+            observer_window_is_requested: false,
+            errors: Arc::new(Vec::new()),
+            debug_log: None,
+            #[cfg(feature = "druid")]
+            theme: Rc::new(Theme::default()),
+            calibration_queue: Vec::new(),
+            calibrated_sound: None,
+            since_calibration_sound: ZERO,
+            settings_save_is_requested: false,
+            writes_flush_is_requested: false,
+            suggestion_is_dismissed: false,
+            audio: Rc::new(SystemAudioBackend::default()),
+            playing_ambient_sound: None,
+            alarm_sounds: Vec::new(),
+            chosen_sound_file_slot: SoundFileSlot::Alarm,
+            speech: Rc::new(SystemSpeechBackend),
+            alarm_repeat: None,
+            overtime: None,
+            continuous_work_time: ZERO,
+            finished_pomodoro: None,
+            imported_days_off: Vec::new(),
+            microphone_is_in_use: false,
+            fullscreen_is_active: false,
+            fullscreen_detection_problem: None,
+            tray_icon_problem: None,
+            fullscreen_paused_break: false,
+            workspace_is_distracting: false,
+            workspace_paused_work: false,
+            interruptions: 0,
+            escalation: None,
+            snoozed_banner: None,
+            period_snooze_left: None,
+            banner_snooze_left: ZERO,
+            break_input_lock_escapes: 0,
+            current_user: None,
+            users: Arc::new(Vec::new()),
+            user_to_switch_to: String::new(),
+            output_devices: Arc::new(Vec::new()),
+            quit_is_requested: false,
+            settings,
         }
     }
 }
 
 impl TomataState {
     pub fn new(settings: Settings) -> TomataState {
-        TomataState {
+        let mut state = TomataState {
+            focus_prompt_is_shown: settings.is_focus_question_asked(),
+            notifier_backend: settings.get_notification_backend(),
             settings,
             ..Default::default()
+        };
+        state.notifier =
+            notifier::make_notifier(state.notifier_backend, &state.notification_actions);
+        state
+    }
+
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Replaces the audio backend, e.g., with the silent one of the headless
+    /// mode or a mock in the tests.
+    pub fn set_audio_backend(&mut self, audio: Rc<dyn AudioBackend>) {
+        self.audio = audio;
+    }
+
+    pub fn play_sound(&mut self, event: SoundEvent) {
+        let is_played = match event {
+            SoundEvent::PeriodEnding | SoundEvent::OvertimeWarning(_) | SoundEvent::Escalation
+                if self.is_on_call() || self.fullscreen_is_active =>
+            {
+                false
+            }
+            SoundEvent::PeriodEnding => {
+                self.settings
+                    .is_period_ending_sound_enabled_for(self.current_period)
+                    && !self.are_breaks_deferred()
+            }
+            SoundEvent::VolumeCheck | SoundEvent::Escalation => true,
+            SoundEvent::OvertimeWarning(_) => !self.is_in_meeting_mode(),
+        };
+        if is_played {
+            self.beep(event);
+        }
+    }
+
+    pub fn calculate_volume(&self, event: SoundEvent) -> f32 {
+        let volume = self.settings.get_beep_volume()
+            * self.settings.get_event_volume(event)
+            * self
+                .settings
+                .get_quiet_hours_volume(tomata::time_of_local_day(self.wall_clock_time));
+        match event {
+            SoundEvent::OvertimeWarning(count) => {
+                (volume * (1.0 + OVERTIME_VOLUME_STEP * count.saturating_sub(1) as f32)).min(1.0)
+            }
+            _ => volume,
+        }
+    }
+
+    /// Remembers which of the sounds is chosen in the file dialog.
+    pub fn choose_sound_file(&mut self, slot: SoundFileSlot) {
+        self.chosen_sound_file_slot = slot;
+    }
+
+    /// Sets the file chosen in the file dialog of the settings.
+    pub fn set_chosen_sound_file(&mut self, path: &Path) {
+        self.settings
+            .set_sound_file(self.chosen_sound_file_slot, path);
+        self.prepare_sounds();
+    }
+
+    /// Plays the sound of the event in the calibration panel, regardless
+    /// of the current period.
+    pub fn preview_sound(&mut self, event: SoundEvent) {
+        self.beep(event);
+    }
+
+    /// Plays the sounds of all the events one after another, see
+    /// [`TomataState::advance_sound_calibration`].
+    pub fn start_sound_calibration(&mut self) {
+        self.calibration_queue = CALIBRATED_SOUNDS.iter().rev().copied().collect();
+        self.since_calibration_sound = CALIBRATION_SOUND_INTERVAL;
+    }
+
+    pub fn advance_sound_calibration(&mut self, elapsed: Duration) {
+        if self.calibrated_sound.is_none() && self.calibration_queue.is_empty() {
+            return;
+        }
+        self.since_calibration_sound += elapsed;
+        if self.since_calibration_sound < CALIBRATION_SOUND_INTERVAL {
+            return;
+        }
+        self.since_calibration_sound = ZERO;
+        self.calibrated_sound = self.calibration_queue.pop();
+        if let Some(event) = self.calibrated_sound {
+            self.beep(event);
         }
     }
 
-    pub fn beep(&self) {
-        let volume = self.settings.get_beep_volume();
-        std::thread::spawn(move || {
-            BEEPER.get().unwrap().beep(volume).unwrap();
+    /// Returns the sound being played by the calibration.
+    pub fn get_calibrated_sound(&self) -> Option<SoundEvent> {
+        self.calibrated_sound
+    }
+
+    fn beep(&mut self, event: SoundEvent) {
+        let volume = self.calculate_volume(event);
+        let path = match event {
+            SoundEvent::PeriodEnding => self
+                .settings
+                .get_period_ending_sound_file(self.current_period),
+            _ => self.settings.get_sound_file(SoundFileSlot::Alarm),
+        };
+        let sound = match path.map(Path::to_path_buf) {
+            Some(path) => self.load_alarm_sound(path),
+            None => Sound::Beep,
+        };
+        let result = if self.settings.are_sounds_headphones_only() {
+            self.audio
+                .play_through_headphones(&sound, volume)
+                .map(|is_played| {
+                    if !is_played {
+                        self.show_visual_alert(event);
+                    }
+                })
+        } else if let Some(device) = self.settings.get_output_device() {
+            // A disconnected device does not silence the alarm.
+            match self.audio.play_through_device(device, &sound, volume) {
+                Ok(false) => self.audio.play(&sound, volume),
+                result => result.map(|_| ()),
+            }
+        } else {
+            self.audio.play(&sound, volume)
+        };
+        if let Err(error) = result {
+            self.report_error(
+                format!("Could not play the sound: {}", error),
+                Some(RetryAction::PlaySound(event)),
+            );
+        }
+    }
+
+    /// Loops the ambient sound of the settings while a work period runs,
+    /// the audio thread is told only about the changes.
+    pub fn update_ambient_sound(&mut self) {
+        let is_working = self.current_period == Period::Work && !self.stopwatch_is_paused;
+        let ambient = self
+            .settings
+            .get_ambient_sound()
+            .filter(|_| is_working)
+            .map(|sound| (sound, self.settings.get_ambient_volume()));
+        if ambient == self.playing_ambient_sound {
+            return;
+        }
+        self.playing_ambient_sound = ambient;
+        if let Err(error) = self.audio.set_ambient_sound(ambient) {
+            self.report_error(format!("Could not play the ambient sound: {}", error), None);
+        }
+    }
+
+    /// Decodes the sound files of the settings and sets up the chosen
+    /// output device ahead, so the sounds play right at the transitions.
+    pub fn prepare_sounds(&mut self) {
+        for slot in SOUND_FILE_SLOTS.iter() {
+            if let Some(path) = self.settings.get_sound_file(*slot) {
+                self.load_alarm_sound(path.to_path_buf());
+            }
+        }
+        if let Some(device) = self.settings.get_output_device() {
+            if let Err(error) = self.audio.prepare_device(device) {
+                self.report_error(
+                    format!("Could not set up the sound device: {}", error),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Returns the sound of the file, read only the first time. The beep
+    /// is played when it cannot be read, the error is reported once.
+    fn load_alarm_sound(&mut self, path: PathBuf) -> Sound {
+        // The files no longer in the settings are forgotten.
+        let settings = &self.settings;
+        self.alarm_sounds.retain(|(loaded, _)| {
+            SOUND_FILE_SLOTS
+                .iter()
+                .any(|slot| settings.get_sound_file(*slot) == Some(loaded.as_path()))
         });
+        if !self.alarm_sounds.iter().any(|(loaded, _)| *loaded == path) {
+            let alarm_sound = sound::load_alarm_sound(&path)
+                .map(Arc::new)
+                .map_err(|error| error.to_string());
+            if let Err(error) = &alarm_sound {
+                let message = format!(
+                    "Could not read the alarm sound {}: {}",
+                    path.display(),
+                    error
+                );
+                self.report_error(message, None);
+            }
+            self.alarm_sounds.push((path.clone(), alarm_sound));
+        }
+        match self.alarm_sounds.iter().find(|(loaded, _)| *loaded == path) {
+            Some((_, Ok(alarm_sound))) => Sound::Recording(alarm_sound.clone()),
+            _ => Sound::Beep,
+        }
+    }
+
+    /// Why the sound file of the slot could not be read, shown under it.
+    pub fn get_sound_file_error(&self, slot: SoundFileSlot) -> Option<&str> {
+        let path = self.settings.get_sound_file(slot)?;
+        self.alarm_sounds
+            .iter()
+            .find(|(loaded, _)| loaded == path)
+            .and_then(|(_, alarm_sound)| alarm_sound.as_ref().err())
+            .map(String::as_str)
+    }
+
+    /// Shows the banner in place of the sound that cannot be heard
+    /// without the headphones.
+    fn show_visual_alert(&mut self, event: SoundEvent) {
+        let summary = match event {
+            SoundEvent::PeriodEnding => "The period ends in a few seconds.",
+            SoundEvent::VolumeCheck => "No headphones are connected.",
+            SoundEvent::OvertimeWarning(_) => "The break is still waiting.",
+            SoundEvent::Escalation => "The period is over.",
+        };
+        self.show_banner(Banner::from(
+            &Notification::new()
+                .summary(summary)
+                .body("The sounds are played only through the headphones.")
+                .finalize(),
+        ));
+    }
+
+    /// Replaces the speech backend, e.g., with a mock in the tests.
+    #[allow(dead_code)] // used in tests
+    pub fn set_speech_backend(&mut self, speech: Rc<dyn SpeechBackend>) {
+        self.speech = speech;
+    }
+
+    pub fn read_remaining_time(&mut self) {
+        let phrase = speech::make_remaining_time_phrase(self.calculate_remaining_time());
+        if let Err(error) = self.speech.speak(&phrase) {
+            self.report_error(
+                format!("Could not read the remaining time: {}", error),
+                None,
+            );
+        }
+    }
+
+    fn speak_period_change(&mut self, finished_period: Period) {
+        let phrase =
+            speech::make_period_change_phrase(finished_period, self.current_period, &self.settings);
+        if let Err(error) = self.speech.speak(&phrase) {
+            self.report_error(format!("Could not announce the period: {}", error), None);
+        }
+    }
+
+    /// Queues the failure for the error banner, unless the same one
+    /// is already waiting there, e.g., when it repeats on every tick.
+    pub fn report_error(&mut self, message: String, retry: Option<RetryAction>) {
+        eprintln!("{}", message);
+        if let Some(log) = &mut self.debug_log {
+            Arc::make_mut(log).record_error(tomata::now_timestamp(), &message);
+        }
+        let error = RecoverableError::new(message, retry);
+        if !self.errors.contains(&error) {
+            Arc::make_mut(&mut self.errors).push(error);
+        }
+    }
+
+    pub fn get_error(&self) -> Option<&RecoverableError> {
+        self.errors.first()
+    }
+
+    pub fn dismiss_error(&mut self) {
+        if !self.errors.is_empty() {
+            Arc::make_mut(&mut self.errors).remove(0);
+        }
+    }
+
+    /// Dismisses the oldest failure and tries the failed action again,
+    /// it is queued anew if it fails again.
+    pub fn retry_error(&mut self) {
+        let retry = self.get_error().and_then(RecoverableError::get_retry);
+        self.dismiss_error();
+        match retry {
+            Some(RetryAction::SaveSettings) => self.request_settings_save(),
+            Some(RetryAction::HandleEvent(event, hook)) => {
+                if let Some(event) = self.refresh_event(event) {
+                    self.retried_events.push((event, hook));
+                }
+            }
+            Some(RetryAction::PlaySound(event)) => self.play_sound(event),
+            Some(RetryAction::FlushWrites) => self.writes_flush_is_requested = true,
+            None => {}
+        }
+    }
+
+    /// The settings are saved by the engine, see [`TomataState::take_settings_save_request`].
+    pub fn request_settings_save(&mut self) {
+        self.settings_save_is_requested = true;
+    }
+
+    pub fn take_settings_save_request(&mut self) -> bool {
+        std::mem::take(&mut self.settings_save_is_requested)
+    }
+
+    /// Whether the held back writes are to be done right away,
+    /// e.g., when the user retries the failed ones.
+    pub fn take_writes_flush_request(&mut self) -> bool {
+        std::mem::take(&mut self.writes_flush_is_requested)
     }
 
     pub fn is_stopwatch_paused(&self) -> bool {
         self.stopwatch_is_paused
     }
 
+    pub fn get_current_period(&self) -> Period {
+        self.current_period
+    }
+
     pub fn is_period_finished(&self) -> bool {
         self.period_is_finished
     }
 
     pub fn start_stopwatch(&mut self) {
+        if self.is_in_meeting_mode() {
+            self.toggle_meeting_mode();
+        }
+        // Starting the stopwatch while the break waits for the end
+        // of a meeting means that the user does not want to wait.
+        if let Some(period) = self.deferred_break.take() {
+            self.activate_period(period);
+        }
         self.stopwatch_is_paused = false;
+        self.escalation = None;
+        self.alarm_repeat = None;
+        self.period_snooze_left = None;
+        if self.current_period == Period::Work {
+            self.rest_time = None;
+            self.work_is_held_for_rest = false;
+        }
+        self.mark_period_start();
     }
 
     pub fn pause_stopwatch(&mut self) {
         self.stopwatch_is_paused = true;
+        self.workspace_paused_work = false;
+        self.fullscreen_paused_break = false;
+    }
+
+    pub fn toggle_stopwatch(&mut self) {
+        if self.stopwatch_is_paused {
+            self.start_stopwatch();
+        } else {
+            self.pause_stopwatch();
+        }
+    }
+
+    /// Moves on to the next period the way it follows the current one,
+    /// which might abandon the current work period.
+    pub fn skip_period(&mut self) {
+        self.abort_work_period();
+        self.cycle_to_next_period();
+    }
+
+    /// Starts a work period requested by another application, lasting
+    /// `duration` instead of the configured time, if there is one.
+    pub fn start_session(&mut self, duration: Option<Duration>, tag: Option<String>) {
+        self.switch_to_period(Period::Work);
+        self.period_duration_override = duration.map(|duration| duration.as_secs());
+        if tag.is_some() {
+            self.session_tag = tag;
+            self.focus_prompt_is_shown = false;
+        }
+        self.start_stopwatch();
+    }
+
+    fn calculate_period_duration(&self) -> Duration {
+        self.period_duration_override.map_or_else(
+            || match self.get_current_cycle_block() {
+                Some(block) => *block.duration,
+                None => self
+                    .settings
+                    .convert_period_to_duration(self.current_period),
+            },
+            Duration::from_secs,
+        )
+    }
+
+    /// Returns the block of the sequence of the user running now,
+    /// none when the user switched to another period.
+    fn get_current_cycle_block(&self) -> Option<&CycleBlock> {
+        let sequence = self.settings.get_cycle_sequence();
+        sequence
+            .get(self.cycle_position % sequence.len().max(1))
+            .filter(|block| block.period == self.current_period)
+    }
+
+    pub fn get_cycle_position(&self) -> usize {
+        self.cycle_position
+    }
+
+    /// Keeps the edited sequence under the name typed by the user.
+    pub fn save_cycle_profile(&mut self) {
+        let name = self.cycle_profile_name.trim().to_string();
+        if !name.is_empty() {
+            self.settings.save_cycle_profile(&name);
+            self.cycle_profile_name.clear();
+        }
     }
 
     pub fn reset_stopwatch(&mut self) {
+        self.abort_work_period();
         self.activate_period(self.current_period);
     }
 
+    /// Activates the period chosen by the user, which might abandon
+    /// the current one, unlike [`TomataState::activate_period`].
+    pub fn switch_to_period(&mut self, period: Period) {
+        self.abort_work_period();
+        self.activate_period(period);
+        if !self.is_current_period_enabled() {
+            self.pass_disabled_period();
+        }
+    }
+
+    fn is_current_period_enabled(&self) -> bool {
+        self.get_current_cycle_block().is_some()
+            || self.settings.is_period_enabled(self.current_period)
+    }
+
+    /// Moves on from the disabled period, e.g., of zero length or chosen
+    /// with its button, instead of standing at zero. It stays when there
+    /// is no enabled period to move on to.
+    fn pass_disabled_period(&mut self) {
+        let current_period = self.current_period;
+        let can_move_on = [Period::Work, Period::ShortBreak, Period::LongBreak]
+            .iter()
+            .any(|period| *period != current_period && self.settings.is_period_enabled(*period));
+        if can_move_on {
+            // It is not recorded, it did not run.
+            self.period_started_at = None;
+            self.cycle_to_next_period();
+        }
+    }
+
     pub fn cycle_to_next_period(&mut self) {
+        let period_is_finished = self.period_is_finished;
+        let finished_period = self.current_period;
+        self.activate_next_period();
+        if period_is_finished && self.settings.are_period_changes_spoken() {
+            self.speak_period_change(finished_period);
+        }
+        // Escalating only when the period ends by itself, the user
+        // skipping it knows about the change.
+        if period_is_finished && self.stopwatch_is_paused && self.settings.is_escalation_enabled() {
+            self.escalation = Some(Escalation::default());
+        }
+        if period_is_finished && self.stopwatch_is_paused && self.settings.is_alarm_repeated() {
+            self.alarm_repeat = Some(Rc::new(ZERO));
+        }
+    }
+
+    fn activate_next_period(&mut self) {
+        if !self.settings.get_cycle_sequence().is_empty() {
+            self.activate_next_cycle_block();
+            return;
+        }
         match self.current_period {
             Period::Work => {
+                let work_is_finished = self.period_is_finished;
+                if work_is_finished {
+                    self.record_finished_work_period();
+                }
                 if self.is_long_break_next() {
-                    self.activate_period(Period::LongBreak);
-                } else if self.settings.get_short_breaks_number() > 0 {
-                    self.activate_period(Period::ShortBreak);
+                    self.activate_break(Period::LongBreak);
+                } else if self.settings.is_period_enabled(Period::ShortBreak) {
+                    self.activate_break(Period::ShortBreak);
                 } else {
+                    // The disabled short break passes instantly, so it still
+                    // counts towards the long break.
+                    self.short_breaks_finished += 1;
                     self.activate_period(Period::Work);
                 }
+                if work_is_finished && self.current_period != Period::Work {
+                    self.overtime = Some(ZERO);
+                }
             }
             Period::ShortBreak => {
+                self.record_ended_period();
+                if self.period_is_finished {
+                    self.continuous_work_time = ZERO;
+                }
                 self.short_breaks_finished += 1;
                 self.activate_period(Period::Work);
             }
             Period::LongBreak => {
+                self.record_ended_period();
+                if self.period_is_finished {
+                    self.continuous_work_time = ZERO;
+                }
                 self.short_breaks_finished = 0;
+                self.work_periods_finished = 0;
+                self.activate_period(Period::Work);
+            }
+            Period::Custom(_) => {
+                self.record_ended_period();
                 self.activate_period(Period::Work);
             }
         }
     }
 
+    fn record_finished_work_period(&mut self) {
+        self.rest_time = Some(Rc::new(ZERO));
+        self.user_is_resting = true;
+        self.record_finished_period();
+        self.work_periods_finished += 1;
+        let count = history::count_completed_pomodoros(
+            &self.recent_records,
+            tomata::start_of_local_day(self.wall_clock_time),
+        );
+        self.finished_pomodoro = Some((self.session_tag.clone(), count));
+    }
+
+    /// Follows the sequence of the user, from the block after the current
+    /// one, or after the last one that ran.
+    fn activate_next_cycle_block(&mut self) {
+        let work_is_finished = self.current_period == Period::Work && self.period_is_finished;
+        if work_is_finished {
+            self.record_finished_work_period();
+        } else if self.current_period != Period::Work {
+            self.record_ended_period();
+            let is_break = matches!(self.current_period, Period::ShortBreak | Period::LongBreak);
+            if self.period_is_finished && is_break {
+                self.continuous_work_time = ZERO;
+            }
+        }
+        let sequence = self.settings.get_cycle_sequence().clone();
+        self.cycle_position = (self.cycle_position + 1) % sequence.len();
+        self.activate_period(sequence[self.cycle_position].period);
+        if work_is_finished && self.current_period != Period::Work {
+            self.overtime = Some(ZERO);
+        }
+    }
+
     pub fn activate_period(&mut self, period: Period) {
+        self.unhandled_events
+            .push(TomataEvent::PeriodActivated(period));
+        self.deferred_break = None;
         self.current_period = period;
         self.period_is_finished = false;
+        self.user_is_away = false;
+        self.end_break_early_prompt_is_shown = false;
+        self.work_idle_time = ZERO;
+        self.away_time_prompt_is_shown = false;
+        self.meeting_end_prompt_is_shown = false;
         self.elapsed_time = Rc::new(ZERO);
-        if self.settings.does_next_period_start_automatically() {
-            self.stopwatch_is_paused = false;
-        } else {
+        self.period_started_at = None;
+        self.paused_time = ZERO;
+        self.interruptions = 0;
+        self.workspace_paused_work = false;
+        self.fullscreen_paused_break = false;
+        self.application_samples.clear();
+        self.break_input_lock_escapes = 0;
+        self.overtime = None;
+        self.escalation = None;
+        self.alarm_repeat = None;
+        self.period_snooze_left = None;
+        self.session_tag = self
+            .primary_project
+            .clone()
+            .filter(|_| period == Period::Work);
+        self.period_duration_override = None;
+        self.focus_prompt_is_shown =
+            period == Period::Work && self.settings.is_focus_question_asked();
+        let is_late_work = period == Period::Work && self.is_after_bedtime();
+        self.stopwatch_is_paused = !self.settings.does_next_period_start_automatically()
+            || self.is_workday_over()
+            || self.is_day_off(self.wall_clock_time)
+            || (is_late_work && self.settings.is_late_work_held());
+        if is_late_work && self.stopwatch_is_paused {
+            self.warn_about_late_work("The next work period does not start by itself.");
+        }
+        self.work_is_held_for_rest =
+            period == Period::Work && !self.stopwatch_is_paused && self.is_rest_too_short();
+        if self.work_is_held_for_rest {
             self.stopwatch_is_paused = true;
         }
+        if !self.stopwatch_is_paused {
+            self.mark_period_start();
+        }
+        self.notify_period(period);
+    }
 
-        if self.settings.are_system_notifications_enabled() {
-            Notification::from(period).show().unwrap();
+    /// The notification offers to start the period waiting for the user,
+    /// to skip it or to snooze it.
+    fn notify_period(&mut self, period: Period) {
+        let mut notification = match period {
+            Period::Custom(index) => self
+                .settings
+                .get_custom_period(index)
+                .map_or_else(|| Notification::from(period), Notification::from),
+            _ => Notification::from(period),
+        };
+        if self.stopwatch_is_paused {
+            let label = match period {
+                Period::Work => "Start work",
+                Period::ShortBreak | Period::LongBreak => "Start break",
+                Period::Custom(_) => "Start",
+            };
+            notification.action(ControlCommand::Resume.name(), label);
         }
+        notification
+            .action(ControlCommand::SkipPeriod.name(), "Skip")
+            .action(ControlCommand::Snooze.name(), "Snooze 5 min");
+        if let Some((tag, count)) = self.finished_pomodoro.take() {
+            if period != Period::Work {
+                notification.summary = self.make_finished_pomodoro_summary(
+                    notification.summary.trim_end_matches('.'),
+                    tag.as_deref(),
+                    count,
+                );
+            }
+        }
+        self.notify(notification);
+    }
+
+    fn make_finished_pomodoro_summary(
+        &self,
+        summary: &str,
+        tag: Option<&str>,
+        count: usize,
+    ) -> String {
+        let task = match tag {
+            Some(tag) => format!("'{}'", tag),
+            None => "the work period".to_string(),
+        };
+        tomata::render_template(
+            self.settings.get_finished_work_notification_template(),
+            &[
+                ("summary", summary),
+                ("task", &task),
+                ("count", &tomata::to_ordinal(count)),
+            ],
+        )
     }
 
     pub fn increase_elapsed_time(&mut self, value: Duration) {
-        if self.is_period_finishing() && self.settings.is_period_ending_sound_enabled() {
-            self.beep();
+        // A period of zero length would finish on every tick, instead it
+        // never runs, the cycle moves on (see `cycle_to_next_period`).
+        if !self.is_current_period_enabled() {
+            self.pass_disabled_period();
+            return;
         }
-
+        let is_period_finishing = self.is_period_finishing();
+        let remaining_seconds = tomata::round_up_to_seconds(self.calculate_remaining_time());
         self.elapsed_time = Rc::new(*self.elapsed_time + value);
-        let period_duration = self
-            .settings
-            .convert_period_to_duration(self.current_period);
+        if self.current_period == Period::Work {
+            self.increase_continuous_work_time(value);
+            self.announce_remaining_time(remaining_seconds);
+        }
+        // Beeping once per each of the final seconds, however often the ticks come.
+        // The alarm sound of the user is played only at the first of them.
+        if is_period_finishing
+            && tomata::round_up_to_seconds(self.calculate_remaining_time()) < remaining_seconds
+            && (self
+                .settings
+                .get_period_ending_sound_file(self.current_period)
+                .is_none()
+                || remaining_seconds >= PERIOD_ENDING_WARNING_TIME)
+        {
+            self.play_sound(SoundEvent::PeriodEnding);
+        }
+        let period_duration = self.calculate_period_duration();
         if period_duration <= *self.elapsed_time {
             self.period_is_finished = true;
         }
     }
 
-    pub fn calculate_remaining_time(&self) -> Duration {
-        let period_duration = self
-            .settings
-            .convert_period_to_duration(self.current_period);
-        if period_duration <= *self.elapsed_time {
-            return ZERO;
+    /// Warns once when the work continues past the limit because
+    /// the breaks are skipped, e.g., by starting the work periods manually.
+    fn increase_continuous_work_time(&mut self, value: Duration) {
+        let limit = self.settings.get_long_run_limit();
+        let was_long_run = self.is_long_run();
+        self.continuous_work_time += value;
+        if self.is_long_run() && !was_long_run {
+            self.notify(
+                Notification::new()
+                    .appname(tomata::APPLICATION_NAME)
+                    .summary("You have skipped too many breaks.")
+                    .body(&format!(
+                        "You have been working for {} without a break.",
+                        tomata::duration_to_string(&limit)
+                    ))
+                    .clone(),
+            );
         }
-        period_duration - *self.elapsed_time
     }
 
-    fn is_period_finishing(&self) -> bool {
-        self.calculate_remaining_time() <= Duration::from_secs(5)
+    /// Posts a silent notification each time the remaining time of the work
+    /// period reaches another multiple of the interval, e.g., "15:00 left".
+    fn announce_remaining_time(&mut self, previous_remaining_seconds: Duration) {
+        let interval = self
+            .settings
+            .get_remaining_time_announcement_interval()
+            .as_secs()
+            .max(1);
+        let before = previous_remaining_seconds.as_secs();
+        let after = tomata::round_up_to_seconds(self.calculate_remaining_time()).as_secs();
+        if !self.settings.are_remaining_time_announcements_enabled()
+            || after == 0
+            || (before.saturating_sub(1) / interval) == ((after - 1) / interval)
+        {
+            return;
+        }
+        let remaining = ((after - 1) / interval + 1) * interval;
+        let remaining = format!("{}:{:0>2}", remaining / MINUTE_S, remaining % MINUTE_S);
+        let summary = match self.session_tag.as_deref() {
+            Some(tag) => format!("{} left \u{2014} {}", remaining, tag),
+            None => format!("{} left of the work period", remaining),
+        };
+        let mut notification = Notification::new();
+        notification
+            .appname(tomata::APPLICATION_NAME)
+            .summary(&summary);
+        #[cfg(all(unix, not(target_os = "macos")))]
+        notification.hint(notify_rust::Hint::SuppressSound(true));
+        self.notify(notification);
     }
 
-    fn is_long_break_next(&self) -> bool {
-        self.short_breaks_finished == self.settings.get_short_breaks_number()
-            && self.settings.are_long_breaks_included()
+    fn is_long_run(&self) -> bool {
+        self.settings.is_long_run_warning_enabled()
+            && self.continuous_work_time > self.settings.get_long_run_limit()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::tomata::HOUR_S;
+    /// Returns how many work sessions were finished today past the continuous work limit.
+    pub fn count_long_runs(&self) -> usize {
+        history::count_long_runs(
+            &self.recent_records,
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
 
-    fn make_default_test_state() -> TomataState {
-        // normal settings but with system notifications are disabled
-        let settings = Settings::new(
-            Duration::from_secs(1),
-            Duration::from_secs(1),
-            Duration::from_secs(1),
-            2,
-            true,
-            true,
-            false, // during tests we don't want the system notifications
-            // running
-            false, // during tests we don't want the beep sound effect
-            0.1,
+    /// Counts the overtime after the work period while the break is not
+    /// started and reminds about the break each time the overtime reaches
+    /// another multiple of the threshold, louder every time.
+    pub fn advance_overtime(&mut self, elapsed: Duration) {
+        let overtime = match self.overtime {
+            Some(overtime) => overtime,
+            None => return,
+        };
+        if self.period_started_at.is_some() {
+            self.overtime = None;
+            return;
+        }
+        let new_overtime = overtime + elapsed;
+        self.overtime = Some(new_overtime);
+        let threshold = self.settings.get_overtime_threshold().as_millis().max(1);
+        let reminders_count = (new_overtime.as_millis() / threshold) as u32;
+        if !self.settings.is_overtime_reminder_enabled()
+            || reminders_count == (overtime.as_millis() / threshold) as u32
+        {
+            return;
+        }
+        self.notify(
+            Notification::new()
+                .appname(tomata::APPLICATION_NAME)
+                .summary("You really should take a break.")
+                .body(&format!(
+                    "The work period finished {} minutes ago.",
+                    new_overtime.as_secs() / MINUTE_S
+                ))
+                .clone(),
         );
-        TomataState::new(settings)
+        self.play_sound(SoundEvent::OvertimeWarning(reminders_count));
     }
 
-    #[test]
-    fn remaining_time_is_zero_when_elapsed_time_is_bigger_than_period_time() {
-        let duration = Duration::from_secs(HOUR_S);
-        let mut state = make_default_test_state();
-        state.elapsed_time = Rc::new(duration);
-        let remaining_time = state.calculate_remaining_time();
-        assert_eq!(remaining_time, ZERO);
+    /// Escalates the alert about the finished period while the next one
+    /// waits for the user to start it, not while they are in a meeting.
+    pub fn advance_escalation(&mut self, elapsed: Duration) {
+        if self.is_in_meeting_mode() {
+            return;
+        }
+        let stage = match self.escalation.as_mut() {
+            Some(escalation) => escalation.advance(
+                elapsed,
+                self.settings.get_escalation_sound_delay(),
+                self.settings.get_escalation_overlay_delay(),
+            ),
+            None => return,
+        };
+        if let Some(stage) = stage {
+            self.unhandled_events
+                .push(TomataEvent::AlertEscalated(stage));
+            if stage == EscalationStage::Sound {
+                self.play_sound(SoundEvent::Escalation);
+            }
+        }
     }
 
-    #[test]
-    fn increasing_elapsed_time() {
-        let duration = Duration::from_secs(HOUR_S);
-        let mut state = make_default_test_state();
-        state.increase_elapsed_time(duration.clone());
-        assert_eq!(*state.elapsed_time, duration);
+    /// Beeps every interval of the settings until the next period
+    /// is started or the alarm is silenced, never during a meeting.
+    pub fn advance_alarm_repeat(&mut self, elapsed: Duration) {
+        let since_alarm = match &self.alarm_repeat {
+            Some(since_alarm) if !self.is_in_meeting_mode() => **since_alarm + elapsed,
+            _ => return,
+        };
+        if since_alarm < self.settings.get_alarm_repeat_interval() {
+            self.alarm_repeat = Some(Rc::new(since_alarm));
+            return;
+        }
+        self.alarm_repeat = Some(Rc::new(ZERO));
+        self.play_sound(SoundEvent::Escalation);
     }
 
-    #[test]
-    fn activating_work_period() {
-        let mut state = make_default_test_state();
-        state.activate_period(Period::Work);
-        assert_eq!(state.current_period, Period::Work);
-        assert_eq!(
-            state.stopwatch_is_paused,
-            !state.settings.does_next_period_start_automatically()
-        );
-        assert_eq!(*state.elapsed_time, ZERO);
+    pub fn is_alarm_repeating(&self) -> bool {
+        self.alarm_repeat.is_some()
     }
 
-    #[test]
-    fn activating_short_break() {
-        let mut state = make_default_test_state();
-        state.activate_period(Period::ShortBreak);
-        assert_eq!(state.current_period, Period::ShortBreak);
-        assert_eq!(
-            state.stopwatch_is_paused,
-            !state.settings.does_next_period_start_automatically()
-        );
-        assert_eq!(*state.elapsed_time, ZERO);
+    pub fn silence_alarm(&mut self) {
+        self.alarm_repeat = None;
     }
 
-    #[test]
-    fn activating_long_break() {
-        let mut state = make_default_test_state();
-        state.activate_period(Period::LongBreak);
-        assert_eq!(state.current_period, Period::LongBreak);
-        assert_eq!(
-            state.stopwatch_is_paused,
-            !state.settings.does_next_period_start_automatically()
-        );
-        assert_eq!(*state.elapsed_time, ZERO);
+    /// Counts the pauses of the started period, so its record can tell
+    /// the focused time from the wall-clock span.
+    pub fn increase_paused_time(&mut self, value: Duration) {
+        if self.period_started_at.is_some() {
+            self.paused_time += value;
+        }
     }
 
-    #[test]
-    fn cycling_over_all_periods() {
-        let mut state = make_default_test_state();
-        let short_breaks_number = state.settings.get_short_breaks_number();
-        for _ in 0..short_breaks_number {
-            assert_eq!(state.current_period, Period::Work);
-            state.cycle_to_next_period();
-            assert_eq!(state.current_period, Period::ShortBreak);
-            state.cycle_to_next_period();
+    /// Tells whether the idle time of the user should be registered
+    /// to offer ending the break early.
+    pub fn is_waiting_for_user_return(&self) -> bool {
+        self.settings.is_ending_break_early_offered()
+            && self.current_period != Period::Work
+            && !self.stopwatch_is_paused
+            && !self.end_break_early_prompt_is_shown
+    }
+
+    /// Tells whether the idle time of the user should be registered
+    /// to tell the rest from the time spent at the computer.
+    pub fn is_measuring_rest(&self) -> bool {
+        self.settings.is_rest_enforced()
+            && self.rest_time.is_some()
+            && (matches!(self.current_period, Period::ShortBreak | Period::LongBreak)
+                || self.work_is_held_for_rest)
+    }
+
+    fn is_rest_too_short(&self) -> bool {
+        self.settings.is_rest_enforced()
+            && self
+                .rest_time
+                .as_deref()
+                .is_some_and(|rest| *rest < self.settings.get_minimum_rest())
+    }
+
+    /// Returns how much longer the user has to rest before the work
+    /// period held back starts by itself.
+    pub fn get_missing_rest_time(&self) -> Option<Duration> {
+        let rest = self
+            .rest_time
+            .as_deref()
+            .filter(|_| self.work_is_held_for_rest)?;
+        Some(self.settings.get_minimum_rest().saturating_sub(*rest))
+    }
+
+    /// Counts the rest during the breaks and while the work waits for it,
+    /// and starts the work once the user has rested enough.
+    pub fn advance_rest(&mut self, elapsed: Duration) {
+        if !self.is_measuring_rest() {
+            return;
         }
-        assert_eq!(state.current_period, Period::Work);
-        if state.settings.are_long_breaks_included() {
-            state.cycle_to_next_period();
-            assert_eq!(state.current_period, Period::LongBreak);
-            state.cycle_to_next_period();
-            assert_eq!(state.current_period, Period::Work);
+        if let Some(rest) = &mut self.rest_time {
+            if self.user_is_resting {
+                *rest = Rc::new(**rest + elapsed);
+            }
+        }
+        if self.work_is_held_for_rest && !self.is_rest_too_short() {
+            self.start_stopwatch();
         }
     }
 
-    #[test]
-    fn checking_if_period_is_finishing() {
-        let state = make_default_test_state();
-        assert!(state.is_period_finishing());
+    /// Tells whether the idle time of the user should be registered
+    /// to ask about the time away from the work.
+    pub fn is_watching_away_time(&self) -> bool {
+        self.settings.is_away_time_reviewed()
+            && self.current_period == Period::Work
+            && !self.stopwatch_is_paused
+            && !self.away_time_prompt_is_shown
+    }
+
+    /// The workspaces are watched only while a work period runs, or waits
+    /// for the user to come back from a distraction workspace.
+    pub fn is_watching_workspaces(&self) -> bool {
+        !self.settings.get_distraction_workspaces().is_empty()
+            && self.current_period == Period::Work
+            && (!self.stopwatch_is_paused || self.workspace_paused_work)
+    }
+
+    /// Counts the switch to a distraction workspace as an interruption
+    /// of the work period, which is paused until the user switches back
+    /// if the settings say so.
+    pub fn set_focused_workspace(&mut self, workspace: &str) {
+        let is_distracting = self
+            .settings
+            .get_distraction_workspaces()
+            .contains(&workspace);
+        if is_distracting && !self.workspace_is_distracting && !self.stopwatch_is_paused {
+            self.interruptions += 1;
+            if self.settings.is_paused_on_distraction_workspace() {
+                self.pause_stopwatch();
+                self.workspace_paused_work = true;
+            }
+        } else if !is_distracting && self.workspace_paused_work {
+            self.start_stopwatch();
+            self.workspace_paused_work = false;
+        }
+        self.workspace_is_distracting = is_distracting;
+    }
+
+    /// The focused application is sampled only while a work period runs.
+    pub fn is_sampling_applications(&self) -> bool {
+        self.settings.is_application_sampling_enabled()
+            && self.current_period == Period::Work
+            && !self.stopwatch_is_paused
+    }
+
+    pub fn register_active_application(&mut self, application: &str) {
+        if self.is_sampling_applications() {
+            self.application_samples.add(application);
+        }
+    }
+
+    /// Returns which part of today's work time was spent in each of the applications.
+    pub fn summarize_applications(&self) -> Vec<(String, f64)> {
+        history::summarize_applications(
+            &self.get_shown_records(),
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    /// Shows the prompt to end the break early when the user comes back
+    /// to the computer after being away during the break.
+    pub fn register_user_idle_time(&mut self, idle_time: Duration) {
+        if self.is_measuring_rest() {
+            self.user_is_resting = idle_time >= AWAY_IDLE_TIME;
+        }
+        if self.is_watching_away_time() {
+            self.register_work_idle_time(idle_time);
+        }
+        if !self.is_waiting_for_user_return() {
+            return;
+        }
+        if idle_time >= AWAY_IDLE_TIME {
+            self.user_is_away = true;
+        } else if self.user_is_away && idle_time <= RETURN_IDLE_TIME {
+            self.user_is_away = false;
+            self.end_break_early_prompt_is_shown = true;
+        }
+    }
+
+    /// Asks what the time away was when the user comes back after being
+    /// idle for long during the work period.
+    fn register_work_idle_time(&mut self, idle_time: Duration) {
+        if idle_time >= AWAY_FROM_WORK_IDLE_TIME {
+            self.work_idle_time = self.work_idle_time.max(idle_time);
+        } else if self.work_idle_time > ZERO && idle_time <= RETURN_IDLE_TIME {
+            self.away_from_work_time = self.work_idle_time.min(*self.elapsed_time);
+            self.work_idle_time = ZERO;
+            self.away_time_prompt_is_shown = true;
+        }
+    }
+
+    pub fn is_away_time_prompt_shown(&self) -> bool {
+        self.away_time_prompt_is_shown
+    }
+
+    pub fn get_away_from_work_time(&self) -> Duration {
+        self.away_from_work_time
+    }
+
+    /// Keeps the time away counted as the work, or takes it out of the work
+    /// period, which then runs for that much longer, and records it as
+    /// a short break if it was one.
+    pub fn classify_away_time(&mut self, classification: AwayTimeClassification) {
+        self.away_time_prompt_is_shown = false;
+        let away_time = self.away_from_work_time;
+        if classification == AwayTimeClassification::Work || self.current_period != Period::Work {
+            return;
+        }
+        self.elapsed_time = Rc::new(self.elapsed_time.saturating_sub(away_time));
+        self.continuous_work_time = self.continuous_work_time.saturating_sub(away_time);
+        self.increase_paused_time(away_time);
+        if classification == AwayTimeClassification::Break {
+            let finished_at = tomata::now_timestamp();
+            self.add_record(SessionRecord::new(
+                RecordedPeriod::ShortBreak,
+                finished_at.saturating_sub(away_time.as_secs()),
+                finished_at,
+            ));
+        }
+    }
+
+    pub fn is_end_break_early_prompt_shown(&self) -> bool {
+        self.end_break_early_prompt_is_shown
+    }
+
+    pub fn end_break_early(&mut self) {
+        self.cycle_to_next_period();
+        self.start_stopwatch();
+    }
+
+    pub fn dismiss_end_break_early_prompt(&mut self) {
+        self.end_break_early_prompt_is_shown = false;
+    }
+
+    pub fn advance_reminders(&mut self, elapsed: Duration) {
+        for kind in self.reminders.advance(&self.settings, elapsed) {
+            self.notify(Notification::from(kind));
+        }
+    }
+
+    pub fn is_focus_prompt_shown(&self) -> bool {
+        self.focus_prompt_is_shown
+    }
+
+    /// Makes the answer to the focus question the tag of the current session.
+    pub fn confirm_focus_answer(&mut self) {
+        let answer = self.focus_answer.trim();
+        if !answer.is_empty() {
+            self.session_tag = Some(answer.to_string());
+        }
+        self.focus_answer.clear();
+        self.focus_prompt_is_shown = false;
+    }
+
+    /// The tags offered in the focus prompt, the most recently used first.
+    pub fn list_recent_tags(&self) -> Vec<&str> {
+        history::list_recent_tags(&self.recent_records, RECENT_TAGS_COUNT)
+    }
+
+    /// Tags the session with the recent tag at `index` in one go.
+    pub fn pick_recent_tag(&mut self, index: usize) {
+        if let Some(tag) = self.list_recent_tags().get(index) {
+            self.focus_answer = tag.to_string();
+            self.confirm_focus_answer();
+        }
+    }
+
+    /// Completes the answer to the first recent tag starting with it,
+    /// regardless of the case, returns whether there was one.
+    pub fn complete_focus_answer(&mut self) -> bool {
+        let prefix = self.focus_answer.to_lowercase();
+        if prefix.is_empty() {
+            return false;
+        }
+        let completion = self
+            .list_recent_tags()
+            .into_iter()
+            .find(|tag| tag.to_lowercase().starts_with(&prefix) && tag.len() > prefix.len())
+            .map(str::to_string);
+        match completion {
+            Some(tag) => {
+                self.focus_answer = tag;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn dismiss_focus_prompt(&mut self) {
+        self.focus_answer.clear();
+        self.focus_prompt_is_shown = false;
+    }
+
+    pub fn show_day_plan_prompt(&mut self) {
+        self.day_plan_project = self.primary_project.clone().unwrap_or_default();
+        self.day_plan_prompt_is_shown = true;
+    }
+
+    pub fn is_day_plan_prompt_shown(&self) -> bool {
+        self.day_plan_prompt_is_shown
+    }
+
+    /// Restores the primary project chosen earlier today.
+    pub fn set_primary_project(&mut self, project: Option<String>) {
+        if self.current_period == Period::Work && self.session_tag == self.primary_project {
+            self.session_tag = project.clone();
+        }
+        self.primary_project = project;
+    }
+
+    /// Makes the project the default tag of today's work periods and
+    /// plans it for the daily goal, unless it is already planned.
+    pub fn confirm_day_plan(&mut self) {
+        let project = match self.day_plan_project.trim() {
+            "" => None,
+            project => Some(project.to_string()),
+        };
+        if let Some(name) = &project {
+            if !self
+                .planned_tasks
+                .iter()
+                .any(|task| task.get_name() == name)
+            {
+                let task = PlannedTask::new(
+                    self.next_task_id,
+                    name.clone(),
+                    self.settings.get_daily_goal(),
+                );
+                self.next_task_id += 1;
+                Arc::make_mut(&mut self.planned_tasks).push(task);
+                self.plan_is_modified = true;
+            }
+        }
+        self.set_primary_project(project);
+        self.primary_project_is_modified = true;
+        self.dismiss_day_plan_prompt();
+    }
+
+    pub fn dismiss_day_plan_prompt(&mut self) {
+        self.day_plan_project.clear();
+        self.day_plan_prompt_is_shown = false;
+    }
+
+    /// Hands over the primary project if it has been chosen since
+    /// the last call, `Some(None)` when it has been cleared.
+    pub fn take_modified_primary_project(&mut self) -> Option<Option<String>> {
+        if std::mem::take(&mut self.primary_project_is_modified) {
+            Some(self.primary_project.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn get_session_tag(&self) -> Option<&str> {
+        self.session_tag.as_deref()
+    }
+
+    /// Hands over the records of the finished sessions that
+    /// are not stored in the history yet.
+    pub fn take_unsaved_records(&mut self) -> Vec<SessionRecord> {
+        std::mem::take(&mut self.unsaved_records)
+    }
+
+    /// Adds a countdown described by the name and the duration
+    /// entered by the user (see [`tomata::parse_duration`]).
+    pub fn add_countdown(&mut self) {
+        let duration = match tomata::parse_duration(&self.new_countdown_duration) {
+            Some(duration) if duration > ZERO => duration,
+            _ => return,
+        };
+        let name = match self.new_countdown_name.trim() {
+            "" => "Timer".to_string(),
+            name => name.to_string(),
+        };
+        let countdown = Countdown::new(self.next_countdown_id, name, duration);
+        self.next_countdown_id += 1;
+        Arc::make_mut(&mut self.countdowns).push(countdown);
+        self.new_countdown_name.clear();
+        self.new_countdown_duration.clear();
+    }
+
+    pub fn remove_countdown(&mut self, id: u64) {
+        Arc::make_mut(&mut self.countdowns).retain(|countdown| countdown.get_id() != id);
+    }
+
+    pub fn advance_countdowns(&mut self, elapsed: Duration) {
+        if self.countdowns.iter().all(Countdown::is_finished) {
+            return;
+        }
+        let finished: Vec<Notification> = Arc::make_mut(&mut self.countdowns)
+            .iter_mut()
+            .filter_map(|countdown| {
+                if countdown.advance(elapsed) {
+                    Some(Notification::from(&*countdown))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for notification in finished {
+            self.notify(notification);
+        }
+    }
+
+    /// Pauses the cycle and silences the notifications until it is toggled
+    /// again, then the time spent in the meeting is recorded in the history
+    /// and the stopwatch returns to its previous state.
+    pub fn toggle_meeting_mode(&mut self) {
+        match self.meeting_mode_started_at.take() {
+            Some(started_at) => {
+                let finished_at = tomata::now_timestamp();
+                self.add_record(SessionRecord::new(
+                    RecordedPeriod::Meeting,
+                    started_at.min(finished_at),
+                    finished_at,
+                ));
+                self.stopwatch_is_paused = self.stopwatch_was_paused_before_meeting;
+                self.meeting_mode_follows_calendar = false;
+            }
+            None => {
+                self.meeting_mode_started_at = Some(tomata::now_timestamp());
+                self.stopwatch_was_paused_before_meeting = self.stopwatch_is_paused;
+                self.stopwatch_is_paused = true;
+                self.alarm_repeat = None;
+                self.escalation = None;
+            }
+        }
+    }
+
+    pub fn set_recent_records(&mut self, records: Vec<SessionRecord>) {
+        self.recent_records = Arc::new(records);
+    }
+
+    pub fn set_users(&mut self, current_user: Option<String>, users: Vec<String>) {
+        self.current_user = current_user;
+        self.users = Arc::new(users);
+    }
+
+    /// Lists the output devices again, e.g., after plugging in a headset.
+    pub fn refresh_output_devices(&mut self) {
+        match self.audio.list_output_devices() {
+            Ok(devices) => self.output_devices = Arc::new(devices),
+            Err(error) => {
+                self.report_error(format!("Could not list the sound devices: {}", error), None)
+            }
+        }
+    }
+
+    /// Returns the devices to choose from, the chosen one too while it is
+    /// disconnected, so the choice is kept.
+    pub fn get_output_device_choices(&self) -> Vec<String> {
+        let mut devices = self.output_devices.to_vec();
+        if let Some(device) = self.settings.get_output_device() {
+            if !devices.iter().any(|name| name == device) {
+                devices.push(device.to_string());
+            }
+        }
+        devices
+    }
+
+    pub fn get_current_user(&self) -> Option<&str> {
+        self.current_user.as_deref()
+    }
+
+    /// Returns the users other than the current one.
+    pub fn get_other_users(&self) -> Vec<&str> {
+        self.users
+            .iter()
+            .map(String::as_str)
+            .filter(|&user| Some(user) != self.get_current_user())
+            .collect()
+    }
+
+    /// Launches the application with the data of the entered user,
+    /// this one quits once the other is started.
+    pub fn switch_user(&mut self) {
+        let user = self.user_to_switch_to.trim().to_string();
+        if user.is_empty() || self.get_current_user() == Some(user.as_str()) {
+            return;
+        }
+        match users::relaunch_as_user(&user, self.get_current_user()) {
+            Ok(()) => self.quit_is_requested = true,
+            Err(error) => {
+                self.report_error(format!("Could not switch to {}: {}", user, error), None)
+            }
+        }
+    }
+
+    /// Tells whether the application should quit, once.
+    pub fn request_quit(&mut self) {
+        self.quit_is_requested = true;
+    }
+
+    pub fn take_quit_request(&mut self) -> bool {
+        std::mem::take(&mut self.quit_is_requested)
+    }
+
+    pub fn set_planned_tasks(&mut self, tasks: Vec<PlannedTask>) {
+        self.next_task_id = tasks
+            .iter()
+            .map(|task| task.get_id() + 1)
+            .max()
+            .unwrap_or(0);
+        self.planned_tasks = Arc::new(tasks);
+    }
+
+    /// Plans the task described by the name and the estimated
+    /// number of pomodoros entered by the user.
+    pub fn add_planned_task(&mut self) {
+        let estimate = match self.new_task_estimate.trim().parse::<u32>() {
+            Ok(estimate) if estimate > 0 => estimate,
+            _ => return,
+        };
+        let name = match self.new_task_name.trim() {
+            "" => return,
+            name => name.to_string(),
+        };
+        let task = PlannedTask::new(self.next_task_id, name, estimate);
+        self.next_task_id += 1;
+        Arc::make_mut(&mut self.planned_tasks).push(task);
+        self.new_task_name.clear();
+        self.new_task_estimate.clear();
+        self.plan_is_modified = true;
+    }
+
+    pub fn remove_planned_task(&mut self, id: u64) {
+        Arc::make_mut(&mut self.planned_tasks).retain(|task| task.get_id() != id);
+        self.plan_is_modified = true;
+    }
+
+    /// Tags the current session with the name of the planned task.
+    pub fn focus_on_planned_task(&mut self, id: u64) {
+        if let Some(task) = self.planned_tasks.iter().find(|task| task.get_id() == id) {
+            self.session_tag = Some(task.get_name().to_string());
+            self.focus_answer.clear();
+            self.focus_prompt_is_shown = false;
+        }
+    }
+
+    /// Hands over the plan if it has changed since the last call.
+    pub fn take_modified_plan(&mut self) -> Option<Vec<PlannedTask>> {
+        if std::mem::take(&mut self.plan_is_modified) {
+            Some(self.planned_tasks.to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// Compares today's plan with the work periods completed today.
+    pub fn compare_plan(&self) -> Vec<PlanComparison> {
+        planning::compare_plan(
+            &self.planned_tasks,
+            &self.get_shown_records(),
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    pub fn is_in_meeting_mode(&self) -> bool {
+        self.meeting_mode_started_at.is_some()
+    }
+
+    /// Replaces the notifier, e.g., with a mock in the tests,
+    /// until the notification backend in the settings changes.
+    #[allow(dead_code)] // used in tests
+    pub fn set_notifier(&mut self, notifier: Rc<dyn Notifier>) {
+        self.notifier = notifier;
+        self.notifier_backend = self.settings.get_notification_backend();
+    }
+
+    /// Hands over the commands chosen with the buttons of the notifications.
+    pub fn take_notification_commands(&self) -> Vec<ControlCommand> {
+        self.notification_actions.take_commands()
+    }
+
+    fn notify(&mut self, mut notification: Notification) {
+        if !self.are_notifications_shown() {
+            return;
+        }
+        notification.appname(self.settings.get_notification_app_name());
+        if let Some(icon) = self.settings.get_notification_icon() {
+            notification.icon(icon);
+        }
+        let backend = self.settings.get_notification_backend();
+        if backend != self.notifier_backend {
+            self.notifier = notifier::make_notifier(backend, &self.notification_actions);
+            self.notifier_backend = backend;
+        }
+        match self.notifier.notify(&notification) {
+            Ok(Delivery::Sent) => {}
+            Ok(Delivery::InWindow) => self.show_banner(Banner::from(&notification)),
+            Err(error) => {
+                // E.g., there is no notification daemon running, the banner
+                // makes sure the notification is not lost.
+                eprintln!("Could not show the notification: {}", error);
+                self.show_banner(Banner::from(&notification));
+            }
+        }
+    }
+
+    fn show_banner(&mut self, banner: Banner) {
+        // The newer notification supersedes the snoozed one.
+        self.snoozed_banner = None;
+        self.banner = Some(banner);
+    }
+
+    pub fn get_banner(&self) -> Option<&Banner> {
+        self.banner.as_ref()
+    }
+
+    pub fn dismiss_banner(&mut self) {
+        self.banner = None;
+        self.silence_alarm();
+    }
+
+    pub fn start_onboarding(&mut self) {
+        self.onboarding_is_shown = true;
+    }
+
+    pub fn is_onboarding_shown(&self) -> bool {
+        self.onboarding_is_shown
+    }
+
+    pub fn is_big_display_shown(&self) -> bool {
+        self.big_display_is_shown
+    }
+
+    pub fn toggle_big_display(&mut self) {
+        self.big_display_is_shown = !self.big_display_is_shown;
+    }
+
+    pub fn request_observer_window(&mut self) {
+        self.observer_window_is_requested = true;
+    }
+
+    /// Returns whether a read-only window mirroring the timer should be
+    /// opened, only once for each of the requests.
+    pub fn take_observer_window_request(&mut self) -> bool {
+        std::mem::take(&mut self.observer_window_is_requested)
+    }
+
+    /// The state was made with the default settings, the prompt
+    /// follows the ones chosen in the wizard instead.
+    pub fn finish_onboarding(&mut self) {
+        self.request_settings_save();
+        self.onboarding_is_shown = false;
+        self.focus_prompt_is_shown = self.settings.is_focus_question_asked();
+    }
+
+    #[cfg(feature = "druid")]
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = Rc::new(theme);
+    }
+
+    #[cfg(feature = "druid")]
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_whats_new(&mut self, notes: Option<String>) {
+        self.whats_new = notes;
+    }
+
+    pub fn get_whats_new(&self) -> Option<&str> {
+        self.whats_new.as_deref()
+    }
+
+    pub fn dismiss_whats_new(&mut self) {
+        self.whats_new = None;
+    }
+
+    /// Pauses the period, it starts after [`PERIOD_SNOOZE_TIME`].
+    pub fn snooze_period(&mut self) {
+        self.pause_stopwatch();
+        self.period_snooze_left = Some(PERIOD_SNOOZE_TIME);
+    }
+
+    /// Starts the snoozed period once the snooze time passes,
+    /// notifying about it again.
+    pub fn advance_period_snooze(&mut self, elapsed: Duration) {
+        let left = match self.period_snooze_left {
+            Some(left) => left.saturating_sub(elapsed),
+            None => return,
+        };
+        if left > ZERO {
+            self.period_snooze_left = Some(left);
+            return;
+        }
+        self.start_stopwatch();
+        self.notify_period(self.current_period);
+    }
+
+    /// Hides the banner for [`BANNER_SNOOZE_TIME`].
+    pub fn snooze_banner(&mut self) {
+        if let Some(banner) = self.banner.take() {
+            self.snoozed_banner = Some(banner);
+            self.banner_snooze_left = BANNER_SNOOZE_TIME;
+        }
+    }
+
+    /// Brings the snoozed banner back once the snooze time passes.
+    pub fn advance_banner_snooze(&mut self, elapsed: Duration) {
+        if self.snoozed_banner.is_none() {
+            return;
+        }
+        self.banner_snooze_left = self.banner_snooze_left.saturating_sub(elapsed);
+        if self.banner_snooze_left == ZERO {
+            self.banner = self.snoozed_banner.take();
+        }
+    }
+
+    /// During a call the notifications are shown even when they are
+    /// disabled, as they replace the muted sounds.
+    fn are_notifications_shown(&self) -> bool {
+        (self.settings.are_system_notifications_enabled() || self.is_on_call())
+            && !self.is_in_meeting_mode()
+    }
+
+    pub fn set_microphone_in_use(&mut self, is_in_use: bool) {
+        self.microphone_is_in_use = is_in_use;
+    }
+
+    /// The overlays and the sounds are suppressed while a fullscreen
+    /// application is active, the running break is paused until it is gone
+    /// if the settings say so.
+    pub fn set_fullscreen_active(&mut self, is_active: bool) {
+        if is_active
+            && !self.fullscreen_is_active
+            && self.current_period != Period::Work
+            && !self.stopwatch_is_paused
+            && self.settings.are_breaks_paused_by_fullscreen()
+        {
+            self.pause_stopwatch();
+            self.fullscreen_paused_break = true;
+        } else if !is_active && self.fullscreen_paused_break {
+            self.start_stopwatch();
+            self.fullscreen_paused_break = false;
+        }
+        self.fullscreen_is_active = is_active;
+    }
+
+    pub fn set_fullscreen_detection_problem(&mut self, problem: Option<String>) {
+        self.fullscreen_detection_problem = problem;
+    }
+
+    /// Why the enabled fullscreen detection does not work, e.g.,
+    /// on Wayland, `None` when it works or is disabled.
+    pub fn get_fullscreen_detection_problem(&self) -> Option<&str> {
+        self.fullscreen_detection_problem
+            .as_deref()
+            .filter(|_| self.settings.is_fullscreen_detection_enabled())
+    }
+
+    /// The sounds are muted while the microphone is in use.
+    pub fn is_on_call(&self) -> bool {
+        self.settings.is_call_detection_enabled() && self.microphone_is_in_use
+    }
+
+    /// Sets when the meeting the user is in ends, `None` means there is no
+    /// meeting right now. The break postponed because of the meeting
+    /// starts as soon as it ends.
+    pub fn set_meeting_end(&mut self, ends_at: Option<u64>) {
+        self.meeting_ends_at = ends_at;
+        self.follow_calendar_meeting(ends_at);
+        if ends_at.is_none() {
+            if let Some(period) = self.deferred_break.take() {
+                self.activate_period(period);
+            }
+        }
+    }
+
+    /// Enters the meeting mode when a meeting of the calendar begins and
+    /// leaves it when the meeting ends, offering a fresh work period then.
+    /// The meeting mode entered or left by the user is left alone, but
+    /// the next meeting pauses the cycle again.
+    fn follow_calendar_meeting(&mut self, ends_at: Option<u64>) {
+        match ends_at {
+            Some(end) => {
+                if self.settings.is_cycle_paused_during_meetings()
+                    && self.calendar_meeting_end != Some(end)
+                    && !self.is_in_meeting_mode()
+                {
+                    self.toggle_meeting_mode();
+                    self.meeting_mode_follows_calendar = true;
+                }
+                self.calendar_meeting_end = Some(end);
+            }
+            None => {
+                self.calendar_meeting_end = None;
+                if self.meeting_mode_follows_calendar {
+                    self.toggle_meeting_mode();
+                    self.meeting_end_prompt_is_shown = true;
+                }
+            }
+        }
+    }
+
+    pub fn is_meeting_end_prompt_shown(&self) -> bool {
+        self.meeting_end_prompt_is_shown
+    }
+
+    pub fn start_work_after_meeting(&mut self) {
+        self.switch_to_period(Period::Work);
+        self.start_stopwatch();
+    }
+
+    pub fn dismiss_meeting_end_prompt(&mut self) {
+        self.meeting_end_prompt_is_shown = false;
+    }
+
+    pub fn get_deferred_break(&self) -> Option<Period> {
+        self.deferred_break
+    }
+
+    fn are_breaks_deferred(&self) -> bool {
+        self.meeting_ends_at.is_some() && self.settings.are_breaks_deferred_during_meetings()
+    }
+
+    /// Activates the break unless the user is in a meeting, then
+    /// the break waits for the meeting to end.
+    fn activate_break(&mut self, period: Period) {
+        if self.are_breaks_deferred() {
+            self.deferred_break = Some(period);
+            self.period_is_finished = false;
+            self.stopwatch_is_paused = true;
+        } else {
+            self.activate_period(period);
+        }
+    }
+
+    pub fn get_focus_status(&self) -> FocusStatus {
+        FocusStatus {
+            period: self.current_period,
+            is_running: !self.stopwatch_is_paused,
+            free_at: match self.current_period {
+                Period::Work | Period::Custom(_) => Some(self.calculate_end_timestamp()),
+                Period::ShortBreak | Period::LongBreak => None,
+            },
+            remaining_seconds: tomata::round_up_to_seconds(self.calculate_remaining_time())
+                .as_secs(),
+        }
+    }
+
+    /// Describes the current period for the tooltip of the tray icon,
+    /// e.g., "🍅 Work period: 12:05 left, paused".
+    pub fn make_tray_tooltip(&self) -> String {
+        let notification = match self.current_period {
+            Period::Custom(index) => self.settings.get_custom_period(index).map_or_else(
+                || Notification::from(self.current_period),
+                Notification::from,
+            ),
+            period => Notification::from(period),
+        };
+        let mut tooltip = format!(
+            "{} {}: {} left",
+            self.settings.get_period_icon(self.current_period),
+            notification.summary.trim_end_matches('.'),
+            tomata::duration_to_string(&tomata::round_up_to_seconds(
+                self.calculate_remaining_time()
+            ))
+        );
+        if self.stopwatch_is_paused {
+            tooltip.push_str(", paused");
+        }
+        if let Some(tag) = self.session_tag.as_deref() {
+            tooltip.push_str(&format!(" \u{2014} {}", tag));
+        }
+        tooltip
+    }
+
+    pub fn set_tray_icon_problem(&mut self, problem: Option<String>) {
+        self.tray_icon_problem = problem;
+    }
+
+    /// Why the enabled tray icon is not shown, shown in the settings.
+    pub fn get_tray_icon_problem(&self) -> Option<&str> {
+        self.tray_icon_problem
+            .as_deref()
+            .filter(|_| self.settings.is_tray_icon_enabled())
+    }
+
+    pub fn set_share_link(&mut self, link: Option<String>) {
+        self.share_link = link;
+    }
+
+    pub fn get_share_link(&self) -> Option<&str> {
+        self.share_link.as_deref()
+    }
+
+    /// Returns the link to the page mirroring the timer, meant for
+    /// the partner when pair programming rather than for everyone.
+    pub fn get_timer_link(&self) -> Option<String> {
+        self.share_link.as_deref().map(server::make_timer_link)
+    }
+
+    /// Asks the user to confirm blocking, as it edits the hosts file.
+    pub fn request_blocklist(&mut self) {
+        self.blocklist_prompt_is_shown = true;
+    }
+
+    pub fn is_blocklist_prompt_shown(&self) -> bool {
+        self.blocklist_prompt_is_shown
+    }
+
+    pub fn dismiss_blocklist_prompt(&mut self) {
+        self.blocklist_prompt_is_shown = false;
+    }
+
+    pub fn confirm_blocklist(&mut self) {
+        self.blocklist_prompt_is_shown = false;
+        self.settings.set_blocklist_enabled(true);
+        self.unhandled_events
+            .push(TomataEvent::BlocklistToggled(self.current_period));
+    }
+
+    pub fn disable_blocklist(&mut self) {
+        self.settings.set_blocklist_enabled(false);
+        self.unhandled_events
+            .push(TomataEvent::BlocklistToggled(self.current_period));
+    }
+
+    /// Brings the event up to date, it may be retried long after it failed.
+    /// The events of the past periods are about the current one instead,
+    /// the alert that is no longer escalated is dropped.
+    fn refresh_event(&self, event: TomataEvent) -> Option<TomataEvent> {
+        match event {
+            TomataEvent::PeriodActivated(_) => {
+                Some(TomataEvent::PeriodActivated(self.current_period))
+            }
+            TomataEvent::BlocklistToggled(_) => {
+                Some(TomataEvent::BlocklistToggled(self.current_period))
+            }
+            TomataEvent::AlertEscalated(stage) => Some(event).filter(|_| {
+                self.escalation
+                    .as_ref()
+                    .is_some_and(|escalation| escalation.get_stage() == stage)
+            }),
+            TomataEvent::ApplicationQuitting => Some(event),
+        }
+    }
+
+    /// Hands over the retried events with the names of the hooks
+    /// that are to handle them.
+    pub fn take_retried_events(&mut self) -> Vec<(TomataEvent, &'static str)> {
+        std::mem::take(&mut self.retried_events)
+    }
+
+    pub fn take_unhandled_events(&mut self) -> Vec<TomataEvent> {
+        if let Some(log) = &mut self.debug_log {
+            let now = tomata::now_timestamp();
+            let log = Arc::make_mut(log);
+            self.unhandled_events
+                .iter()
+                .for_each(|&event| log.record_event(now, event));
+        }
+        std::mem::take(&mut self.unhandled_events)
+    }
+
+    /// Starts keeping the internals for the debug window.
+    pub fn enable_debug_log(&mut self) {
+        self.debug_log.get_or_insert_with(Default::default);
+    }
+
+    pub fn is_debug_log_enabled(&self) -> bool {
+        self.debug_log.is_some()
+    }
+
+    pub fn record_debug_tick(&mut self, elapsed: Duration) {
+        if let Some(log) = &mut self.debug_log {
+            Arc::make_mut(log).record_tick(tomata::now_timestamp(), elapsed);
+        }
+    }
+
+    /// Describes the recent internals and dumps the rest of the state,
+    /// empty unless the debug log is enabled.
+    pub fn make_debug_report(&self) -> String {
+        let log = match &self.debug_log {
+            Some(log) => log,
+            None => return String::new(),
+        };
+        // The log is already described, it is left out of the dump.
+        let mut state = self.clone();
+        state.debug_log = None;
+        log.make_report(tomata::now_timestamp(), &format!("{:#?}", state))
+    }
+
+    /// Tells whether the screen should be locked because a long break
+    /// has just begun and clears the request.
+    pub fn take_screen_lock_request(&mut self) -> bool {
+        std::mem::take(&mut self.screen_lock_is_requested)
+    }
+
+    /// Returns today's records with their indices, the breaks are left out.
+    pub fn list_todays_sessions(&self) -> Vec<(usize, &SessionRecord)> {
+        let today = tomata::start_of_local_day(self.wall_clock_time);
+        self.recent_records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| {
+                record.finished_at >= today
+                    && (!record.is_aborted || self.settings.are_aborted_sessions_recorded())
+                    && !matches!(
+                        record.period,
+                        RecordedPeriod::ShortBreak | RecordedPeriod::LongBreak
+                    )
+            })
+            .collect()
+    }
+
+    /// Returns the local midnight that starts the day shown in the timeline.
+    pub fn get_timeline_day(&self) -> u64 {
+        self.timeline_day
+            .unwrap_or_else(|| tomata::start_of_local_day(self.wall_clock_time))
+    }
+
+    /// Moves the timeline a day back, as far as the recent records go.
+    pub fn show_earlier_timeline_day(&mut self) {
+        let today = tomata::start_of_local_day(self.wall_clock_time);
+        let earliest = today.saturating_sub((history::RECENT_DAYS - 1) * tomata::DAY_S);
+        let day = tomata::start_of_local_day(self.get_timeline_day().saturating_sub(1));
+        if day >= earliest.saturating_sub(tomata::HOUR_S) {
+            self.timeline_day = Some(day);
+        }
+    }
+
+    pub fn show_later_timeline_day(&mut self) {
+        let today = tomata::start_of_local_day(self.wall_clock_time);
+        let day = next_local_day(self.get_timeline_day());
+        self.timeline_day = if day < today { Some(day) } else { None };
+    }
+
+    /// Returns the blocks of the day shown in the timeline.
+    pub fn make_timeline(&self) -> Vec<TimelineBlock> {
+        let day = self.get_timeline_day();
+        history::make_timeline(&self.get_shown_records(), day, next_local_day(day))
+    }
+
+    /// Returns the address of the issue of the record, if it has one
+    /// and the address of its tracker is known.
+    pub fn make_issue_url(&self, record: &SessionRecord) -> Option<String> {
+        record
+            .issue
+            .as_ref()
+            .and_then(|issue| issue.make_url(self.settings.get_jira_url()))
+    }
+
+    /// Asks for the issue of the recent record at `index` to be opened in the browser.
+    pub fn open_session_issue(&mut self, index: usize) {
+        self.issue_url_to_open = self
+            .recent_records
+            .get(index)
+            .and_then(|record| self.make_issue_url(record));
+    }
+
+    pub fn take_issue_url_to_open(&mut self) -> Option<String> {
+        self.issue_url_to_open.take()
+    }
+
+    fn mark_period_start(&mut self) {
+        if self.period_started_at.is_none() {
+            if self.current_period == Period::Work && self.is_after_bedtime() {
+                self.warn_about_late_work("Consider leaving the rest for tomorrow.");
+            }
+            self.period_started_at = Some(tomata::now_timestamp());
+            self.screen_lock_is_requested = self.current_period == Period::LongBreak
+                && self.settings.is_screen_locked_on_long_break();
+        }
+    }
+
+    /// Moves the beginnings of the started period and the meeting back
+    /// together with the wall clock, so they keep their true durations.
+    pub fn correct_clock_jump(&mut self, backward_jump_seconds: u64) {
+        let shift = |started_at: u64| started_at.saturating_sub(backward_jump_seconds);
+        self.period_started_at = self.period_started_at.map(shift);
+        self.meeting_mode_started_at = self.meeting_mode_started_at.map(shift);
+    }
+
+    fn record_finished_period(&mut self) {
+        let record = self.make_period_record();
+        self.add_record(record);
+    }
+
+    /// Records the break or the custom period, the skipped one too,
+    /// unless it never started.
+    fn record_ended_period(&mut self) {
+        if self.period_is_finished || self.period_started_at.is_some() {
+            let mut record = self.make_period_record();
+            record.is_skipped = !self.period_is_finished;
+            self.add_record(record);
+        }
+    }
+
+    fn make_period_record(&self) -> SessionRecord {
+        let finished_at = tomata::now_timestamp();
+        let tag = match self.current_period {
+            Period::Custom(index) => self
+                .settings
+                .get_custom_period(index)
+                .map(|kind| kind.name.clone()),
+            _ => self.session_tag.clone(),
+        };
+        let started_at = self
+            .period_started_at
+            .unwrap_or(finished_at)
+            .min(finished_at);
+        SessionRecord {
+            issue: tag.as_deref().and_then(IssueReference::find),
+            application: self.application_samples.find_dominant().map(str::to_string),
+            interruptions: self.interruptions,
+            tag,
+            paused_seconds: self.paused_time.as_secs(),
+            is_long_run: self.is_long_run(),
+            ..SessionRecord::new(self.current_period.into(), started_at, finished_at)
+        }
+    }
+
+    /// Records what is in progress as the application quits, the unfinished
+    /// work period as an aborted session, and lets the integrations clean up.
+    pub fn prepare_shutdown(&mut self) {
+        if self.meeting_mode_started_at.is_some() {
+            self.toggle_meeting_mode();
+        }
+        if self.aborted_record.is_some() {
+            self.confirm_abort_reason();
+        }
+        if self.period_started_at.is_some() && !self.period_is_finished {
+            let mut record = self.make_period_record();
+            record.is_aborted = self.current_period == Period::Work;
+            self.add_record(record);
+            self.period_started_at = None;
+        }
+        self.unhandled_events.push(TomataEvent::ApplicationQuitting);
+    }
+
+    /// Records the unfinished work period as aborted and skipped, if the
+    /// abandoned sessions are shown, once the user tells why it was abandoned.
+    fn abort_work_period(&mut self) {
+        if self.period_started_at.is_none()
+            || self.current_period != Period::Work
+            || self.period_is_finished
+        {
+            return;
+        }
+        // The previous abandoned session should not wait forever.
+        self.dismiss_abort_reason_prompt();
+        let record = SessionRecord {
+            is_aborted: true,
+            is_long_run: false,
+            is_skipped: true,
+            ..self.make_period_record()
+        };
+        if self.settings.are_aborted_sessions_recorded() {
+            self.aborted_record = Some(record);
+            self.abort_reason_prompt_is_shown = true;
+        } else {
+            self.add_record(record);
+        }
+    }
+
+    /// Returns the recent records shown and counted, the aborted sessions
+    /// are left out unless the user wants to see them.
+    fn get_shown_records(&self) -> Cow<'_, [SessionRecord]> {
+        if self.settings.are_aborted_sessions_recorded() {
+            Cow::Borrowed(&self.recent_records)
+        } else {
+            Cow::Owned(
+                self.recent_records
+                    .iter()
+                    .filter(|record| !record.is_aborted)
+                    .cloned()
+                    .collect(),
+            )
+        }
+    }
+
+    pub fn is_abort_reason_prompt_shown(&self) -> bool {
+        self.abort_reason_prompt_is_shown
+    }
+
+    /// Tells whether the main window should close right away, otherwise
+    /// it is minimized, hidden to the tray, or the user is asked, see
+    /// [`TomataState::take_close_action`].
+    pub fn request_window_close(&mut self) -> bool {
+        match self.settings.get_close_action() {
+            CloseAction::Quit => return true,
+            CloseAction::Minimize => self.close_action = Some(CloseAction::Minimize),
+            CloseAction::Ask => self.close_prompt_is_shown = true,
+        }
+        false
+    }
+
+    pub fn is_close_prompt_shown(&self) -> bool {
+        self.close_prompt_is_shown
+    }
+
+    /// Applies the action chosen in the prompt, and keeps it in
+    /// the settings if the user wants the choice remembered.
+    pub fn choose_close_action(&mut self, action: CloseAction) {
+        self.close_prompt_is_shown = false;
+        if self.close_choice_is_remembered {
+            self.settings.set_close_action(action);
+            self.request_settings_save();
+        }
+        self.close_action = Some(action);
+    }
+
+    pub fn dismiss_close_prompt(&mut self) {
+        self.close_prompt_is_shown = false;
+    }
+
+    pub fn take_close_action(&mut self) -> Option<CloseAction> {
+        self.close_action.take()
+    }
+
+    pub fn confirm_abort_reason(&mut self) {
+        let reason = self.abort_reason.trim();
+        let reason = if reason.is_empty() {
+            None
+        } else {
+            Some(reason.to_string())
+        };
+        if let Some(mut record) = self.aborted_record.take() {
+            record.abort_reason = reason;
+            self.add_record(record);
+        }
+        self.abort_reason.clear();
+        self.abort_reason_prompt_is_shown = false;
+    }
+
+    /// Records the aborted session without the reason.
+    pub fn dismiss_abort_reason_prompt(&mut self) {
+        self.abort_reason.clear();
+        self.confirm_abort_reason();
+    }
+
+    /// Returns how many sessions were aborted today for each of the reasons.
+    pub fn count_abort_reasons(&self) -> Vec<(String, usize)> {
+        history::count_abort_reasons(
+            &self.get_shown_records(),
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    pub fn sum_up_today(&self) -> Totals {
+        history::sum_up(
+            &self.get_shown_records(),
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    pub fn sum_up_this_week(&self) -> Totals {
+        history::sum_up(
+            &self.get_shown_records(),
+            tomata::start_of_local_week(self.wall_clock_time),
+        )
+    }
+
+    /// Returns the wall-clock span and the focused time of today's work sessions.
+    pub fn summarize_work_time(&self) -> (Duration, Duration) {
+        history::summarize_work_time(
+            &self.get_shown_records(),
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    /// Returns the suggested work period unless the suggestions are
+    /// disabled or the suggestion was dismissed.
+    pub fn suggest_work_period(&self) -> Option<WorkPeriodSuggestion> {
+        if !self.settings.are_duration_suggestions_shown() || self.suggestion_is_dismissed {
+            return None;
+        }
+        let today = tomata::start_of_local_day(self.wall_clock_time);
+        suggestions::suggest_work_period(
+            &self.recent_records,
+            self.settings.convert_period_to_duration(Period::Work),
+            today.saturating_sub((suggestions::ANALYSED_DAYS - 1) * tomata::DAY_S),
+        )
+    }
+
+    pub fn apply_suggestion(&mut self) {
+        if let Some(suggestion) = self.suggest_work_period() {
+            self.settings
+                .set_period_duration(Period::Work, suggestion.suggested);
+        }
+        self.dismiss_suggestion();
+    }
+
+    pub fn dismiss_suggestion(&mut self) {
+        self.suggestion_is_dismissed = true;
+    }
+
+    /// The sessions running across the midnight are split into the days.
+    fn add_record(&mut self, record: SessionRecord) {
+        for record in history::split_at_midnights(record) {
+            Arc::make_mut(&mut self.recent_records).push(record.clone());
+            self.unsaved_records.push(record);
+        }
+    }
+
+    pub fn calculate_remaining_time(&self) -> Duration {
+        let period_duration = self.calculate_period_duration();
+        if period_duration <= *self.elapsed_time {
+            return ZERO;
+        }
+        period_duration - *self.elapsed_time
+    }
+
+    /// Ends the workday once the time passes its configured end,
+    /// there is no workday to end on the days off.
+    pub fn set_wall_clock_time(&mut self, timestamp: u64) {
+        let workday_was_over = self.is_workday_over();
+        self.wall_clock_time = timestamp;
+        if self.is_workday_over() && !workday_was_over && !self.is_day_off(timestamp) {
+            self.end_workday();
+        }
+    }
+
+    /// After the end of the workday the next periods do not start automatically.
+    pub fn is_workday_over(&self) -> bool {
+        self.settings.is_workday_end_enabled()
+            && tomata::time_of_local_day(self.wall_clock_time) >= self.settings.get_workday_end()
+    }
+
+    fn is_after_bedtime(&self) -> bool {
+        self.settings
+            .is_after_bedtime(tomata::time_of_local_day(self.wall_clock_time))
+    }
+
+    fn warn_about_late_work(&mut self, body: &str) {
+        self.show_banner(Banner::from(
+            &Notification::new()
+                .summary("It is past your bedtime.")
+                .body(body)
+                .finalize(),
+        ));
+    }
+
+    pub fn set_imported_days_off(&mut self, days_off: Vec<Date>) {
+        self.imported_days_off = days_off;
+    }
+
+    /// Tells whether the day of `timestamp` is a day off, either set in the
+    /// settings or read from the holiday calendar.
+    pub fn is_day_off(&self, timestamp: u64) -> bool {
+        let date = tomata::local_date(timestamp);
+        self.settings.get_days_off().contains(&date) || self.imported_days_off.contains(&date)
+    }
+
+    /// Returns for how many consecutive days, skipping the days off,
+    /// at least one pomodoro was completed.
+    pub fn calculate_streak(&self) -> usize {
+        history::calculate_streak(
+            &self.recent_records,
+            tomata::start_of_local_day(self.wall_clock_time),
+            |day| self.is_day_off(day),
+        )
+    }
+
+    /// Returns the beginning and the end of the previous week when today
+    /// is Monday, the day the summaries are written.
+    pub fn find_summarized_week(&self) -> Option<(u64, u64)> {
+        summary::find_previous_week(tomata::start_of_local_day(self.wall_clock_time))
+    }
+
+    pub fn make_weekly_summary(&self, week_start: u64, week_end: u64) -> String {
+        summary::make_weekly_summary(
+            &self.get_shown_records(),
+            week_start,
+            week_end,
+            self.calculate_streak(),
+        )
+    }
+
+    /// Returns how many pomodoros were completed today.
+    pub fn count_todays_pomodoros(&self) -> usize {
+        history::count_completed_pomodoros(
+            &self.recent_records,
+            tomata::start_of_local_day(self.wall_clock_time),
+        )
+    }
+
+    fn end_workday(&mut self) {
+        let today = tomata::start_of_local_day(self.wall_clock_time);
+        let count = history::count_completed_pomodoros(&self.recent_records, today);
+        let (_, focused_time) = self.summarize_work_time();
+        self.notify(
+            Notification::new()
+                .appname(tomata::APPLICATION_NAME)
+                .summary("Your workday is over.")
+                .body(&format!(
+                    "You have completed {} pomodoro{} today and focused for {}.",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    tomata::duration_to_string(&focused_time)
+                ))
+                .clone(),
+        );
+    }
+
+    /// Fraction of the current period that already elapsed, between 0 and 1.
+    pub fn calculate_period_progress(&self) -> f64 {
+        let period_duration = self.calculate_period_duration();
+        if period_duration == ZERO {
+            return 0.0;
+        }
+        (self.elapsed_time.as_secs_f64() / period_duration.as_secs_f64()).min(1.0)
+    }
+
+    /// Returns when the current period ends if the stopwatch runs
+    /// from now on, so a paused period keeps ending later.
+    pub fn calculate_end_timestamp(&self) -> u64 {
+        self.wall_clock_time + self.calculate_remaining_time().as_secs()
+    }
+
+    pub fn is_break_overlay_shown(&self) -> bool {
+        let is_escalated = self
+            .escalation
+            .as_ref()
+            .is_some_and(|escalation| escalation.get_stage() == EscalationStage::Overlay);
+        ((self.settings.is_break_overlay_shown() && self.current_period != Period::Work)
+            || is_escalated)
+            && !self.fullscreen_is_active
+            && !self.is_in_meeting_mode()
+    }
+
+    /// Whether the break overlay swallows the keyboard and the mouse, i.e.,
+    /// during the first seconds of the break, until it is escaped.
+    pub fn is_break_input_locked(&self) -> bool {
+        self.settings.is_break_input_lock_enabled()
+            && self.current_period != Period::Work
+            && self.is_break_overlay_shown()
+            && *self.elapsed_time < self.settings.get_break_input_lock_duration()
+            && self.break_input_lock_escapes < BREAK_INPUT_LOCK_ESCAPES
+    }
+
+    /// Returns how long the input stays locked, unless it is escaped.
+    pub fn calculate_break_input_lock_left(&self) -> Duration {
+        self.settings
+            .get_break_input_lock_duration()
+            .checked_sub(*self.elapsed_time)
+            .unwrap_or(ZERO)
+    }
+
+    /// Counts the presses of the escape key while the input is locked,
+    /// pressing it enough times in a row unlocks the input in an emergency.
+    pub fn register_locked_key_press(&mut self, is_escape: bool) {
+        if is_escape {
+            self.break_input_lock_escapes += 1;
+        } else {
+            self.break_input_lock_escapes = 0;
+        }
+    }
+
+    /// The focus banner is up while the work period runs.
+    pub fn is_focus_banner_shown(&self) -> bool {
+        self.settings.is_focus_banner_shown()
+            && self.current_period == Period::Work
+            && !self.stopwatch_is_paused
+    }
+
+    /// Returns the text of the focus banner, e.g., "Focus session — back at 14:35".
+    pub fn make_focus_banner_text(&self) -> String {
+        format!(
+            "Focus session \u{2014} back at {}",
+            tomata::timestamp_to_local_time_string(self.calculate_end_timestamp())
+        )
+    }
+
+    /// Returns the opacity of the overlay that gradually dims the window
+    /// during the final minutes of a work period, `0.0` means no dimming.
+    pub fn calculate_dimming_level(&self) -> f64 {
+        let dimming_duration = self.settings.get_dimming_duration();
+        let remaining_time = self.calculate_remaining_time();
+        if !self.settings.is_window_dimmed_before_break()
+            || self.current_period != Period::Work
+            || remaining_time >= dimming_duration
+        {
+            return 0.0;
+        }
+        MAX_DIMMING_LEVEL * (1.0 - remaining_time.as_secs_f64() / dimming_duration.as_secs_f64())
+    }
+
+    fn is_period_finishing(&self) -> bool {
+        self.calculate_remaining_time() <= PERIOD_ENDING_WARNING_TIME
+    }
+
+    /// The counters are compared with `>=`, so the long break is not missed
+    /// when the number in the settings drops below them in the middle of a cycle.
+    fn is_long_break_next(&self) -> bool {
+        if !self.settings.is_period_enabled(Period::LongBreak) {
+            return false;
+        }
+        match self.settings.get_long_break_trigger() {
+            LongBreakTrigger::AfterShortBreaks => {
+                self.short_breaks_finished >= self.settings.get_short_breaks_number()
+            }
+            LongBreakTrigger::AfterWorkPeriods => {
+                self.work_periods_finished >= self.settings.get_work_periods_before_long_break()
+            }
+        }
+    }
+}
+
+/// Returns the local midnight after `day`, the days with the change
+/// of the daylight saving time are shorter or longer than 24 hours.
+fn next_local_day(day: u64) -> u64 {
+    tomata::start_of_local_day(day + tomata::DAY_S + tomata::DAY_S / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cycle;
+    use crate::tomata::HOUR_S;
+    use std::cell::RefCell;
+    use std::error::Error;
+
+    #[derive(Debug, Default)]
+    struct MockAudioBackend {
+        volumes: RefCell<Vec<f32>>,
+        recordings_played: RefCell<usize>,
+        fails: bool,
+        has_headphones: bool,
+        devices: Vec<String>,
+        devices_played: RefCell<Vec<String>>,
+        devices_prepared: RefCell<Vec<String>>,
+        ambient_sounds: RefCell<Vec<Option<(AmbientSound, f32)>>>,
+    }
+
+    impl AudioBackend for MockAudioBackend {
+        fn play(&self, sound: &Sound, volume: f32) -> Result<(), Box<dyn Error>> {
+            if self.fails {
+                return Err("the sound device is lost".into());
+            }
+            self.volumes.borrow_mut().push(volume);
+            if let Sound::Recording(_) = sound {
+                *self.recordings_played.borrow_mut() += 1;
+            }
+            Ok(())
+        }
+
+        fn play_through_headphones(
+            &self,
+            sound: &Sound,
+            volume: f32,
+        ) -> Result<bool, Box<dyn Error>> {
+            if self.has_headphones {
+                self.play(sound, volume)?;
+            }
+            Ok(self.has_headphones)
+        }
+
+        fn play_through_device(
+            &self,
+            name: &str,
+            sound: &Sound,
+            volume: f32,
+        ) -> Result<bool, Box<dyn Error>> {
+            let is_connected = self.devices.iter().any(|device| device == name);
+            if is_connected {
+                self.play(sound, volume)?;
+                self.devices_played.borrow_mut().push(name.to_string());
+            }
+            Ok(is_connected)
+        }
+
+        fn list_output_devices(&self) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(self.devices.clone())
+        }
+
+        fn prepare_device(&self, name: &str) -> Result<bool, Box<dyn Error>> {
+            self.devices_prepared.borrow_mut().push(name.to_string());
+            Ok(self.devices.iter().any(|device| device == name))
+        }
+
+        fn set_ambient_sound(
+            &self,
+            ambient: Option<(AmbientSound, f32)>,
+        ) -> Result<(), Box<dyn Error>> {
+            self.ambient_sounds.borrow_mut().push(ambient);
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MockSpeechBackend {
+        phrases: RefCell<Vec<String>>,
+    }
+
+    impl SpeechBackend for MockSpeechBackend {
+        fn speak(&self, text: &str) -> Result<(), Box<dyn Error>> {
+            self.phrases.borrow_mut().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    fn make_test_state_with_sound(work_period: Duration) -> (TomataState, Rc<MockAudioBackend>) {
+        let mut state = make_default_test_state();
+        state.settings.work_period = Rc::new(work_period);
+        state.settings.period_ending_sound_is_enabled = true;
+        let audio = Rc::new(MockAudioBackend::default());
+        state.set_audio_backend(audio.clone());
+        (state, audio)
+    }
+
+    #[derive(Debug, Default)]
+    struct MockNotifier {
+        summaries: RefCell<Vec<String>>,
+        /// The application name and the icon of each notification.
+        senders: RefCell<Vec<(String, String)>>,
+        /// The identifiers and the labels of the buttons, one after another.
+        actions: RefCell<Vec<Vec<String>>>,
+        /// Behaves as if there was no notification daemon.
+        fails: bool,
+    }
+
+    impl Notifier for MockNotifier {
+        fn notify(&self, notification: &Notification) -> Result<Delivery, Box<dyn Error>> {
+            if self.fails {
+                return Err("no notification daemon".into());
+            }
+            self.summaries
+                .borrow_mut()
+                .push(notification.summary.clone());
+            self.senders
+                .borrow_mut()
+                .push((notification.appname.clone(), notification.icon.clone()));
+            self.actions.borrow_mut().push(notification.actions.clone());
+            Ok(Delivery::Sent)
+        }
+    }
+
+    fn make_default_test_state() -> TomataState {
+        // normal settings but with system notifications are disabled
+        let settings = Settings::new(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            2,
+            true,
+            true,
+            false, // during tests we don't want the system notifications
+            // running
+            false, // during tests we don't want the beep sound effect
+            0.1,
+        );
+        let mut state = TomataState::new(settings);
+        // Nor the sound devices when the settings are replaced.
+        state.set_audio_backend(Rc::new(MockAudioBackend::default()));
+        state
+    }
+
+    #[test]
+    fn remaining_time_is_zero_when_elapsed_time_is_bigger_than_period_time() {
+        let duration = Duration::from_secs(HOUR_S);
+        let mut state = make_default_test_state();
+        state.elapsed_time = Rc::new(duration);
+        let remaining_time = state.calculate_remaining_time();
+        assert_eq!(remaining_time, ZERO);
+    }
+
+    #[test]
+    fn calculating_period_progress() {
+        let mut state = make_default_test_state();
+        state.increase_elapsed_time(Duration::from_millis(250));
+        assert_eq!(state.calculate_period_progress(), 0.25);
+        state.increase_elapsed_time(Duration::from_secs(HOUR_S));
+        assert_eq!(state.calculate_period_progress(), 1.0);
+    }
+
+    #[test]
+    fn increasing_elapsed_time() {
+        let duration = Duration::from_secs(HOUR_S);
+        let mut state = make_default_test_state();
+        state.increase_elapsed_time(duration);
+        assert_eq!(*state.elapsed_time, duration);
+    }
+
+    #[test]
+    fn activating_work_period() {
+        let mut state = make_default_test_state();
+        state.activate_period(Period::Work);
+        assert_eq!(state.current_period, Period::Work);
+        assert_eq!(
+            state.stopwatch_is_paused,
+            !state.settings.does_next_period_start_automatically()
+        );
+        assert_eq!(*state.elapsed_time, ZERO);
+    }
+
+    #[test]
+    fn activating_short_break() {
+        let mut state = make_default_test_state();
+        state.activate_period(Period::ShortBreak);
+        assert_eq!(state.current_period, Period::ShortBreak);
+        assert_eq!(
+            state.stopwatch_is_paused,
+            !state.settings.does_next_period_start_automatically()
+        );
+        assert_eq!(*state.elapsed_time, ZERO);
+    }
+
+    #[test]
+    fn activating_long_break() {
+        let mut state = make_default_test_state();
+        state.activate_period(Period::LongBreak);
+        assert_eq!(state.current_period, Period::LongBreak);
+        assert_eq!(
+            state.stopwatch_is_paused,
+            !state.settings.does_next_period_start_automatically()
+        );
+        assert_eq!(*state.elapsed_time, ZERO);
+    }
+
+    #[test]
+    fn cycling_over_all_periods() {
+        let mut state = make_default_test_state();
+        let short_breaks_number = state.settings.get_short_breaks_number();
+        for _ in 0..short_breaks_number {
+            assert_eq!(state.current_period, Period::Work);
+            state.cycle_to_next_period();
+            assert_eq!(state.current_period, Period::ShortBreak);
+            state.cycle_to_next_period();
+        }
+        assert_eq!(state.current_period, Period::Work);
+        if state.settings.are_long_breaks_included() {
+            state.cycle_to_next_period();
+            assert_eq!(state.current_period, Period::LongBreak);
+            state.cycle_to_next_period();
+            assert_eq!(state.current_period, Period::Work);
+        }
+    }
+
+    fn finish_work_period(state: &mut TomataState) {
+        assert_eq!(state.current_period, Period::Work);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+    }
+
+    #[test]
+    fn long_break_after_work_periods_despite_skipped_short_breaks() {
+        let mut state = make_default_test_state();
+        state.settings.long_break_trigger = LongBreakTrigger::AfterWorkPeriods;
+        state.settings.work_periods_before_long_break = 3;
+        for _ in 0..2 {
+            finish_work_period(&mut state);
+            assert_eq!(state.current_period, Period::ShortBreak);
+            // The short break is skipped.
+            state.switch_to_period(Period::Work);
+        }
+        finish_work_period(&mut state);
+        assert_eq!(state.current_period, Period::LongBreak);
+        state.cycle_to_next_period();
+        finish_work_period(&mut state);
+        assert_eq!(state.current_period, Period::ShortBreak);
+    }
+
+    #[test]
+    fn skipped_short_breaks_postpone_long_break_after_short_breaks() {
+        let mut state = make_default_test_state();
+        for _ in 0..3 {
+            finish_work_period(&mut state);
+            assert_eq!(state.current_period, Period::ShortBreak);
+            state.switch_to_period(Period::Work);
+        }
+    }
+
+    #[test]
+    fn long_break_when_counter_exceeds_decreased_number() {
+        let mut state = make_default_test_state();
+        finish_work_period(&mut state);
+        state.cycle_to_next_period();
+        finish_work_period(&mut state);
+        state.cycle_to_next_period();
+        assert_eq!(state.short_breaks_finished, 2);
+        state.settings.short_breaks_number = 1;
+        finish_work_period(&mut state);
+        assert_eq!(state.current_period, Period::LongBreak);
+    }
+
+    #[test]
+    fn passing_zero_length_and_disabled_periods() {
+        let mut state = make_default_test_state();
+        state.settings.work_period = Rc::new(ZERO);
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        assert!(!state.is_period_finished());
+        assert_eq!(state.current_period, Period::ShortBreak);
+        assert!(state.take_unsaved_records().is_empty());
+
+        state.settings.work_period = Rc::new(Duration::from_secs(1));
+        state.settings.short_breaks_number = 0;
+        state.settings.long_breaks_are_included = false;
+        state.switch_to_period(Period::LongBreak);
+        assert_eq!(state.current_period, Period::Work);
+        state.switch_to_period(Period::ShortBreak);
+        assert_eq!(state.current_period, Period::Work);
+
+        // Nothing else can run, the disabled work stays.
+        state.settings.work_period = Rc::new(ZERO);
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        assert_eq!(state.current_period, Period::Work);
+        assert_eq!(*state.elapsed_time, ZERO);
+    }
+
+    #[test]
+    fn skipping_zero_length_short_breaks() {
+        let mut state = make_default_test_state();
+        state.settings.short_break_period = Rc::new(ZERO);
+        let short_breaks_number = state.settings.get_short_breaks_number();
+        for _ in 0..short_breaks_number {
+            finish_work_period(&mut state);
+            assert_eq!(state.current_period, Period::Work);
+        }
+        finish_work_period(&mut state);
+        assert_eq!(state.current_period, Period::LongBreak);
+    }
+
+    #[test]
+    fn skipping_zero_length_long_breaks() {
+        let mut state = make_default_test_state();
+        state.settings.long_break_period = Rc::new(ZERO);
+        state.settings.short_breaks_number = 0;
+        finish_work_period(&mut state);
+        assert_eq!(state.current_period, Period::Work);
+    }
+
+    #[test]
+    fn disabling_short_breaks_entirely() {
+        let mut state = make_default_test_state();
+        state.settings.short_breaks_number = 0;
+        state.settings.long_breaks_are_included = false;
+        for _ in 0..3 {
+            finish_work_period(&mut state);
+            assert_eq!(state.current_period, Period::Work);
+        }
+    }
+
+    #[test]
+    fn offering_to_end_break_early_when_user_returns() {
+        let mut state = make_default_test_state();
+        state.settings.ending_break_early_is_offered = true;
+        state.activate_period(Period::ShortBreak);
+        state.start_stopwatch();
+        state.register_user_idle_time(Duration::from_secs(1));
+        assert!(!state.is_end_break_early_prompt_shown());
+        state.register_user_idle_time(AWAY_IDLE_TIME);
+        assert!(!state.is_end_break_early_prompt_shown());
+        state.register_user_idle_time(Duration::from_secs(1));
+        assert!(state.is_end_break_early_prompt_shown());
+
+        state.end_break_early();
+        assert_eq!(state.current_period, Period::Work);
+        assert_eq!(state.short_breaks_finished, 1);
+        assert!(!state.is_stopwatch_paused());
+        assert!(!state.is_end_break_early_prompt_shown());
+    }
+
+    #[test]
+    fn not_offering_to_end_break_early_during_work() {
+        let mut state = make_default_test_state();
+        state.settings.ending_break_early_is_offered = true;
+        state.start_stopwatch();
+        state.register_user_idle_time(AWAY_IDLE_TIME);
+        state.register_user_idle_time(Duration::from_secs(1));
+        assert!(!state.is_end_break_early_prompt_shown());
+    }
+
+    #[test]
+    fn asking_focus_question_when_work_period_starts() {
+        let mut state = make_default_test_state();
+        state.settings.focus_question_is_asked = true;
+        state.activate_period(Period::ShortBreak);
+        assert!(!state.is_focus_prompt_shown());
+        state.activate_period(Period::Work);
+        assert!(state.is_focus_prompt_shown());
+        state.focus_answer = " API refactor ".to_string();
+        state.confirm_focus_answer();
+        assert!(!state.is_focus_prompt_shown());
+        assert_eq!(state.get_session_tag(), Some("API refactor"));
+    }
+
+    #[test]
+    fn recording_finished_work_period_with_its_tag() {
+        let mut state = make_default_test_state();
+        state.session_tag = Some("API refactor".to_string());
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].period, RecordedPeriod::Work);
+        assert_eq!(records[0].tag.as_deref(), Some("API refactor"));
+        assert!(state.take_unsaved_records().is_empty());
+        assert_eq!(state.get_session_tag(), None);
+    }
+
+    #[test]
+    fn recording_paused_time_of_work_period() {
+        let mut state = make_default_test_state();
+        state.increase_paused_time(Duration::from_secs(HOUR_S));
+        state.start_stopwatch();
+        state.pause_stopwatch();
+        state.increase_paused_time(Duration::from_secs(90));
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        let records = state.take_unsaved_records();
+        assert_eq!(records[0].paused_seconds, 90);
+        assert_eq!(state.paused_time, ZERO);
+    }
+
+    #[test]
+    fn not_recording_skipped_work_period() {
+        let mut state = make_default_test_state();
+        state.cycle_to_next_period();
+        assert!(state.take_unsaved_records().is_empty());
+    }
+
+    #[test]
+    fn adding_and_removing_countdowns() {
+        let mut state = make_default_test_state();
+        state.new_countdown_name = "Tea".to_string();
+        state.new_countdown_duration = "3".to_string();
+        state.add_countdown();
+        state.new_countdown_duration = "not a number".to_string();
+        state.add_countdown();
+        state.new_countdown_duration = "1h30".to_string();
+        state.add_countdown();
+        assert_eq!(state.countdowns.len(), 2);
+        assert_eq!(state.countdowns[0].get_name(), "Tea");
+        assert_eq!(
+            state.countdowns[0].get_remaining_time(),
+            Duration::from_secs(3 * MINUTE_S)
+        );
+        assert_eq!(state.countdowns[1].get_name(), "Timer");
+        assert_eq!(
+            state.countdowns[1].get_remaining_time(),
+            Duration::from_secs(90 * MINUTE_S)
+        );
+
+        state.advance_countdowns(Duration::from_secs(MINUTE_S));
+        assert_eq!(
+            state.countdowns[0].get_remaining_time(),
+            Duration::from_secs(2 * MINUTE_S)
+        );
+        let id = state.countdowns[0].get_id();
+        state.remove_countdown(id);
+        assert_eq!(state.countdowns.len(), 1);
+        assert_eq!(state.countdowns[0].get_name(), "Timer");
+    }
+
+    #[test]
+    fn deferring_break_until_meeting_ends() {
+        let mut state = make_default_test_state();
+        state.settings.breaks_are_deferred_during_meetings = true;
+        state.set_meeting_end(Some(u64::MAX));
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::Work);
+        assert_eq!(state.get_deferred_break(), Some(Period::ShortBreak));
+        assert!(!state.is_period_finished());
+        assert_eq!(state.take_unsaved_records().len(), 1);
+
+        state.set_meeting_end(None);
+        assert_eq!(state.current_period, Period::ShortBreak);
+        assert_eq!(state.get_deferred_break(), None);
+    }
+
+    #[test]
+    fn starting_deferred_break_without_waiting_for_meeting_end() {
+        let mut state = make_default_test_state();
+        state.settings.breaks_are_deferred_during_meetings = true;
+        state.set_meeting_end(Some(u64::MAX));
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        state.start_stopwatch();
+        assert_eq!(state.current_period, Period::ShortBreak);
+        assert!(!state.is_stopwatch_paused());
+    }
+
+    #[test]
+    fn requesting_screen_lock_when_long_break_begins() {
+        let mut state = make_default_test_state();
+        state.settings.screen_is_locked_on_long_break = true;
+        state.activate_period(Period::ShortBreak);
+        assert!(!state.take_screen_lock_request());
+        state.activate_period(Period::LongBreak);
+        assert!(state.take_screen_lock_request());
+        assert!(!state.take_screen_lock_request());
+        // Resuming the paused break does not lock the screen again.
+        state.pause_stopwatch();
+        state.start_stopwatch();
+        assert!(!state.take_screen_lock_request());
+    }
+
+    #[test]
+    fn dimming_window_before_break() {
+        let mut state = make_default_test_state();
+        state.settings.work_period = Rc::new(Duration::from_secs(10));
+        state.settings.dimming_duration = Rc::new(Duration::from_secs(4));
+        state.increase_elapsed_time(Duration::from_secs(8));
+        assert_eq!(state.calculate_dimming_level(), 0.0);
+
+        state.settings.window_is_dimmed_before_break = true;
+        assert_eq!(state.calculate_dimming_level(), MAX_DIMMING_LEVEL / 2.0);
+        state.activate_period(Period::ShortBreak);
+        assert_eq!(state.calculate_dimming_level(), 0.0);
+    }
+
+    #[test]
+    fn suppressing_overlay_and_pausing_break_in_fullscreen() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(1));
+        state.settings.break_overlay_is_shown = true;
+        state.settings.fullscreen_pauses_breaks = true;
+        state.activate_period(Period::ShortBreak);
+        state.start_stopwatch();
+        state.set_fullscreen_active(true);
+        assert!(!state.is_break_overlay_shown());
+        assert!(state.stopwatch_is_paused);
+        state.play_sound(SoundEvent::Escalation);
+        assert!(audio.volumes.borrow().is_empty());
+        state.set_fullscreen_active(false);
+        assert!(state.is_break_overlay_shown());
+        assert!(!state.stopwatch_is_paused);
+    }
+
+    #[test]
+    fn navigating_timeline_days() {
+        let mut state = make_default_test_state();
+        let today = tomata::start_of_local_day(state.wall_clock_time);
+        assert_eq!(state.get_timeline_day(), today);
+        state.show_later_timeline_day();
+        assert_eq!(state.get_timeline_day(), today);
+        for _ in 0..history::RECENT_DAYS + 5 {
+            state.show_earlier_timeline_day();
+        }
+        assert!(state.get_timeline_day() < today - 58 * tomata::DAY_S);
+        assert!(state.get_timeline_day() > today - 60 * tomata::DAY_S);
+        for _ in 0..history::RECENT_DAYS {
+            state.show_later_timeline_day();
+        }
+        assert_eq!(state.timeline_day, None);
+    }
+
+    #[test]
+    fn showing_focus_banner_during_running_work() {
+        let mut state = make_default_test_state();
+        state.settings.focus_banner_is_shown = true;
+        assert!(!state.is_focus_banner_shown());
+        state.start_stopwatch();
+        assert!(state.is_focus_banner_shown());
+        state.activate_period(Period::ShortBreak);
+        assert!(!state.is_focus_banner_shown());
+    }
+
+    #[test]
+    fn showing_break_overlay_only_during_breaks() {
+        let mut state = make_default_test_state();
+        state.settings.break_overlay_is_shown = true;
+        assert!(!state.is_break_overlay_shown());
+        state.activate_period(Period::ShortBreak);
+        assert!(state.is_break_overlay_shown());
+        state.activate_period(Period::LongBreak);
+        assert!(state.is_break_overlay_shown());
+    }
+
+    #[test]
+    fn emitting_event_when_period_is_activated() {
+        let mut state = make_default_test_state();
+        state.activate_period(Period::ShortBreak);
+        state.cycle_to_next_period();
+        assert_eq!(
+            state.take_unhandled_events(),
+            vec![
+                TomataEvent::PeriodActivated(Period::ShortBreak),
+                TomataEvent::PeriodActivated(Period::Work)
+            ]
+        );
+        assert!(state.take_unhandled_events().is_empty());
+    }
+
+    #[test]
+    fn enabling_blocklist_after_confirmation() {
+        let mut state = make_default_test_state();
+        state.request_blocklist();
+        assert!(state.is_blocklist_prompt_shown());
+        assert!(!state.settings.is_blocklist_enabled());
+        state.confirm_blocklist();
+        assert!(!state.is_blocklist_prompt_shown());
+        assert!(state.settings.is_blocklist_enabled());
+        assert_eq!(
+            state.take_unhandled_events(),
+            vec![TomataEvent::BlocklistToggled(Period::Work)]
+        );
+    }
+
+    #[test]
+    fn notifying_through_injected_notifier() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.activate_period(Period::ShortBreak);
+        state.toggle_meeting_mode();
+        state.activate_period(Period::Work);
+        assert_eq!(
+            *notifier.summaries.borrow(),
+            vec!["Short break.".to_string()]
+        );
+    }
+
+    #[test]
+    fn mentioning_finished_pomodoro_in_break_notification() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        finish_work_period(&mut state);
+        state.switch_to_period(Period::Work);
+        state.session_tag = Some("API refactor".to_string());
+        finish_work_period(&mut state);
+        assert_eq!(
+            *notifier.summaries.borrow(),
+            vec![
+                "Short break \u{2014} finished the work period, 1st pomodoro today".to_string(),
+                "Work period.".to_string(),
+                "Short break \u{2014} finished 'API refactor', 2nd pomodoro today".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn showing_and_dismissing_banner() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.settings.notification_backend = NotificationBackend::Banner;
+        state.activate_period(Period::ShortBreak);
+        assert_eq!(
+            state.get_banner().map(Banner::get_summary),
+            Some("Short break.")
+        );
+        state.dismiss_banner();
+        assert!(state.get_banner().is_none());
+    }
+
+    #[test]
+    fn overriding_notification_app_name_and_icon() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.activate_period(Period::ShortBreak);
+        state.settings.notification_app_name = "Focus".to_string();
+        state.settings.notification_icon = "/opt/tomato.png".to_string();
+        state.activate_period(Period::Work);
+        assert_eq!(
+            *notifier.senders.borrow(),
+            vec![
+                ("tomata".to_string(), String::new()),
+                ("Focus".to_string(), "/opt/tomato.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn showing_banner_when_notification_fails() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.set_notifier(Rc::new(MockNotifier {
+            fails: true,
+            ..Default::default()
+        }));
+        state.activate_period(Period::LongBreak);
+        assert_eq!(
+            state.get_banner().map(Banner::get_summary),
+            Some("Long break.")
+        );
+    }
+
+    #[test]
+    fn snoozing_banner() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.settings.notification_backend = NotificationBackend::Banner;
+        state.activate_period(Period::ShortBreak);
+        state.snooze_banner();
+        assert!(state.get_banner().is_none());
+        state.advance_banner_snooze(BANNER_SNOOZE_TIME - Duration::from_secs(1));
+        assert!(state.get_banner().is_none());
+        state.advance_banner_snooze(Duration::from_secs(1));
+        assert_eq!(
+            state.get_banner().map(Banner::get_summary),
+            Some("Short break.")
+        );
+    }
+
+    #[test]
+    fn toggling_meeting_mode() {
+        let mut state = make_default_test_state();
+        state.start_stopwatch();
+        state.toggle_meeting_mode();
+        assert!(state.is_in_meeting_mode());
+        assert!(state.is_stopwatch_paused());
+        assert!(!state.are_notifications_shown());
+
+        state.toggle_meeting_mode();
+        assert!(!state.is_in_meeting_mode());
+        assert!(!state.is_stopwatch_paused());
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].period, RecordedPeriod::Meeting);
+    }
+
+    #[test]
+    fn meeting_mode_keeps_paused_stopwatch_paused() {
+        let mut state = make_default_test_state();
+        state.pause_stopwatch();
+        state.toggle_meeting_mode();
+        state.toggle_meeting_mode();
+        assert!(state.is_stopwatch_paused());
+    }
+
+    #[test]
+    fn comparing_plan_with_completed_work() {
+        let mut state = make_default_test_state();
+        state.new_task_name = "API refactor".to_string();
+        state.new_task_estimate = "1".to_string();
+        state.add_planned_task();
+        assert!(state.take_modified_plan().is_some());
+        assert!(state.take_modified_plan().is_none());
+
+        state.focus_on_planned_task(0);
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        let comparison = &state.compare_plan()[0];
+        assert_eq!(comparison.name, "API refactor");
+        assert_eq!((comparison.planned, comparison.completed), (1, 1));
+    }
+
+    #[test]
+    fn not_planning_task_without_estimate() {
+        let mut state = make_default_test_state();
+        state.new_task_name = "API refactor".to_string();
+        state.new_task_estimate = "many".to_string();
+        state.add_planned_task();
+        assert!(state.planned_tasks.is_empty());
+        assert!(state.take_modified_plan().is_none());
+    }
+
+    #[test]
+    fn recording_aborted_work_period_with_reason() {
+        let mut state = make_default_test_state();
+        state.settings.aborted_sessions_are_recorded = true;
+        state.start_stopwatch();
+        state.switch_to_period(Period::ShortBreak);
+        assert!(state.is_abort_reason_prompt_shown());
+        assert!(state.take_unsaved_records().is_empty());
+
+        state.abort_reason = "phone call".to_string();
+        state.confirm_abort_reason();
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_aborted);
+        assert_eq!(records[0].abort_reason.as_deref(), Some("phone call"));
+        assert_eq!(
+            state.count_abort_reasons(),
+            vec![("phone call".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn not_recording_aborted_work_period_that_did_not_start() {
+        let mut state = make_default_test_state();
+        state.settings.aborted_sessions_are_recorded = true;
+        state.reset_stopwatch();
+        assert!(!state.is_abort_reason_prompt_shown());
+    }
+
+    #[test]
+    fn calculating_end_timestamp() {
+        let mut state = make_default_test_state();
+        state.set_wall_clock_time(100);
+        assert_eq!(state.calculate_end_timestamp(), 101);
+        // The end moves along with the clock while the stopwatch is paused.
+        state.set_wall_clock_time(200);
+        assert_eq!(state.calculate_end_timestamp(), 201);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        assert_eq!(state.calculate_end_timestamp(), 200);
+    }
+
+    #[test]
+    fn checking_if_period_is_finishing() {
+        let state = make_default_test_state();
+        assert!(state.is_period_finishing());
+    }
+
+    #[test]
+    fn beeping_during_final_seconds() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(8));
+        for _ in 0..8 {
+            state.increase_elapsed_time(Duration::from_secs(1));
+        }
+        assert_eq!(*audio.volumes.borrow(), vec![0.1; 5]);
+    }
+
+    #[test]
+    fn beeping_once_per_second_with_short_ticks() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(8));
+        for _ in 0..80 {
+            state.increase_elapsed_time(Duration::from_millis(100));
+        }
+        assert_eq!(audio.volumes.borrow().len(), 5);
+    }
+
+    #[test]
+    fn warning_about_overtime_louder_every_time() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(1));
+        state.settings.overtime_reminder_is_enabled = true;
+        state.settings.overtime_threshold = Rc::new(Duration::from_secs(2));
+        state.settings.next_period_starts_automatically = false;
+        finish_work_period(&mut state);
+        audio.volumes.borrow_mut().clear();
+        for _ in 0..5 {
+            state.advance_overtime(Duration::from_secs(1));
+        }
+        let volumes = audio.volumes.borrow().clone();
+        assert_eq!(volumes.len(), 2);
+        assert!(volumes[1] > volumes[0]);
+        state.start_stopwatch();
+        state.advance_overtime(Duration::from_secs(60));
+        assert_eq!(audio.volumes.borrow().len(), 2);
+    }
+
+    #[test]
+    fn flagging_long_run_of_skipped_breaks() {
+        let mut state = make_default_test_state();
+        state.settings.long_run_warning_is_enabled = true;
+        state.settings.long_run_limit = Rc::new(Duration::from_secs(2));
+        for _ in 0..3 {
+            finish_work_period(&mut state);
+            state.switch_to_period(Period::Work);
+        }
+        let records = state.take_unsaved_records();
+        let flags: Vec<bool> = records.iter().map(|record| record.is_long_run).collect();
+        assert_eq!(flags, vec![false, false, true]);
+        assert_eq!(state.count_long_runs(), 1);
+
+        // Finishing a break starts counting from the beginning.
+        finish_work_period(&mut state);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        finish_work_period(&mut state);
+        let records = state.take_unsaved_records();
+        assert_eq!(records[1].period, RecordedPeriod::ShortBreak);
+        assert!(!records[2].is_long_run);
+    }
+
+    #[test]
+    fn announcing_remaining_time_every_interval() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.settings.remaining_time_announcements_are_enabled = true;
+        state
+            .settings
+            .set_period_duration(Period::Work, Duration::from_secs(40 * MINUTE_S));
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.session_tag = Some("API refactor".to_string());
+        for _ in 0..(26 * MINUTE_S) {
+            state.increase_elapsed_time(Duration::from_secs(1));
+        }
+        assert_eq!(
+            *notifier.summaries.borrow(),
+            vec![
+                "30:00 left \u{2014} API refactor".to_string(),
+                "15:00 left \u{2014} API refactor".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn pausing_work_on_distraction_workspace() {
+        let mut state = make_default_test_state();
+        state.settings.distraction_workspaces = "chat, 9".to_string();
+        state.start_stopwatch();
+        state.set_focused_workspace("1: code");
+        state.set_focused_workspace("chat");
+        assert!(state.stopwatch_is_paused);
+        assert!(state.is_watching_workspaces());
+        state.set_focused_workspace("9");
+        state.set_focused_workspace("1: code");
+        assert!(!state.stopwatch_is_paused);
+
+        state.settings.distraction_workspace_pauses = false;
+        state.set_focused_workspace("chat");
+        assert!(!state.stopwatch_is_paused);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert_eq!(state.take_unsaved_records()[0].interruptions, 2);
+    }
+
+    #[test]
+    fn applying_work_period_suggestion() {
+        let mut state = make_default_test_state();
+        state.settings.duration_suggestions_are_shown = true;
+        state
+            .settings
+            .set_period_duration(Period::Work, Duration::from_secs(HOUR_S));
+        let now = state.wall_clock_time;
+        let records = (0..10)
+            .map(|i| SessionRecord {
+                is_aborted: i % 2 == 0,
+                ..SessionRecord::new(RecordedPeriod::Work, now - 30 * MINUTE_S, now)
+            })
+            .collect();
+        state.set_recent_records(records);
+        assert!(state.suggest_work_period().is_some());
+        state.apply_suggestion();
+        assert_eq!(
+            state.settings.convert_period_to_duration(Period::Work),
+            Duration::from_secs(30 * MINUTE_S)
+        );
+        assert_eq!(state.suggest_work_period(), None);
+    }
+
+    #[test]
+    fn routing_sounds() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(1));
+        state.settings.period_ending_sound_is_enabled = false;
+        state.increase_elapsed_time(Duration::from_secs(1));
+        assert!(audio.volumes.borrow().is_empty());
+        // Trying out the volume works even with the period ending sound disabled.
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(audio.volumes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn ending_workday() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.settings.workday_end_is_enabled = true;
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        let workday_end = tomata::start_of_local_day(state.wall_clock_time)
+            + state.settings.get_workday_end().as_secs();
+        state.set_wall_clock_time(workday_end - 1);
+        finish_work_period(&mut state);
+        assert!(!state.is_stopwatch_paused());
+        state.set_wall_clock_time(workday_end);
+        state.set_wall_clock_time(workday_end + 1);
+        assert!(state.is_workday_over());
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert_eq!(state.get_current_period(), Period::Work);
+        assert!(state.is_stopwatch_paused());
+        assert_eq!(
+            *notifier.summaries.borrow(),
+            vec![
+                "Short break \u{2014} finished the work period, 1st pomodoro today".to_string(),
+                "Your workday is over.".to_string(),
+                "Work period.".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_starting_periods_automatically_on_days_off() {
+        let mut state = make_default_test_state();
+        state.settings.workday_end_is_enabled = true;
+        state.settings.workday_end = Rc::new(ZERO);
+        let today = tomata::local_date(state.wall_clock_time);
+        state.set_imported_days_off(vec![today]);
+        assert!(state.is_day_off(state.wall_clock_time));
+        finish_work_period(&mut state);
+        assert!(state.is_stopwatch_paused());
+        state.set_imported_days_off(Vec::new());
+        state.settings.workday_end_is_enabled = false;
+        state.switch_to_period(Period::Work);
+        assert!(!state.is_stopwatch_paused());
+    }
+
+    #[test]
+    fn cycling_through_custom_period() {
+        let mut state = make_default_test_state();
+        state.settings = serde_json::from_str(
+            r#"{"custom_periods": [{"name": "Email", "duration": {"secs": 1, "nanos": 0}}],
+                "next_period_starts_automatically": true,
+                "system_notifications_are_enabled": true}"#,
+        )
+        .unwrap();
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.switch_to_period(Period::Custom(0));
+        state.increase_elapsed_time(Duration::from_secs(1));
+        assert!(state.is_period_finished());
+        state.cycle_to_next_period();
+        assert_eq!(state.get_current_period(), Period::Work);
+        assert_eq!(state.recent_records[0].period, RecordedPeriod::Custom);
+        assert_eq!(state.recent_records[0].tag.as_deref(), Some("Email"));
+        assert_eq!(
+            *notifier.summaries.borrow(),
+            vec!["Email.".to_string(), "Work period.".to_string()]
+        );
+    }
+
+    #[test]
+    fn opening_issue_of_session() {
+        let mut state = make_default_test_state();
+        state.settings.jira_url = "https://example.atlassian.net".to_string();
+        state.session_tag = Some("Fix login PROJ-123".to_string());
+        state.start_stopwatch();
+        finish_work_period(&mut state);
+        let sessions = state.list_todays_sessions();
+        assert_eq!(sessions.len(), 1);
+        let (index, record) = sessions[0];
+        assert_eq!(record.issue.as_ref().unwrap().to_string(), "PROJ-123");
+        state.open_session_issue(index);
+        assert_eq!(
+            state.take_issue_url_to_open().as_deref(),
+            Some("https://example.atlassian.net/browse/PROJ-123")
+        );
+        assert_eq!(state.take_issue_url_to_open(), None);
+    }
+
+    #[test]
+    fn recording_dominant_application() {
+        let mut state = make_default_test_state();
+        state.register_active_application("firefox");
+        state.settings.application_sampling_is_enabled = true;
+        state.start_stopwatch();
+        for application in ["Code", "firefox", "Code"].iter() {
+            state.register_active_application(application);
+        }
+        finish_work_period(&mut state);
+        state.register_active_application("firefox");
+        assert_eq!(state.recent_records[0].application.as_deref(), Some("Code"));
+        Arc::make_mut(&mut state.recent_records)[0].started_at -= 60;
+        state.set_wall_clock_time(state.recent_records[0].finished_at);
+        assert_eq!(
+            state.summarize_applications(),
+            vec![("Code".to_string(), 1.0)]
+        );
+    }
+
+    #[test]
+    fn muting_sounds_during_call() {
+        let mut state = make_default_test_state();
+        let audio = Rc::new(MockAudioBackend::default());
+        state.set_audio_backend(audio.clone());
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.settings.period_ending_sound_is_enabled = true;
+        state.set_microphone_in_use(true);
+        state.play_sound(SoundEvent::PeriodEnding);
+        assert_eq!(audio.volumes.borrow().len(), 1);
+
+        state.settings.call_detection_is_enabled = true;
+        assert!(state.is_on_call());
+        state.play_sound(SoundEvent::PeriodEnding);
+        state.play_sound(SoundEvent::OvertimeWarning(1));
+        assert_eq!(audio.volumes.borrow().len(), 1);
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(audio.volumes.borrow().len(), 2);
+        finish_work_period(&mut state);
+        assert_eq!(notifier.summaries.borrow().len(), 1);
+    }
+
+    #[test]
+    fn escalating_unacknowledged_alert() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(1));
+        state.settings.next_period_starts_automatically = false;
+        state.settings.escalation_is_enabled = true;
+        state.start_stopwatch();
+        finish_work_period(&mut state);
+        state.take_unhandled_events();
+        let volumes_count = audio.volumes.borrow().len();
+        state.advance_escalation(Duration::from_secs(30));
+        assert_eq!(audio.volumes.borrow().len(), volumes_count + 1);
+        assert!(!state.is_break_overlay_shown());
+        state.advance_escalation(Duration::from_secs(60));
+        assert!(state.is_break_overlay_shown());
+        assert_eq!(
+            state.take_unhandled_events(),
+            vec![
+                TomataEvent::AlertEscalated(EscalationStage::Sound),
+                TomataEvent::AlertEscalated(EscalationStage::Overlay)
+            ]
+        );
+        state.start_stopwatch();
+        assert!(!state.is_break_overlay_shown());
+
+        state.skip_period();
+        state.pause_stopwatch();
+        finish_work_period(&mut state);
+        assert!(state.escalation.is_some());
+        state.toggle_meeting_mode();
+        let volumes_count = audio.volumes.borrow().len();
+        state.advance_escalation(Duration::from_secs(90));
+        assert_eq!(audio.volumes.borrow().len(), volumes_count);
+        assert!(!state.is_break_overlay_shown());
+    }
+
+    #[test]
+    fn finishing_onboarding() {
+        let mut state = TomataState::default();
+        state.start_onboarding();
+        state.settings.work_period = Rc::new(Duration::from_secs(50 * MINUTE_S));
+        state.finish_onboarding();
+        assert!(!state.is_onboarding_shown());
+        assert_eq!(
+            state.calculate_remaining_time(),
+            Duration::from_secs(50 * MINUTE_S)
+        );
+    }
+
+    #[test]
+    fn queueing_and_retrying_errors() {
+        let mut state = make_default_test_state();
+        state.set_audio_backend(Rc::new(MockAudioBackend {
+            fails: true,
+            ..Default::default()
+        }));
+        state.play_sound(SoundEvent::VolumeCheck);
+        state.play_sound(SoundEvent::VolumeCheck);
+        state.report_error(
+            "Could not save the settings: disk full".to_string(),
+            Some(RetryAction::SaveSettings),
+        );
+        assert_eq!(state.errors.len(), 2);
+
+        state.retry_error();
+        assert_eq!(
+            state.get_error().map(RecoverableError::get_message),
+            Some("Could not save the settings: disk full")
+        );
+        assert_eq!(state.errors.len(), 2);
+
+        state.retry_error();
+        assert!(state.take_settings_save_request());
+        state.dismiss_error();
+        assert_eq!(state.get_error(), None);
+
+        state.report_error(
+            "Could not save the session history: disk full".to_string(),
+            Some(RetryAction::FlushWrites),
+        );
+        assert!(!state.take_writes_flush_request());
+        state.retry_error();
+        assert!(state.take_writes_flush_request());
+    }
+
+    #[test]
+    fn closing_window() {
+        let mut state = make_default_test_state();
+        assert!(state.request_window_close());
+
+        state.settings.close_action = CloseAction::Ask;
+        assert!(!state.request_window_close());
+        assert!(state.is_close_prompt_shown());
+        state.choose_close_action(CloseAction::Minimize);
+        assert_eq!(state.take_close_action(), Some(CloseAction::Minimize));
+        assert_eq!(state.get_settings().get_close_action(), CloseAction::Ask);
+
+        state.request_window_close();
+        state.close_choice_is_remembered = true;
+        state.choose_close_action(CloseAction::Quit);
+        assert_eq!(state.take_close_action(), Some(CloseAction::Quit));
+        assert!(state.take_settings_save_request());
+        assert!(state.request_window_close());
+    }
+
+    #[test]
+    fn reading_remaining_time() {
+        let mut state = make_default_test_state();
+        state.settings.work_period = Rc::new(Duration::from_secs(25 * MINUTE_S));
+        let speech = Rc::new(MockSpeechBackend::default());
+        state.set_speech_backend(speech.clone());
+        state.read_remaining_time();
+        assert_eq!(
+            speech.phrases.borrow().as_slice(),
+            ["twenty-five minutes remaining"]
+        );
+    }
+
+    #[test]
+    fn calibrating_sounds() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(1));
+        state.settings.beep_volume = 0.5;
+        state.settings.escalation_volume = 0.5;
+        state.toggle_meeting_mode();
+        state.start_sound_calibration();
+        for _ in 0..CALIBRATED_SOUNDS.len() {
+            state.advance_sound_calibration(CALIBRATION_SOUND_INTERVAL);
+        }
+        assert_eq!(state.get_calibrated_sound(), Some(SoundEvent::Escalation));
+        assert_eq!(audio.volumes.borrow().as_slice(), [0.5, 0.5, 1.0, 0.25]);
+        state.advance_sound_calibration(CALIBRATION_SOUND_INTERVAL);
+        assert_eq!(state.get_calibrated_sound(), None);
+    }
+
+    #[test]
+    fn switching_to_invalid_user() {
+        let mut state = make_default_test_state();
+        state.set_users(
+            Some("alice".to_string()),
+            vec!["alice".to_string(), "bob".to_string()],
+        );
+        assert_eq!(state.get_other_users(), vec!["bob"]);
+        state.user_to_switch_to = "alice".to_string();
+        state.switch_user();
+        assert!(state.get_error().is_none());
+        state.user_to_switch_to = "../bob".to_string();
+        state.switch_user();
+        assert!(!state.take_quit_request());
+        assert!(state.get_error().is_some());
+    }
+
+    #[test]
+    fn correcting_backward_clock_jump() {
+        let mut state = make_default_test_state();
+        state.period_started_at = Some(tomata::now_timestamp() + HOUR_S);
+        state.correct_clock_jump(HOUR_S + 10);
+        assert!(state.period_started_at.unwrap() <= tomata::now_timestamp());
+        state.period_started_at = Some(tomata::now_timestamp() + HOUR_S);
+        state.record_finished_period();
+        let record = &state.recent_records[0];
+        assert_eq!(record.started_at, record.finished_at);
+    }
+
+    #[test]
+    fn recording_unfinished_period_on_shutdown() {
+        let mut state = make_default_test_state();
+        state.start_stopwatch();
+        state.prepare_shutdown();
+        assert_eq!(state.recent_records.len(), 1);
+        assert!(state.recent_records[0].is_aborted);
+        assert_eq!(
+            state.take_unhandled_events().last(),
+            Some(&TomataEvent::ApplicationQuitting)
+        );
+        state.prepare_shutdown();
+        assert_eq!(state.recent_records.len(), 1);
+    }
+
+    #[test]
+    fn toggling_and_skipping_periods() {
+        let mut state = make_default_test_state();
+        state.toggle_stopwatch();
+        assert!(!state.is_stopwatch_paused());
+        state.toggle_stopwatch();
+        assert!(state.is_stopwatch_paused());
+        state.skip_period();
+        assert_eq!(state.get_current_period(), Period::ShortBreak);
+        assert!(state.recent_records[0].is_aborted);
+        assert!(state.recent_records[0].is_skipped);
+    }
+
+    #[test]
+    fn recording_aborted_work_period_without_showing_it() {
+        let mut state = make_default_test_state();
+        state.start_stopwatch();
+        state.reset_stopwatch();
+        assert!(!state.is_abort_reason_prompt_shown());
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_aborted && records[0].is_skipped);
+        assert!(state.count_abort_reasons().is_empty());
+        assert!(state.list_todays_sessions().is_empty());
+
+        state.settings.aborted_sessions_are_recorded = true;
+        assert_eq!(
+            state.count_abort_reasons(),
+            vec![("no reason".to_string(), 1)]
+        );
+        assert_eq!(state.list_todays_sessions().len(), 1);
+    }
+
+    #[test]
+    fn locking_break_input_until_escaped() {
+        let mut state = make_default_test_state();
+        state.settings.break_overlay_is_shown = true;
+        state.settings.break_input_lock_is_enabled = true;
+        state.activate_period(Period::ShortBreak);
+        assert!(state.is_break_input_locked());
+        for _ in 0..BREAK_INPUT_LOCK_ESCAPES - 1 {
+            state.register_locked_key_press(true);
+        }
+        state.register_locked_key_press(false);
+        state.register_locked_key_press(true);
+        assert!(state.is_break_input_locked());
+        for _ in 0..BREAK_INPUT_LOCK_ESCAPES - 1 {
+            state.register_locked_key_press(true);
+        }
+        assert!(!state.is_break_input_locked());
+        state.activate_period(Period::Work);
+        state.activate_period(Period::LongBreak);
+        assert!(state.is_break_input_locked());
+        state.elapsed_time = Rc::new(Duration::from_secs(30));
+        assert!(!state.is_break_input_locked());
+    }
+
+    #[test]
+    fn showing_banner_instead_of_sound_without_headphones() {
+        let mut state = make_default_test_state();
+        state.settings.sounds_are_headphones_only = true;
+        let audio = Rc::new(MockAudioBackend::default());
+        state.set_audio_backend(audio.clone());
+        state.play_sound(SoundEvent::Escalation);
+        assert!(audio.volumes.borrow().is_empty());
+        assert_eq!(
+            state.get_banner().map(Banner::get_summary),
+            Some("The period is over.")
+        );
+        state.dismiss_banner();
+        let audio = Rc::new(MockAudioBackend {
+            has_headphones: true,
+            ..Default::default()
+        });
+        state.set_audio_backend(audio.clone());
+        state.play_sound(SoundEvent::Escalation);
+        assert_eq!(audio.volumes.borrow().len(), 1);
+        assert!(state.get_banner().is_none());
+    }
+
+    #[test]
+    fn starting_session_requested_by_uri() {
+        let mut state = make_default_test_state();
+        state.switch_to_period(Period::ShortBreak);
+        state.start_session(
+            Some(Duration::from_secs(10 * MINUTE_S)),
+            Some("PROJ-1".to_string()),
+        );
+        assert_eq!(state.get_current_period(), Period::Work);
+        assert!(!state.stopwatch_is_paused);
+        assert_eq!(state.get_session_tag(), Some("PROJ-1"));
+        assert_eq!(
+            state.calculate_remaining_time(),
+            Duration::from_secs(10 * MINUTE_S)
+        );
+        state.cycle_to_next_period();
+        state.cycle_to_next_period();
+        assert_eq!(
+            state.calculate_remaining_time(),
+            state.settings.convert_period_to_duration(Period::Work)
+        );
+    }
+
+    #[test]
+    fn making_tray_tooltip() {
+        let mut state = make_default_test_state();
+        state.session_tag = Some("PROJ-1".to_string());
+        let tooltip = state.make_tray_tooltip();
+        assert!(tooltip.ends_with(" Work period: 00:00:01 left, paused \u{2014} PROJ-1"));
+        state.switch_to_period(Period::ShortBreak);
+        state.start_stopwatch();
+        assert!(state
+            .make_tray_tooltip()
+            .ends_with(" Short break: 00:00:01 left"));
+    }
+
+    #[test]
+    fn keeping_debug_log_only_when_enabled() {
+        let mut state = make_default_test_state();
+        state.record_debug_tick(Duration::from_secs(1));
+        state.report_error("Could not save the settings".to_string(), None);
+        assert_eq!(state.make_debug_report(), "");
+        state.enable_debug_log();
+        state.record_debug_tick(Duration::from_secs(1));
+        state.switch_to_period(Period::LongBreak);
+        state.take_unhandled_events();
+        state.report_error("Could not save the plan".to_string(), None);
+        let report = state.make_debug_report();
+        let (log, dump) = report.split_once("\nState:\n").unwrap();
+        assert!(log.starts_with("Ticks: 1, "));
+        assert!(log.contains("PeriodActivated(LongBreak)"));
+        assert!(log.contains("Could not save the plan"));
+        assert!(!log.contains("Could not save the settings"));
+        assert!(dump.contains("debug_log: None"));
+    }
+
+    #[test]
+    fn recording_finished_and_skipped_periods() {
+        let mut state = make_default_test_state();
+        state.settings.aborted_sessions_are_recorded = true;
+        finish_work_period(&mut state);
+        state.start_stopwatch();
+        state.skip_period();
+        state.start_stopwatch();
+        state.skip_period();
+        state.dismiss_abort_reason_prompt();
+        let records = state.take_unsaved_records();
+        let periods: Vec<(RecordedPeriod, bool, bool)> = records
+            .iter()
+            .map(|record| (record.period, record.is_skipped, record.is_aborted))
+            .collect();
+        assert_eq!(
+            periods,
+            vec![
+                (RecordedPeriod::Work, false, false),
+                (RecordedPeriod::ShortBreak, true, false),
+                (RecordedPeriod::Work, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn picking_and_completing_recent_tags() {
+        let mut state = make_default_test_state();
+        for tag in ["Email", "API refactor", "Api docs"].iter() {
+            state.focus_answer = tag.to_string();
+            state.confirm_focus_answer();
+            finish_work_period(&mut state);
+            state.switch_to_period(Period::Work);
+        }
+        assert_eq!(
+            state.list_recent_tags(),
+            vec!["Api docs", "API refactor", "Email"]
+        );
+        state.focus_answer = "api r".to_string();
+        assert!(state.complete_focus_answer());
+        assert_eq!(state.focus_answer, "API refactor");
+        assert!(!state.complete_focus_answer());
+        state.focus_answer = "Meeting".to_string();
+        assert!(!state.complete_focus_answer());
+        state.focus_prompt_is_shown = true;
+        state.pick_recent_tag(2);
+        assert_eq!(state.get_session_tag(), Some("Email"));
+        assert!(!state.is_focus_prompt_shown());
+    }
+
+    #[test]
+    fn pausing_cycle_during_calendar_meeting() {
+        let mut state = make_default_test_state();
+        state.settings.cycle_is_paused_during_meetings = true;
+        state.start_stopwatch();
+        state.set_meeting_end(Some(u64::MAX));
+        assert!(state.is_in_meeting_mode());
+        assert!(state.is_stopwatch_paused());
+
+        state.set_meeting_end(None);
+        assert!(!state.is_in_meeting_mode());
+        assert!(state.is_meeting_end_prompt_shown());
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].period, RecordedPeriod::Meeting);
+
+        state.start_work_after_meeting();
+        assert!(!state.is_meeting_end_prompt_shown());
+        assert_eq!(state.get_current_period(), Period::Work);
+        assert!(!state.is_stopwatch_paused());
+    }
+
+    #[test]
+    fn leaving_calendar_meeting_early() {
+        let mut state = make_default_test_state();
+        state.settings.cycle_is_paused_during_meetings = true;
+        state.set_meeting_end(Some(u64::MAX));
+        state.toggle_meeting_mode();
+        state.set_meeting_end(Some(u64::MAX));
+        assert!(!state.is_in_meeting_mode());
+        state.set_meeting_end(None);
+        assert!(!state.is_meeting_end_prompt_shown());
+        state.set_meeting_end(Some(u64::MAX - 1));
+        assert!(state.is_in_meeting_mode());
+    }
+
+    #[test]
+    fn playing_alarm_sound_file() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(8));
+        let path = std::env::temp_dir().join(format!("tomata-alarm-{}.wav", std::process::id()));
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        wav.extend_from_slice(&[0x40, 0x1F, 0, 0, 0x80, 0x3E, 0, 0, 2, 0, 16, 0]);
+        wav.extend_from_slice(b"data\x02\0\0\0\0\x40");
+        std::fs::write(&path, wav).unwrap();
+        state.choose_sound_file(SoundFileSlot::WorkEnding);
+        state.set_chosen_sound_file(&path);
+        for _ in 0..8 {
+            state.increase_elapsed_time(Duration::from_secs(1));
+        }
+        assert_eq!(*audio.volumes.borrow(), vec![0.1]);
+        assert_eq!(*audio.recordings_played.borrow(), 1);
+        std::fs::remove_file(&path).unwrap();
+        // Only the end of the work period has its own sound.
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(*audio.recordings_played.borrow(), 1);
+
+        state
+            .settings
+            .set_sound_file(SoundFileSlot::Alarm, &path.with_extension("mp3"));
+        state.play_sound(SoundEvent::VolumeCheck);
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(audio.volumes.borrow().len(), 4);
+        assert_eq!(*audio.recordings_played.borrow(), 1);
+        assert!(state.get_error().is_some());
+        assert!(state.get_sound_file_error(SoundFileSlot::Alarm).is_some());
+        assert!(state
+            .get_sound_file_error(SoundFileSlot::WorkEnding)
+            .is_none());
+    }
+
+    #[test]
+    fn choosing_output_device() {
+        let mut state = make_default_test_state();
+        let audio = Rc::new(MockAudioBackend {
+            devices: vec!["Speakers".to_string(), "Headset".to_string()],
+            ..Default::default()
+        });
+        state.set_audio_backend(audio.clone());
+        state.refresh_output_devices();
+        assert_eq!(
+            state.get_output_device_choices(),
+            vec!["Speakers", "Headset"]
+        );
+
+        state.settings.output_device = "Speakers".to_string();
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(*audio.devices_played.borrow(), vec!["Speakers"]);
+
+        // The alarm falls back to the default device.
+        state.settings.output_device = "USB Speakers".to_string();
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(audio.devices_played.borrow().len(), 1);
+        assert_eq!(audio.volumes.borrow().len(), 2);
+        assert_eq!(
+            state.get_output_device_choices(),
+            vec!["Speakers", "Headset", "USB Speakers"]
+        );
+    }
+
+    #[test]
+    fn asking_about_time_away_from_work() {
+        let mut state = make_default_test_state();
+        state.settings.away_time_is_reviewed = true;
+        state.start_stopwatch();
+        state.period_started_at = Some(tomata::now_timestamp());
+        state.increase_elapsed_time(Duration::from_secs(20 * MINUTE_S));
+        state.register_user_idle_time(AWAY_IDLE_TIME);
+        state.register_user_idle_time(Duration::from_secs(1));
+        assert!(!state.is_away_time_prompt_shown());
+
+        let away_time = Duration::from_secs(12 * MINUTE_S);
+        state.register_user_idle_time(AWAY_FROM_WORK_IDLE_TIME);
+        state.register_user_idle_time(away_time);
+        assert!(state.is_watching_away_time());
+        state.register_user_idle_time(Duration::from_secs(1));
+        assert!(state.is_away_time_prompt_shown());
+        assert!(!state.is_watching_away_time());
+        assert_eq!(state.get_away_from_work_time(), away_time);
+
+        state.classify_away_time(AwayTimeClassification::Break);
+        assert!(!state.is_away_time_prompt_shown());
+        assert_eq!(*state.elapsed_time, Duration::from_secs(8 * MINUTE_S));
+        assert_eq!(state.paused_time, away_time);
+        let records = state.take_unsaved_records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].period, RecordedPeriod::ShortBreak);
+        assert_eq!(records[0].calculate_span(), away_time);
+    }
+
+    #[test]
+    fn keeping_or_discarding_time_away_from_work() {
+        let mut state = make_default_test_state();
+        state.settings.away_time_is_reviewed = true;
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(20 * MINUTE_S));
+        state.register_user_idle_time(Duration::from_secs(6 * MINUTE_S));
+        state.register_user_idle_time(Duration::from_secs(1));
+        state.classify_away_time(AwayTimeClassification::Work);
+        assert_eq!(*state.elapsed_time, Duration::from_secs(20 * MINUTE_S));
+
+        state.register_user_idle_time(Duration::from_secs(6 * MINUTE_S));
+        state.register_user_idle_time(Duration::from_secs(1));
+        state.classify_away_time(AwayTimeClassification::Discarded);
+        assert_eq!(*state.elapsed_time, Duration::from_secs(14 * MINUTE_S));
+        assert!(state.take_unsaved_records().is_empty());
+    }
+
+    #[test]
+    fn following_cycle_sequence() {
+        let mut state = make_default_test_state();
+        state.settings.add_cycle_block(Period::Work);
+        state.settings.add_cycle_block(Period::LongBreak);
+        state.settings.add_cycle_block(Period::Custom(0));
+        state
+            .settings
+            .shift_cycle_block_duration(0, Duration::from_secs(25 * MINUTE_S), true);
+        // The work period of a second is lengthened to the shortest block.
+        assert_eq!(
+            state.calculate_remaining_time(),
+            cycle::MIN_BLOCK_DURATION + Duration::from_secs(25 * MINUTE_S)
+        );
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::LongBreak);
+        assert_eq!(state.get_cycle_position(), 1);
+        assert!(state.calculate_remaining_time() > ZERO);
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::Custom(0));
+        // The custom period is not defined, its block still runs.
+        assert_eq!(state.calculate_remaining_time(), cycle::MIN_BLOCK_DURATION);
+        state.start_stopwatch();
+        state.increase_elapsed_time(cycle::MIN_BLOCK_DURATION);
+        assert!(state.period_is_finished);
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::Work);
+        assert_eq!(state.get_cycle_position(), 0);
+
+        // Switching by hand leaves the default duration.
+        state.switch_to_period(Period::ShortBreak);
+        assert_eq!(
+            state.calculate_remaining_time(),
+            state
+                .settings
+                .convert_period_to_duration(Period::ShortBreak)
+        );
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::LongBreak);
+
+        state.cycle_profile_name = " Deep work ".to_string();
+        state.save_cycle_profile();
+        assert_eq!(state.settings.get_cycle_profiles()[0].name, "Deep work");
+        assert!(state.cycle_profile_name.is_empty());
+    }
+
+    #[test]
+    fn speaking_period_changes() {
+        let mut state = make_default_test_state();
+        let speech = Rc::new(MockSpeechBackend::default());
+        state.set_speech_backend(speech.clone());
+        state.settings.period_changes_are_spoken = true;
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        // Skipped by the user, the change is not announced.
+        state.cycle_to_next_period();
+        assert_eq!(
+            speech.phrases.borrow().as_slice(),
+            ["Work period finished, take a short break"]
+        );
+    }
+
+    #[test]
+    fn holding_work_until_user_rests() {
+        let mut state = make_default_test_state();
+        state.settings.next_period_starts_automatically = true;
+        state.settings.rest_is_enforced = true;
+        state.settings.minimum_rest = Rc::new(Duration::from_secs(3));
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::ShortBreak);
+        // Back at the computer during the break, it is not rest.
+        state.register_user_idle_time(Duration::from_secs(1));
+        state.advance_rest(Duration::from_secs(10));
+        state.register_user_idle_time(AWAY_IDLE_TIME);
+        state.advance_rest(Duration::from_secs(1));
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert_eq!(state.current_period, Period::Work);
+        assert!(state.is_stopwatch_paused());
+        assert_eq!(state.get_missing_rest_time(), Some(Duration::from_secs(2)));
+        state.advance_rest(Duration::from_secs(2));
+        assert!(!state.is_stopwatch_paused());
+        assert_eq!(state.get_missing_rest_time(), None);
+        assert!(!state.is_measuring_rest());
+    }
+
+    #[test]
+    fn looping_ambient_sound_during_work() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(60));
+        state.settings.ambient_sound = Some(AmbientSound::Rain);
+        state.settings.ambient_volume = 0.5;
+        state.start_stopwatch();
+        state.update_ambient_sound();
+        state.update_ambient_sound();
+        state.pause_stopwatch();
+        state.update_ambient_sound();
+        state.start_stopwatch();
+        state.switch_to_period(Period::ShortBreak);
+        state.start_stopwatch();
+        state.update_ambient_sound();
+        assert_eq!(
+            *audio.ambient_sounds.borrow(),
+            vec![Some((AmbientSound::Rain, 0.5)), None]
+        );
+    }
+
+    #[test]
+    fn planning_day() {
+        let mut state = make_default_test_state();
+        state.show_day_plan_prompt();
+        assert!(state.is_day_plan_prompt_shown());
+        state.settings.daily_goal = 6;
+        state.day_plan_project = " API refactor ".to_string();
+        state.confirm_day_plan();
+        assert!(!state.is_day_plan_prompt_shown());
+        assert_eq!(state.get_session_tag(), Some("API refactor"));
+        let plan = state.take_modified_plan().unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].get_name(), "API refactor");
+        assert_eq!(plan[0].get_estimate(), 6);
+        assert_eq!(
+            state.take_modified_primary_project(),
+            Some(Some("API refactor".to_string()))
+        );
+        assert_eq!(state.take_modified_primary_project(), None);
+        state.switch_to_period(Period::ShortBreak);
+        assert_eq!(state.get_session_tag(), None);
+        state.switch_to_period(Period::Work);
+        assert_eq!(state.get_session_tag(), Some("API refactor"));
+        // Planned already, it is not added again.
+        state.show_day_plan_prompt();
+        state.confirm_day_plan();
+        assert_eq!(state.take_modified_plan(), None);
+    }
+
+    #[test]
+    fn warning_about_work_after_bedtime() {
+        let mut state = make_default_test_state();
+        state.settings.next_period_starts_automatically = true;
+        state.settings.bedtime_warning_is_enabled = true;
+        let bedtime = tomata::start_of_local_day(state.wall_clock_time)
+            + state.settings.get_bedtime().as_secs();
+        state.set_wall_clock_time(bedtime - 1);
+        state.start_stopwatch();
+        assert_eq!(state.get_banner(), None);
+        finish_work_period(&mut state);
+        state.set_wall_clock_time(bedtime);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert!(!state.is_stopwatch_paused());
+        assert_eq!(
+            state.get_banner().map(Banner::get_summary),
+            Some("It is past your bedtime.")
+        );
+        state.dismiss_banner();
+        state.settings.late_work_is_held = true;
+        finish_work_period(&mut state);
+        state.increase_elapsed_time(Duration::from_secs(1));
+        state.cycle_to_next_period();
+        assert!(state.is_stopwatch_paused());
+        assert_eq!(
+            state.get_banner().map(Banner::get_body),
+            Some("The next work period does not start by itself.")
+        );
+    }
+
+    #[test]
+    fn repeating_alarm_until_acknowledged() {
+        let (mut state, audio) = make_test_state_with_sound(Duration::from_secs(60));
+        state.settings.next_period_starts_automatically = false;
+        state.settings.alarm_is_repeated = true;
+        state.settings.alarm_repeat_interval = Rc::new(Duration::from_secs(10));
+        state.start_stopwatch();
+        state.increase_elapsed_time(Duration::from_secs(60));
+        let beeps = audio.volumes.borrow().len();
+        state.cycle_to_next_period();
+        assert!(state.is_alarm_repeating());
+        for _ in 0..25 {
+            state.advance_alarm_repeat(Duration::from_secs(1));
+        }
+        assert_eq!(audio.volumes.borrow().len(), beeps + 2);
+        state.start_stopwatch();
+        assert!(!state.is_alarm_repeating());
+        state.advance_alarm_repeat(Duration::from_secs(10));
+        assert_eq!(audio.volumes.borrow().len(), beeps + 2);
+        // Skipped by the user, the period does not raise the alarm.
+        state.skip_period();
+        assert!(!state.is_alarm_repeating());
+
+        state.increase_elapsed_time(Duration::from_secs(60));
+        state.cycle_to_next_period();
+        assert!(state.is_alarm_repeating());
+        state.toggle_meeting_mode();
+        assert!(!state.is_alarm_repeating());
+        state.advance_alarm_repeat(Duration::from_secs(10));
+        assert_eq!(audio.volumes.borrow().len(), beeps + 2);
+    }
+
+    #[test]
+    fn acting_on_period_notification() {
+        let mut state = make_default_test_state();
+        state.settings.system_notifications_are_enabled = true;
+        state.settings.next_period_starts_automatically = false;
+        let notifier = Rc::new(MockNotifier::default());
+        state.set_notifier(notifier.clone());
+        state.activate_period(Period::ShortBreak);
+        assert_eq!(
+            notifier.actions.borrow()[0],
+            [
+                "resume",
+                "Start break",
+                "skip-period",
+                "Skip",
+                "snooze",
+                "Snooze 5 min"
+            ]
+        );
+        state.snooze_period();
+        state.advance_period_snooze(PERIOD_SNOOZE_TIME - Duration::from_secs(1));
+        assert!(state.is_stopwatch_paused());
+        state.advance_period_snooze(Duration::from_secs(1));
+        assert!(!state.is_stopwatch_paused());
+        assert_eq!(
+            notifier.actions.borrow()[1],
+            ["skip-period", "Skip", "snooze", "Snooze 5 min"]
+        );
+        state.advance_period_snooze(PERIOD_SNOOZE_TIME);
+        assert_eq!(notifier.summaries.borrow().len(), 2);
+    }
+
+    #[test]
+    fn preparing_sounds_ahead() {
+        let mut state = make_default_test_state();
+        let audio = Rc::new(MockAudioBackend {
+            devices: vec!["Headset".to_string()],
+            ..Default::default()
+        });
+        state.set_audio_backend(audio.clone());
+        state.settings.output_device = "Headset".to_string();
+        state.settings.alarm_sound_file = "missing.wav".to_string();
+        state.prepare_sounds();
+        assert_eq!(*audio.devices_prepared.borrow(), vec!["Headset"]);
+        // The file is read once, ahead of the alarm.
+        assert_eq!(state.errors.len(), 1);
+        assert!(state
+            .alarm_sounds
+            .iter()
+            .any(|(path, _)| path.ends_with("missing.wav")));
+        state.play_sound(SoundEvent::VolumeCheck);
+        assert_eq!(state.errors.len(), 1);
+        assert_eq!(*audio.devices_played.borrow(), vec!["Headset"]);
+    }
+
+    #[test]
+    fn retrying_failed_event_hook() {
+        let mut state = make_default_test_state();
+        state.report_error(
+            "Could not handle PeriodActivated(Work) in blocklist: denied".to_string(),
+            Some(RetryAction::HandleEvent(
+                TomataEvent::PeriodActivated(Period::Work),
+                "blocklist",
+            )),
+        );
+        state.report_error(
+            "Could not handle AlertEscalated(Sound) in ntfy: offline".to_string(),
+            Some(RetryAction::HandleEvent(
+                TomataEvent::AlertEscalated(EscalationStage::Sound),
+                "ntfy",
+            )),
+        );
+        state.activate_period(Period::ShortBreak);
+        state.take_unhandled_events();
+        // The retry is about the break that is on now, not the past work.
+        state.retry_error();
+        state.retry_error();
+        assert_eq!(
+            state.take_retried_events(),
+            vec![(
+                TomataEvent::PeriodActivated(Period::ShortBreak),
+                "blocklist"
+            )]
+        );
+        assert!(state.take_unhandled_events().is_empty());
     }
 }
@@ -0,0 +1,117 @@
+//! Suggestions of the period durations based on the history, e.g.,
+//! shorter work periods when the work sessions are often abandoned
+//! at roughly the same point.
+use std::time::Duration;
+
+use crate::history::{RecordedPeriod, SessionRecord};
+use crate::tomata::MINUTE_S;
+
+/// How many days of the history are analysed.
+pub const ANALYSED_DAYS: u64 = 14;
+/// Fewer work sessions than this say too little about the habits.
+const MIN_SESSIONS: usize = 10;
+/// The suggestion is made once at least this part of the sessions is abandoned.
+const MIN_ABANDON_RATE: f64 = 0.3;
+/// The suggested durations are multiples of this step.
+const SUGGESTION_STEP_S: u64 = 5 * MINUTE_S;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkPeriodSuggestion {
+    pub current: Duration,
+    /// Part of the work sessions that were abandoned, between 0 and 1.
+    pub abandon_rate: f64,
+    /// The median focused time of the abandoned sessions.
+    pub abandoned_after: Duration,
+    pub suggested: Duration,
+}
+
+impl WorkPeriodSuggestion {
+    pub fn describe(&self) -> String {
+        format!(
+            "You abandon {:.0}% of {}-min sessions after {} min \u{2014} try {}-minute work periods.",
+            self.abandon_rate * 100.0,
+            self.current.as_secs() / MINUTE_S,
+            self.abandoned_after.as_secs() / MINUTE_S,
+            self.suggested.as_secs() / MINUTE_S,
+        )
+    }
+}
+
+/// Suggests a shorter work period when many of the work sessions finished
+/// since `since` (seconds since the UNIX epoch) were abandoned before
+/// the end of the `current` one.
+pub fn suggest_work_period(
+    records: &[SessionRecord],
+    current: Duration,
+    since: u64,
+) -> Option<WorkPeriodSuggestion> {
+    let sessions: Vec<&SessionRecord> = records
+        .iter()
+        .filter(|record| {
+            record.period == RecordedPeriod::Work
+                && !record.is_continued
+                && record.finished_at >= since
+        })
+        .collect();
+    if sessions.len() < MIN_SESSIONS {
+        return None;
+    }
+    let mut abandoned_after: Vec<Duration> = sessions
+        .iter()
+        .filter(|record| record.is_aborted)
+        .map(|record| record.calculate_focused_time())
+        .filter(|focused_time| *focused_time < current)
+        .collect();
+    let abandon_rate = abandoned_after.len() as f64 / sessions.len() as f64;
+    if abandon_rate < MIN_ABANDON_RATE {
+        return None;
+    }
+    abandoned_after.sort();
+    let median = abandoned_after[abandoned_after.len() / 2];
+    let suggested = Duration::from_secs(
+        (median.as_secs() / SUGGESTION_STEP_S * SUGGESTION_STEP_S).max(SUGGESTION_STEP_S),
+    );
+    if suggested >= current {
+        return None;
+    }
+    Some(WorkPeriodSuggestion {
+        current,
+        abandon_rate,
+        abandoned_after: median,
+        suggested,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_work_record(minutes: u64, is_aborted: bool) -> SessionRecord {
+        SessionRecord {
+            is_aborted,
+            ..SessionRecord::new(RecordedPeriod::Work, 1000, 1000 + minutes * MINUTE_S)
+        }
+    }
+
+    #[test]
+    fn suggesting_shorter_work_period() {
+        let mut records: Vec<SessionRecord> = (0..6).map(|_| make_work_record(25, false)).collect();
+        records.extend([19, 21, 22, 23].iter().map(|&m| make_work_record(m, true)));
+        let current = Duration::from_secs(25 * MINUTE_S);
+        let suggestion = suggest_work_period(&records, current, 0).unwrap();
+        assert_eq!(suggestion.suggested, Duration::from_secs(20 * MINUTE_S));
+        assert_eq!(
+            suggestion.describe(),
+            "You abandon 40% of 25-min sessions after 22 min \u{2014} try 20-minute work periods."
+        );
+    }
+
+    #[test]
+    fn not_suggesting_with_few_abandoned_sessions() {
+        let mut records: Vec<SessionRecord> = (0..9).map(|_| make_work_record(25, false)).collect();
+        records.push(make_work_record(10, true));
+        let current = Duration::from_secs(25 * MINUTE_S);
+        assert_eq!(suggest_work_period(&records, current, 0), None);
+        assert_eq!(suggest_work_period(&records[..5], current, 0), None);
+    }
+}
@@ -0,0 +1,204 @@
+//! User themes, i.e., the colors and the sizes of the widgets read from
+//! `theme.toml` next to the settings, e.g.:
+//!
+//! ```toml
+//! # Solarized dark
+//! window_background = "#002B36"
+//! text_color = "#93A1A1"
+//! text_size = 15
+//! ```
+//!
+//! Only the flat `key = value` subset of TOML is supported. The file is
+//! read again whenever it changes, so the themes can be tried out live.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use druid::{theme, Color, Env};
+
+pub const THEME_FILE: &str = "theme.toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    window_background: Option<Color>,
+    text_color: Option<Color>,
+    button_light: Option<Color>,
+    button_dark: Option<Color>,
+    accent: Option<Color>,
+    border: Option<Color>,
+    text_size: Option<f64>,
+    large_text_size: Option<f64>,
+    border_radius: Option<f64>,
+}
+
+impl Theme {
+    /// Parses the theme, every malformed line or unknown key is reported
+    /// as in `line 3: unknown key 'colour'`.
+    pub fn parse(toml: &str) -> Result<Theme, Vec<String>> {
+        let mut theme = Theme::default();
+        let mut errors = Vec::new();
+        for (number, line) in toml.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            let line = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None if line.is_empty() => continue,
+                None => {
+                    errors.push(format!("line {}: expected `key = value`", number + 1));
+                    continue;
+                }
+            };
+            if let Err(error) = theme.set(line.0, line.1) {
+                errors.push(format!("line {}: {}", number + 1, error));
+            }
+        }
+        if errors.is_empty() {
+            Ok(theme)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let color = match key {
+            "window_background" => &mut self.window_background,
+            "text_color" => &mut self.text_color,
+            "button_light" => &mut self.button_light,
+            "button_dark" => &mut self.button_dark,
+            "accent" => &mut self.accent,
+            "border" => &mut self.border,
+            _ => {
+                let size = match key {
+                    "text_size" => &mut self.text_size,
+                    "large_text_size" => &mut self.large_text_size,
+                    "border_radius" => &mut self.border_radius,
+                    _ => return Err(format!("unknown key '{}'", key)),
+                };
+                *size = Some(parse_size(value)?);
+                return Ok(());
+            }
+        };
+        *color = Some(parse_color(value)?);
+        Ok(())
+    }
+
+    /// Overrides the values of the default theme set in the file.
+    pub fn apply(&self, env: &mut Env) {
+        let colors = [
+            (theme::WINDOW_BACKGROUND_COLOR, &self.window_background),
+            (theme::LABEL_COLOR, &self.text_color),
+            (theme::BUTTON_LIGHT, &self.button_light),
+            (theme::BUTTON_DARK, &self.button_dark),
+            (theme::PRIMARY_LIGHT, &self.accent),
+            (theme::PRIMARY_DARK, &self.accent),
+            (theme::BORDER_DARK, &self.border),
+        ];
+        for (key, color) in colors.iter() {
+            if let Some(color) = color {
+                env.set(key.clone(), color.clone());
+            }
+        }
+        let sizes = [
+            (theme::TEXT_SIZE_NORMAL, self.text_size),
+            (theme::TEXT_SIZE_LARGE, self.large_text_size),
+            (theme::BUTTON_BORDER_RADIUS, self.border_radius),
+        ];
+        for (key, size) in sizes.iter() {
+            if let Some(size) = size {
+                env.set(key.clone(), *size);
+            }
+        }
+    }
+}
+
+/// Cuts the comment off the line, the `#` inside the quotes belongs to the value.
+fn strip_comment(line: &str) -> &str {
+    let mut is_quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => is_quoted = !is_quoted,
+            '#' if !is_quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// The colors are quoted hex strings, e.g., `"#002B36"`.
+fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted color, found {}", value))?;
+    Color::from_hex_str(hex).map_err(|_| format!("invalid color {}", value))
+}
+
+fn parse_size(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(size) if size > 0.0 => Ok(size),
+        _ => Err(format!("expected a positive number, found {}", value)),
+    }
+}
+
+/// Keeps the theme read from the file until the file is modified.
+#[derive(Debug, Default)]
+pub struct ThemeFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ThemeFile {
+    /// Returns the theme when the file at `path` changed since the last
+    /// call, the default theme when the file is removed.
+    pub fn reload(&mut self, path: &Path) -> Option<Result<Theme, Vec<String>>> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        let modified = match modified {
+            Ok(modified) => Some(modified),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Some(Err(vec![error.to_string()])),
+        };
+        if self.path == path && self.modified == modified {
+            return None;
+        }
+        self.path = path.to_path_buf();
+        self.modified = modified;
+        if modified.is_none() {
+            return Some(Ok(Theme::default()));
+        }
+        Some(
+            fs::read_to_string(path)
+                .map_err(|error| vec![error.to_string()])
+                .and_then(|toml| Theme::parse(&toml)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_theme() {
+        let theme = Theme::parse(
+            "# Solarized dark\n\nwindow_background = \"#002B36\" # base03\ntext_size = 15\n",
+        )
+        .unwrap();
+        assert_eq!(theme.window_background, Some(Color::rgb8(0x00, 0x2B, 0x36)));
+        assert_eq!(theme.text_size, Some(15.0));
+        assert_eq!(theme.text_color, None);
+    }
+
+    #[test]
+    fn parsing_invalid_theme() {
+        let errors =
+            Theme::parse("colour = \"#FFFFFF\"\ntext_color = FFFFFF\ntext_size\n").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "line 1: unknown key 'colour'",
+                "line 2: expected a quoted color, found FFFFFF",
+                "line 3: expected `key = value`",
+            ]
+        );
+    }
+}
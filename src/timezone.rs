@@ -0,0 +1,491 @@
+//! The offset of the local time from UTC. The `time` crate cannot tell it
+//! on Unix, so the time zone is read from the `TZ` variable, or the TZif
+//! file it names, or `/etc/localtime`, once and kept for the whole run.
+use std::convert::TryInto;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use once_cell::sync::OnceCell;
+#[cfg(not(unix))]
+use time::OffsetDateTime;
+use time::UtcOffset;
+
+use crate::tomata::{DAY_S, HOUR_S, MINUTE_S};
+
+/// Where the TZif files of the named time zones are installed.
+const ZONE_INFO_DIRECTORY: &str = "/usr/share/zoneinfo";
+const LOCAL_TIME_FILE: &str = "/etc/localtime";
+
+static LOCAL_TIME_ZONE: OnceCell<TimeZone> = OnceCell::new();
+
+/// The offsets of a time zone, from the transitions of its TZif file and
+/// the rule of the daylight saving time following them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    /// The offset before the first transition, in seconds east of UTC.
+    initial_offset: i32,
+    /// The moments the offset changes at and the offsets from then on.
+    transitions: Vec<(i64, i32)>,
+    rule: Option<TimeZoneRule>,
+}
+
+/// The daylight saving time rule of a POSIX `TZ` value,
+/// e.g., `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct TimeZoneRule {
+    standard_offset: i32,
+    daylight_saving: Option<DaylightSaving>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct DaylightSaving {
+    offset: i32,
+    /// The local standard time it starts at.
+    start: (RuleDay, i64),
+    /// The local daylight saving time it ends at.
+    end: (RuleDay, i64),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum RuleDay {
+    /// `Jn`, the day of the year counted from 1 without February 29.
+    Julian(u16),
+    /// `n`, the day of the year counted from 0.
+    Zero(u16),
+    /// `Mm.w.d`, the `d` weekday (0 is Sunday) of the `w` week of
+    /// the month `m`, the fifth week is the last one.
+    Month(u8, u8, u8),
+}
+
+impl TimeZone {
+    /// The time zone of the same offset all the time, e.g., UTC.
+    pub fn fixed(offset: i32) -> TimeZone {
+        TimeZone {
+            initial_offset: offset,
+            transitions: Vec::new(),
+            rule: None,
+        }
+    }
+
+    /// Reads the value of the `TZ` variable, e.g., `CET-1CEST,M3.5.0,M10.5.0/3`.
+    pub fn from_posix(value: &str) -> Option<TimeZone> {
+        let rule = parse_rule(value)?;
+        Some(TimeZone {
+            initial_offset: rule.standard_offset,
+            transitions: Vec::new(),
+            rule: Some(rule),
+        })
+    }
+
+    /// Reads the compiled time zone file, e.g., `/etc/localtime`.
+    pub fn from_tzif(bytes: &[u8]) -> Option<TimeZone> {
+        if bytes.get(0..4)? != b"TZif" {
+            return None;
+        }
+        let version = *bytes.get(4)?;
+        let (header, data) = read_tzif_header(bytes, 4)?;
+        if version < b'2' {
+            return read_tzif_data(&bytes[data..], &header, 4).map(|(zone, _)| zone);
+        }
+        // The version 2 repeats the data with 64 bits times, then the rule.
+        let second = &bytes[data + header.data_length(4)..];
+        if second.get(0..4)? != b"TZif" {
+            return None;
+        }
+        let (header, data) = read_tzif_header(second, 8)?;
+        let (mut zone, end) = read_tzif_data(&second[data..], &header, 8)?;
+        let footer = String::from_utf8_lossy(&second[data + end..]);
+        let footer = footer.trim_matches('\n');
+        if !footer.is_empty() {
+            zone.rule = parse_rule(footer);
+        }
+        Some(zone)
+    }
+
+    /// Returns the offset from UTC in seconds at `timestamp`.
+    pub fn offset_at(&self, timestamp: i64) -> i32 {
+        let index = self
+            .transitions
+            .partition_point(|(changed_at, _)| *changed_at <= timestamp);
+        match (index, self.rule) {
+            (0, _) if !self.transitions.is_empty() => self.initial_offset,
+            (index, Some(rule)) if index == self.transitions.len() => rule.offset_at(timestamp),
+            (0, None) => self.initial_offset,
+            (index, _) => self.transitions[index - 1].1,
+        }
+    }
+}
+
+/// The counts of the records in a TZif file.
+struct TzifHeader {
+    utc_indicators: usize,
+    standard_indicators: usize,
+    leap_seconds: usize,
+    transitions: usize,
+    types: usize,
+    characters: usize,
+}
+
+impl TzifHeader {
+    fn data_length(&self, time_size: usize) -> usize {
+        self.transitions * (time_size + 1)
+            + self.types * 6
+            + self.characters
+            + self.leap_seconds * (time_size + 4)
+            + self.standard_indicators
+            + self.utc_indicators
+    }
+}
+
+/// Returns the counts and where the data after them starts.
+fn read_tzif_header(bytes: &[u8], time_size: usize) -> Option<(TzifHeader, usize)> {
+    let count = |index: usize| -> Option<usize> {
+        let offset = 20 + index * 4;
+        Some(u32::from_be_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    let header = TzifHeader {
+        utc_indicators: count(0)?,
+        standard_indicators: count(1)?,
+        leap_seconds: count(2)?,
+        transitions: count(3)?,
+        types: count(4)?,
+        characters: count(5)?,
+    };
+    if bytes.len() < 44 + header.data_length(time_size) || header.types == 0 {
+        return None;
+    }
+    Some((header, 44))
+}
+
+/// Returns the zone without the rule and where its data ends.
+fn read_tzif_data(data: &[u8], header: &TzifHeader, time_size: usize) -> Option<(TimeZone, usize)> {
+    let times = &data[..header.transitions * time_size];
+    let types = &data[times.len()..times.len() + header.transitions];
+    let infos = &data[times.len() + types.len()..];
+    let type_offset = |index: u8| -> Option<i32> {
+        let info = infos.get(usize::from(index) * 6..usize::from(index) * 6 + 4)?;
+        Some(i32::from_be_bytes(info.try_into().ok()?))
+    };
+    let mut transitions = Vec::with_capacity(header.transitions);
+    for (time, index) in times.chunks_exact(time_size).zip(types) {
+        let time = match time_size {
+            4 => i64::from(i32::from_be_bytes(time.try_into().ok()?)),
+            _ => i64::from_be_bytes(time.try_into().ok()?),
+        };
+        transitions.push((time, type_offset(*index)?));
+    }
+    let zone = TimeZone {
+        initial_offset: type_offset(0)?,
+        transitions,
+        rule: None,
+    };
+    Some((zone, header.data_length(time_size)))
+}
+
+impl TimeZoneRule {
+    fn offset_at(&self, timestamp: i64) -> i32 {
+        let daylight_saving = match self.daylight_saving {
+            Some(daylight_saving) => daylight_saving,
+            None => return self.standard_offset,
+        };
+        let (year, _, _) =
+            civil_from_days((timestamp + i64::from(self.standard_offset)).div_euclid(DAY_S as i64));
+        let start = daylight_saving.start.0.find_day(year) * DAY_S as i64 + daylight_saving.start.1
+            - i64::from(self.standard_offset);
+        let end = daylight_saving.end.0.find_day(year) * DAY_S as i64 + daylight_saving.end.1
+            - i64::from(daylight_saving.offset);
+        // On the southern hemisphere it lasts over the turn of the year.
+        let is_daylight_saving = if start < end {
+            (start..end).contains(&timestamp)
+        } else {
+            !(end..start).contains(&timestamp)
+        };
+        if is_daylight_saving {
+            daylight_saving.offset
+        } else {
+            self.standard_offset
+        }
+    }
+}
+
+impl RuleDay {
+    /// Returns the day as days since the UNIX epoch.
+    fn find_day(self, year: i64) -> i64 {
+        let january_first = days_from_civil(year, 1, 1);
+        let is_leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        match self {
+            RuleDay::Julian(day) => {
+                let is_past_february = day > 59;
+                january_first + i64::from(day) - 1 + i64::from(is_past_february && is_leap_year)
+            }
+            RuleDay::Zero(day) => january_first + i64::from(day),
+            RuleDay::Month(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                let next_month = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month + 1, 1)
+                };
+                // The UNIX epoch was on Thursday.
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first
+                    + (i64::from(weekday) - first_weekday).rem_euclid(7)
+                    + (i64::from(week) - 1) * 7;
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+/// Days since the UNIX epoch of the date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The year, the month and the day of the days since the UNIX epoch.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u8;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u8;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Parses `STD offset [DST [offset] [,start[/time],end[/time]]]`.
+fn parse_rule(value: &str) -> Option<TimeZoneRule> {
+    let rest = skip_zone_name(value)?;
+    let (standard, rest) = parse_time(rest)?;
+    // The offsets of POSIX are west of UTC.
+    let standard_offset = -standard as i32;
+    if rest.is_empty() {
+        return Some(TimeZoneRule {
+            standard_offset,
+            daylight_saving: None,
+        });
+    }
+    let rest = skip_zone_name(rest)?;
+    let (offset, rest) = match rest.chars().next() {
+        Some(',') | None => (standard_offset + HOUR_S as i32, rest),
+        Some(_) => {
+            let (daylight_saving, rest) = parse_time(rest)?;
+            (-daylight_saving as i32, rest)
+        }
+    };
+    // The rule of the United States is the default one.
+    let rest = if rest.is_empty() {
+        ",M3.2.0,M11.1.0"
+    } else {
+        rest
+    };
+    let mut parts = rest.strip_prefix(',')?.splitn(2, ',');
+    let start = parse_rule_moment(parts.next()?)?;
+    let end = parse_rule_moment(parts.next()?)?;
+    Some(TimeZoneRule {
+        standard_offset,
+        daylight_saving: Some(DaylightSaving { offset, start, end }),
+    })
+}
+
+/// Skips `CET` or the quoted `<+03>` name.
+fn skip_zone_name(value: &str) -> Option<&str> {
+    let rest = match value.strip_prefix('<') {
+        Some(quoted) => &quoted[quoted.find('>')? + 1..],
+        None => value.trim_start_matches(|c: char| c.is_ascii_alphabetic()),
+    };
+    if value.len() - rest.len() < 3 {
+        return None;
+    }
+    Some(rest)
+}
+
+/// Parses `[+-]hh[:mm[:ss]]` into seconds.
+fn parse_time(value: &str) -> Option<(i64, &str)> {
+    let (sign, value) = match value.as_bytes().first()? {
+        b'-' => (-1, &value[1..]),
+        b'+' => (1, &value[1..]),
+        _ => (1, value),
+    };
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(value.len());
+    let mut seconds = 0;
+    for (part, unit) in value[..end].split(':').zip([HOUR_S, MINUTE_S, 1].iter()) {
+        seconds += part.parse::<i64>().ok()? * *unit as i64;
+    }
+    Some((sign * seconds, &value[end..]))
+}
+
+/// Parses `M3.5.0/3` with the time of the change, 2:00 by default.
+fn parse_rule_moment(value: &str) -> Option<(RuleDay, i64)> {
+    let mut parts = value.splitn(2, '/');
+    let day = parts.next()?;
+    let time = match parts.next() {
+        Some(time) => match parse_time(time)? {
+            (time, "") => time,
+            _ => return None,
+        },
+        None => 2 * HOUR_S as i64,
+    };
+    let day = if let Some(julian) = day.strip_prefix('J') {
+        RuleDay::Julian(julian.parse().ok().filter(|day| (1..=365).contains(day))?)
+    } else if let Some(month) = day.strip_prefix('M') {
+        let mut numbers = month.splitn(3, '.').map(|number| number.parse::<u8>().ok());
+        let month = numbers.next()??;
+        let week = numbers.next()??;
+        let weekday = numbers.next()??;
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+            return None;
+        }
+        RuleDay::Month(month, week, weekday)
+    } else {
+        RuleDay::Zero(day.parse().ok().filter(|day| *day <= 365)?)
+    };
+    Some((day, time))
+}
+
+/// Reads the time zone of the system ahead of its first use, before the
+/// other threads start, it is UTC when it cannot be read.
+pub fn init_local_time_zone() {
+    LOCAL_TIME_ZONE.get_or_init(load_local_time_zone);
+}
+
+fn load_local_time_zone() -> TimeZone {
+    let read_file = |path: &Path| {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| TimeZone::from_tzif(&bytes))
+    };
+    match env::var("TZ") {
+        Ok(value) if !value.is_empty() => {
+            let name = value.strip_prefix(':').unwrap_or(&value);
+            let path = Path::new(ZONE_INFO_DIRECTORY).join(name);
+            read_file(&path)
+                .or_else(|| TimeZone::from_posix(name))
+                .unwrap_or_else(|| TimeZone::fixed(0))
+        }
+        _ => read_file(Path::new(LOCAL_TIME_FILE)).unwrap_or_else(|| TimeZone::fixed(0)),
+    }
+}
+
+/// Returns the offset of the local time from UTC at `timestamp`
+/// (seconds since the UNIX epoch).
+pub fn local_offset_at(timestamp: i64) -> UtcOffset {
+    #[cfg(not(unix))]
+    {
+        // The offset is read from the system on the other platforms.
+        let date_time = OffsetDateTime::from_unix_timestamp(timestamp);
+        if let Ok(offset) = UtcOffset::try_local_offset_at(date_time) {
+            return offset;
+        }
+    }
+    let offset = LOCAL_TIME_ZONE
+        .get_or_init(load_local_time_zone)
+        .offset_at(timestamp);
+    UtcOffset::seconds(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-15 and 2024-07-15 at noon UTC.
+    const WINTER: i64 = 1_705_320_000;
+    const SUMMER: i64 = 1_721_044_800;
+
+    #[test]
+    fn reading_posix_time_zone() {
+        let warsaw = TimeZone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(warsaw.offset_at(WINTER), 3600);
+        assert_eq!(warsaw.offset_at(SUMMER), 7200);
+        // The summer time started at 2024-03-31 01:00 UTC.
+        assert_eq!(warsaw.offset_at(1_711_846_799), 3600);
+        assert_eq!(warsaw.offset_at(1_711_846_800), 7200);
+        // It ended at 2024-10-27 01:00 UTC.
+        assert_eq!(warsaw.offset_at(1_729_990_799), 7200);
+        assert_eq!(warsaw.offset_at(1_729_990_800), 3600);
+
+        let sydney = TimeZone::from_posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset_at(WINTER), 11 * 3600);
+        assert_eq!(sydney.offset_at(SUMMER), 10 * 3600);
+        let new_york = TimeZone::from_posix("EST5EDT").unwrap();
+        assert_eq!(new_york.offset_at(WINTER), -5 * 3600);
+        assert_eq!(new_york.offset_at(SUMMER), -4 * 3600);
+        let kolkata = TimeZone::from_posix("<+0530>-5:30").unwrap();
+        assert_eq!(kolkata.offset_at(SUMMER), 19800);
+        assert_eq!(TimeZone::from_posix("UTC0").unwrap().offset_at(SUMMER), 0);
+        assert!(TimeZone::from_posix("Europe/Warsaw").is_none());
+        assert!(TimeZone::from_posix("CET-1CEST,M13.5.0,M10.5.0").is_none());
+    }
+
+    #[test]
+    fn reading_tzif_file() {
+        // One transition from +01:00 to +02:00 at `SUMMER`, followed by
+        // the rule of the Central European time.
+        let header = |time_count: u32| {
+            let mut header = b"TZif2".to_vec();
+            header.extend_from_slice(&[0; 15]);
+            for count in [0, 0, 0, time_count, 2, 8].iter() {
+                header.extend_from_slice(&u32::to_be_bytes(*count));
+            }
+            header
+        };
+        let types = [0, 0, 0x0E, 0x10, 0, 0, 0, 0, 0x1C, 0x20, 1, 4];
+        let mut file = header(0);
+        file.extend_from_slice(&types);
+        file.extend_from_slice(b"CET\0CEST");
+        file.extend_from_slice(&header(1));
+        file.extend_from_slice(&SUMMER.to_be_bytes());
+        file.push(1);
+        file.extend_from_slice(&types);
+        file.extend_from_slice(b"CET\0CEST");
+        file.extend_from_slice(b"\nCET-1CEST,M3.5.0,M10.5.0/3\n");
+        let zone = TimeZone::from_tzif(&file).unwrap();
+        assert_eq!(zone.offset_at(WINTER), 3600);
+        assert_eq!(zone.offset_at(SUMMER - 1), 3600);
+        // After the transitions the rule applies.
+        assert_eq!(zone.offset_at(SUMMER + 180 * DAY_S as i64), 3600);
+        assert_eq!(zone.offset_at(SUMMER + 365 * DAY_S as i64), 7200);
+        assert!(TimeZone::from_tzif(&file[..60]).is_none());
+        assert!(TimeZone::from_tzif(b"ID3").is_none());
+    }
+
+    #[test]
+    fn converting_civil_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 2, 29), 19782);
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(
+            RuleDay::Month(3, 5, 0).find_day(2024),
+            days_from_civil(2024, 3, 31)
+        );
+        assert_eq!(
+            RuleDay::Month(11, 1, 0).find_day(2024),
+            days_from_civil(2024, 11, 3)
+        );
+        assert_eq!(
+            RuleDay::Julian(60).find_day(2024),
+            days_from_civil(2024, 3, 1)
+        );
+        assert_eq!(
+            RuleDay::Zero(59).find_day(2024),
+            days_from_civil(2024, 2, 29)
+        );
+    }
+}
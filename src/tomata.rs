@@ -1,27 +1,77 @@
-use std::time::Duration;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use druid::Data;
+#[cfg(feature = "druid")]
+use druid::{Data, Lens};
 use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use crate::timezone;
 
 pub const APPLICATION_NAME: &str = "tomata";
 
-pub const WINDOW_SIZE_PX: (f64, f64) = if cfg!(windows) {
-    (520., 480.)
-} else {
-    (520., 460.)
-};
+/// The size the window is opened with, it is fitted to its content
+/// once laid out (see [`calculate_window_height`]).
+pub const INITIAL_WINDOW_SIZE_PX: (f64, f64) = (520., 460.);
+/// The tabs are given at least this height, their pages scroll.
+pub const MIN_TABS_HEIGHT_PX: f64 = 260.;
 
 pub const SECOND_S: u64 = 1;
 pub const MINUTE_S: u64 = SECOND_S * 60;
 pub const HOUR_S: u64 = MINUTE_S * 60;
+pub const DAY_S: u64 = HOUR_S * 24;
 
 pub static ZERO: Duration = Duration::from_secs(0);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Data)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data))]
 pub enum Period {
     Work,
     ShortBreak,
     LongBreak,
+    /// One of the kinds defined by the user, it is the index in the registry
+    /// (see [`Settings::get_custom_periods`](crate::settings::Settings::get_custom_periods)).
+    /// The cycle continues with a work period after it.
+    Custom(usize),
+}
+
+/// Period defined by the user, e.g., "Email", "Admin" or "Review".
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "druid", derive(Data, Lens))]
+#[serde(default)]
+pub struct CustomPeriodKind {
+    pub name: String,
+    /// Shown in the window title instead of the icon of the built-in periods.
+    pub icon: String,
+    pub duration: Rc<Duration>, // Data cannot be derived for Duration, unless it is in Rc
+    /// `RRGGBB` color of the keyboard lighting.
+    pub color: String,
+    pub ending_sound_is_enabled: bool,
+    pub notification: String,
+}
+
+impl Default for CustomPeriodKind {
+    fn default() -> CustomPeriodKind {
+        CustomPeriodKind {
+            name: "Custom".to_string(),
+            icon: "\u{1F4CB}".to_string(),
+            duration: Rc::new(Duration::from_secs(MINUTE_S * 15)),
+            color: "0000FF".to_string(),
+            ending_sound_is_enabled: true,
+            notification: String::new(),
+        }
+    }
+}
+
+impl From<&CustomPeriodKind> for Notification {
+    fn from(kind: &CustomPeriodKind) -> Notification {
+        Notification::new()
+            .appname("tomata")
+            .summary(&format!("{}.", kind.name))
+            .body(&kind.notification)
+            .clone()
+    }
 }
 
 impl From<Period> for Notification {
@@ -42,6 +92,11 @@ impl From<Period> for Notification {
                 .summary("Long break.")
                 .body("Take a walk, make a coffee, watch something interesting.")
                 .clone(),
+            // The registry knows the texts, see `From<&CustomPeriodKind>`.
+            Period::Custom(_) => Notification::new()
+                .appname("tomata")
+                .summary("Custom period.")
+                .clone(),
         }
     }
 }
@@ -56,6 +111,234 @@ pub fn duration_to_string(duration: &Duration) -> String {
     )
 }
 
+const HOUR_UNITS: &[&str] = &[
+    "h", "hr", "hrs", "hour", "hours", "std", "stunde", "stunden", "godz", "godzina", "godziny",
+    "godzin", "heure", "heures", "hora", "horas", "ora", "ore",
+];
+const MINUTE_UNITS: &[&str] = &[
+    "m", "min", "mins", "minute", "minutes", "minuten", "minuta", "minuty", "minut", "mn",
+    "minuto", "minutos", "minuti",
+];
+const SECOND_UNITS: &[&str] = &[
+    "s", "sec", "secs", "second", "seconds", "sek", "sekunde", "sekunden", "sekunda", "sekundy",
+    "sekund", "seconde", "secondes", "segundo", "segundos", "secondi",
+];
+
+/// Parses the duration typed by the user, e.g., `25`, `25:00`, `1:30:00`,
+/// `1h30`, `90 min`, `1,5 h` or `2 Stunden`. A lone number means minutes,
+/// a number without a unit after another one means the next smaller unit.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    if text.contains(':') {
+        return parse_clock_duration(&text);
+    }
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' || c == ',' {
+            let mut number = String::new();
+            while let Some(&c) = chars
+                .peek()
+                .filter(|c| c.is_ascii_digit() || **c == '.' || **c == ',')
+            {
+                number.push(if c == ',' { '.' } else { c });
+                chars.next();
+            }
+            tokens.push((number, None));
+        } else if c.is_alphabetic() {
+            let mut unit = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphabetic()) {
+                unit.push(c);
+                chars.next();
+            }
+            match tokens.last_mut() {
+                Some((_, last_unit @ None)) => *last_unit = Some(unit),
+                _ => return None,
+            }
+        } else {
+            return None;
+        }
+    }
+    let mut seconds = 0.0;
+    let mut previous_unit: Option<u64> = None;
+    for (number, unit) in &tokens {
+        let unit = match (unit, previous_unit) {
+            (Some(unit), _) => parse_duration_unit(unit)?,
+            (None, None) => MINUTE_S,
+            (None, Some(previous)) if previous > SECOND_S => previous / 60,
+            (None, Some(_)) => return None,
+        };
+        if previous_unit.is_some_and(|previous| unit >= previous) {
+            return None;
+        }
+        seconds += number.parse::<f64>().ok()? * unit as f64;
+        previous_unit = Some(unit);
+    }
+    previous_unit?;
+    Some(Duration::from_secs(seconds.round() as u64))
+}
+
+fn parse_duration_unit(unit: &str) -> Option<u64> {
+    [
+        (HOUR_UNITS, HOUR_S),
+        (MINUTE_UNITS, MINUTE_S),
+        (SECOND_UNITS, SECOND_S),
+    ]
+    .iter()
+    .find(|(units, _)| units.contains(&unit))
+    .map(|&(_, seconds)| seconds)
+}
+
+/// Parses `MM:SS` or `HH:MM:SS`.
+fn parse_clock_duration(text: &str) -> Option<Duration> {
+    let parts = text
+        .split(':')
+        .map(|part| part.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [minutes, seconds] => (0, *minutes, *seconds),
+        [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+        _ => return None,
+    };
+    if seconds >= 60 || (parts.len() == 3 && minutes >= 60) {
+        return None;
+    }
+    Some(Duration::from_secs(
+        hours * HOUR_S + minutes * MINUTE_S + seconds,
+    ))
+}
+
+/// Replaces every `{name}` in `template` with the value of `name`,
+/// unknown names are left untouched.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// Formats `number` as an English ordinal, e.g., `1st`, `12th` or `23rd`.
+pub fn to_ordinal(number: usize) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Rounds up to the whole seconds, so the displayed remaining time
+/// reaches zero exactly when the period finishes.
+pub fn round_up_to_seconds(duration: Duration) -> Duration {
+    let seconds = duration.as_secs();
+    if duration.subsec_nanos() > 0 {
+        Duration::from_secs(seconds + 1)
+    } else {
+        Duration::from_secs(seconds)
+    }
+}
+
+/// Formats seconds since the UNIX epoch as the local time, e.g., `14:37`.
+pub fn timestamp_to_local_time_string(timestamp: u64) -> String {
+    let date_time = OffsetDateTime::from_unix_timestamp(timestamp as i64);
+    let offset = timezone::local_offset_at(date_time.unix_timestamp());
+    let date_time = date_time.to_offset(offset);
+    format!("{:0>2}:{:0>2}", date_time.hour(), date_time.minute())
+}
+
+/// Returns how much time passed since the local midnight at `timestamp`.
+pub fn time_of_local_day(timestamp: u64) -> Duration {
+    let date_time = OffsetDateTime::from_unix_timestamp(timestamp as i64);
+    let offset = timezone::local_offset_at(date_time.unix_timestamp());
+    let date_time = date_time.to_offset(offset);
+    Duration::from_secs(
+        u64::from(date_time.hour()) * HOUR_S
+            + u64::from(date_time.minute()) * MINUTE_S
+            + u64::from(date_time.second()),
+    )
+}
+
+/// Formats seconds since the UNIX epoch as the local date, e.g., `2024-01-31`.
+pub fn timestamp_to_local_date_string(timestamp: u64) -> String {
+    let date = local_date(timestamp);
+    format!("{}-{:0>2}-{:0>2}", date.year(), date.month(), date.day())
+}
+
+/// Returns the local midnight that starts the day of `timestamp`,
+/// both as seconds since the UNIX epoch.
+pub fn start_of_local_day(timestamp: u64) -> u64 {
+    start_of_day(timestamp, |timestamp| {
+        i64::from(timezone::local_offset_at(timestamp as i64).as_seconds())
+    })
+}
+
+/// `offset_at` returns the offset of the local time from UTC at the timestamp, in seconds.
+fn start_of_day(timestamp: u64, offset_at: impl Fn(u64) -> i64) -> u64 {
+    let offset = offset_at(timestamp);
+    let time_of_day = (timestamp as i64 + offset).rem_euclid(DAY_S as i64);
+    let midnight = timestamp as i64 - time_of_day;
+    // The daylight saving time starting or ending earlier during the day
+    // moves the midnight by the change of the offset.
+    (midnight + offset - offset_at(midnight.max(0) as u64)).max(0) as u64
+}
+
+/// Returns the first local midnight after `timestamp`.
+pub fn find_next_local_midnight(timestamp: u64) -> u64 {
+    // Half a day past the next midnight stays on the next day even when
+    // the daylight saving time makes the day shorter or longer.
+    start_of_local_day(start_of_local_day(timestamp) + DAY_S + DAY_S / 2)
+}
+
+/// Returns the local midnight that starts Monday of the week of `timestamp`.
+pub fn start_of_local_week(timestamp: u64) -> u64 {
+    let today = start_of_local_day(timestamp);
+    let days = local_date(today).weekday().number_days_from_monday() as u64;
+    // Half a day keeps the beginning on Monday despite the daylight saving time.
+    start_of_local_day((today + DAY_S / 2).saturating_sub(days * DAY_S))
+}
+
+/// Returns the local midnight that starts the `date`.
+pub fn start_of_local_date(date: Date) -> u64 {
+    let utc_midnight = date.midnight().assume_utc().unix_timestamp().max(0) as u64;
+    // Half a day later it is the same date in any time zone.
+    start_of_local_day(utc_midnight + DAY_S / 2)
+}
+
+/// Parses `2024-12-24` dates.
+pub fn parse_date(date: &str) -> Option<Date> {
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Date::try_from_ymd(year, month, day).ok()
+}
+
+/// Returns the local date at `timestamp` (seconds since the UNIX epoch).
+pub fn local_date(timestamp: u64) -> Date {
+    let date_time = OffsetDateTime::from_unix_timestamp(timestamp as i64);
+    let offset = timezone::local_offset_at(date_time.unix_timestamp());
+    date_time.to_offset(offset).date()
+}
+
+/// Returns the height of the window fitting the content above the tabs
+/// and the tabs of their minimal height. The frame is counted in the size
+/// of the window on some of the platforms, on Windows it is the title bar.
+pub fn calculate_window_height(content_height: f64, frame_height: f64) -> f64 {
+    (content_height + MIN_TABS_HEIGHT_PX + frame_height).ceil()
+}
+
+/// Returns the current time as seconds since the UNIX epoch.
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,4 +351,141 @@ mod tests {
         let as_string = duration_to_string(&duration);
         assert_eq!(as_string, "01:30:10");
     }
+
+    #[test]
+    fn rendering_template() {
+        let text = render_template(
+            "{summary} - {task}, {unknown}",
+            &[("summary", "Short break"), ("task", "'API refactor'")],
+        );
+        assert_eq!(text, "Short break - 'API refactor', {unknown}");
+    }
+
+    #[test]
+    fn formatting_ordinals() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 102, 111]
+            .iter()
+            .map(|&number| to_ordinal(number))
+            .collect();
+        assert_eq!(
+            ordinals,
+            ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "102nd", "111th"]
+        );
+    }
+
+    #[test]
+    fn rounding_up_to_seconds() {
+        assert_eq!(round_up_to_seconds(ZERO), ZERO);
+        assert_eq!(
+            round_up_to_seconds(Duration::from_millis(24_900)),
+            Duration::from_secs(25)
+        );
+        assert_eq!(
+            round_up_to_seconds(Duration::from_secs(3)),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn parsing_durations() {
+        let minutes = |minutes| Some(Duration::from_secs(minutes * MINUTE_S));
+        assert_eq!(parse_duration("25"), minutes(25));
+        assert_eq!(parse_duration(" 25:00 "), minutes(25));
+        assert_eq!(parse_duration("1:30:00"), minutes(90));
+        assert_eq!(parse_duration("1h30"), minutes(90));
+        assert_eq!(parse_duration("1 h 30 min"), minutes(90));
+        assert_eq!(parse_duration("90 min"), minutes(90));
+        assert_eq!(parse_duration("90MIN"), minutes(90));
+        assert_eq!(parse_duration("1,5 h"), minutes(90));
+        assert_eq!(parse_duration("1.5h"), minutes(90));
+        assert_eq!(parse_duration("2 Stunden"), minutes(120));
+        assert_eq!(parse_duration("3 godziny"), minutes(180));
+        assert_eq!(parse_duration("45 secondes"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration("2m30"), Some(Duration::from_secs(150)));
+        assert_eq!(parse_duration("0"), Some(ZERO));
+    }
+
+    #[test]
+    fn rejecting_invalid_durations() {
+        for text in [
+            "",
+            "h",
+            "abc",
+            "25 parsecs",
+            "30m 1h",
+            "1h 2h",
+            "5s30",
+            "1:2:3:4",
+            "25:60",
+            "1:60:00",
+            "-5",
+            "1..5h",
+            "25:",
+            "1h30 + 5",
+        ] {
+            assert_eq!(parse_duration(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn finding_start_of_day_across_daylight_saving_time_changes() {
+        const DAY: u64 = 100 * DAY_S;
+        // The clocks go forward at 01:00 UTC and back a day later.
+        let offset_at = |timestamp: u64| match timestamp {
+            t if t < DAY + HOUR_S => HOUR_S as i64,
+            t if t < DAY + DAY_S + HOUR_S => 2 * HOUR_S as i64,
+            _ => HOUR_S as i64,
+        };
+        let midnight = DAY - HOUR_S;
+        assert_eq!(start_of_day(midnight + 12 * HOUR_S, offset_at), midnight);
+        let next_midnight = midnight + 23 * HOUR_S;
+        assert_eq!(
+            start_of_day(next_midnight + 12 * HOUR_S, offset_at),
+            next_midnight
+        );
+        let last_midnight = next_midnight + 25 * HOUR_S;
+        assert_eq!(
+            start_of_day(last_midnight + 12 * HOUR_S, offset_at),
+            last_midnight
+        );
+        assert_eq!(start_of_day(last_midnight, offset_at), last_midnight);
+    }
+
+    #[test]
+    fn finding_start_of_day_in_time_zone() {
+        let warsaw = timezone::TimeZone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let offset_at = |timestamp: u64| i64::from(warsaw.offset_at(timestamp as i64));
+        // 2024-07-15 00:30 in Warsaw is still July 14 in UTC.
+        let midnight = 1_720_994_400;
+        assert_eq!(start_of_day(midnight + 30 * MINUTE_S, offset_at), midnight);
+        assert_eq!(start_of_day(midnight - 1, offset_at), midnight - DAY_S);
+    }
+
+    #[test]
+    fn finding_start_of_week() {
+        // Wednesday, 2021-06-16, in the middle of the day in any time zone.
+        let timestamp = 1_623_844_800;
+        let week_start = start_of_local_week(timestamp);
+        assert_eq!(local_date(week_start).weekday(), time::Weekday::Monday);
+        assert_eq!(week_start, start_of_local_day(week_start));
+        assert!(timestamp - week_start > DAY_S && timestamp - week_start < 3 * DAY_S);
+        assert_eq!(start_of_local_week(week_start), week_start);
+    }
+
+    #[test]
+    fn finding_start_of_date() {
+        let date = parse_date("2021-06-16").unwrap();
+        let start = start_of_local_date(date);
+        assert_eq!(local_date(start), date);
+        assert_eq!(start, start_of_local_day(start));
+        assert_eq!(parse_date("2021-02-30"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn calculating_window_height() {
+        assert_eq!(calculate_window_height(200.0, 0.0), 460.0);
+        assert_eq!(calculate_window_height(320.4, 0.0), 581.0);
+        assert_eq!(calculate_window_height(200.0, 31.0), 491.0);
+    }
 }
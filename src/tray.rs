@@ -0,0 +1,132 @@
+//! Status icon in the system tray, showing the current period and the
+//! remaining time in its tooltip, so the main window can stay minimized.
+//! The icon is shown by `yad --notification`, whose menu runs the control
+//! subcommands of the application (see [`crate::control`]). Without a tray
+//! library working with druid, the icon is offered only on the systems
+//! `yad` runs on, i.e., Linux and the BSDs.
+use std::env;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::control;
+use crate::control::ControlCommand;
+use crate::tomata;
+
+const TRAY_ICON: &str = "appointment-soon";
+
+/// The entries of the context menu and the commands they send.
+const TRAY_MENU: &[(&str, ControlCommand)] = &[
+    ("Start", ControlCommand::Resume),
+    ("Pause", ControlCommand::Pause),
+    ("Skip", ControlCommand::SkipPeriod),
+    ("Show window", ControlCommand::ShowWindow),
+    ("Quit", ControlCommand::Quit),
+];
+
+/// Whether the icon can be shown on this platform at all.
+pub fn is_supported() -> bool {
+    cfg!(all(unix, not(target_os = "macos")))
+}
+
+#[derive(Debug)]
+pub struct TrayIcon {
+    process: Child,
+    stdin: ChildStdin,
+    tooltip: String,
+}
+
+impl TrayIcon {
+    /// Shows the icon, the working directory of the menu commands is
+    /// the current one, so they reach this application.
+    pub fn start() -> io::Result<TrayIcon> {
+        let executable = env::current_exe()?;
+        let mut process = Command::new("yad")
+            .args([
+                "--notification",
+                "--listen",
+                &format!("--image={}", TRAY_ICON),
+                &format!("--text={}", tomata::APPLICATION_NAME),
+                &format!(
+                    "--command={}",
+                    control::make_shell_command(&executable, &ControlCommand::ShowWindow)
+                ),
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => {
+                    io::Error::new(io::ErrorKind::NotFound, "`yad` is not installed")
+                }
+                _ => error,
+            })?;
+        let stdin = process
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "`yad` has no standard input"))?;
+        let mut tray_icon = TrayIcon {
+            process,
+            stdin,
+            tooltip: String::new(),
+        };
+        tray_icon.send(&make_menu(&executable))?;
+        Ok(tray_icon)
+    }
+
+    /// Changes the tooltip, unless it is the same.
+    pub fn set_tooltip(&mut self, tooltip: &str) -> io::Result<()> {
+        if self.tooltip == tooltip {
+            return Ok(());
+        }
+        self.tooltip = tooltip.to_string();
+        // Every line is a command, the tooltip has to fit in one.
+        self.send(&format!("tooltip:{}", tooltip.replace('\n', " ")))
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() {
+            let _ = self.process.kill();
+        }
+        let _ = self.process.wait();
+    }
+}
+
+fn make_menu(executable: &Path) -> String {
+    let entries: Vec<String> = TRAY_MENU
+        .iter()
+        // `yad` splits the actions like a shell.
+        .map(|(name, command)| {
+            format!(
+                "{}!{}",
+                name,
+                control::make_shell_command(executable, command)
+            )
+        })
+        .collect();
+    format!("menu:{}", entries.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn making_menu() {
+        assert_eq!(
+            make_menu(Path::new("/opt/my apps/tomata")),
+            "menu:Start!'/opt/my apps/tomata' resume\
+             |Pause!'/opt/my apps/tomata' pause\
+             |Skip!'/opt/my apps/tomata' skip-period\
+             |Show window!'/opt/my apps/tomata' show-window\
+             |Quit!'/opt/my apps/tomata' quit"
+        );
+    }
+}
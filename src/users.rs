@@ -0,0 +1,108 @@
+//! Separate data of the users sharing the computer. Once the `users`
+//! directory exists, every user gets their own directory inside it, e.g.,
+//! `users/alice`, holding their settings, history and plan. The user is
+//! the one logged into the system, unless `TOMATA_USER` names another.
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const USERS_DIRECTORY: &str = "users";
+pub const USER_VARIABLE: &str = "TOMATA_USER";
+
+/// The names become the directories, so only the safe characters are allowed.
+pub fn is_valid_user_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || ['-', '_', '.'].contains(&c))
+}
+
+/// Returns the user whose data is used, `None` when the data is shared.
+pub fn find_current_user() -> Option<String> {
+    if let Some(user) = env::var_os(USER_VARIABLE) {
+        return user.into_string().ok();
+    }
+    if !Path::new(USERS_DIRECTORY).is_dir() {
+        return None;
+    }
+    ["USER", "USERNAME"]
+        .iter()
+        .find_map(|variable| env::var(variable).ok())
+}
+
+fn make_user_directory(user: &str) -> PathBuf {
+    Path::new(USERS_DIRECTORY).join(user)
+}
+
+/// Makes the directory of the user the working one, so all the data files
+/// are read and written there, creating it on the first use.
+pub fn enter_user_directory(user: &str) -> io::Result<()> {
+    if !is_valid_user_name(user) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid user name '{}'", user),
+        ));
+    }
+    let directory = make_user_directory(user);
+    fs::create_dir_all(&directory)?;
+    env::set_current_dir(directory)
+}
+
+/// Lists the users with a directory, read from the directory of `current_user`.
+pub fn list_users(current_user: Option<&str>) -> Vec<String> {
+    let directory = match current_user {
+        Some(_) => PathBuf::from(".."),
+        None => PathBuf::from(USERS_DIRECTORY),
+    };
+    let mut users: Vec<String> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| is_valid_user_name(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    users.sort();
+    users
+}
+
+/// Launches the application again with the data of `user`, the running
+/// one is expected to quit afterwards.
+pub fn relaunch_as_user(user: &str, current_user: Option<&str>) -> io::Result<()> {
+    if !is_valid_user_name(user) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid user name '{}'", user),
+        ));
+    }
+    // The working directory is the one of the current user, if there is one.
+    let base_directory = match current_user {
+        Some(_) => Path::new("..").join(".."),
+        None => PathBuf::from("."),
+    };
+    Command::new(env::current_exe()?)
+        .current_dir(base_directory)
+        .env(USER_VARIABLE, user)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validating_user_names() {
+        assert!(is_valid_user_name("alice"));
+        assert!(is_valid_user_name("bob.smith-2"));
+        assert!(!is_valid_user_name(""));
+        assert!(!is_valid_user_name(".."));
+        assert!(!is_valid_user_name("../alice"));
+        assert!(!is_valid_user_name("a b"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Switching the desktop wallpaper at the boundaries of the periods,
+//! so the current period is visible at a glance. The wallpaper of the
+//! user is restored when the application exits.
+use std::fmt;
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+use crate::settings::Settings;
+use crate::tomata::Period;
+
+pub trait WallpaperBackend: fmt::Debug {
+    /// Returns the current wallpaper, in the form accepted by `set_wallpaper`.
+    fn get_wallpaper(&self) -> io::Result<String>;
+    fn set_wallpaper(&self, wallpaper: &str) -> io::Result<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemWallpaperBackend;
+
+#[cfg(target_os = "linux")]
+const GNOME_BACKGROUND_SCHEMA: &str = "org.gnome.desktop.background";
+
+impl WallpaperBackend for SystemWallpaperBackend {
+    #[cfg(target_os = "linux")]
+    fn get_wallpaper(&self) -> io::Result<String> {
+        let uri = run_command(
+            "gsettings",
+            &["get", GNOME_BACKGROUND_SCHEMA, "picture-uri"],
+        )?;
+        Ok(uri.trim().trim_matches('\'').to_string())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_wallpaper(&self, wallpaper: &str) -> io::Result<()> {
+        let uri = if wallpaper.starts_with("file://") {
+            wallpaper.to_string()
+        } else {
+            format!("file://{}", wallpaper)
+        };
+        run_command(
+            "gsettings",
+            &["set", GNOME_BACKGROUND_SCHEMA, "picture-uri", &uri],
+        )
+        .map(drop)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn get_wallpaper(&self) -> io::Result<String> {
+        let path = run_command(
+            "osascript",
+            &[
+                "-e",
+                "tell application \"System Events\" to get picture of current desktop",
+            ],
+        )?;
+        Ok(path.trim().to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn set_wallpaper(&self, wallpaper: &str) -> io::Result<()> {
+        let script = format!(
+            "tell application \"System Events\" to set picture of every desktop to \"{}\"",
+            wallpaper.replace('"', "\\\"")
+        );
+        run_command("osascript", &["-e", &script]).map(drop)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn get_wallpaper(&self) -> io::Result<String> {
+        Err(unsupported())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn set_wallpaper(&self, _wallpaper: &str) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_command(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("`{}` exited with {}", program, output.status),
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        "changing the wallpaper is not supported on this platform",
+    )
+}
+
+/// Shows the wallpaper configured for the current period and
+/// remembers the original one to restore it afterwards.
+#[derive(Debug)]
+pub struct WallpaperSwitcher {
+    backend: Box<dyn WallpaperBackend>,
+    original_wallpaper: Option<String>,
+    shown_period: Option<Period>,
+}
+
+impl WallpaperSwitcher {
+    pub fn new(backend: Box<dyn WallpaperBackend>) -> WallpaperSwitcher {
+        WallpaperSwitcher {
+            backend,
+            original_wallpaper: None,
+            shown_period: None,
+        }
+    }
+
+    /// Sets the wallpaper of `period` if it has changed since the last call,
+    /// the original wallpaper is restored for the periods without one.
+    pub fn switch(&mut self, settings: &Settings, period: Period) -> io::Result<()> {
+        if self.shown_period == Some(period) {
+            return Ok(());
+        }
+        self.shown_period = Some(period);
+        let wallpaper = match settings.get_wallpaper(period) {
+            Some(wallpaper) => wallpaper,
+            None => return self.restore(),
+        };
+        if self.original_wallpaper.is_none() {
+            self.original_wallpaper = Some(self.backend.get_wallpaper()?);
+        }
+        self.backend.set_wallpaper(&wallpaper.to_string_lossy())
+    }
+
+    pub fn restore(&mut self) -> io::Result<()> {
+        match self.original_wallpaper.take() {
+            Some(original) => self.backend.set_wallpaper(&original),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for WallpaperSwitcher {
+    fn drop(&mut self) {
+        if let Err(error) = self.restore() {
+            eprintln!("Could not restore the wallpaper: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug)]
+    struct FakeBackend {
+        wallpaper: Rc<RefCell<String>>,
+    }
+
+    impl WallpaperBackend for FakeBackend {
+        fn get_wallpaper(&self) -> io::Result<String> {
+            Ok(self.wallpaper.borrow().clone())
+        }
+
+        fn set_wallpaper(&self, wallpaper: &str) -> io::Result<()> {
+            *self.wallpaper.borrow_mut() = wallpaper.to_string();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn switching_and_restoring_wallpaper() {
+        let settings = Settings {
+            work_wallpaper: "work.png".to_string(),
+            ..Settings::default()
+        };
+        let wallpaper = Rc::new(RefCell::new("original.png".to_string()));
+        let mut switcher = WallpaperSwitcher::new(Box::new(FakeBackend {
+            wallpaper: Rc::clone(&wallpaper),
+        }));
+
+        switcher.switch(&settings, Period::Work).unwrap();
+        assert_eq!(*wallpaper.borrow(), "work.png");
+        // The break has no wallpaper configured, so the original one returns.
+        switcher.switch(&settings, Period::ShortBreak).unwrap();
+        assert_eq!(*wallpaper.borrow(), "original.png");
+
+        switcher.switch(&settings, Period::Work).unwrap();
+        drop(switcher);
+        assert_eq!(*wallpaper.borrow(), "original.png");
+    }
+}
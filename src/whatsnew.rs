@@ -0,0 +1,100 @@
+//! The "What's new" panel shown once after an upgrade. The notes are
+//! the section of the changelog embedded at compile time for the current
+//! version, the version of the last run is kept in the UI state file.
+//! So is the plan of the day, asked for at the first launch of the day.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+pub const UI_STATE_FILE: &str = "ui_state.json";
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+
+/// State of the user interface that is not a setting, kept between the runs.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UiState {
+    #[serde(default)]
+    pub last_run_version: Option<String>,
+    /// The start of the day the plan was asked for, in seconds since
+    /// the UNIX epoch.
+    #[serde(default)]
+    pub last_planned_day: Option<u64>,
+    /// The project the work of that day is tagged with by default.
+    #[serde(default)]
+    pub primary_project: Option<String>,
+}
+
+pub fn load_ui_state(path: impl AsRef<Path>) -> io::Result<UiState> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(io::Error::from),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(UiState::default()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn save_ui_state(path: impl AsRef<Path>, state: &UiState) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(state)?)
+}
+
+/// Keeps the primary project of today's plan, the other state is left as is.
+pub fn save_primary_project(path: impl AsRef<Path>, project: Option<String>) -> io::Result<()> {
+    let mut state = load_ui_state(&path)?;
+    state.primary_project = project;
+    save_ui_state(path, &state)
+}
+
+/// Returns the notes of `version`, i.e., the lines under its `## [version]`
+/// heading up to the next heading of the same level.
+pub fn find_release_notes(changelog: &str, version: &str) -> Option<String> {
+    let heading = format!("## [{}]", version);
+    let mut lines = changelog
+        .lines()
+        .skip_while(|line| !line.starts_with(&heading));
+    lines.next()?;
+    let notes: Vec<&str> = lines.take_while(|line| !line.starts_with("## ")).collect();
+    let notes = notes.join("\n").trim().to_string();
+    if notes.is_empty() {
+        None
+    } else {
+        Some(notes)
+    }
+}
+
+/// Returns the notes to show when the application runs for the first time
+/// since the upgrade, none on a fresh install with no previous version.
+pub fn find_whats_new(last_run_version: Option<&str>, is_fresh_install: bool) -> Option<String> {
+    if is_fresh_install || last_run_version == Some(VERSION) {
+        return None;
+    }
+    find_release_notes(CHANGELOG, VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_release_notes() {
+        let changelog =
+            "# Changelog\n\n## [Unreleased]\n\n- added b\n\n## [0.1.0] - 2020-10-12\n\n- added a\n";
+        assert_eq!(
+            find_release_notes(changelog, "Unreleased").as_deref(),
+            Some("- added b")
+        );
+        assert_eq!(
+            find_release_notes(changelog, "0.1.0").as_deref(),
+            Some("- added a")
+        );
+        assert_eq!(find_release_notes(changelog, "0.2.0"), None);
+    }
+
+    #[test]
+    fn showing_whats_new_once_after_upgrade() {
+        assert!(find_whats_new(Some("0.0.1"), false).is_some());
+        assert!(find_whats_new(None, false).is_some());
+        assert_eq!(find_whats_new(Some(VERSION), false), None);
+        assert_eq!(find_whats_new(None, true), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! Watching the focused workspace of the i3 and sway window managers,
+//! so switching to one of the "distraction" workspaces during work is
+//! noticed. The workspaces are queried through the IPC of the compositor
+//! with the command line tools coming with it.
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::process::Command;
+
+#[cfg(any(target_os = "linux", test))]
+use serde::Deserialize;
+
+pub trait WorkspaceProbe: fmt::Debug {
+    /// Returns the name of the focused workspace, `None` when it cannot
+    /// be determined, e.g., there is no i3 or sway running.
+    fn focused_workspace(&self) -> Option<String>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemWorkspaceProbe;
+
+impl WorkspaceProbe for SystemWorkspaceProbe {
+    #[cfg(target_os = "linux")]
+    fn focused_workspace(&self) -> Option<String> {
+        // Both tools speak the same protocol, sway sets its own socket variable.
+        let program = if std::env::var_os("SWAYSOCK").is_some() {
+            "swaymsg"
+        } else if std::env::var_os("I3SOCK").is_some() {
+            "i3-msg"
+        } else {
+            return None;
+        };
+        let output = Command::new(program)
+            .args(["-t", "get_workspaces"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        find_focused_workspace(&String::from_utf8(output.stdout).ok()?)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn focused_workspace(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug, Deserialize)]
+struct Workspace {
+    name: String,
+    focused: bool,
+}
+
+/// The reply lists all the workspaces, e.g.,
+/// `[{"num": 1, "name": "1: code", "focused": true, ...}, ...]`.
+#[cfg(any(target_os = "linux", test))]
+fn find_focused_workspace(reply: &str) -> Option<String> {
+    let workspaces: Vec<Workspace> = serde_json::from_str(reply).ok()?;
+    workspaces
+        .into_iter()
+        .find(|workspace| workspace.focused)
+        .map(|workspace| workspace.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finding_focused_workspace() {
+        let reply = r#"[
+            {"num": 1, "name": "1: code", "visible": false, "focused": false},
+            {"num": 9, "name": "chat", "visible": true, "focused": true}
+        ]"#;
+        assert_eq!(find_focused_workspace(reply).as_deref(), Some("chat"));
+        assert_eq!(find_focused_workspace("[]"), None);
+        assert_eq!(find_focused_workspace("not json"), None);
+    }
+}
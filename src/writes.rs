@@ -0,0 +1,106 @@
+//! Batching of the writes to the disk, so the application does not cause
+//! noticeable IO on the spinning disks and the SD cards. The changes are
+//! held back and written together at the boundaries of the periods, when
+//! the application quits or once they waited for [`FLUSH_INTERVAL`].
+use std::time::{Duration, Instant};
+
+/// The longest a change waits for being written.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct WriteBuffer<T> {
+    items: Vec<T>,
+    /// When the oldest of the items was added.
+    pending_since: Option<Instant>,
+}
+
+impl<T> Default for WriteBuffer<T> {
+    fn default() -> WriteBuffer<T> {
+        WriteBuffer {
+            items: Vec::new(),
+            pending_since: None,
+        }
+    }
+}
+
+impl<T> WriteBuffer<T> {
+    pub fn extend(&mut self, items: impl IntoIterator<Item = T>, now: Instant) {
+        let count = self.items.len();
+        self.items.extend(items);
+        if self.items.len() > count && self.pending_since.is_none() {
+            self.pending_since = Some(now);
+        }
+    }
+
+    /// Returns the taken items that could not be written, they are
+    /// written again with the next ones.
+    pub fn put_back(&mut self, items: Vec<T>, now: Instant) {
+        if items.is_empty() {
+            return;
+        }
+        let newer = std::mem::replace(&mut self.items, items);
+        self.items.extend(newer);
+        self.pending_since.get_or_insert(now);
+    }
+
+    /// The items still waiting, the oldest first.
+    pub fn get_pending(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Returns the items to write, the oldest first, once they waited
+    /// long enough or `is_forced`, e.g., at the boundary of the periods.
+    pub fn take_due(&mut self, now: Instant, is_forced: bool) -> Vec<T> {
+        let is_due = self
+            .pending_since
+            .is_some_and(|since| is_forced || now.duration_since(since) >= FLUSH_INTERVAL);
+        if !is_due {
+            return Vec::new();
+        }
+        self.pending_since = None;
+        std::mem::take(&mut self.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holding_back_writes_until_due() {
+        let mut buffer = WriteBuffer::default();
+        let start = Instant::now();
+        assert!(buffer.take_due(start, true).is_empty());
+        buffer.extend(vec![1, 2], start);
+        buffer.extend(vec![3], start + Duration::from_secs(30));
+        assert_eq!(buffer.get_pending(), &[1, 2, 3]);
+        assert!(buffer
+            .take_due(start + Duration::from_secs(59), false)
+            .is_empty());
+        assert_eq!(
+            buffer.take_due(start + FLUSH_INTERVAL, false),
+            vec![1, 2, 3]
+        );
+        buffer.extend(vec![4], start + FLUSH_INTERVAL);
+        assert!(buffer.take_due(start + FLUSH_INTERVAL, false).is_empty());
+        assert_eq!(buffer.take_due(start + FLUSH_INTERVAL, true), vec![4]);
+        assert!(buffer
+            .take_due(start + 3 * FLUSH_INTERVAL, false)
+            .is_empty());
+    }
+
+    #[test]
+    fn putting_back_failed_writes() {
+        let mut buffer = WriteBuffer::default();
+        let start = Instant::now();
+        buffer.extend(vec![1, 2], start);
+        let failed = buffer.take_due(start, true);
+        buffer.extend(vec![3], start);
+        buffer.put_back(failed, start);
+        assert_eq!(buffer.get_pending(), &[1, 2, 3]);
+        assert_eq!(
+            buffer.take_due(start + FLUSH_INTERVAL, false),
+            vec![1, 2, 3]
+        );
+    }
+}